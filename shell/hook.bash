@@ -0,0 +1,11 @@
+# pmx shell hook: applies the profiles pinned in .pmx.toml when entering a directory
+_pmx_hook() {
+    if [[ "$PWD" != "${_PMX_LAST_DIR:-}" ]]; then
+        _PMX_LAST_DIR="$PWD"
+        pmx internal-hook
+    fi
+}
+
+if [[ ";${PROMPT_COMMAND:-};" != *";_pmx_hook;"* ]]; then
+    PROMPT_COMMAND="_pmx_hook${PROMPT_COMMAND:+;$PROMPT_COMMAND}"
+fi
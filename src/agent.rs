@@ -0,0 +1,224 @@
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// An AI agent whose system prompt file pmx manages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Agent {
+    Claude,
+    Codex,
+    Gemini,
+    Cursor,
+    /// Continue, which reads a directory of rule files rather than a single prompt file
+    Continue,
+    /// A tool declared under `[agents.custom.<name>]`
+    Custom(&'static CustomAgent),
+}
+
+/// A tool reading a markdown system prompt that pmx has no built-in support for
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct CustomAgent {
+    pub name: String,
+    /// The user-wide prompt file, before `~` is expanded
+    pub target: PathBuf,
+    /// Name of the prompt file inside a project
+    pub file_name: String,
+    /// Text written between the previous content and an appended profile
+    pub separator: Option<String>,
+}
+
+impl Agent {
+    /// The built-in agents; custom ones come from the configuration
+    pub const ALL: [Agent; 5] = [
+        Agent::Claude,
+        Agent::Codex,
+        Agent::Gemini,
+        Agent::Cursor,
+        Agent::Continue,
+    ];
+
+    /// The built-in agent called `name`, or the one declared under `[agents.custom.<name>]`
+    pub fn named(storage: &crate::storage::Storage, name: &str) -> crate::Result<Agent> {
+        use clap::ValueEnum;
+
+        if let Ok(agent) = Agent::from_str(name, true) {
+            return Ok(agent);
+        }
+        let Some(config) = storage.config.agents.custom.get(name) else {
+            anyhow::bail!(
+                "Unknown agent '{}'; declare it under [agents.custom.{}] in config.toml",
+                name,
+                name
+            );
+        };
+        let file_name = match &config.project_file {
+            Some(file_name) => file_name.clone(),
+            None => config
+                .target
+                .file_name()
+                .map(|file_name| file_name.to_string_lossy().into_owned())
+                .ok_or_else(|| {
+                    anyhow::anyhow!("The target of custom agent '{}' is not a file", name)
+                })?,
+        };
+        Ok(Agent::Custom(intern(CustomAgent {
+            name: name.to_string(),
+            target: config.target.clone(),
+            file_name,
+            separator: config.separator.clone(),
+        })))
+    }
+
+    /// The built-in agents followed by the custom ones, in name order
+    pub fn all(storage: &crate::storage::Storage) -> crate::Result<Vec<Agent>> {
+        let mut agents = Agent::ALL.to_vec();
+        for name in storage.config.agents.custom.keys() {
+            agents.push(Agent::named(storage, name)?);
+        }
+        Ok(agents)
+    }
+
+    /// Text written between the previous content and an appended profile, if not a blank line
+    pub fn separator(self) -> Option<&'static str> {
+        match self {
+            Agent::Custom(custom) => custom.separator.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Whether the agent reads a directory with one rule file per profile, so that
+    /// its target is that directory
+    pub fn has_rules_dir(self) -> bool {
+        matches!(self, Agent::Continue)
+    }
+
+    /// Identifier used on the command line and in state files
+    pub fn name(self) -> &'static str {
+        match self {
+            Agent::Claude => "claude",
+            Agent::Codex => "codex",
+            Agent::Gemini => "gemini",
+            Agent::Cursor => "cursor",
+            Agent::Continue => "continue",
+            Agent::Custom(custom) => &custom.name,
+        }
+    }
+
+    /// Human readable name used in messages
+    pub fn display_name(self) -> &'static str {
+        match self {
+            Agent::Claude => "Claude",
+            Agent::Codex => "Codex",
+            Agent::Gemini => "Gemini",
+            Agent::Cursor => "Cursor",
+            Agent::Continue => "Continue",
+            Agent::Custom(custom) => &custom.name,
+        }
+    }
+
+    /// Name of the system prompt file the agent reads, or of its rules directory
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Agent::Claude => "CLAUDE.md",
+            Agent::Codex => "AGENTS.md",
+            Agent::Gemini => "GEMINI.md",
+            Agent::Cursor => ".cursorrules",
+            Agent::Continue => "rules",
+            Agent::Custom(custom) => &custom.file_name,
+        }
+    }
+
+    /// The user-wide prompt file, e.g. `~/.claude/CLAUDE.md`, unless `[agents.<name>] target`
+    /// points elsewhere
+    pub fn global_target(self, storage: &crate::storage::Storage) -> crate::Result<PathBuf> {
+        match storage
+            .config
+            .agents
+            .get(self)
+            .and_then(|config| config.target.as_deref())
+        {
+            Some(target) => expand_home(target),
+            None => self.default_target(),
+        }
+    }
+
+    /// The user-wide prompt file the agent reads when nothing is configured
+    pub fn default_target(self) -> crate::Result<PathBuf> {
+        let dir = match self {
+            Agent::Claude => ".claude",
+            Agent::Codex => ".codex",
+            Agent::Gemini => ".gemini",
+            Agent::Cursor => ".cursor",
+            Agent::Continue => ".continue",
+            Agent::Custom(custom) => return expand_home(&custom.target),
+        };
+        Ok(crate::utils::home_dir()?.join(dir).join(self.file_name()))
+    }
+
+    /// The prompt file the agent reads inside a project directory, e.g. `./CLAUDE.md`
+    pub fn project_target(self, project_dir: &Path) -> PathBuf {
+        match self {
+            Agent::Continue => project_dir.join(".continue").join(self.file_name()),
+            _ => project_dir.join(self.file_name()),
+        }
+    }
+
+    /// Resolve the target file, using the current directory when `project` is set
+    pub fn target(
+        self,
+        storage: &crate::storage::Storage,
+        project: bool,
+    ) -> crate::Result<PathBuf> {
+        if project {
+            let cwd = std::env::current_dir()
+                .map_err(|e| anyhow::anyhow!("Failed to get current directory: {}", e))?;
+            Ok(self.project_target(&cwd))
+        } else {
+            self.global_target(storage)
+        }
+    }
+
+    pub fn is_enabled(self, storage: &crate::storage::Storage) -> bool {
+        storage.config.agents.is_enabled(self)
+    }
+}
+
+/// A `'static` copy of `agent`, so that `Agent` stays `Copy`. Equal declarations
+/// share one copy, so at most one is kept per custom agent configuration.
+fn intern(agent: CustomAgent) -> &'static CustomAgent {
+    static INTERNED: Mutex<Vec<&'static CustomAgent>> = Mutex::new(Vec::new());
+
+    let mut interned = INTERNED.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(existing) = interned.iter().find(|existing| ***existing == agent) {
+        return existing;
+    }
+    let agent: &'static CustomAgent = Box::leak(Box::new(agent));
+    interned.push(agent);
+    agent
+}
+
+impl clap::ValueEnum for Agent {
+    fn value_variants<'a>() -> &'a [Self] {
+        &Agent::ALL
+    }
+
+    fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
+        match self {
+            Agent::Custom(_) => None,
+            agent => Some(clap::builder::PossibleValue::new(agent.name())),
+        }
+    }
+}
+
+/// Replace a leading `~` with the home directory
+pub(crate) fn expand_home(path: &Path) -> crate::Result<PathBuf> {
+    match path.strip_prefix("~") {
+        Ok(rest) => Ok(crate::utils::home_dir()?.join(rest)),
+        Err(_) => Ok(path.to_path_buf()),
+    }
+}
+
+impl std::fmt::Display for Agent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
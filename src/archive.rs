@@ -0,0 +1,279 @@
+use std::io::{Read, Write};
+use std::path::Path;
+
+use anyhow::{bail, ensure};
+
+/// A file in an archive: its `/`-separated relative path and content
+pub type Entry = (String, Vec<u8>);
+
+/// Most bytes an archive may unpack to, so that a small archive cannot fill the disk
+const MAX_UNPACKED_BYTES: u64 = 256 * 1024 * 1024;
+
+/// How a bundle of files is packaged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    /// Gzip-compressed tarball
+    #[value(name = "tar.gz")]
+    TarGz,
+    Zip,
+    /// Plain directory
+    Dir,
+}
+
+impl Format {
+    /// The format a destination's extension asks for, a directory otherwise
+    pub fn infer(dest: &Path) -> Self {
+        let name = dest.to_string_lossy().to_lowercase();
+        if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Format::TarGz
+        } else if name.ends_with(".zip") {
+            Format::Zip
+        } else {
+            Format::Dir
+        }
+    }
+}
+
+pub fn write(format: Format, dest: &Path, entries: &[Entry]) -> crate::Result<()> {
+    for (path, _) in entries {
+        check_path(path)?;
+    }
+    if format == Format::Dir {
+        for (path, content) in entries {
+            let file = dest.join(path);
+            if let Some(parent) = file.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| {
+                    anyhow::anyhow!("Failed to create directory {}: {}", parent.display(), e)
+                })?;
+            }
+            std::fs::write(&file, content)
+                .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", file.display(), e))?;
+        }
+        return Ok(());
+    }
+
+    let bytes = match format {
+        Format::TarGz => {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(&tar(entries)?)?;
+            encoder.finish()?
+        }
+        Format::Zip => zip(entries)?,
+        Format::Dir => unreachable!(),
+    };
+    std::fs::write(dest, bytes)
+        .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", dest.display(), e))
+}
+
+/// Files of a directory, tarball or zip archive, detected from its content.
+/// Hidden files other than folder profile markers are left out, and paths
+/// escaping the archive are refused.
+pub fn read(source: &Path) -> crate::Result<Vec<Entry>> {
+    let entries = if source.is_dir() {
+        crate::storage::recursive_list(source)?
+            .into_iter()
+            .filter_map(|path| Some(path.strip_prefix(source).ok()?.to_path_buf()))
+            .map(|relative| relative.to_string_lossy().replace('\\', "/"))
+            .filter(|relative| !is_hidden(relative))
+            .map(|relative| {
+                let content = std::fs::read(source.join(&relative))
+                    .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", relative, e))?;
+                Ok((relative, content))
+            })
+            .collect::<crate::Result<Vec<_>>>()?
+    } else {
+        let bytes = std::fs::read(source)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", source.display(), e))?;
+        if bytes.starts_with(&[0x1f, 0x8b]) {
+            let tarball = unpack(
+                flate2::read::GzDecoder::new(bytes.as_slice()),
+                MAX_UNPACKED_BYTES,
+            )
+            .map_err(|e| anyhow::anyhow!("Failed to decompress {}: {}", source.display(), e))?;
+            untar(&tarball)?
+        } else if bytes.starts_with(b"PK\x03\x04") {
+            unzip(&bytes)?
+        } else {
+            bail!("{} is not a tar.gz or zip archive", source.display());
+        }
+    };
+
+    let mut visible = Vec::new();
+    for (path, content) in entries {
+        let path = path.trim_start_matches("./").to_string();
+        check_path(&path)?;
+        if !is_hidden(&path) {
+            visible.push((path, content));
+        }
+    }
+    Ok(visible)
+}
+
+/// Whether `path` is hidden, or in a hidden directory, and not a folder profile marker
+fn is_hidden(path: &str) -> bool {
+    let (name, dirs) = path
+        .rsplit_once('/')
+        .map_or((path, ""), |(dirs, name)| (name, dirs));
+    dirs.split('/').any(|dir| dir.starts_with('.'))
+        || (name.starts_with('.') && name != crate::storage::FOLDER_MARKER)
+}
+
+/// Everything `reader` yields, refusing to go past `limit` bytes
+fn unpack(reader: impl Read, limit: u64) -> crate::Result<Vec<u8>> {
+    let mut unpacked = Vec::new();
+    reader.take(limit + 1).read_to_end(&mut unpacked)?;
+    ensure!(
+        unpacked.len() as u64 <= limit,
+        "Archive unpacks to more than {} bytes",
+        limit
+    );
+    Ok(unpacked)
+}
+
+fn check_path(path: &str) -> crate::Result<()> {
+    ensure!(
+        !path.is_empty()
+            && !path.starts_with('/')
+            && !path.contains('\\')
+            && !path.split('/').any(|part| part == ".." || part.is_empty()),
+        "Unsafe path in archive: {}",
+        path
+    );
+    Ok(())
+}
+
+/// A tar archive of `entries`; long paths get GNU long-name headers
+fn tar(entries: &[Entry]) -> crate::Result<Vec<u8>> {
+    let mut builder = tar::Builder::new(Vec::new());
+    for (path, content) in entries {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(0);
+        builder
+            .append_data(&mut header, path, content.as_slice())
+            .map_err(|e| anyhow::anyhow!("Failed to add {} to tar archive: {}", path, e))?;
+    }
+    Ok(builder.into_inner()?)
+}
+
+/// Regular files of a tar archive
+fn untar(bytes: &[u8]) -> crate::Result<Vec<Entry>> {
+    let malformed = |e: std::io::Error| anyhow::anyhow!("Malformed tar archive: {}", e);
+
+    let mut archive = tar::Archive::new(bytes);
+    let mut entries = Vec::new();
+    for entry in archive.entries().map_err(malformed)? {
+        let mut entry = entry.map_err(malformed)?;
+        // Directories, links and other metadata carry no file content
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = String::from_utf8_lossy(&entry.path_bytes()).to_string();
+        let mut content = Vec::new();
+        entry.read_to_end(&mut content).map_err(malformed)?;
+        entries.push((name, content));
+    }
+    Ok(entries)
+}
+
+/// A zip archive of `entries`, deflated
+fn zip(entries: &[Entry]) -> crate::Result<Vec<u8>> {
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    for (path, content) in entries {
+        writer
+            .start_file(path.as_str(), options)
+            .map_err(|e| anyhow::anyhow!("Failed to add {} to zip archive: {}", path, e))?;
+        writer.write_all(content)?;
+    }
+    let out = writer
+        .finish()
+        .map_err(|e| anyhow::anyhow!("Failed to write zip archive: {}", e))?;
+    Ok(out.into_inner())
+}
+
+/// Files of a zip archive, checked against their checksums
+fn unzip(bytes: &[u8]) -> crate::Result<Vec<Entry>> {
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes))
+        .map_err(|e| anyhow::anyhow!("Malformed zip archive: {}", e))?;
+
+    let mut entries = Vec::new();
+    let mut budget = MAX_UNPACKED_BYTES;
+    for index in 0..archive.len() {
+        let file = archive
+            .by_index(index)
+            .map_err(|e| anyhow::anyhow!("Malformed zip archive: {}", e))?;
+        if file.is_dir() {
+            continue;
+        }
+        let name = file.name().to_string();
+        let content = unpack(file, budget)
+            .map_err(|e| anyhow::anyhow!("Failed to decompress {}: {}", name, e))?;
+        budget = budget.saturating_sub(content.len() as u64);
+        entries.push((name, content));
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn sample() -> Vec<Entry> {
+        let long = format!("repo/{}/deep.md", "nested".repeat(20));
+        vec![
+            ("config.toml".to_string(), b"[agents]\n".to_vec()),
+            ("repo/lang/rust.md".to_string(), b"Rust\n".repeat(300)),
+            (long, Vec::new()),
+        ]
+    }
+
+    #[test]
+    fn test_round_trip_every_format() {
+        let temp_dir = TempDir::new().unwrap();
+        for (format, dest) in [
+            (Format::TarGz, "bundle.tar.gz"),
+            (Format::Zip, "bundle.zip"),
+            (Format::Dir, "bundle"),
+        ] {
+            let dest = temp_dir.path().join(dest);
+            assert_eq!(Format::infer(&dest), format);
+            write(format, &dest, &sample()).unwrap();
+            let mut entries = read(&dest).unwrap();
+            entries.sort();
+            let mut expected = sample();
+            expected.sort();
+            assert_eq!(entries, expected, "{format:?}");
+        }
+    }
+
+    #[test]
+    fn test_unsafe_paths_are_refused() {
+        let temp_dir = TempDir::new().unwrap();
+        let dest = temp_dir.path().join("evil.zip");
+        std::fs::write(
+            &dest,
+            zip(&[("../outside.md".to_string(), Vec::new())]).unwrap(),
+        )
+        .unwrap();
+        let error = read(&dest).unwrap_err();
+        assert_eq!(error.to_string(), "Unsafe path in archive: ../outside.md");
+        assert!(write(Format::Dir, &dest, &[("/etc/x".to_string(), Vec::new())]).is_err());
+    }
+
+    #[test]
+    fn test_unpack_stops_at_the_limit() {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+        encoder.write_all(&vec![0; 4096]).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let unpacked = unpack(flate2::read::GzDecoder::new(compressed.as_slice()), 4096);
+        assert_eq!(unpacked.unwrap().len(), 4096);
+        let error = unpack(flate2::read::GzDecoder::new(compressed.as_slice()), 4095).unwrap_err();
+        assert_eq!(error.to_string(), "Archive unpacks to more than 4095 bytes");
+    }
+}
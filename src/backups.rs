@@ -0,0 +1,227 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::agent::Agent;
+use crate::state::Backup;
+use crate::storage::Storage;
+
+/// Directory next to `config.toml` holding agent files as they were before pmx changed them
+pub(crate) const BACKUPS_DIR: &str = "backups";
+
+/// Keep a copy of `target`, and what pmx had recorded about it, before pmx
+/// overwrites or removes it. Only the newest `[backups] keep` copies per file remain.
+pub fn save(storage: &Storage, agent: Agent, target: &Path) -> crate::Result<()> {
+    let keep = storage.config.backups.keep;
+    if keep == 0 {
+        return Ok(());
+    }
+
+    let created = crate::utils::format_timestamp(std::time::SystemTime::now());
+    let file = copy_of(storage, agent, &created, target)?;
+    push(storage, target, file, created, BTreeMap::new())
+}
+
+/// Like [`save`] for the files pmx is about to write or remove together in the
+/// directory `dir`, such as rule files; restoring `dir` puts them all back
+pub fn save_files(
+    storage: &Storage,
+    agent: Agent,
+    dir: &Path,
+    files: &[PathBuf],
+) -> crate::Result<()> {
+    if storage.config.backups.keep == 0 || files.is_empty() {
+        return Ok(());
+    }
+
+    let created = crate::utils::format_timestamp(std::time::SystemTime::now());
+    let mut copies = BTreeMap::new();
+    for file in files {
+        copies.insert(
+            file.to_string_lossy().to_string(),
+            copy_of(storage, agent, &created, file)?,
+        );
+    }
+    push(storage, dir, None, created, copies)
+}
+
+/// Copy `target` under `backups/`, returning `None` when it does not exist
+fn copy_of(
+    storage: &Storage,
+    agent: Agent,
+    created: &str,
+    target: &Path,
+) -> crate::Result<Option<String>> {
+    match std::fs::read(target) {
+        Ok(content) => Ok(Some(write_copy(storage, agent, created, &content)?)),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Record a backup of `target` along with what pmx had recorded for it, dropping
+/// the oldest beyond `[backups] keep`
+fn push(
+    storage: &Storage,
+    target: &Path,
+    file: Option<String>,
+    created: String,
+    files: BTreeMap<String, Option<String>>,
+) -> crate::Result<()> {
+    let mut dropped = Vec::new();
+    crate::state::update(storage, |state| {
+        let backup = Backup {
+            file,
+            created,
+            applied: state.get(target).cloned(),
+            files,
+        };
+        dropped = state.push_backup(target, backup, storage.config.backups.keep);
+    })?;
+    for backup in dropped {
+        for file in backup
+            .file
+            .into_iter()
+            .chain(backup.files.into_values().flatten())
+        {
+            let _ = std::fs::remove_file(storage.path.join(BACKUPS_DIR).join(file));
+        }
+    }
+    Ok(())
+}
+
+/// Put `target` back as it was before pmx last changed it, removing it if it did not
+/// exist then. Returns the backup that was restored, or `None` when there is none.
+pub fn restore(storage: &Storage, target: &Path) -> crate::Result<Option<Backup>> {
+    let state = crate::state::State::load(&storage.path)?;
+    let Some(backup) = state.last_backup(target).cloned() else {
+        return Ok(None);
+    };
+
+    let copies: Vec<(PathBuf, Option<PathBuf>)> = if backup.files.is_empty() {
+        vec![(target.to_path_buf(), backup.file.clone())]
+    } else {
+        backup
+            .files
+            .iter()
+            .map(|(path, file)| (PathBuf::from(path), file.clone()))
+            .collect()
+    }
+    .into_iter()
+    .map(|(path, file)| {
+        (
+            path,
+            file.map(|file| storage.path.join(BACKUPS_DIR).join(file)),
+        )
+    })
+    .collect();
+    for (path, copy) in &copies {
+        put_back(storage, path, copy.as_deref())?;
+    }
+
+    crate::state::update(storage, |state| {
+        state.pop_backup(target);
+        state.set_applied(target, backup.applied.clone());
+    })?;
+    for copy in copies.into_iter().filter_map(|(_, copy)| copy) {
+        let _ = std::fs::remove_file(copy);
+    }
+    Ok(Some(backup))
+}
+
+/// Write the backup `copy` to `target`, or remove `target` when there is no copy
+fn put_back(storage: &Storage, target: &Path, copy: Option<&Path>) -> crate::Result<()> {
+    match copy {
+        Some(copy) => {
+            let content = std::fs::read(copy)
+                .map_err(|e| anyhow::anyhow!("Failed to read backup {}: {}", copy.display(), e))?;
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| {
+                    anyhow::anyhow!("Failed to create directory {}: {}", parent.display(), e)
+                })?;
+            }
+            crate::utils::write_unlinked(target, content, &storage.path.join("repo"))
+                .map_err(|e| anyhow::anyhow!("Failed to restore {}: {}", target.display(), e))?;
+        }
+        None if std::fs::symlink_metadata(target).is_ok() => std::fs::remove_file(target)
+            .map_err(|e| anyhow::anyhow!("Failed to remove {}: {}", target.display(), e))?,
+        None => {}
+    }
+    Ok(())
+}
+
+/// Write `content` to a new file under `backups/`, returning its name there
+fn write_copy(
+    storage: &Storage,
+    agent: Agent,
+    created: &str,
+    content: &[u8],
+) -> crate::Result<String> {
+    let dir = storage.path.join(BACKUPS_DIR);
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| anyhow::anyhow!("Failed to create directory {}: {}", dir.display(), e))?;
+
+    // Colons are not allowed in file names on Windows
+    let stem = format!("{}-{}", agent.name(), created.replace(':', ""));
+    let mut name = format!("{stem}.md");
+    let mut n = 1;
+    while dir.join(&name).exists() {
+        name = format!("{stem}-{n}.md");
+        n += 1;
+    }
+
+    let path = dir.join(&name);
+    std::fs::write(&path, content)
+        .map_err(|e| anyhow::anyhow!("Failed to write backup {}: {}", path.display(), e))?;
+    Ok(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_save_and_restore_in_order() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::initialize(temp_dir.path().join("storage")).unwrap();
+        let target = temp_dir.path().join("CLAUDE.md");
+
+        // First application: nothing to keep but the fact the file was absent
+        save(&storage, Agent::Claude, &target).unwrap();
+        std::fs::write(&target, "Base\n").unwrap();
+        crate::state::update(&storage, |state| {
+            state.record_set(Agent::Claude, &target, "base");
+            state.record_written(&target, b"Base\n");
+        })
+        .unwrap();
+
+        save(&storage, Agent::Claude, &target).unwrap();
+        std::fs::write(&target, "Rust\n").unwrap();
+        crate::state::update(&storage, |state| {
+            state.record_set(Agent::Claude, &target, "rust")
+        })
+        .unwrap();
+
+        let restored = restore(&storage, &target).unwrap().unwrap();
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "Base\n");
+        assert_eq!(restored.applied.unwrap().profiles, vec!["base"]);
+        let state = crate::state::State::load(&storage.path).unwrap();
+        assert_eq!(state.get(&target).unwrap().profiles, vec!["base"]);
+        assert!(!state.has_drifted(&target, b"Base\n"));
+
+        assert!(restore(&storage, &target).unwrap().unwrap().file.is_none());
+        assert!(!target.exists());
+        assert!(
+            crate::state::State::load(&storage.path)
+                .unwrap()
+                .get(&target)
+                .is_none()
+        );
+        assert!(restore(&storage, &target).unwrap().is_none());
+        assert_eq!(
+            std::fs::read_dir(storage.path.join(BACKUPS_DIR))
+                .unwrap()
+                .count(),
+            0
+        );
+    }
+}
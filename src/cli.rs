@@ -7,64 +7,619 @@ use clap::{Args, Parser, Subcommand};
 #[command(about = "A prompt management suite")]
 #[command(version)]
 pub struct Arg {
-    /// Path to the storage directory
-    #[arg(long)]
-    pub config: Option<PathBuf>,
+    /// Path to the storage directory (`--config` is accepted as an older name)
+    #[arg(long, alias = "config")]
+    pub storage: Option<PathBuf>,
+    /// Append structured (JSON) logs to this file
+    #[arg(long, global = true)]
+    pub log_file: Option<PathBuf>,
+    /// Format of log lines: text or json (stderr defaults to text, log files to json)
+    #[arg(long, global = true, value_enum)]
+    pub log_format: Option<crate::logging::LogFormat>,
+    /// Report more on stderr: -v for progress, -vv for debugging, -vvv for everything
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+    /// Report only errors on stderr
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+    /// Print the agent files that would be created, overwritten or removed without touching them
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+    /// Answer yes to confirmation prompts, e.g. to delete profiles from scripts
+    #[arg(short, long, global = true)]
+    pub yes: bool,
     #[command(subcommand)]
     pub command: Command,
 }
 
+/// Subcommand path of a command line, such as `profile set`, without any
+/// arguments, which may carry profile content or secrets
+pub fn command_name(matches: &clap::ArgMatches) -> String {
+    let mut names = Vec::new();
+    let mut matches = matches;
+    while let Some((name, sub_matches)) = matches.subcommand() {
+        names.push(name);
+        matches = sub_matches;
+    }
+    names.join(" ")
+}
+
 #[derive(Debug, Subcommand)]
 pub enum Command {
     /// Set Claude profile from a stored configuration
-    SetClaudeProfile(ClaudeProfile),
+    SetClaudeProfile(SetProfileArgs),
     /// Reset the current Claude profile
-    ResetClaudeProfile,
+    ResetClaudeProfile(ResetProfileArgs),
+    /// Undo the last change pmx made to the Claude profile
+    RestoreClaudeProfile(ResetProfileArgs),
     /// Append Claude profile to existing configuration
-    AppendClaudeProfile(ClaudeProfile),
+    AppendClaudeProfile(AppendProfileArgs),
+    /// Strip an appended profile from the Claude configuration
+    RemoveClaudeProfile(RemoveProfileArgs),
     /// Set Codex profile from a stored configuration
-    SetCodexProfile(CodexProfile),
+    SetCodexProfile(SetProfileArgs),
     /// Reset the current Codex profile
-    ResetCodexProfile,
+    ResetCodexProfile(ResetProfileArgs),
+    /// Undo the last change pmx made to the Codex profile
+    RestoreCodexProfile(ResetProfileArgs),
     /// Append Codex profile to existing configuration
-    AppendCodexProfile(CodexProfile),
+    AppendCodexProfile(AppendProfileArgs),
+    /// Strip an appended profile from the Codex configuration
+    RemoveCodexProfile(RemoveProfileArgs),
+    /// Set Gemini profile from a stored configuration
+    SetGeminiProfile(SetProfileArgs),
+    /// Reset the current Gemini profile
+    ResetGeminiProfile(ResetProfileArgs),
+    /// Undo the last change pmx made to the Gemini profile
+    RestoreGeminiProfile(ResetProfileArgs),
+    /// Append Gemini profile to existing configuration
+    AppendGeminiProfile(AppendProfileArgs),
+    /// Strip an appended profile from the Gemini configuration
+    RemoveGeminiProfile(RemoveProfileArgs),
+    /// Set Cursor profile from a stored configuration
+    SetCursorProfile(SetProfileArgs),
+    /// Reset the current Cursor profile
+    ResetCursorProfile(ResetProfileArgs),
+    /// Undo the last change pmx made to the Cursor profile
+    RestoreCursorProfile(ResetProfileArgs),
+    /// Append Cursor profile to existing configuration
+    AppendCursorProfile(AppendProfileArgs),
+    /// Strip an appended profile from the Cursor configuration
+    RemoveCursorProfile(RemoveProfileArgs),
+    /// Write profiles as Continue rule files, replacing those pmx wrote before
+    SetContinueProfile(SetProfileArgs),
+    /// Remove the Continue rule files pmx wrote
+    ResetContinueProfile(ResetProfileArgs),
+    /// Undo the last change pmx made to the Continue rules
+    RestoreContinueProfile(ResetProfileArgs),
+    /// Add profiles as Continue rule files, keeping the others
+    AppendContinueProfile(AppendProfileArgs),
+    /// Remove the Continue rule file of a profile
+    RemoveContinueProfile(RemoveProfileArgs),
+    /// Set a profile for any agent, including those declared under `[agents.custom]`
+    SetProfile(AgentSetProfileArgs),
+    /// Reset the profile of any agent
+    ResetProfile(AgentResetProfileArgs),
+    /// Undo the last change pmx made to any agent's profile
+    RestoreProfile(AgentResetProfileArgs),
+    /// Append a profile for any agent
+    AppendProfile(AgentAppendProfileArgs),
+    /// Strip an appended profile from any agent's configuration
+    RemoveProfile(AgentRemoveProfileArgs),
+    /// Apply the profiles pinned in the nearest `.pmx.toml`
+    Apply(ApplyArgs),
+    /// Show the profiles applied to each agent and how they deviate from the nearest `.pmx.toml`
+    Status(StatusArgs),
+    /// Set up storage and apply project pins non-interactively (devcontainers, CI)
+    Bootstrap(BootstrapArgs),
+    /// Create a new storage directory with a commented config.toml
+    Init(InitArgs),
     /// Profile management commands
     #[command(subcommand)]
     Profile(ProfileCommand),
+    /// Apply the profile bundles declared under `[bundles]`
+    #[command(subcommand)]
+    Bundle(BundleCommand),
+    /// Install and manage shared prompt packs
+    #[command(subcommand)]
+    Pack(PackCommand),
+    /// Import profiles from other tools
+    #[command(subcommand)]
+    Import(ImportCommand),
+    /// Exchange the profile repository with a git remote, or update its submodules
+    Sync(SyncArgs),
+    /// Check profiles and installed packs for modified, missing or unexpected files
+    Verify(VerifyArgs),
     /// Generate shell completions
     Completion(CompletionArgs),
+    /// Inspect the pmx configuration
+    #[command(subcommand)]
+    Config(ConfigCommand),
+    /// Print a shell hook that applies `.pmx.toml` pins when changing directories
+    Hook(HookArgs),
+    /// Print a prompt segment showing the profiles applied to each agent
+    ShellPlugin(ShellPluginArgs),
+    /// Apply the current directory's `.pmx.toml` (hidden, run by the shell hook)
+    #[command(hide = true)]
+    InternalHook,
     /// Internal completion commands (hidden)
     #[command(subcommand, hide = true)]
     InternalCompletion(InternalCompletionCommand),
+    /// Refresh the cached release information (hidden)
+    #[command(hide = true)]
+    InternalUpdateCheck,
     /// Run MCP server to expose prompts
     Mcp(McpArgs),
+    /// Print where pmx reads and writes its files
+    Paths,
+    /// Check the storage, configuration, agent files and extensions for problems
+    Doctor,
+    /// Watch the profile repository and report changes as they happen
+    Watch(WatchArgs),
+    /// Show where an agent's prompt file lives and which profile is applied
+    Which(WhichArgs),
+    /// Show how an agent's prompt file differs from a profile
+    Diff(DiffArgs),
+    /// Generate shell aliases for switching profiles
+    #[command(subcommand)]
+    Alias(AliasCommand),
+    /// Rewrite profiles across the repository
+    #[command(subcommand)]
+    Refactor(RefactorCommand),
+    /// Inspect the `<{{VARIABLE}}>` placeholders used by profiles
+    #[command(subcommand)]
+    Vars(VarsCommand),
     /// Execute extension subcommand
     #[command(external_subcommand)]
     Extension(Vec<String>),
 }
 
 #[derive(Debug, Args)]
-pub struct ClaudeProfile {
-    /// Path to the profile to apply
-    pub path: String,
+pub struct SetProfileArgs {
+    /// Profiles to apply, in order
+    #[arg(required = true)]
+    pub profiles: Vec<String>,
+    /// Rewrite the target file even if it already matches the profile or was not written by pmx
+    #[arg(long)]
+    pub force: bool,
+    /// Back up a target file not written by pmx before overwriting it
+    #[arg(long)]
+    pub backup: bool,
+    /// Use the agent's file in the current directory instead of the user-wide one
+    #[arg(long, alias = "local")]
+    pub project: bool,
+    /// Write profiles marked sensitive without asking
+    #[arg(long)]
+    pub allow_sensitive: bool,
+    /// Symlink the agent's file to the profile instead of copying it, so edits
+    /// to the profile show up without applying it again
+    #[arg(long)]
+    pub link: bool,
+}
+
+impl SetProfileArgs {
+    pub fn apply_options(&self) -> crate::commands::apply::ApplyOptions {
+        crate::commands::apply::ApplyOptions {
+            force: self.force,
+            backup: self.backup,
+            interactive: crate::utils::is_interactive(),
+            project: self.project,
+            allow_sensitive: self.allow_sensitive,
+            link: self.link,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct AppendProfileArgs {
+    /// Profiles to apply, in order
+    #[arg(required = true)]
+    pub profiles: Vec<String>,
+    /// Append the profile even if the target already contains it
+    #[arg(long)]
+    pub allow_duplicate: bool,
+    /// Choose which sections of the profile to include and where they go
+    #[arg(short, long)]
+    pub interactive: bool,
+    /// Use the agent's file in the current directory instead of the user-wide one
+    #[arg(long, alias = "local")]
+    pub project: bool,
+    /// Write profiles marked sensitive without asking
+    #[arg(long)]
+    pub allow_sensitive: bool,
+}
+
+impl AppendProfileArgs {
+    pub fn apply_options(&self) -> crate::commands::apply::ApplyOptions {
+        crate::commands::apply::ApplyOptions {
+            allow_duplicate: self.allow_duplicate,
+            choose_sections: self.interactive,
+            interactive: crate::utils::is_interactive(),
+            project: self.project,
+            allow_sensitive: self.allow_sensitive,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct RemoveProfileArgs {
+    /// Name of the appended profile to strip
+    pub name: String,
+    /// Use the agent's file in the current directory instead of the user-wide one
+    #[arg(long, alias = "local")]
+    pub project: bool,
+}
+
+impl RemoveProfileArgs {
+    pub fn apply_options(&self) -> crate::commands::apply::ApplyOptions {
+        crate::commands::apply::ApplyOptions {
+            project: self.project,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct ResetProfileArgs {
+    /// Use the agent's file in the current directory instead of the user-wide one
+    #[arg(long, alias = "local")]
+    pub project: bool,
+}
+
+impl ResetProfileArgs {
+    pub fn apply_options(&self) -> crate::commands::apply::ApplyOptions {
+        crate::commands::apply::ApplyOptions {
+            project: self.project,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct AgentSetProfileArgs {
+    /// Built-in agent, or one declared under `[agents.custom]`
+    #[arg(long)]
+    pub agent: String,
+    #[command(flatten)]
+    pub profile: SetProfileArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct AgentAppendProfileArgs {
+    /// Built-in agent, or one declared under `[agents.custom]`
+    #[arg(long)]
+    pub agent: String,
+    #[command(flatten)]
+    pub profile: AppendProfileArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct AgentRemoveProfileArgs {
+    /// Built-in agent, or one declared under `[agents.custom]`
+    #[arg(long)]
+    pub agent: String,
+    #[command(flatten)]
+    pub profile: RemoveProfileArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct AgentResetProfileArgs {
+    /// Built-in agent, or one declared under `[agents.custom]`
+    #[arg(long)]
+    pub agent: String,
+    #[command(flatten)]
+    pub reset: ResetProfileArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct ApplyArgs {
+    /// Rewrite agent files even if they already match the pinned profiles or were not written by pmx
+    #[arg(long)]
+    pub force: bool,
+    /// Back up agent files not written by pmx before overwriting them
+    #[arg(long)]
+    pub backup: bool,
+    /// Write profiles marked sensitive without asking
+    #[arg(long)]
+    pub allow_sensitive: bool,
+}
+
+impl ApplyArgs {
+    pub fn apply_options(&self) -> crate::commands::apply::ApplyOptions {
+        crate::commands::apply::ApplyOptions {
+            force: self.force,
+            backup: self.backup,
+            interactive: crate::utils::is_interactive(),
+            allow_sensitive: self.allow_sensitive,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct InitArgs {
+    /// Directory to initialize instead of the resolved storage location
+    #[arg(long)]
+    pub path: Option<PathBuf>,
+    /// Add a few example profiles under `examples/`
+    #[arg(long)]
+    pub examples: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct BootstrapArgs {
+    /// Project directory whose `.pmx.toml` pins are applied to its local agent files
+    #[arg(long, default_value = ".")]
+    pub target: PathBuf,
+    /// Profiles to import: a local directory or a git URL. Defaults to the existing storage
+    #[arg(long)]
+    pub source: Option<String>,
+    /// Replace the config.toml of existing storage with the source's when they differ
+    #[arg(long, requires = "source")]
+    pub force: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct WhichArgs {
+    /// Agent to inspect
+    #[arg(value_enum)]
+    pub agent: crate::agent::Agent,
+    /// Resolve the project-local file in the current directory
+    #[arg(long)]
+    pub project: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct DiffArgs {
+    /// Agent whose prompt file to compare
+    #[arg(value_enum)]
+    pub agent: crate::agent::Agent,
+    /// Profile to compare it with
+    pub profile: String,
+    /// Compare the project-local file in the current directory
+    #[arg(long)]
+    pub project: bool,
+    /// Highlight changed words within lines instead of whole lines
+    #[arg(long)]
+    pub word_diff: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AliasCommand {
+    /// Print `alias` definitions to source from a shell startup file
+    Generate(AliasGenerateArgs),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum RefactorCommand {
+    /// Rename a `<{{VARIABLE}}>` placeholder in every profile using it
+    RenameVar(RenameVarArgs),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum VarsCommand {
+    /// List every placeholder with the profiles using it
+    List,
+}
+
+#[derive(Debug, Args)]
+pub struct RenameVarArgs {
+    /// Current placeholder name
+    pub old: String,
+    /// New placeholder name
+    pub new: String,
+    /// Only rewrite profiles whose name matches this glob, e.g. `coding/*`
+    #[arg(long)]
+    pub filter: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct AliasGenerateArgs {
+    /// Profiles to alias (defaults to pinned and most used profiles)
+    pub profiles: Vec<String>,
+    /// How many of the most used profiles to include
+    #[arg(long, default_value_t = 10)]
+    pub limit: usize,
 }
 
 #[derive(Debug, Args)]
-pub struct CodexProfile {
-    /// Path to the profile to apply
-    pub path: String,
+pub struct WatchArgs {
+    /// Check changed profiles for placeholder and front-matter problems
+    #[arg(long)]
+    pub lint: bool,
+    /// Milliseconds without changes before a batch is reported (default: `[watch] debounce_ms`)
+    #[arg(long, value_name = "MS")]
+    pub debounce: Option<u64>,
 }
 
 #[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct CompletionArgs {
     /// Shell to generate completions for
+    #[arg(value_enum, required = true)]
+    pub shell: Option<Shell>,
+    #[command(subcommand)]
+    pub command: Option<CompletionCommand>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CompletionCommand {
+    /// Write the completion script to the shell's per-user completion directory
+    Install(CompletionInstallArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct CompletionInstallArgs {
+    /// Shell to install completions for (detected from $SHELL by default)
+    #[arg(long, value_enum)]
+    pub shell: Option<Shell>,
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum Shell {
+    Zsh,
+}
+
+#[derive(Debug, Args)]
+pub struct HookArgs {
+    /// Shell to generate the hook for
+    #[arg(value_enum)]
+    pub shell: HookShell,
+}
+
+#[derive(Debug, Args)]
+pub struct ShellPluginArgs {
+    /// Shell to generate the plugin for
     #[arg(value_enum)]
-    pub shell: Shell,
+    pub shell: PluginShell,
 }
 
 #[derive(Debug, Clone, clap::ValueEnum)]
-pub enum Shell {
+pub enum PluginShell {
+    Zsh,
+}
+
+#[derive(Debug, Args)]
+pub struct StatusArgs {
+    /// Print `<agent>\t<profiles>\t<state>\t<file>` per agent instead of the project report
+    #[arg(long)]
+    pub porcelain: bool,
+}
+
+#[derive(Debug, Clone, clap::ValueEnum)]
+pub enum HookShell {
     Zsh,
+    Bash,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ImportCommand {
+    /// Import notes from an Obsidian vault or notes directory, updating earlier imports
+    Obsidian(ObsidianImportArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct ObsidianImportArgs {
+    /// Vault or notes directory
+    pub vault: PathBuf,
+    /// TOML rules selecting notes by folder or tag and naming the profiles
+    #[arg(long)]
+    pub map: Option<PathBuf>,
+    /// Overwrite existing profiles that were not imported from the same note
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum BundleCommand {
+    /// Set the bundle's profiles, in order, on one agent or all enabled agents
+    Apply(BundleApplyArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct BundleApplyArgs {
+    /// Name of the bundle under `[bundles]`
+    pub name: String,
+    /// Agent to apply the bundle to, or `all` for every enabled agent
+    #[arg(long, default_value = "all")]
+    pub agent: String,
+    /// Rewrite agent files even if they already match the bundle or were not written by pmx
+    #[arg(long)]
+    pub force: bool,
+    /// Back up agent files not written by pmx before overwriting them
+    #[arg(long)]
+    pub backup: bool,
+    /// Use the agents' files in the current directory instead of the user-wide ones
+    #[arg(long, alias = "local")]
+    pub project: bool,
+    /// Write profiles marked sensitive without asking
+    #[arg(long)]
+    pub allow_sensitive: bool,
+}
+
+impl BundleApplyArgs {
+    pub fn apply_options(&self) -> crate::commands::apply::ApplyOptions {
+        crate::commands::apply::ApplyOptions {
+            force: self.force,
+            backup: self.backup,
+            interactive: crate::utils::is_interactive(),
+            project: self.project,
+            allow_sensitive: self.allow_sensitive,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum PackCommand {
+    /// Install a pack from a directory or git URL
+    Install(PackInstallArgs),
+    /// Create a skeleton for a new pack
+    New(PackNewArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct PackNewArgs {
+    /// Directory to create the pack in
+    pub path: PathBuf,
+    /// Pack name (defaults to the directory name)
+    #[arg(long)]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct PackInstallArgs {
+    /// Local directory or git URL of the pack
+    pub source: String,
+    /// Directory name under the repository (defaults to the source's name)
+    #[arg(long)]
+    pub name: Option<String>,
+    /// Replace an already installed pack with the same name
+    #[arg(long)]
+    pub force: bool,
+    /// Verify SHA256SUMS.minisig with this minisign public key
+    #[arg(long, conflicts_with = "gpg")]
+    pub minisign_key: Option<String>,
+    /// Verify SHA256SUMS.asc with gpg against your keyring
+    #[arg(long)]
+    pub gpg: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Print a JSON Schema for config.toml
+    Schema,
+    /// Print the value of a setting, defaults included
+    Get(ConfigKeyArgs),
+    /// Change a setting in config.toml, refusing values pmx cannot read
+    Set(ConfigSetArgs),
+    /// Remove a setting from config.toml so that its default applies
+    Unset(ConfigKeyArgs),
+    /// Print every setting with its value, defaults included
+    List,
+}
+
+#[derive(Debug, Args)]
+pub struct ConfigKeyArgs {
+    /// Dotted key, e.g. `mcp.log_requests` or `agents.claude.enabled`
+    pub key: String,
+}
+
+#[derive(Debug, Args)]
+pub struct ConfigSetArgs {
+    /// Dotted key, e.g. `mcp.log_requests` or `agents.claude.enabled`
+    pub key: String,
+    /// TOML value (bare words are strings); several values make a list
+    #[arg(required = true)]
+    pub values: Vec<String>,
+    /// Append the values to the list instead of replacing it
+    #[arg(long, conflicts_with = "remove")]
+    pub add: bool,
+    /// Remove the values from the list
+    #[arg(long)]
+    pub remove: bool,
 }
 
 #[derive(Debug, Subcommand)]
@@ -72,15 +627,66 @@ pub enum ProfileCommand {
     /// List all available profiles
     List,
     /// Edit an existing profile using $EDITOR
-    Edit(ProfileArgs),
-    /// Delete a profile (with confirmation)
+    Edit(EditArgs),
+    /// Move a profile to the trash (with confirmation)
     Delete(ProfileArgs),
+    /// List, restore or purge deleted profiles
+    #[command(subcommand)]
+    Trash(TrashCommand),
     /// Create a new profile using $EDITOR
-    Create(ProfileArgs),
+    Create(CreateArgs),
     /// Show profile content
-    Show(ProfileArgs),
+    Show(ShowArgs),
+    /// Render a profile to HTML, optionally opening it in the browser
+    Preview(PreviewArgs),
+    /// Show which profiles include which, flagging cycles and missing profiles
+    Graph(GraphArgs),
     /// Copy profile contents to clipboard
-    Copy(ProfileArgs),
+    #[command(alias = "copy")]
+    Clip(CopyArgs),
+    /// Copy a profile to a new name in the repository
+    Duplicate(DuplicateArgs),
+    /// Save the clipboard's text as a profile
+    Paste(PasteArgs),
+    /// Print a profile with its `<{{VARIABLE}}>` placeholders filled in
+    Render(RenderArgs),
+    /// Normalize profile markdown formatting
+    Fmt(FmtArgs),
+    /// Check profiles for placeholder, include, front-matter and formatting problems
+    Lint(LintArgs),
+    /// Count characters, words and tokens of profiles and applied agent files
+    Stats(StatsArgs),
+    /// Three-way merge two profiles, marking conflicts
+    Merge(MergeArgs),
+    /// Show the differences between two profiles
+    Diff(ProfileDiffArgs),
+    /// List the recorded changes to a profile
+    History(ProfileArgs),
+    /// Restore a profile as it was at an earlier revision
+    Revert(RevertArgs),
+    /// Find profiles whose name or content matches a query
+    Search(SearchArgs),
+    /// Copy a markdown file or a directory of them into the repository
+    Import(ProfileImportArgs),
+    /// Package profiles, and optionally config.toml, into a tar.gz, zip or directory bundle
+    Export(ProfileExportArgs),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum TrashCommand {
+    /// List deleted profiles, most recently deleted first
+    List,
+    /// Put the most recently deleted profile of this name back
+    Restore(ProfileArgs),
+    /// Remove deleted profiles for good (with confirmation)
+    Purge(TrashPurgeArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct TrashPurgeArgs {
+    /// Only remove profiles deleted more than this many days ago
+    #[arg(long, value_name = "DAYS")]
+    pub older_than: Option<u64>,
 }
 
 #[derive(Debug, Args)]
@@ -89,9 +695,383 @@ pub struct ProfileArgs {
     pub name: String,
 }
 
+#[derive(Debug, Args)]
+pub struct SearchArgs {
+    /// Text to look for
+    pub query: String,
+    /// Match regardless of letter case
+    #[arg(short, long)]
+    pub ignore_case: bool,
+    /// Treat the query as a regular expression
+    #[arg(short = 'e', long)]
+    pub regex: bool,
+    /// Lines of context to show around each match
+    #[arg(short = 'C', long, default_value_t = 0)]
+    pub context: usize,
+}
+
+#[derive(Debug, Args)]
+pub struct ProfileImportArgs {
+    /// Markdown file or directory to import
+    pub path: PathBuf,
+    /// Profile name for a file, or the folder to import a directory under
+    #[arg(long = "as", value_name = "NAME")]
+    pub name: Option<String>,
+    /// Import the directory's subdirectories too, keeping their nesting
+    #[arg(short, long)]
+    pub recursive: bool,
+    /// Restore a bundle written by `profile export`
+    #[arg(long, conflicts_with_all = ["name", "recursive"])]
+    pub archive: bool,
+    /// Replace config.toml with the archive's, after confirmation
+    #[arg(long, requires = "archive")]
+    pub with_config: bool,
+    /// Overwrite existing profiles whose content differs
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ProfileExportArgs {
+    /// Archive file or directory to write
+    pub dest: PathBuf,
+    /// Profiles to include (default: all)
+    pub profiles: Vec<String>,
+    /// Bundle format (default: from the destination's extension, else a directory)
+    #[arg(long, value_enum)]
+    pub format: Option<crate::archive::Format>,
+    /// Include config.toml, without its token, sync URL and passphrase command
+    #[arg(long)]
+    pub with_config: bool,
+    /// Overwrite an existing destination
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct RevertArgs {
+    /// Name of the profile
+    pub name: String,
+    /// Revision to restore, as listed by `profile history`
+    #[arg(long)]
+    pub to: String,
+}
+
+#[derive(Debug, Args)]
+pub struct EditArgs {
+    /// Name of the profile
+    pub name: String,
+    /// Editor command to use instead of $EDITOR, e.g. "code --wait"
+    #[arg(long)]
+    pub editor: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct CreateArgs {
+    /// Name of the profile
+    pub name: String,
+    /// Editor command to use instead of $EDITOR, e.g. "code --wait"
+    #[arg(long, conflicts_with = "input")]
+    pub editor: Option<String>,
+    /// Take the content from a file instead of opening an editor
+    #[arg(long, value_name = "PATH", group = "input")]
+    pub from_file: Option<PathBuf>,
+    /// Take the content from standard input instead of opening an editor
+    #[arg(long, group = "input")]
+    pub stdin: bool,
+    /// Start from a template in `templates/` of the storage, or a built-in one
+    /// (architect, code-review, debugging), filling in its placeholders
+    #[arg(long, group = "input")]
+    pub template: Option<String>,
+    /// Value for a template placeholder instead of asking for it (repeatable)
+    #[arg(
+        long = "var",
+        value_name = "KEY=VALUE",
+        value_parser = crate::template::parse_var,
+        requires = "template"
+    )]
+    pub vars: Vec<(String, String)>,
+    /// Overwrite the profile if it already exists
+    #[arg(long, requires = "input")]
+    pub force: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct CopyArgs {
+    /// Profiles to copy, concatenated in order
+    #[arg(required = true)]
+    pub names: Vec<String>,
+    /// Text placed between profiles (`\n` and `\t` are expanded)
+    #[arg(long)]
+    pub separator: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct DuplicateArgs {
+    /// Profile to copy
+    pub from: String,
+    /// Name of the new profile
+    pub to: String,
+}
+
+#[derive(Debug, Args)]
+pub struct PasteArgs {
+    /// Name of the profile
+    pub name: String,
+    /// Add the text to the end of the profile if it already exists
+    #[arg(long, conflicts_with = "force")]
+    pub append: bool,
+    /// Overwrite the profile if it already exists
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct RenderArgs {
+    /// Name of the profile
+    pub name: String,
+    /// Value for a placeholder (repeatable)
+    #[arg(long = "var", value_name = "KEY=VALUE", value_parser = crate::template::parse_var)]
+    pub vars: Vec<(String, String)>,
+    /// Take values for the remaining placeholders from environment variables
+    #[arg(long)]
+    pub env: bool,
+    /// Copy the result to the clipboard instead of printing it
+    #[arg(long)]
+    pub copy: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ShowArgs {
+    /// Name of the profile
+    pub name: String,
+    /// Print the front-matter fields (title, license, source, ...) instead of the content,
+    /// optionally as `--metadata=toml` or `--metadata=json`
+    #[arg(
+        long,
+        value_enum,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "text"
+    )]
+    pub metadata: Option<MetadataFormat>,
+    /// Print the content without its front-matter
+    #[arg(long, conflicts_with = "metadata")]
+    pub body: bool,
+    /// Print the content, includes, template arguments, metadata, size and
+    /// modification time as JSON
+    #[arg(long, conflicts_with_all = ["metadata", "body"])]
+    pub json: bool,
+    /// Show the values of `<{{secret:NAME}}>` placeholders instead of masking them
+    #[arg(long)]
+    pub reveal: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum MetadataFormat {
+    /// One `key: value` line per field
+    Text,
+    Toml,
+    Json,
+}
+
+#[derive(Debug, Args)]
+pub struct SyncArgs {
+    #[command(subcommand)]
+    pub command: Option<SyncCommand>,
+    /// Update git submodules in `repo/` to the tip of their tracked branches
+    #[arg(long, global = true)]
+    pub submodules: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SyncCommand {
+    /// Fill an empty repository with a clone of the `[sync]` remote
+    Clone(SyncCloneArgs),
+    /// Commit local changes and merge the remote's into the repository
+    Pull,
+    /// Commit local changes and send the repository to the remote
+    Push,
+}
+
+#[derive(Debug, Args)]
+pub struct SyncCloneArgs {
+    /// Remote to clone instead of `[sync] url`
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct VerifyArgs {
+    /// Record the repository as it is now instead of checking it
+    #[arg(long)]
+    pub update: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct GraphArgs {
+    /// Print the graph as Graphviz dot
+    #[arg(long)]
+    pub dot: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct PreviewArgs {
+    /// Name of the profile
+    pub name: String,
+    /// Open the rendered page in the default browser instead of printing it
+    #[arg(long)]
+    pub browser: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct FmtArgs {
+    /// Name of the profile to format
+    #[arg(required_unless_present = "all", conflicts_with = "all")]
+    pub name: Option<String>,
+    /// Format every profile in the repository
+    #[arg(long)]
+    pub all: bool,
+    /// Report profiles that need formatting without rewriting them
+    #[arg(long)]
+    pub check: bool,
+    /// Wrap lines to this width (overrides `format.wrap_width` in config.toml)
+    #[arg(long)]
+    pub width: Option<usize>,
+}
+
+#[derive(Debug, Args)]
+pub struct LintArgs {
+    /// Name of the profile to check (default: every profile)
+    pub name: Option<String>,
+    /// Print the problems as a JSON array, for CI
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct StatsArgs {
+    /// Name of the profile to measure (default: every profile and the agent files)
+    pub name: Option<String>,
+    /// How to estimate tokens (default: `[tokens] tokenizer` in config.toml)
+    #[arg(long, value_enum)]
+    pub tokenizer: Option<crate::tokens::Tokenizer>,
+}
+
+#[derive(Debug, Args)]
+pub struct MergeArgs {
+    /// Profile holding our version
+    pub ours: String,
+    /// Profile holding their version
+    pub theirs: String,
+    /// Profile holding the common ancestor of both versions
+    #[arg(long)]
+    pub base: Option<String>,
+    /// Save the merged result as this profile instead of printing it
+    #[arg(long, short)]
+    pub output: Option<String>,
+    /// Save the result with `--output` even when it has conflict markers
+    #[arg(long, requires = "output")]
+    pub force: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ProfileDiffArgs {
+    /// Profile to compare from
+    pub old: String,
+    /// Profile to compare to
+    pub new: String,
+    /// Highlight changed words within lines instead of whole lines
+    #[arg(long)]
+    pub word_diff: bool,
+}
+
 #[derive(Debug, Args)]
 pub struct McpArgs {
-    // No arguments needed - MCP server reads from config.toml
+    /// Runs the server when omitted
+    #[command(subcommand)]
+    pub command: Option<McpCommand>,
+    /// How clients connect to the server
+    #[arg(long, value_enum, default_value_t = McpTransport::Stdio)]
+    pub transport: McpTransport,
+    /// Address to listen on for the `ws` and `http` transports
+    /// [default: `[mcp] bind`, else 127.0.0.1:8765]
+    #[arg(long)]
+    pub bind: Option<String>,
+    /// Port to listen on, replacing the port of the bind address
+    #[arg(long)]
+    pub port: Option<u16>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum McpTransport {
+    /// Serve a single client over stdin/stdout
+    Stdio,
+    /// Accept WebSocket clients on `--bind`
+    Ws,
+    /// Accept Streamable HTTP clients at `/mcp` on `--bind`
+    Http,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum McpCommand {
+    /// Send one request to an MCP server and print the JSON result
+    Call(McpCallArgs),
+    /// Register the pmx server in an MCP client's configuration
+    Install(McpInstallArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct McpInstallArgs {
+    /// Client whose configuration is updated
+    #[arg(long, value_enum)]
+    pub client: McpClient,
+    /// Name of the server entry
+    #[arg(long, default_value = "pmx")]
+    pub name: String,
+    /// Use the user-wide configuration instead of the current project's
+    /// (Claude Code and Cursor)
+    #[arg(long)]
+    pub global: bool,
+    /// Write to this configuration file instead of the client's default
+    #[arg(long)]
+    pub file: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum McpClient {
+    /// `claude_desktop_config.json`
+    ClaudeDesktop,
+    /// `.mcp.json` in the project, or `~/.claude.json` with `--global`
+    ClaudeCode,
+    /// `.cursor/mcp.json` in the project, or `~/.cursor/mcp.json` with `--global`
+    Cursor,
+}
+
+#[derive(Debug, Args)]
+pub struct McpCallArgs {
+    /// Request to send
+    #[arg(value_enum)]
+    pub method: McpMethod,
+    /// Prompt or tool name, required for `prompts/get` and `tools/call`
+    pub name: Option<String>,
+    /// Prompt or tool argument (repeatable)
+    #[arg(long = "arg", value_name = "KEY=VALUE")]
+    pub args: Vec<String>,
+    /// Server command to spawn instead of this pmx's own `mcp` server
+    #[arg(long)]
+    pub server: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum McpMethod {
+    #[value(name = "prompts/list")]
+    PromptsList,
+    #[value(name = "prompts/get")]
+    PromptsGet,
+    #[value(name = "tools/list")]
+    ToolsList,
+    #[value(name = "tools/call")]
+    ToolsCall,
 }
 
 #[derive(Debug, Subcommand)]
@@ -100,8 +1080,50 @@ pub enum InternalCompletionCommand {
     ClaudeProfiles,
     /// List available Codex profiles (internal)
     CodexProfiles,
+    /// List available Gemini profiles (internal)
+    GeminiProfiles,
+    /// List available Cursor profiles (internal)
+    CursorProfiles,
+    /// List available Continue profiles (internal)
+    ContinueProfiles,
     /// List enabled agent commands (internal)
     EnabledCommands,
+    /// List enabled agents, including custom ones (internal)
+    AgentNames,
     /// List available profiles for profile commands (internal)
-    ProfileNames,
+    ProfileNames {
+        /// Complete this partial name one directory at a time, e.g. `design/` before `design/plan`
+        prefix: Option<String>,
+    },
+    /// List the bundles declared in config.toml (internal)
+    #[command(alias = "bundles")]
+    BundleNames,
+    /// List the templates for `profile create --template` (internal)
+    TemplateNames,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_command_name_leaves_out_arguments() {
+        let matches = Arg::command()
+            .try_get_matches_from([
+                "pmx",
+                "profile",
+                "create",
+                "notes",
+                "--from-file",
+                "secret.md",
+            ])
+            .unwrap();
+        assert_eq!(command_name(&matches), "profile create");
+
+        let matches = Arg::command()
+            .try_get_matches_from(["pmx", "--yes", "set-claude-profile", "work"])
+            .unwrap();
+        assert_eq!(command_name(&matches), "set-claude-profile");
+    }
 }
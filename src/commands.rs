@@ -1,6 +1,36 @@
+pub mod alias;
+pub mod apply;
+pub mod bootstrap;
+pub mod bundle;
 pub mod claude_code;
+pub mod config;
+pub mod continue_dev;
+pub mod cursor;
+pub mod diff;
+pub mod doctor;
+pub mod export;
 pub mod extensions;
+pub mod gemini;
+pub mod graph;
+pub mod hook;
+pub mod import;
+pub mod init;
 pub mod mcp;
+pub mod mcp_client;
+pub mod mcp_http;
+pub mod mcp_install;
+pub mod mcp_ws;
 pub mod openai_codex;
+pub mod pack;
+pub mod paths;
+pub mod preview;
 pub mod profile;
+pub mod project;
+pub mod refactor;
+pub mod rules;
+pub mod sync;
 pub mod utils;
+pub mod vars;
+pub mod verify;
+pub mod watch;
+pub mod which;
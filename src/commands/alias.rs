@@ -0,0 +1,135 @@
+use std::fmt::Write;
+
+use crate::agent::Agent;
+use crate::project::ProjectConfig;
+use crate::storage::Storage;
+
+/// Print alias definitions for `profiles`, or for the pinned and most used profiles
+pub fn generate(storage: &Storage, profiles: &[String], limit: usize) -> crate::Result<()> {
+    let profiles = if profiles.is_empty() {
+        frequent_profiles(storage, limit)?
+    } else {
+        for profile in profiles {
+            anyhow::ensure!(
+                storage.profile_exists(profile),
+                "Profile not found: {}",
+                profile
+            );
+        }
+        profiles.to_vec()
+    };
+
+    if profiles.is_empty() {
+        tracing::warn!("No pinned or previously applied profiles; pass profile names explicitly.");
+        return Ok(());
+    }
+
+    let agents: Vec<Agent> = Agent::all(storage)?
+        .into_iter()
+        .filter(|agent| agent.is_enabled(storage))
+        .collect();
+    print!("{}", aliases(&agents, &profiles)?);
+    Ok(())
+}
+
+/// Profiles pinned in the nearest `.pmx.toml`, followed by the most applied ones
+fn frequent_profiles(storage: &Storage, limit: usize) -> crate::Result<Vec<String>> {
+    let mut profiles = Vec::new();
+
+    let cwd = std::env::current_dir()
+        .map_err(|e| anyhow::anyhow!("Failed to get current directory: {}", e))?;
+    if let Some((_, project)) = ProjectConfig::find(&cwd)? {
+        for (_, pinned) in project.pins()? {
+            profiles.extend(pinned.iter().cloned());
+        }
+    }
+
+    let state = crate::state::State::load(&storage.path)?;
+    profiles.extend(state.most_used(limit).into_iter().map(str::to_string));
+
+    let mut seen = std::collections::HashSet::new();
+    profiles.retain(|profile| storage.profile_exists(profile) && seen.insert(profile.clone()));
+    Ok(profiles)
+}
+
+/// Render one `alias` line per agent and profile, e.g.
+/// `alias cc-work-default='pmx set-claude-profile work/default'`
+fn aliases(agents: &[Agent], profiles: &[String]) -> crate::Result<String> {
+    let mut output = String::new();
+    for profile in profiles {
+        for agent in agents {
+            let command = match agent {
+                Agent::Custom(custom) => format!(
+                    "pmx set-profile --agent {} {}",
+                    custom.name,
+                    shell_quote(profile)
+                ),
+                agent => format!("pmx set-{}-profile {}", agent.name(), shell_quote(profile)),
+            };
+            writeln!(
+                output,
+                "alias {}-{}={}",
+                alias_prefix(*agent),
+                alias_name(profile),
+                shell_quote(&command)
+            )?;
+        }
+    }
+    Ok(output)
+}
+
+fn alias_prefix(agent: Agent) -> &'static str {
+    match agent {
+        Agent::Claude => "cc",
+        Agent::Codex => "cx",
+        Agent::Gemini => "gm",
+        Agent::Cursor => "cu",
+        Agent::Continue => "cn",
+        Agent::Custom(custom) => &custom.name,
+    }
+}
+
+/// Turn a profile path into something usable as an alias name
+fn alias_name(profile: &str) -> String {
+    profile
+        .split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Single-quote `value` unless it only contains characters the shell leaves alone
+fn shell_quote(value: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "_-./".contains(c);
+    if !value.is_empty() && value.chars().all(safe) {
+        return value.to_string();
+    }
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alias_name() {
+        assert_eq!(alias_name("work/default"), "work-default");
+        assert_eq!(alias_name("My Notes.v2"), "My-Notes-v2");
+    }
+
+    #[test]
+    fn test_aliases() {
+        let output = aliases(
+            &[Agent::Claude, Agent::Codex],
+            &["work/default".to_string(), "it's".to_string()],
+        )
+        .unwrap();
+        assert_eq!(
+            output,
+            "alias cc-work-default='pmx set-claude-profile work/default'\n\
+             alias cx-work-default='pmx set-codex-profile work/default'\n\
+             alias cc-it-s='pmx set-claude-profile '\\''it'\\''\\'\\'''\\''s'\\'''\n\
+             alias cx-it-s='pmx set-codex-profile '\\''it'\\''\\'\\'''\\''s'\\'''\n"
+        );
+    }
+}
@@ -0,0 +1,2088 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::ensure;
+
+use crate::agent::Agent;
+use crate::storage::{ApplyMode, Storage};
+
+/// Behaviour switches shared by the agent apply commands
+#[derive(Debug, Clone, Default)]
+pub struct ApplyOptions {
+    /// Rewrite the target even when it already matches the profile or was not written by pmx
+    pub force: bool,
+    /// Append a profile even when the target already contains it
+    pub allow_duplicate: bool,
+    /// Copy a target not written by pmx aside before overwriting it
+    pub backup: bool,
+    /// Ask on the terminal instead of refusing when the target needs a decision
+    pub interactive: bool,
+    /// When appending, pick the profile's sections and where they go in the target
+    pub choose_sections: bool,
+    /// Use the agent's file in the current directory instead of the user-wide one
+    pub project: bool,
+    /// Print the files that would be created, overwritten or removed instead of touching them
+    pub dry_run: bool,
+    /// Write sensitive profiles without asking
+    pub allow_sensitive: bool,
+    /// Symlink the target to the profile file instead of writing a copy
+    pub link: bool,
+}
+
+/// What to do with a target that was edited since pmx last wrote it
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Resolution {
+    Overwrite,
+    /// The edits were saved as this profile, so the new content must be rendered again
+    Adopted(String),
+    Append,
+    Abort,
+}
+
+/// What an apply command did to an agent's files, or would do with `--dry-run`.
+/// It displays as the line `pmx` prints for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// `target` now holds `label`, such as `profile 'rust'`
+    Applied {
+        target: PathBuf,
+        label: String,
+    },
+    /// `target` already held `label` and was not rewritten
+    UpToDate {
+        target: PathBuf,
+        label: String,
+    },
+    /// `target` was left alone, e.g. because a prompt was declined
+    Unchanged(PathBuf),
+    /// `profile` was appended to `target` as `appended` says
+    Appended {
+        target: PathBuf,
+        profile: String,
+        appended: Appended,
+    },
+    /// What pmx wrote for `profile` was taken out of `target`
+    Removed {
+        target: PathBuf,
+        profile: String,
+    },
+    /// `target` is now a symbolic link to `file`, the file of `profile`
+    Linked {
+        target: PathBuf,
+        file: PathBuf,
+        profile: String,
+    },
+    AlreadyLinked {
+        target: PathBuf,
+        file: PathBuf,
+    },
+    /// `target` was not written by pmx and was copied to `backup` first
+    BackedUp {
+        target: PathBuf,
+        backup: PathBuf,
+    },
+    /// The edits made to `target` since pmx wrote it were saved as `profile`
+    Adopted {
+        target: PathBuf,
+        profile: String,
+    },
+    /// `target` was removed
+    Reset {
+        agent: Agent,
+        target: PathBuf,
+    },
+    /// There was nothing at `target` to reset
+    AlreadyReset {
+        agent: Agent,
+        target: PathBuf,
+    },
+    /// `removed` rule files pmx wrote to `dir` were removed
+    RulesReset {
+        agent: Agent,
+        dir: PathBuf,
+        removed: usize,
+    },
+    /// A rule file was edited since pmx wrote it, so it was kept and pmx no
+    /// longer manages it
+    Kept(PathBuf),
+    /// `target` was put back as `backup` recorded it
+    Restored {
+        target: PathBuf,
+        backup: crate::state::Backup,
+    },
+    /// The agent is disabled in the configuration, so its pinned profiles were skipped
+    Skipped(Agent),
+    /// `target` would be written with `label`; `before` is its current content
+    WouldWrite {
+        target: PathBuf,
+        label: String,
+        before: Option<String>,
+        after: String,
+    },
+    WouldBackUp {
+        target: PathBuf,
+        backup: PathBuf,
+    },
+    WouldLink {
+        target: PathBuf,
+        file: PathBuf,
+    },
+    WouldRemove(PathBuf),
+    /// A rule file would be kept, as it was edited since pmx wrote it
+    WouldKeep(PathBuf),
+}
+
+impl std::fmt::Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Change::Applied { target, label } => {
+                write!(f, "Successfully applied {} to {}", label, target.display())
+            }
+            Change::UpToDate { target, label } => write!(
+                f,
+                "{} is already up to date with {} (use --force to rewrite)",
+                target.display(),
+                label
+            ),
+            Change::Unchanged(target) => write!(f, "Left {} unchanged", target.display()),
+            Change::Appended {
+                target,
+                profile,
+                appended,
+            } => match appended {
+                Appended::Present => write!(
+                    f,
+                    "Profile '{}' is already present in {} (use --allow-duplicate to append it again)",
+                    profile,
+                    target.display()
+                ),
+                Appended::Updated => write!(
+                    f,
+                    "Updated profile '{}' in place in {}",
+                    profile,
+                    target.display()
+                ),
+                Appended::Added => write!(
+                    f,
+                    "Successfully appended profile '{}' to {}",
+                    profile,
+                    target.display()
+                ),
+                Appended::Created => write!(
+                    f,
+                    "Successfully created profile '{}' at {} (no existing profile found)",
+                    profile,
+                    target.display()
+                ),
+            },
+            Change::Removed { target, profile } => {
+                write!(f, "Removed profile '{}' from {}", profile, target.display())
+            }
+            Change::Linked {
+                target,
+                file,
+                profile,
+            } => write!(
+                f,
+                "Linked {} to {} (profile '{}')",
+                target.display(),
+                file.display(),
+                profile
+            ),
+            Change::AlreadyLinked { target, file } => write!(
+                f,
+                "{} is already linked to {}",
+                target.display(),
+                file.display()
+            ),
+            Change::BackedUp { target, backup } => {
+                write!(f, "Backed up {} to {}", target.display(), backup.display())
+            }
+            Change::Adopted { target, profile } => write!(
+                f,
+                "Saved the edits to {} as profile '{}'",
+                target.display(),
+                profile
+            ),
+            Change::Reset { agent, target } => write!(
+                f,
+                "Successfully reset {} profile (removed {})",
+                agent.display_name(),
+                target.display()
+            ),
+            Change::AlreadyReset { agent, target } => write!(
+                f,
+                "No {} profile found at {} (already reset)",
+                agent.display_name(),
+                target.display()
+            ),
+            Change::RulesReset {
+                agent,
+                dir,
+                removed,
+            } => write!(
+                f,
+                "Successfully reset {} profile (removed {} rule files from {})",
+                agent.display_name(),
+                removed,
+                dir.display()
+            ),
+            Change::Kept(path) => write!(
+                f,
+                "Kept {}, which was edited since pmx wrote it; pmx no longer manages it",
+                path.display()
+            ),
+            Change::Restored { target, backup } => {
+                let profiles = backup
+                    .applied
+                    .as_ref()
+                    .map(|applied| format!(" (profile {})", applied.profiles.join(" + ")))
+                    .unwrap_or_default();
+                match backup.file {
+                    _ if !backup.files.is_empty() => write!(
+                        f,
+                        "Restored the rules in {} as they were on {}{profiles}",
+                        target.display(),
+                        backup.created
+                    ),
+                    Some(_) => write!(
+                        f,
+                        "Restored {} as it was on {}{profiles}",
+                        target.display(),
+                        backup.created
+                    ),
+                    None => write!(
+                        f,
+                        "Removed {}, which did not exist before pmx wrote it",
+                        target.display()
+                    ),
+                }
+            }
+            Change::Skipped(agent) => {
+                write!(
+                    f,
+                    "Skipping {}: disabled in the configuration",
+                    agent.name()
+                )
+            }
+            Change::WouldWrite {
+                target,
+                label,
+                before: None,
+                ..
+            } => write!(f, "Would create {} with {}", target.display(), label),
+            Change::WouldWrite {
+                target,
+                label,
+                before: Some(before),
+                after,
+            } => {
+                write!(f, "Would overwrite {} with {}", target.display(), label)?;
+                let diff = crate::diff::render(
+                    before,
+                    after,
+                    &target.display().to_string(),
+                    label,
+                    &crate::diff::DiffOptions {
+                        color: crate::utils::use_color(),
+                        ..Default::default()
+                    },
+                );
+                if !diff.is_empty() {
+                    write!(f, "\n{}", diff.trim_end_matches('\n'))?;
+                }
+                Ok(())
+            }
+            Change::WouldBackUp { target, backup } => write!(
+                f,
+                "Would back up {} to {}",
+                target.display(),
+                backup.display()
+            ),
+            Change::WouldLink { target, file } => {
+                write!(f, "Would link {} to {}", target.display(), file.display())
+            }
+            Change::WouldRemove(path) => write!(f, "Would remove {}", path.display()),
+            Change::WouldKeep(path) => write!(
+                f,
+                "Would keep {}, edited since pmx wrote it",
+                path.display()
+            ),
+        }
+    }
+}
+
+/// Replace the agent's file with `profiles`, the first as it is and the rest in
+/// marked blocks
+pub fn set_profile(
+    storage: &Storage,
+    agent: Agent,
+    profiles: &[String],
+    options: &ApplyOptions,
+) -> crate::Result<Vec<Change>> {
+    ensure_enabled(storage, agent)?;
+    let target = agent.target(storage, options.project)?;
+    set_profile_at(storage, agent, profiles, &target, options)
+}
+
+/// Add `profiles` to the agent's file in marked blocks, in order
+pub fn append_profile(
+    storage: &Storage,
+    agent: Agent,
+    profiles: &[String],
+    options: &ApplyOptions,
+) -> crate::Result<Vec<Change>> {
+    ensure_enabled(storage, agent)?;
+    let target = agent.target(storage, options.project)?;
+    append_profile_at(storage, agent, profiles, &target, options)
+}
+
+/// Set the first profile and append the rest, e.g. for pinned project profiles
+pub fn apply_profiles(
+    storage: &Storage,
+    agent: Agent,
+    profiles: &[String],
+    options: &ApplyOptions,
+) -> crate::Result<Vec<Change>> {
+    ensure_enabled(storage, agent)?;
+    let target = agent.global_target(storage)?;
+    apply_profiles_at(storage, agent, profiles, &target, options)
+}
+
+/// Like `apply_profiles`, but writes the agent's file inside `project_dir`
+pub fn apply_profiles_to_project(
+    storage: &Storage,
+    agent: Agent,
+    profiles: &[String],
+    project_dir: &Path,
+    options: &ApplyOptions,
+) -> crate::Result<Vec<Change>> {
+    ensure_enabled(storage, agent)?;
+    let target = agent.project_target(project_dir);
+    apply_profiles_at(storage, agent, profiles, &target, options)
+}
+
+/// Reset the user-wide file, or the one in the current directory when `project` is set
+pub fn reset_profile(
+    storage: &Storage,
+    agent: Agent,
+    options: &ApplyOptions,
+) -> crate::Result<Vec<Change>> {
+    ensure_enabled(storage, agent)?;
+    let target = agent.target(storage, options.project)?;
+    reset_profile_at(storage, agent, &target, options)
+}
+
+/// Strip the marked block `profile` was appended in from the agent's file
+pub fn remove_profile(
+    storage: &Storage,
+    agent: Agent,
+    profile: &str,
+    options: &ApplyOptions,
+) -> crate::Result<Vec<Change>> {
+    ensure_enabled(storage, agent)?;
+    let target = agent.target(storage, options.project)?;
+    remove_profile_at(storage, agent, profile, &target, options)
+}
+
+fn remove_profile_at(
+    storage: &Storage,
+    agent: Agent,
+    profile: &str,
+    target: &Path,
+    options: &ApplyOptions,
+) -> crate::Result<Vec<Change>> {
+    if agent.has_rules_dir() {
+        return crate::commands::rules::remove(storage, agent, profile, target, options);
+    }
+    let existing = std::fs::read_to_string(target).map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to read {} profile at {}: {}",
+            agent.display_name(),
+            target.display(),
+            e
+        )
+    })?;
+    let Some(range) = crate::markers::find_block(&existing, profile) else {
+        anyhow::bail!(
+            "Profile '{}' was not appended to {}",
+            profile,
+            target.display()
+        );
+    };
+    let updated = remove_block(&existing, range);
+
+    if options.dry_run {
+        let label = format!("profile '{profile}' removed");
+        return Ok(vec![preview_write(target, &updated, &label)]);
+    }
+    crate::backups::save(storage, agent, target)?;
+    crate::utils::write_unlinked(target, &updated, &storage.path.join("repo"))
+        .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", target.display(), e))?;
+    crate::state::update(storage, |state| {
+        state.record_remove(target, profile);
+        state.record_written(target, updated.as_bytes());
+    })?;
+    Ok(vec![Change::Removed {
+        target: target.to_path_buf(),
+        profile: profile.to_string(),
+    }])
+}
+
+/// `content` without the block at `range`, dropping the blank line that
+/// separated it from what came before
+fn remove_block(content: &str, range: std::ops::Range<usize>) -> String {
+    let before = &content[..range.start];
+    let after = &content[range.end..];
+    if after.trim().is_empty() {
+        let before = before.trim_end();
+        return if before.is_empty() {
+            String::new()
+        } else {
+            format!("{before}\n")
+        };
+    }
+    let before = match before.ends_with("\n\n") {
+        true => &before[..before.len() - 1],
+        false => before,
+    };
+    format!("{before}{after}")
+}
+
+/// Undo the last change pmx made to the agent's file, from the backup taken before it
+pub fn restore_profile(
+    storage: &Storage,
+    agent: Agent,
+    project: bool,
+) -> crate::Result<Vec<Change>> {
+    ensure_enabled(storage, agent)?;
+    let target = agent.target(storage, project)?;
+    let Some(backup) = crate::backups::restore(storage, &target)? else {
+        anyhow::bail!("No backup of {} to restore", target.display());
+    };
+    Ok(vec![Change::Restored { target, backup }])
+}
+
+fn ensure_enabled(storage: &Storage, agent: Agent) -> crate::Result<()> {
+    ensure!(
+        agent.is_enabled(storage),
+        "{} profiles are disabled in the configuration.",
+        agent.display_name()
+    );
+    Ok(())
+}
+
+pub(crate) fn check_profile(storage: &Storage, profile: &str) -> crate::Result<()> {
+    storage.get_repo_path(profile).map(|_| ()).map_err(|_| {
+        anyhow::anyhow!(
+            "Profile '{}' not found in {}",
+            profile,
+            storage.path.join("repo").display()
+        )
+    })
+}
+
+/// Whether sensitive profiles among `profiles` may be written to `target`: when
+/// allowed up front or confirmed on the terminal. Refuses outside a terminal.
+pub(crate) fn confirm_sensitive(
+    storage: &Storage,
+    profiles: &[String],
+    target: &Path,
+    options: &ApplyOptions,
+) -> crate::Result<bool> {
+    let sensitive: Vec<String> = profiles
+        .iter()
+        .filter(|profile| crate::crypto::is_sensitive(storage, profile))
+        .map(|profile| format!("'{profile}'"))
+        .collect();
+    if sensitive.is_empty() || options.allow_sensitive {
+        return Ok(true);
+    }
+    let names = sensitive.join(", ");
+    ensure!(
+        options.interactive,
+        "Profile {} is sensitive; pass --allow-sensitive to write it to {}",
+        names,
+        target.display()
+    );
+    dialoguer::Confirm::new()
+        .with_prompt(format!(
+            "Profile {} is sensitive. Write it to {}?",
+            names,
+            target.display()
+        ))
+        .default(false)
+        .interact()
+        .map_err(|e| anyhow::anyhow!("Failed to get confirmation: {}", e))
+}
+
+/// Warn when an expired or overdue profile is applied, unless `[review]` turns it off
+pub(crate) fn warn_if_stale(storage: &Storage, profile: &str) {
+    if !storage.config.review.warn_on_apply {
+        return;
+    }
+    match storage.staleness(profile) {
+        Some(crate::frontmatter::Staleness::Expired(date)) => {
+            tracing::warn!("profile '{profile}' expired on {date}")
+        }
+        Some(crate::frontmatter::Staleness::ReviewDue(date)) => {
+            tracing::warn!("profile '{profile}' was due for review on {date}")
+        }
+        None => {}
+    }
+}
+
+/// What writing `content` to `target` would do, for `--dry-run`
+pub(crate) fn preview_write(target: &Path, content: &str, label: &str) -> Change {
+    Change::WouldWrite {
+        target: target.to_path_buf(),
+        label: label.to_string(),
+        before: std::fs::read_to_string(target).ok(),
+        after: content.to_string(),
+    }
+}
+
+fn create_parent_dir(target: &Path) -> crate::Result<()> {
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| {
+            anyhow::anyhow!("Failed to create directory {}: {}", parent.display(), e)
+        })?;
+    }
+    Ok(())
+}
+
+/// Warn about or refuse content that exceeds the configured `[limits]`
+pub(crate) fn check_size_limits(
+    storage: &Storage,
+    target: &Path,
+    content: &str,
+) -> crate::Result<()> {
+    let limits = &storage.config.limits;
+    let bytes = content.len();
+    let tokens = storage.config.tokens.tokenizer.count(content);
+
+    if let Some(max_bytes) = limits.max_bytes {
+        ensure!(
+            bytes <= max_bytes,
+            "Refusing to write {}: {} bytes exceeds limits.max_bytes ({})",
+            target.display(),
+            bytes,
+            max_bytes
+        );
+    }
+    if let Some(max_tokens) = limits.max_tokens {
+        ensure!(
+            tokens <= max_tokens,
+            "Refusing to write {}: ~{} tokens exceeds limits.max_tokens ({})",
+            target.display(),
+            tokens,
+            max_tokens
+        );
+    }
+
+    if let Some(warn_bytes) = limits.warn_bytes
+        && bytes > warn_bytes
+    {
+        tracing::warn!(
+            "{} will be {} bytes, above limits.warn_bytes ({})",
+            target.display(),
+            bytes,
+            warn_bytes
+        );
+    }
+    if let Some(warn_tokens) = limits.warn_tokens
+        && tokens > warn_tokens
+    {
+        tracing::warn!(
+            "{} will be ~{} tokens, above limits.warn_tokens ({})",
+            target.display(),
+            tokens,
+            warn_tokens
+        );
+    }
+
+    Ok(())
+}
+
+/// Whether `target` may be overwritten: it is empty, recorded in the state, or
+/// carries the pmx banner or marker blocks
+fn is_managed(storage: &Storage, target: &Path, content: &str) -> crate::Result<bool> {
+    if content.trim().is_empty()
+        || crate::markers::has_banner(&storage.config.banner.text, content)
+        || !crate::markers::profiles(content).is_empty()
+    {
+        return Ok(true);
+    }
+    let state = crate::state::State::load(&storage.path)?;
+    Ok(state.get(target).is_some())
+}
+
+/// Refuse to overwrite a hand-written `target` unless forced, backing it up first
+/// when asked to (or when the user agrees at the prompt)
+pub(crate) fn protect_unmanaged(
+    storage: &Storage,
+    target: &Path,
+    options: &ApplyOptions,
+) -> crate::Result<Option<Change>> {
+    let Ok(existing) = std::fs::read(target) else {
+        return Ok(None);
+    };
+    if options.force || is_managed(storage, target, &String::from_utf8_lossy(&existing))? {
+        return Ok(None);
+    }
+
+    tracing::warn!("{} was not written by pmx", target.display());
+    let backup = options.backup
+        || (options.interactive
+            && !options.dry_run
+            && dialoguer::Confirm::new()
+                .with_prompt("Back it up before overwriting it?")
+                .default(true)
+                .interact()
+                .map_err(|e| anyhow::anyhow!("Failed to get confirmation: {}", e))?);
+    ensure!(
+        backup,
+        "Refusing to overwrite {}; rerun with --backup to keep a copy or --force to overwrite it",
+        target.display()
+    );
+
+    let backup_path = backup_path(target);
+    if options.dry_run {
+        return Ok(Some(Change::WouldBackUp {
+            target: target.to_path_buf(),
+            backup: backup_path,
+        }));
+    }
+    std::fs::copy(target, &backup_path).map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to back up {} to {}: {}",
+            target.display(),
+            backup_path.display(),
+            e
+        )
+    })?;
+    Ok(Some(Change::BackedUp {
+        target: target.to_path_buf(),
+        backup: backup_path,
+    }))
+}
+
+/// `<target>.bak`, or `<target>.bak.N` for the first free N when that exists
+fn backup_path(target: &Path) -> PathBuf {
+    let mut path = target.as_os_str().to_owned();
+    path.push(".bak");
+    let mut candidate = PathBuf::from(&path);
+    let mut n = 1;
+    while candidate.exists() {
+        let mut numbered = path.clone();
+        numbered.push(format!(".{n}"));
+        candidate = PathBuf::from(numbered);
+        n += 1;
+    }
+    candidate
+}
+
+/// Decide how to treat `target` when it was edited since pmx last wrote it, asking
+/// the user to overwrite, append, adopt the edits as a profile or abort
+fn resolve_drift(
+    storage: &Storage,
+    profile: &str,
+    target: &Path,
+    content: &str,
+    options: &ApplyOptions,
+) -> crate::Result<Resolution> {
+    let Ok(existing) = std::fs::read(target) else {
+        return Ok(Resolution::Overwrite);
+    };
+    let state = crate::state::State::load(&storage.path)?;
+    if options.force || !state.has_drifted(target, &existing) {
+        return Ok(Resolution::Overwrite);
+    }
+
+    let applied = state
+        .get(target)
+        .map(|a| a.profiles.clone())
+        .unwrap_or_default();
+    if options.dry_run {
+        tracing::warn!(
+            "{} was edited since pmx applied {}; applying would need --force or a choice at the prompt",
+            target.display(),
+            applied.join(" + ")
+        );
+        return Ok(Resolution::Overwrite);
+    }
+    ensure!(
+        options.interactive,
+        "{} was edited since pmx applied {}; rerun with --force to overwrite it",
+        target.display(),
+        applied.join(" + ")
+    );
+    tracing::warn!(
+        "{} was edited since pmx applied {}",
+        target.display(),
+        applied.join(" + ")
+    );
+
+    let existing = String::from_utf8_lossy(&existing).into_owned();
+    let choices = [
+        "Show diff",
+        "Overwrite the edits",
+        "Append the profile, keeping the edits",
+        "Adopt the edits into a profile, then overwrite",
+        "Abort",
+    ];
+    loop {
+        let choice = dialoguer::Select::new()
+            .with_prompt(format!("How should profile '{profile}' be applied?"))
+            .items(&choices)
+            .default(0)
+            .interact()
+            .map_err(|e| anyhow::anyhow!("Failed to get selection: {}", e))?;
+
+        match choice {
+            // Shown with the prompt on stderr, as part of the question
+            0 => eprint!(
+                "{}",
+                crate::diff::render(
+                    &existing,
+                    content,
+                    &target.display().to_string(),
+                    &format!("profile {profile}"),
+                    &crate::diff::DiffOptions {
+                        color: true,
+                        ..Default::default()
+                    },
+                )
+            ),
+            1 => return Ok(Resolution::Overwrite),
+            2 => return Ok(Resolution::Append),
+            3 => {
+                let name: String = dialoguer::Input::new()
+                    .with_prompt("Save the edits as profile")
+                    .default(
+                        applied
+                            .first()
+                            .cloned()
+                            .unwrap_or_else(|| profile.to_string()),
+                    )
+                    .interact_text()
+                    .map_err(|e| anyhow::anyhow!("Failed to get profile name: {}", e))?;
+                crate::commands::profile::validate_profile_name(&name)?;
+                let edits = crate::markers::strip_banner(&storage.config.banner.text, &existing);
+                storage.create_profile(&name, edits)?;
+                return Ok(Resolution::Adopted(name));
+            }
+            _ => return Ok(Resolution::Abort),
+        }
+    }
+}
+
+/// `profile 'a'` for one profile, `profiles a + b` for several
+pub(crate) fn describe_profiles(profiles: &[String]) -> String {
+    match profiles {
+        [profile] => format!("profile '{profile}'"),
+        profiles => format!("profiles {}", profiles.join(" + ")),
+    }
+}
+
+fn set_profile_at(
+    storage: &Storage,
+    agent: Agent,
+    profiles: &[impl AsRef<str>],
+    target: &Path,
+    options: &ApplyOptions,
+) -> crate::Result<Vec<Change>> {
+    let profiles: Vec<String> = profiles.iter().map(|p| p.as_ref().to_string()).collect();
+    if agent.has_rules_dir() {
+        return crate::commands::rules::write(storage, agent, &profiles, target, options, true);
+    }
+    ensure!(!profiles.is_empty(), "No profile to apply");
+    let label = describe_profiles(&profiles);
+
+    let mode = storage.config.agents.get(agent).map(|own| own.mode);
+    if options.link || mode == Some(ApplyMode::Link) {
+        ensure!(
+            profiles.len() == 1,
+            "Cannot link {} to {}: only a single profile can be linked",
+            label,
+            target.display()
+        );
+        return link_profile_at(storage, agent, &profiles[0], target, options);
+    }
+
+    let mut content = render_profiles(storage, agent, &profiles)?;
+    for profile in &profiles {
+        warn_if_stale(storage, profile);
+    }
+
+    // Leave the target (and its mtime) alone when it already holds this content
+    let up_to_date = !options.force
+        && crate::utils::symlink_target(target).is_none()
+        && std::fs::read(target)
+            .map(|existing| {
+                crate::utils::content_hash(&existing)
+                    == crate::utils::content_hash(content.as_bytes())
+            })
+            .unwrap_or(false);
+
+    let mut changes = Vec::new();
+    if !up_to_date {
+        match resolve_drift(storage, &profiles.join(" + "), target, &content, options)? {
+            Resolution::Overwrite => {}
+            Resolution::Adopted(profile) => {
+                changes.push(Change::Adopted {
+                    target: target.to_path_buf(),
+                    profile,
+                });
+                content = render_profiles(storage, agent, &profiles)?;
+            }
+            Resolution::Append => {
+                return append_profile_at(storage, agent, &profiles, target, options);
+            }
+            Resolution::Abort => return Ok(vec![Change::Unchanged(target.to_path_buf())]),
+        }
+
+        changes.extend(protect_unmanaged(storage, target, options)?);
+        check_size_limits(storage, target, &content)?;
+        if options.dry_run {
+            changes.push(preview_write(target, &content, &label));
+            return Ok(changes);
+        }
+        if !confirm_sensitive(storage, &profiles, target, options)? {
+            changes.push(Change::Unchanged(target.to_path_buf()));
+            return Ok(changes);
+        }
+        create_parent_dir(target)?;
+        crate::backups::save(storage, agent, target)?;
+        crate::utils::write_unlinked(target, &content, &storage.path.join("repo"))
+            .map_err(|e| anyhow::anyhow!("Failed to apply {}: {}", label, e))?;
+    }
+
+    if !options.dry_run {
+        crate::state::update(storage, |state| {
+            state.record_set(agent, target, &profiles[0]);
+            for profile in &profiles[1..] {
+                state.record_append(agent, target, profile);
+            }
+            state.record_written(target, content.as_bytes());
+        })?;
+    }
+
+    let target = target.to_path_buf();
+    changes.push(match up_to_date {
+        true => Change::UpToDate { target, label },
+        false => Change::Applied { target, label },
+    });
+    Ok(changes)
+}
+
+/// Point `target` at the file of `profile` with a symbolic link. Only a profile
+/// whose file is what agents should read can be linked, as nothing is rendered;
+/// the banner and `[wrap]` text are left out.
+fn link_profile_at(
+    storage: &Storage,
+    agent: Agent,
+    profile: &str,
+    target: &Path,
+    options: &ApplyOptions,
+) -> crate::Result<Vec<Change>> {
+    check_profile(storage, profile)?;
+    ensure!(
+        !crate::crypto::is_profile_encrypted(storage, profile),
+        "Profile '{}' is encrypted and cannot be linked",
+        profile
+    );
+    let content = storage.get_profile_content(profile)?;
+    ensure!(
+        storage.get_content(profile)? == content
+            && crate::frontmatter::split(&content).0.is_none()
+            && crate::functions::calls(&content).is_empty()
+            && !crate::secrets::placeholder().is_match(&content),
+        "Profile '{}' has front-matter or uses includes, extends, template functions or secrets, which only a copy can leave out or fill in; set it without --link",
+        profile
+    );
+    warn_if_stale(storage, profile);
+
+    let file = std::path::absolute(storage.get_repo_path(profile)?)
+        .map_err(|e| anyhow::anyhow!("Failed to resolve profile '{}': {}", profile, e))?;
+    let linked = crate::utils::symlink_target(target);
+    if !options.force && linked.as_deref() == Some(file.as_path()) {
+        if !options.dry_run {
+            crate::state::update(storage, |state| state.record_set(agent, target, profile))?;
+        }
+        return Ok(vec![Change::AlreadyLinked {
+            target: target.to_path_buf(),
+            file,
+        }]);
+    }
+
+    let mut changes = Vec::new();
+    if linked.is_none() {
+        match resolve_drift(storage, profile, target, &content, options)? {
+            Resolution::Overwrite => {}
+            Resolution::Adopted(profile) => changes.push(Change::Adopted {
+                target: target.to_path_buf(),
+                profile,
+            }),
+            Resolution::Append => {
+                return append_profile_at(storage, agent, &[profile], target, options);
+            }
+            Resolution::Abort => return Ok(vec![Change::Unchanged(target.to_path_buf())]),
+        }
+        changes.extend(protect_unmanaged(storage, target, options)?);
+    }
+    check_size_limits(storage, target, &content)?;
+    if options.dry_run {
+        changes.push(Change::WouldLink {
+            target: target.to_path_buf(),
+            file,
+        });
+        return Ok(changes);
+    }
+    if !confirm_sensitive(storage, &[profile.to_string()], target, options)? {
+        changes.push(Change::Unchanged(target.to_path_buf()));
+        return Ok(changes);
+    }
+
+    create_parent_dir(target)?;
+    crate::backups::save(storage, agent, target)?;
+    if std::fs::symlink_metadata(target).is_ok() {
+        std::fs::remove_file(target)
+            .map_err(|e| anyhow::anyhow!("Failed to replace {}: {}", target.display(), e))?;
+    }
+    crate::utils::symlink(&file, target).map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to link {} to {}: {}",
+            target.display(),
+            file.display(),
+            e
+        )
+    })?;
+    crate::state::update(storage, |state| state.record_set(agent, target, profile))?;
+    changes.push(Change::Linked {
+        target: target.to_path_buf(),
+        file,
+        profile: profile.to_string(),
+    });
+    Ok(changes)
+}
+
+/// What appending a profile did to the agent's file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Appended {
+    /// The file already had the profile
+    Present,
+    /// The profile's block was replaced with its current content
+    Updated,
+    /// A block was added for the profile
+    Added,
+    /// There was no file, so the block became it
+    Created,
+}
+
+/// `existing` with `profile` appended as a marked block, or the block alone when
+/// there is no file yet. `set` is the profile pmx last set as the whole file.
+fn append_block(
+    storage: &Storage,
+    agent: Agent,
+    profile: &str,
+    existing: Option<&str>,
+    set: Option<&str>,
+    options: &ApplyOptions,
+) -> crate::Result<(String, Appended)> {
+    let mut profile_content = storage.render(profile)?;
+    if options.choose_sections {
+        ensure!(
+            crate::utils::is_interactive(),
+            "Choosing sections needs an interactive terminal"
+        );
+        profile_content = choose_sections(profile, &profile_content)?;
+    }
+    let block = crate::markers::wrap(profile, &profile_content);
+    let Some(existing) = existing else {
+        return Ok((block, Appended::Created));
+    };
+
+    if !options.allow_duplicate {
+        if let Some(range) = crate::markers::find_block(existing, profile) {
+            if existing[range.clone()] == block {
+                return Ok((existing.to_string(), Appended::Present));
+            }
+            let mut updated = existing.to_string();
+            updated.replace_range(range, &block);
+            return Ok((updated, Appended::Updated));
+        }
+
+        if set == Some(profile) {
+            return Ok((existing.to_string(), Appended::Present));
+        }
+    }
+
+    // Keep the postamble last when the file ends with it
+    let (_, postamble) = wrapping(storage, agent);
+    let bottom = postamble
+        .and_then(|postamble| {
+            let trimmed = existing.trim_end();
+            trimmed
+                .ends_with(postamble.trim_end())
+                .then(|| trimmed.len() - postamble.trim_end().len())
+        })
+        .unwrap_or(existing.len());
+    let split_at = if options.choose_sections {
+        choose_placement(storage, existing, bottom)?
+    } else {
+        bottom
+    };
+    Ok((
+        insert_block(existing, split_at, &block, agent.separator()),
+        Appended::Added,
+    ))
+}
+
+/// Append each of `profiles` in order, writing the target once so that a
+/// missing profile leaves it untouched
+fn append_profile_at(
+    storage: &Storage,
+    agent: Agent,
+    profiles: &[impl AsRef<str>],
+    target: &Path,
+    options: &ApplyOptions,
+) -> crate::Result<Vec<Change>> {
+    let profiles: Vec<String> = profiles.iter().map(|p| p.as_ref().to_string()).collect();
+    if agent.has_rules_dir() {
+        return crate::commands::rules::write(storage, agent, &profiles, target, options, false);
+    }
+    ensure!(!profiles.is_empty(), "No profile to append");
+    for profile in &profiles {
+        check_profile(storage, profile)?;
+        warn_if_stale(storage, profile);
+    }
+
+    let mut content = if target.exists() {
+        Some(std::fs::read_to_string(target).map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to read existing {} profile: {}",
+                agent.display_name(),
+                e
+            )
+        })?)
+    } else {
+        None
+    };
+    // A set profile has no markers, so rely on the state when the file is unedited
+    let state = crate::state::State::load(&storage.path)?;
+    let set = content
+        .as_deref()
+        .filter(|content| !state.has_drifted(target, content.as_bytes()))
+        .and_then(|_| state.get(target))
+        .and_then(|applied| applied.profiles.first().cloned());
+    let mut outcomes = Vec::new();
+    for profile in &profiles {
+        let (appended, outcome) = append_block(
+            storage,
+            agent,
+            profile,
+            content.as_deref(),
+            set.as_deref(),
+            options,
+        )?;
+        content = Some(appended);
+        outcomes.push((profile, outcome));
+    }
+    let content = content.unwrap_or_default();
+
+    if outcomes
+        .iter()
+        .any(|(_, outcome)| *outcome != Appended::Present)
+    {
+        check_size_limits(storage, target, &content)?;
+        if options.dry_run {
+            let label = outcomes
+                .iter()
+                .map(|(profile, outcome)| match outcome {
+                    Appended::Present => format!("profile '{profile}' unchanged"),
+                    Appended::Updated => format!("profile '{profile}' updated in place"),
+                    Appended::Added => format!("profile '{profile}' appended"),
+                    Appended::Created => format!("profile '{profile}'"),
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            return Ok(vec![preview_write(target, &content, &label)]);
+        }
+        let written: Vec<String> = outcomes
+            .iter()
+            .filter(|(_, outcome)| *outcome != Appended::Present)
+            .map(|(profile, _)| profile.to_string())
+            .collect();
+        if !confirm_sensitive(storage, &written, target, options)? {
+            return Ok(vec![Change::Unchanged(target.to_path_buf())]);
+        }
+        create_parent_dir(target)?;
+        crate::backups::save(storage, agent, target)?;
+        crate::utils::write_unlinked(target, &content, &storage.path.join("repo")).map_err(
+            |e| anyhow::anyhow!("Failed to append {}: {}", describe_profiles(&profiles), e),
+        )?;
+        crate::state::update(storage, |state| {
+            for (profile, outcome) in &outcomes {
+                match outcome {
+                    Appended::Created => state.record_set(agent, target, profile),
+                    Appended::Added => state.record_append(agent, target, profile),
+                    Appended::Present | Appended::Updated => {}
+                }
+            }
+            state.record_written(target, content.as_bytes());
+        })?;
+    }
+
+    Ok(outcomes
+        .into_iter()
+        .map(|(profile, appended)| Change::Appended {
+            target: target.to_path_buf(),
+            profile: profile.clone(),
+            appended,
+        })
+        .collect())
+}
+
+/// Preamble and postamble for `agent`: its own `[agents.<name>]` text, else the
+/// `[wrap]` text, with `{agent}` filled in. Empty text means none. There is no
+/// `{date}`: the text must render the same every day, or files applied on an
+/// earlier day would look edited and be rewritten.
+fn wrapping(storage: &Storage, agent: Agent) -> (Option<String>, Option<String>) {
+    let global = &storage.config.wrap;
+    let own = storage.config.agents.get(agent);
+    let fill = |own: Option<&String>, global: &Option<String>| {
+        own.or(global.as_ref())
+            .filter(|text| !text.trim().is_empty())
+            .map(|text| text.replace("{agent}", agent.name()))
+    };
+    (
+        fill(own.and_then(|a| a.preamble.as_ref()), &global.preamble),
+        fill(own.and_then(|a| a.postamble.as_ref()), &global.postamble),
+    )
+}
+
+/// Ask which sections of a profile to include; all are selected to start with
+fn choose_sections(profile: &str, content: &str) -> crate::Result<String> {
+    let (_, body) = crate::frontmatter::split(content);
+    let (intro, sections) = crate::extends::split_sections(body);
+    if sections.is_empty() {
+        return Ok(content.to_string());
+    }
+
+    let mut parts: Vec<(String, &str)> = Vec::new();
+    if !intro.trim().is_empty() {
+        parts.push(("(text before the first heading)".to_string(), intro));
+    }
+    parts.extend(sections);
+    let labels: Vec<&str> = parts.iter().map(|(label, _)| label.as_str()).collect();
+    let chosen = dialoguer::MultiSelect::new()
+        .with_prompt(format!("Sections of profile '{profile}' to include"))
+        .items(&labels)
+        .defaults(&vec![true; labels.len()])
+        .interact()
+        .map_err(|e| anyhow::anyhow!("Failed to get selection: {}", e))?;
+    ensure!(!chosen.is_empty(), "No sections selected");
+
+    let blocks: Vec<&str> = chosen.iter().map(|&i| parts[i].1.trim_end()).collect();
+    let frontmatter = &content[..content.len() - body.len()];
+    Ok(format!("{frontmatter}{}\n", blocks.join("\n\n")))
+}
+
+/// Ask where in `existing` the block goes: the top (below a pmx banner), the end
+/// of one of its sections, or `bottom`
+fn choose_placement(storage: &Storage, existing: &str, bottom: usize) -> crate::Result<usize> {
+    let top =
+        existing.len() - crate::markers::strip_banner(&storage.config.banner.text, existing).len();
+    let mut choices = vec![("Top".to_string(), top)];
+    choices.extend(
+        section_ends(existing)
+            .into_iter()
+            .filter(|(_, end)| *end < bottom)
+            .map(|(heading, end)| (format!("After section {heading}"), end)),
+    );
+    choices.push(("Bottom".to_string(), bottom));
+
+    let labels: Vec<&str> = choices.iter().map(|(label, _)| label.as_str()).collect();
+    let choice = dialoguer::Select::new()
+        .with_prompt("Where should the profile go?")
+        .items(&labels)
+        .default(labels.len() - 1)
+        .interact()
+        .map_err(|e| anyhow::anyhow!("Failed to get selection: {}", e))?;
+    Ok(choices[choice].1)
+}
+
+/// Each heading of `content` with the byte offset where its section ends
+fn section_ends(content: &str) -> Vec<(String, usize)> {
+    let (intro, sections) = crate::extends::split_sections(content);
+    let mut end = intro.len();
+    sections
+        .into_iter()
+        .map(|(heading, text)| {
+            end += text.len();
+            (heading, end)
+        })
+        .collect()
+}
+
+/// `existing` with `block` inserted as its own paragraph at byte offset `at`, after
+/// `separator` when the agent has one
+fn insert_block(existing: &str, at: usize, block: &str, separator: Option<&str>) -> String {
+    let (head, tail) = existing.split_at(at);
+    let mut combined = if tail.is_empty() {
+        head.to_string()
+    } else {
+        head.trim_end().to_string()
+    };
+    push_separated(&mut combined, block, separator);
+    let tail = tail.trim_start_matches('\n');
+    if !tail.is_empty() {
+        combined.push('\n');
+        combined.push_str(tail);
+    }
+    combined
+}
+
+/// Add `text` after `content`, on the line after `separator`, or else as its own paragraph
+fn push_separated(content: &mut String, text: &str, separator: Option<&str>) {
+    let Some(separator) = separator.filter(|_| !content.is_empty()) else {
+        return push_paragraph(content, text);
+    };
+    content.truncate(content.trim_end_matches('\n').len());
+    content.push('\n');
+    content.push_str(separator);
+    if !separator.ends_with('\n') {
+        content.push('\n');
+    }
+    content.push_str(text.trim_end_matches('\n'));
+    content.push('\n');
+}
+
+/// Add `text` after `content` as its own paragraph
+fn push_paragraph(content: &mut String, text: &str) {
+    if !content.is_empty() {
+        content.push_str(if content.ends_with('\n') {
+            "\n"
+        } else {
+            "\n\n"
+        });
+    }
+    content.push_str(text.trim_end_matches('\n'));
+    content.push('\n');
+}
+
+/// The file content `apply_profiles` produces: the banner, preamble and first profile
+/// followed by marker blocks for the rest and the postamble, as `set` followed by
+/// `append` would write it
+pub fn render_profiles(
+    storage: &Storage,
+    agent: Agent,
+    profiles: &[String],
+) -> crate::Result<String> {
+    let (preamble, postamble) = wrapping(storage, agent);
+    let mut content = String::new();
+    for (i, profile) in profiles.iter().enumerate() {
+        check_profile(storage, profile)?;
+        let profile_content = storage.render(profile)?;
+        if i == 0 {
+            let banner = &storage.config.banner;
+            if banner.enabled {
+                content = crate::markers::banner(&banner.text, profiles);
+                content.push('\n');
+            }
+            if let Some(preamble) = &preamble {
+                content.push_str(preamble.trim_end_matches('\n'));
+                content.push_str("\n\n");
+            }
+            content.push_str(&profile_content);
+            continue;
+        }
+
+        push_separated(
+            &mut content,
+            &crate::markers::wrap(profile, &profile_content),
+            agent.separator(),
+        );
+    }
+    if let Some(postamble) = &postamble {
+        push_paragraph(&mut content, postamble);
+    }
+    Ok(content)
+}
+
+fn apply_profiles_at(
+    storage: &Storage,
+    agent: Agent,
+    profiles: &[String],
+    target: &Path,
+    options: &ApplyOptions,
+) -> crate::Result<Vec<Change>> {
+    if profiles.is_empty() {
+        return Ok(Vec::new());
+    }
+    if agent.has_rules_dir() {
+        return crate::commands::rules::write(storage, agent, profiles, target, options, true);
+    }
+
+    let content = render_profiles(storage, agent, profiles)?;
+    for profile in profiles {
+        warn_if_stale(storage, profile);
+    }
+    let up_to_date = !options.force
+        && crate::utils::symlink_target(target).is_none()
+        && std::fs::read_to_string(target).is_ok_and(|existing| existing == content);
+    let label = describe_profiles(profiles);
+
+    let mut changes = Vec::new();
+    if !up_to_date {
+        changes.extend(protect_unmanaged(storage, target, options)?);
+        check_size_limits(storage, target, &content)?;
+        if options.dry_run {
+            changes.push(preview_write(target, &content, &label));
+            return Ok(changes);
+        }
+        if !confirm_sensitive(storage, profiles, target, options)? {
+            changes.push(Change::Unchanged(target.to_path_buf()));
+            return Ok(changes);
+        }
+        create_parent_dir(target)?;
+        crate::backups::save(storage, agent, target)?;
+        crate::utils::write_unlinked(target, &content, &storage.path.join("repo"))
+            .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", target.display(), e))?;
+    }
+
+    if !options.dry_run {
+        crate::state::update(storage, |state| {
+            state.record_set(agent, target, &profiles[0]);
+            for profile in &profiles[1..] {
+                state.record_append(agent, target, profile);
+            }
+            state.record_written(target, content.as_bytes());
+        })?;
+    }
+
+    let target = target.to_path_buf();
+    changes.push(match up_to_date {
+        true => Change::UpToDate { target, label },
+        false => Change::Applied { target, label },
+    });
+    Ok(changes)
+}
+
+fn reset_profile_at(
+    storage: &Storage,
+    agent: Agent,
+    target: &Path,
+    options: &ApplyOptions,
+) -> crate::Result<Vec<Change>> {
+    if agent.has_rules_dir() {
+        return crate::commands::rules::reset(storage, agent, target, options);
+    }
+    // A link is removed even when the profile it points to is gone
+    let exists = std::fs::symlink_metadata(target).is_ok();
+    let target = target.to_path_buf();
+    if exists && options.dry_run {
+        return Ok(vec![Change::WouldRemove(target)]);
+    }
+    if !exists {
+        return Ok(vec![Change::AlreadyReset { agent, target }]);
+    }
+    crate::backups::save(storage, agent, &target)?;
+    std::fs::remove_file(&target)
+        .map_err(|e| anyhow::anyhow!("Failed to remove {}: {}", target.display(), e))?;
+    crate::state::update(storage, |state| state.clear(&target))?;
+    Ok(vec![Change::Reset { agent, target }])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn create_test_storage() -> (TempDir, Storage) {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::initialize(temp_dir.path().join("storage")).unwrap();
+        storage
+            .create_profile("base", "# Base\nBe helpful.\n")
+            .unwrap();
+        storage.create_profile("extra", "Be brief.\n").unwrap();
+        (temp_dir, storage)
+    }
+
+    #[test]
+    fn test_agent_files_leave_out_front_matter() {
+        let (temp_dir, storage) = create_test_storage();
+        let target = temp_dir.path().join("CLAUDE.md");
+        let options = ApplyOptions::default();
+        storage
+            .create_profile(
+                "review",
+                "---\ndescription: Reviews\nextends: base\narguments:\n  LANG:\n    default: Rust\n---\n\n## Review\nBe thorough.\n",
+            )
+            .unwrap();
+
+        set_profile_at(&storage, Agent::Claude, &["review"], &target, &options).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&target).unwrap(),
+            "<!-- managed by pmx: profile review -->\n# Base\nBe helpful.\n\n## Review\nBe thorough.\n"
+        );
+
+        set_profile_at(&storage, Agent::Claude, &["extra"], &target, &options).unwrap();
+        append_profile_at(&storage, Agent::Claude, &["review"], &target, &options).unwrap();
+        let content = std::fs::read_to_string(&target).unwrap();
+        assert!(content.contains("## Review"));
+        for key in ["description:", "extends:", "arguments:", "---"] {
+            assert!(!content.contains(key), "{key} in {content:?}");
+        }
+    }
+
+    #[test]
+    fn test_agent_files_leave_out_toml_front_matter() {
+        let (temp_dir, storage) = create_test_storage();
+        let target = temp_dir.path().join("CLAUDE.md");
+        storage
+            .create_profile(
+                "review",
+                "+++\ndescription = \"Reviews\"\nextends = \"base\"\n+++\n## Review\nBe thorough.\n",
+            )
+            .unwrap();
+
+        set_profile_at(
+            &storage,
+            Agent::Claude,
+            &["review"],
+            &target,
+            &ApplyOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&target).unwrap(),
+            "<!-- managed by pmx: profile review -->\n# Base\nBe helpful.\n\n## Review\nBe thorough.\n"
+        );
+    }
+
+    #[test]
+    fn test_agent_files_leave_out_json_front_matter() {
+        let (temp_dir, storage) = create_test_storage();
+        let target = temp_dir.path().join("CLAUDE.md");
+        storage
+            .create_profile(
+                "review",
+                "---json\n{\"description\": \"Reviews\", \"tags\": [\"review\"]}\n---\n## Review\nBe thorough.\n",
+            )
+            .unwrap();
+
+        set_profile_at(
+            &storage,
+            Agent::Claude,
+            &["review"],
+            &target,
+            &ApplyOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&target).unwrap(),
+            "<!-- managed by pmx: profile review -->\n## Review\nBe thorough.\n"
+        );
+    }
+
+    #[test]
+    fn test_apply_profiles_sets_then_appends() {
+        let (temp_dir, storage) = create_test_storage();
+        let target = temp_dir.path().join("CLAUDE.md");
+        let profiles = vec!["base".to_string(), "extra".to_string()];
+
+        apply_profiles_at(
+            &storage,
+            Agent::Claude,
+            &profiles,
+            &target,
+            &ApplyOptions::default(),
+        )
+        .unwrap();
+
+        let content = std::fs::read_to_string(&target).unwrap();
+        assert!(
+            content.starts_with(
+                "<!-- managed by pmx: profile base + extra -->\n# Base\nBe helpful.\n"
+            )
+        );
+        assert!(crate::markers::find_block(&content, "extra").is_some());
+        let state = crate::state::State::load(&storage.path).unwrap();
+        assert!(crate::project::is_applied(&state, &target, &profiles));
+    }
+
+    #[test]
+    fn test_set_profile_writes_target_and_state() {
+        let (temp_dir, storage) = create_test_storage();
+        let target = temp_dir.path().join("agent").join("CLAUDE.md");
+
+        set_profile_at(
+            &storage,
+            Agent::Claude,
+            &["base"],
+            &target,
+            &ApplyOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&target).unwrap(),
+            "<!-- managed by pmx: profile base -->\n# Base\nBe helpful.\n"
+        );
+        let state = crate::state::State::load(&storage.path).unwrap();
+        assert_eq!(state.get(&target).unwrap().profiles, vec!["base"]);
+    }
+
+    #[test]
+    fn test_set_profile_skips_up_to_date_target() {
+        let (temp_dir, storage) = create_test_storage();
+        let target = temp_dir.path().join("CLAUDE.md");
+        let changes = set_profile_at(
+            &storage,
+            Agent::Claude,
+            &["base"],
+            &target,
+            &ApplyOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            changes,
+            vec![Change::Applied {
+                target: target.clone(),
+                label: "profile 'base'".to_string(),
+            }]
+        );
+
+        let before = std::fs::metadata(&target).unwrap().modified().unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        let changes = set_profile_at(
+            &storage,
+            Agent::Claude,
+            &["base"],
+            &target,
+            &ApplyOptions::default(),
+        )
+        .unwrap();
+        let after = std::fs::metadata(&target).unwrap().modified().unwrap();
+        assert_eq!(before, after);
+        assert_eq!(
+            changes[0].to_string(),
+            format!(
+                "{} is already up to date with profile 'base' (use --force to rewrite)",
+                target.display()
+            )
+        );
+
+        let force = ApplyOptions {
+            force: true,
+            ..Default::default()
+        };
+        set_profile_at(&storage, Agent::Claude, &["base"], &target, &force).unwrap();
+        let forced = std::fs::metadata(&target).unwrap().modified().unwrap();
+        assert!(forced > before);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_link_copy_and_reset_profile() {
+        let (temp_dir, storage) = create_test_storage();
+        let target = temp_dir.path().join("CLAUDE.md");
+        let file = storage.get_repo_path("base").unwrap();
+        let link = ApplyOptions {
+            link: true,
+            ..Default::default()
+        };
+
+        set_profile_at(&storage, Agent::Claude, &["base"], &target, &link).unwrap();
+        assert_eq!(crate::utils::symlink_target(&target), Some(file.clone()));
+        std::fs::write(&file, "# Base\nBe thorough.\n").unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&target).unwrap(),
+            "# Base\nBe thorough.\n"
+        );
+
+        // Copying replaces the link rather than writing through it
+        let options = ApplyOptions::default();
+        set_profile_at(&storage, Agent::Claude, &["extra"], &target, &options).unwrap();
+        assert!(crate::utils::symlink_target(&target).is_none());
+        assert_eq!(
+            std::fs::read_to_string(&file).unwrap(),
+            "# Base\nBe thorough.\n"
+        );
+
+        storage
+            .create_profile("combined", "{{include: extra}}\n")
+            .unwrap();
+        assert!(set_profile_at(&storage, Agent::Claude, &["combined"], &target, &link).is_err());
+
+        set_profile_at(&storage, Agent::Claude, &["base"], &target, &link).unwrap();
+        reset_profile_at(&storage, Agent::Claude, &target, &options).unwrap();
+        assert!(std::fs::symlink_metadata(&target).is_err());
+        assert!(file.exists());
+    }
+
+    #[test]
+    fn test_append_and_reset_profile() {
+        let (temp_dir, storage) = create_test_storage();
+        let target = temp_dir.path().join("AGENTS.md");
+        let options = ApplyOptions::default();
+
+        append_profile_at(&storage, Agent::Codex, &["base"], &target, &options).unwrap();
+        append_profile_at(&storage, Agent::Codex, &["extra"], &target, &options).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&target).unwrap(),
+            "<!-- pmx:begin:base -->\n# Base\nBe helpful.\n<!-- pmx:end -->\n\n\
+             <!-- pmx:begin:extra -->\nBe brief.\n<!-- pmx:end -->\n"
+        );
+        let state = crate::state::State::load(&storage.path).unwrap();
+        assert_eq!(state.get(&target).unwrap().profiles, vec!["base", "extra"]);
+
+        reset_profile_at(&storage, Agent::Codex, &target, &options).unwrap();
+        assert!(!target.exists());
+        let state = crate::state::State::load(&storage.path).unwrap();
+        assert!(state.get(&target).is_none());
+    }
+
+    #[test]
+    fn test_set_and_append_several_profiles() {
+        let (temp_dir, storage) = create_test_storage();
+        storage.create_profile("style", "Use tabs.\n").unwrap();
+        let target = temp_dir.path().join("CLAUDE.md");
+        let options = ApplyOptions::default();
+
+        set_profile_at(
+            &storage,
+            Agent::Claude,
+            &["base", "extra"],
+            &target,
+            &options,
+        )
+        .unwrap();
+        let content = std::fs::read_to_string(&target).unwrap();
+        assert!(content.contains("Be helpful.\n"));
+        assert!(crate::markers::find_block(&content, "extra").is_some());
+        let state = crate::state::State::load(&storage.path).unwrap();
+        assert_eq!(state.get(&target).unwrap().profiles, vec!["base", "extra"]);
+
+        // A missing profile stops the whole command before anything is written
+        assert!(
+            append_profile_at(
+                &storage,
+                Agent::Claude,
+                &["style", "missing"],
+                &target,
+                &options
+            )
+            .is_err()
+        );
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), content);
+
+        append_profile_at(
+            &storage,
+            Agent::Claude,
+            &["extra", "style"],
+            &target,
+            &options,
+        )
+        .unwrap();
+        let appended = std::fs::read_to_string(&target).unwrap();
+        assert_eq!(appended.matches("Be brief.").count(), 1);
+        assert!(appended.ends_with(&crate::markers::wrap("style", "Use tabs.\n")));
+        let state = crate::state::State::load(&storage.path).unwrap();
+        assert_eq!(
+            state.get(&target).unwrap().profiles,
+            vec!["base", "extra", "style"]
+        );
+    }
+
+    #[test]
+    fn test_sensitive_profile_needs_confirmation() {
+        let (temp_dir, storage) = create_test_storage();
+        // Written directly so that the test needs no passphrase
+        std::fs::write(
+            storage.path.join("repo/secret.md"),
+            "---\nsensitive: true\n---\nInternal only.\n",
+        )
+        .unwrap();
+        let target = temp_dir.path().join("CLAUDE.md");
+
+        let error = set_profile_at(
+            &storage,
+            Agent::Claude,
+            &["base", "secret"],
+            &target,
+            &ApplyOptions::default(),
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("--allow-sensitive"));
+        assert!(!target.exists());
+        assert!(
+            append_profile_at(
+                &storage,
+                Agent::Claude,
+                &["secret"],
+                &target,
+                &ApplyOptions::default()
+            )
+            .is_err()
+        );
+
+        let allowed = ApplyOptions {
+            allow_sensitive: true,
+            ..Default::default()
+        };
+        set_profile_at(&storage, Agent::Claude, &["secret"], &target, &allowed).unwrap();
+        assert!(
+            std::fs::read_to_string(&target)
+                .unwrap()
+                .contains("Internal only.")
+        );
+    }
+
+    #[test]
+    fn test_remove_appended_profile() {
+        let (temp_dir, storage) = create_test_storage();
+        let target = temp_dir.path().join("CLAUDE.md");
+        let options = ApplyOptions::default();
+
+        set_profile_at(&storage, Agent::Claude, &["base"], &target, &options).unwrap();
+        let before = std::fs::read_to_string(&target).unwrap();
+        append_profile_at(&storage, Agent::Claude, &["extra"], &target, &options).unwrap();
+        assert!(remove_profile_at(&storage, Agent::Claude, "base", &target, &options).is_err());
+
+        let changes =
+            remove_profile_at(&storage, Agent::Claude, "extra", &target, &options).unwrap();
+        assert_eq!(
+            changes,
+            vec![Change::Removed {
+                target: target.clone(),
+                profile: "extra".to_string(),
+            }]
+        );
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), before);
+        let state = crate::state::State::load(&storage.path).unwrap();
+        assert_eq!(state.get(&target).unwrap().profiles, vec!["base"]);
+        assert!(!state.has_drifted(&target, before.as_bytes()));
+
+        assert_eq!(remove_block("a\n\n<!-- b -->\nc\n", 3..14), "a\nc\n");
+    }
+
+    #[test]
+    fn test_dry_run_leaves_target_untouched() {
+        let (temp_dir, storage) = create_test_storage();
+        let target = temp_dir.path().join("nested/CLAUDE.md");
+        let dry_run = ApplyOptions {
+            dry_run: true,
+            ..Default::default()
+        };
+
+        let changes =
+            set_profile_at(&storage, Agent::Claude, &["base"], &target, &dry_run).unwrap();
+        assert_eq!(
+            changes[0].to_string(),
+            format!("Would create {} with profile 'base'", target.display())
+        );
+        append_profile_at(&storage, Agent::Claude, &["extra"], &target, &dry_run).unwrap();
+        assert!(!target.parent().unwrap().exists());
+
+        set_profile_at(
+            &storage,
+            Agent::Claude,
+            &["base"],
+            &target,
+            &ApplyOptions::default(),
+        )
+        .unwrap();
+        let applied = std::fs::read_to_string(&target).unwrap();
+        let changes =
+            set_profile_at(&storage, Agent::Claude, &["extra"], &target, &dry_run).unwrap();
+        let [Change::WouldWrite { before, after, .. }] = changes.as_slice() else {
+            panic!("unexpected changes: {changes:?}");
+        };
+        assert_eq!(before.as_deref(), Some(applied.as_str()));
+        assert!(after.contains("Be brief."));
+        append_profile_at(&storage, Agent::Claude, &["extra"], &target, &dry_run).unwrap();
+        let changes = reset_profile_at(&storage, Agent::Claude, &target, &dry_run).unwrap();
+        assert_eq!(changes, vec![Change::WouldRemove(target.clone())]);
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), applied);
+        let state = crate::state::State::load(&storage.path).unwrap();
+        assert_eq!(state.get(&target).unwrap().profiles, vec!["base"]);
+        // The only backup is the one taken before the real write
+        crate::backups::restore(&storage, &target).unwrap();
+        assert!(!target.exists());
+    }
+
+    #[test]
+    fn test_custom_agent_separator() {
+        let (temp_dir, mut storage) = create_test_storage();
+        storage.config.banner.enabled = false;
+        storage.config.agents = toml::from_str(
+            "[custom.aider]\ntarget = \"/unused/CONVENTIONS.md\"\nseparator = \"---\"\n",
+        )
+        .unwrap();
+        let aider = Agent::named(&storage, "aider").unwrap();
+        let target = temp_dir.path().join("CONVENTIONS.md");
+        let options = ApplyOptions::default();
+
+        set_profile_at(&storage, aider, &["base"], &target, &options).unwrap();
+        append_profile_at(&storage, aider, &["extra"], &target, &options).unwrap();
+        let appended = std::fs::read_to_string(&target).unwrap();
+        assert_eq!(
+            appended,
+            "# Base\nBe helpful.\n---\n<!-- pmx:begin:extra -->\nBe brief.\n<!-- pmx:end -->\n"
+        );
+        assert_eq!(
+            render_profiles(&storage, aider, &["base".to_string(), "extra".to_string()]).unwrap(),
+            appended
+        );
+        let state = crate::state::State::load(&storage.path).unwrap();
+        assert_eq!(state.get(&target).unwrap().agent, "aider");
+    }
+
+    #[test]
+    fn test_insert_block_at_section_end() {
+        let existing = "# Intro\nHello.\n\n# Tools\nUse cargo.\n";
+        let ends = section_ends(existing);
+        assert_eq!(
+            ends,
+            vec![
+                ("# Intro".to_string(), 16),
+                ("# Tools".to_string(), existing.len())
+            ]
+        );
+
+        let block = crate::markers::wrap("extra", "Be brief.\n");
+        assert_eq!(
+            insert_block(existing, ends[0].1, &block, None),
+            "# Intro\nHello.\n\n<!-- pmx:begin:extra -->\nBe brief.\n<!-- pmx:end -->\n\n\
+             # Tools\nUse cargo.\n"
+        );
+        assert_eq!(
+            insert_block(existing, 0, &block, None),
+            format!("{block}\n{existing}")
+        );
+        assert_eq!(
+            insert_block(existing, existing.len(), &block, None),
+            format!("{existing}\n{block}")
+        );
+    }
+
+    #[test]
+    fn test_append_skips_duplicates() {
+        let (temp_dir, storage) = create_test_storage();
+        let target = temp_dir.path().join("CLAUDE.md");
+        std::fs::write(&target, "# Mine\n").unwrap();
+        let options = ApplyOptions::default();
+
+        append_profile_at(&storage, Agent::Claude, &["extra"], &target, &options).unwrap();
+        let once = std::fs::read_to_string(&target).unwrap();
+        let appended = |changes: Vec<Change>| match changes.as_slice() {
+            [Change::Appended { appended, .. }] => *appended,
+            _ => panic!("unexpected changes: {changes:?}"),
+        };
+        let changes =
+            append_profile_at(&storage, Agent::Claude, &["extra"], &target, &options).unwrap();
+        assert_eq!(appended(changes), Appended::Present);
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), once);
+
+        // An edited profile replaces its existing block instead of adding another
+        storage.create_profile("extra", "Be very brief.\n").unwrap();
+        let changes =
+            append_profile_at(&storage, Agent::Claude, &["extra"], &target, &options).unwrap();
+        assert_eq!(appended(changes), Appended::Updated);
+        let updated = std::fs::read_to_string(&target).unwrap();
+        assert_eq!(updated.matches("pmx:begin:extra").count(), 1);
+        assert!(updated.contains("Be very brief."));
+
+        let allow = ApplyOptions {
+            allow_duplicate: true,
+            ..Default::default()
+        };
+        append_profile_at(&storage, Agent::Claude, &["extra"], &target, &allow).unwrap();
+        let duplicated = std::fs::read_to_string(&target).unwrap();
+        assert_eq!(duplicated.matches("pmx:begin:extra").count(), 2);
+    }
+
+    #[test]
+    fn test_append_skips_content_applied_with_set() {
+        let (temp_dir, storage) = create_test_storage();
+        let target = temp_dir.path().join("CLAUDE.md");
+        let options = ApplyOptions::default();
+
+        set_profile_at(&storage, Agent::Claude, &["base"], &target, &options).unwrap();
+        append_profile_at(&storage, Agent::Claude, &["base"], &target, &options).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&target).unwrap(),
+            "<!-- managed by pmx: profile base -->\n# Base\nBe helpful.\n"
+        );
+
+        // The same text written by hand is not a profile pmx applied
+        std::fs::write(&target, "# Base\nBe helpful.\n").unwrap();
+        append_profile_at(&storage, Agent::Claude, &["base"], &target, &options).unwrap();
+        let content = std::fs::read_to_string(&target).unwrap();
+        assert!(crate::markers::find_block(&content, "base").is_some());
+    }
+
+    #[test]
+    fn test_size_limits() {
+        let (temp_dir, mut storage) = create_test_storage();
+        let target = temp_dir.path().join("CLAUDE.md");
+        let options = ApplyOptions::default();
+
+        storage.config.limits.warn_bytes = Some(5);
+        set_profile_at(&storage, Agent::Claude, &["base"], &target, &options).unwrap();
+
+        storage.config.limits.max_tokens = Some(5);
+        let result = append_profile_at(&storage, Agent::Claude, &["extra"], &target, &options);
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("exceeds limits.max_tokens")
+        );
+        assert_eq!(
+            std::fs::read_to_string(&target).unwrap(),
+            "<!-- managed by pmx: profile base -->\n# Base\nBe helpful.\n"
+        );
+    }
+
+    #[test]
+    fn test_set_profile_missing_profile() {
+        let (temp_dir, storage) = create_test_storage();
+        let target = temp_dir.path().join("CLAUDE.md");
+        let result = set_profile_at(
+            &storage,
+            Agent::Claude,
+            &["missing"],
+            &target,
+            &ApplyOptions::default(),
+        );
+        assert!(result.is_err());
+        assert!(!target.exists());
+    }
+
+    #[test]
+    fn test_set_profile_protects_unmanaged_target() {
+        let (temp_dir, storage) = create_test_storage();
+        let target = temp_dir.path().join("CLAUDE.md");
+        std::fs::write(&target, "# Hand-written\n").unwrap();
+
+        let result = set_profile_at(
+            &storage,
+            Agent::Claude,
+            &["base"],
+            &target,
+            &ApplyOptions::default(),
+        );
+        assert!(result.unwrap_err().to_string().contains("--backup"));
+        assert_eq!(
+            std::fs::read_to_string(&target).unwrap(),
+            "# Hand-written\n"
+        );
+
+        let backup = ApplyOptions {
+            backup: true,
+            ..Default::default()
+        };
+        let changes = set_profile_at(&storage, Agent::Claude, &["base"], &target, &backup).unwrap();
+        assert_eq!(
+            changes[0],
+            Change::BackedUp {
+                target: target.clone(),
+                backup: temp_dir.path().join("CLAUDE.md.bak"),
+            }
+        );
+        assert_eq!(
+            std::fs::read_to_string(temp_dir.path().join("CLAUDE.md.bak")).unwrap(),
+            "# Hand-written\n"
+        );
+
+        // Once pmx wrote the file, switching profiles needs no flags
+        set_profile_at(
+            &storage,
+            Agent::Claude,
+            &["extra"],
+            &target,
+            &ApplyOptions::default(),
+        )
+        .unwrap();
+        assert!(!temp_dir.path().join("CLAUDE.md.bak.1").exists());
+    }
+
+    #[test]
+    fn test_set_profile_without_banner() {
+        let (temp_dir, mut storage) = create_test_storage();
+        let target = temp_dir.path().join("CLAUDE.md");
+        storage.config.banner.enabled = false;
+
+        set_profile_at(
+            &storage,
+            Agent::Claude,
+            &["base"],
+            &target,
+            &ApplyOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&target).unwrap(),
+            "# Base\nBe helpful.\n"
+        );
+    }
+
+    #[test]
+    fn test_preamble_and_postamble() {
+        let (temp_dir, mut storage) = create_test_storage();
+        let target = temp_dir.path().join("CLAUDE.md");
+        storage.config.banner.enabled = false;
+        storage.config.wrap.preamble = Some("Written for {agent}.".to_string());
+        storage.config.wrap.postamble = Some("Follow repo conventions.\n".to_string());
+        let options = ApplyOptions::default();
+
+        set_profile_at(&storage, Agent::Claude, &["base"], &target, &options).unwrap();
+        append_profile_at(&storage, Agent::Claude, &["extra"], &target, &options).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&target).unwrap(),
+            format!(
+                "Written for claude.\n\n# Base\nBe helpful.\n\n{}\nFollow repo conventions.\n",
+                crate::markers::wrap("extra", "Be brief.\n")
+            )
+        );
+
+        // An agent's own empty text leaves the global one out
+        storage
+            .config
+            .agents
+            .agents
+            .get_mut("claude")
+            .unwrap()
+            .preamble = Some(String::new());
+        assert_eq!(
+            render_profiles(&storage, Agent::Claude, &["extra".to_string()]).unwrap(),
+            "Be brief.\n\nFollow repo conventions.\n"
+        );
+    }
+
+    #[test]
+    fn test_set_profile_refuses_edited_target() {
+        let (temp_dir, storage) = create_test_storage();
+        let target = temp_dir.path().join("CLAUDE.md");
+        let options = ApplyOptions::default();
+        set_profile_at(&storage, Agent::Claude, &["base"], &target, &options).unwrap();
+
+        std::fs::write(&target, "# Base\nBe helpful, and edited.\n").unwrap();
+        let result = set_profile_at(&storage, Agent::Claude, &["extra"], &target, &options);
+        assert!(result.unwrap_err().to_string().contains("was edited since"));
+
+        let force = ApplyOptions {
+            force: true,
+            ..Default::default()
+        };
+        set_profile_at(&storage, Agent::Claude, &["extra"], &target, &force).unwrap();
+        assert!(
+            std::fs::read_to_string(&target)
+                .unwrap()
+                .ends_with("Be brief.\n")
+        );
+    }
+}
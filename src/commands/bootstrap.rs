@@ -0,0 +1,182 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::ensure;
+
+use crate::commands::apply::{self, ApplyOptions};
+use crate::project::ProjectConfig;
+use crate::storage::Storage;
+
+/// Materialize storage from `source` and apply the target's `.pmx.toml` pins to its
+/// project-local agent files, without touching the home directory or asking questions
+pub fn bootstrap(
+    args: &crate::cli::BootstrapArgs,
+    storage_path: Option<PathBuf>,
+) -> crate::Result<()> {
+    ensure!(
+        args.target.is_dir(),
+        "Target directory does not exist: {}",
+        args.target.display()
+    );
+    let storage_path = storage_path.unwrap_or_else(|| args.target.join(".pmx"));
+
+    let storage = match &args.source {
+        Some(source) if crate::utils::is_git_url(source) => {
+            let checkout = tempfile::TempDir::new()
+                .map_err(|e| anyhow::anyhow!("Failed to create temporary directory: {}", e))?;
+            crate::utils::git_clone(source, checkout.path())?;
+            materialize(checkout.path(), &storage_path, args.force)?
+        }
+        Some(source) => materialize(Path::new(source), &storage_path, args.force)?,
+        None => Storage::new(storage_path)?,
+    };
+    println!("Using storage at {}", storage.path.display());
+
+    let Some(project) = ProjectConfig::load(&args.target)? else {
+        println!(
+            "No {} in {}, nothing to apply",
+            crate::project::PROJECT_FILE,
+            args.target.display()
+        );
+        return Ok(());
+    };
+
+    for (agent, profiles) in project.pins()? {
+        if !agent.is_enabled(&storage) {
+            println!("Skipping {}: disabled in the configuration", agent.name());
+            continue;
+        }
+        let changes = apply::apply_profiles_to_project(
+            &storage,
+            agent,
+            profiles,
+            &args.target,
+            &ApplyOptions::default(),
+        )?;
+        for change in changes {
+            println!("{change}");
+        }
+    }
+    Ok(())
+}
+
+/// Create (or refresh) storage at `storage_path` from a source directory, which is
+/// either a pmx storage directory (`config.toml` + `repo/`) or a plain tree of profiles.
+/// The source's `config.toml` only replaces a differing one of existing storage with `force`.
+fn materialize(source: &Path, storage_path: &Path, force: bool) -> crate::Result<Storage> {
+    ensure!(
+        source.is_dir(),
+        "Source directory does not exist: {}",
+        source.display()
+    );
+
+    let existing = storage_path.exists();
+    if !existing {
+        Storage::initialize(storage_path.to_path_buf())?;
+    }
+
+    let source_config = source.join("config.toml");
+    if source_config.is_file() {
+        let config = storage_path.join("config.toml");
+        let differs = std::fs::read(&source_config).ok() != std::fs::read(&config).ok();
+        ensure!(
+            !existing || !differs || force,
+            "{} differs from the config.toml in {}; pass --force to replace it",
+            config.display(),
+            source.display()
+        );
+        std::fs::copy(&source_config, &config)
+            .map_err(|e| anyhow::anyhow!("Failed to copy config.toml: {}", e))?;
+    }
+
+    let profile_root = if source.join("repo").is_dir() {
+        source.join("repo")
+    } else {
+        source.to_path_buf()
+    };
+    let storage = Storage::new(storage_path.to_path_buf())?;
+
+    let files = crate::utils::visible_files(&profile_root)?;
+    // Copy every file so folder profiles keep their auxiliary files
+    crate::utils::copy_files(&profile_root, &storage.path.join("repo"), &files)?;
+    crate::manifest::refresh(&storage.path, &files)?;
+    println!("Imported {} file(s) from {}", files.len(), source.display());
+
+    Ok(storage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_materialize_profile_tree() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("source");
+        std::fs::create_dir_all(source.join("lang")).unwrap();
+        std::fs::create_dir_all(source.join(".git")).unwrap();
+        std::fs::write(source.join("base.md"), "Base\n").unwrap();
+        std::fs::write(source.join("lang").join("rust.md"), "Rust\n").unwrap();
+        std::fs::write(source.join(".git").join("HEAD.md"), "ignored").unwrap();
+        std::fs::write(source.join("lang").join("notes.txt"), "kept").unwrap();
+
+        let storage = materialize(&source, &temp_dir.path().join("storage"), false).unwrap();
+        let mut profiles = storage.list_repos().unwrap();
+        profiles.sort();
+        assert_eq!(profiles, vec!["base", "lang/rust"]);
+        assert!(storage.path.join("repo/lang/notes.txt").exists());
+        assert!(!storage.path.join("repo/.git").exists());
+    }
+
+    #[test]
+    fn test_bootstrap_applies_pins_to_project_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("source");
+        std::fs::create_dir_all(&source).unwrap();
+        std::fs::write(source.join("base.md"), "Base\n").unwrap();
+
+        let target = temp_dir.path().join("project");
+        std::fs::create_dir_all(&target).unwrap();
+        std::fs::write(
+            target.join(crate::project::PROJECT_FILE),
+            "[profiles]\ncodex = [\"base\"]\n",
+        )
+        .unwrap();
+
+        let args = crate::cli::BootstrapArgs {
+            target: target.clone(),
+            source: Some(source.to_string_lossy().to_string()),
+            force: false,
+        };
+        bootstrap(&args, Some(temp_dir.path().join("storage"))).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(target.join("AGENTS.md")).unwrap(),
+            "<!-- managed by pmx: profile base -->\nBase\n"
+        );
+        assert!(!target.join("CLAUDE.md").exists());
+    }
+
+    #[test]
+    fn test_materialize_keeps_a_differing_config_without_force() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("source");
+        std::fs::create_dir_all(source.join("repo")).unwrap();
+        std::fs::write(source.join("repo").join("base.md"), "Base\n").unwrap();
+        let storage_path = temp_dir.path().join("storage");
+        Storage::initialize(storage_path.clone()).unwrap();
+        let config = storage_path.join("config.toml");
+        let original = std::fs::read_to_string(&config).unwrap();
+        let shared = format!("{original}\n# shared by the team\n");
+        std::fs::write(source.join("config.toml"), &shared).unwrap();
+
+        let error = materialize(&source, &storage_path, false).unwrap_err();
+        assert!(error.to_string().contains("--force"));
+        assert_eq!(std::fs::read_to_string(&config).unwrap(), original);
+
+        materialize(&source, &storage_path, true).unwrap();
+        assert_eq!(std::fs::read_to_string(&config).unwrap(), shared);
+        // Identical configs need no --force
+        materialize(&source, &storage_path, false).unwrap();
+    }
+}
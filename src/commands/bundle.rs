@@ -0,0 +1,100 @@
+use anyhow::ensure;
+
+use crate::agent::Agent;
+use crate::commands::apply::{ApplyOptions, Change};
+use crate::storage::Storage;
+
+/// Set the profiles of bundle `name` on `agent`, or on every enabled agent for `all`
+pub fn apply(
+    storage: &Storage,
+    name: &str,
+    agent: &str,
+    options: &ApplyOptions,
+) -> crate::Result<Vec<Change>> {
+    let profiles = profiles(storage, name)?;
+    let mut changes = Vec::new();
+    for agent in agents(storage, agent)? {
+        changes.extend(crate::commands::apply::set_profile(
+            storage, agent, profiles, options,
+        )?);
+    }
+    Ok(changes)
+}
+
+/// Profiles of the bundle, checked up front so that no agent is changed when one is missing
+fn profiles<'a>(storage: &'a Storage, name: &str) -> crate::Result<&'a [String]> {
+    let profiles = storage.bundle(name)?;
+    ensure!(!profiles.is_empty(), "Bundle '{}' lists no profiles", name);
+    for profile in profiles {
+        ensure!(
+            storage.profile_exists(profile),
+            "Bundle '{}' lists profile '{}', which does not exist",
+            name,
+            profile
+        );
+    }
+    Ok(profiles)
+}
+
+fn agents(storage: &Storage, name: &str) -> crate::Result<Vec<Agent>> {
+    if name != "all" {
+        return Ok(vec![Agent::named(storage, name)?]);
+    }
+    let agents: Vec<Agent> = Agent::all(storage)?
+        .into_iter()
+        .filter(|agent| agent.is_enabled(storage))
+        .collect();
+    ensure!(
+        !agents.is_empty(),
+        "No agents are enabled in the configuration"
+    );
+    Ok(agents)
+}
+
+/// `name: a + b` for each bundle, for `profile list`
+pub fn describe(storage: &Storage) -> Vec<String> {
+    storage
+        .config
+        .bundles
+        .iter()
+        .map(|(name, profiles)| format!("{name}: {}", profiles.join(" + ")))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_bundle_profiles_and_agents() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut storage = Storage::initialize(temp_dir.path().join("storage")).unwrap();
+        storage.create_profile("base", "Be helpful.\n").unwrap();
+        storage.create_profile("rust", "Use cargo.\n").unwrap();
+        storage.config.bundles.insert(
+            "backend".to_string(),
+            vec!["base".to_string(), "rust".to_string()],
+        );
+        storage.config.bundles.insert(
+            "broken".to_string(),
+            vec!["base".to_string(), "missing".to_string()],
+        );
+
+        assert_eq!(profiles(&storage, "backend").unwrap(), ["base", "rust"]);
+        let error = profiles(&storage, "broken").unwrap_err();
+        assert!(error.to_string().contains("profile 'missing'"));
+        assert!(profiles(&storage, "frontend").is_err());
+        assert_eq!(
+            describe(&storage),
+            ["backend: base + rust", "broken: base + missing"]
+        );
+
+        assert_eq!(agents(&storage, "codex").unwrap(), [Agent::Codex]);
+        assert!(agents(&storage, "nope").is_err());
+        assert_eq!(
+            agents(&storage, "all").unwrap().len(),
+            Agent::all(&storage).unwrap().len()
+        );
+    }
+}
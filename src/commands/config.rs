@@ -0,0 +1,305 @@
+use anyhow::{bail, ensure};
+use serde_json::Value;
+use toml_edit::{DocumentMut, Item, TableLike};
+
+use crate::storage::{Config, Storage};
+
+/// JSON Schema describing `config.toml`
+pub fn schema_json() -> crate::Result<String> {
+    let schema = schemars::schema_for!(crate::storage::Config);
+    serde_json::to_string_pretty(&schema)
+        .map_err(|e| anyhow::anyhow!("Failed to serialize config schema: {}", e))
+}
+
+pub fn schema() -> crate::Result<()> {
+    println!("{}", schema_json()?);
+    Ok(())
+}
+
+pub fn get(storage: &Storage, key: &str) -> crate::Result<()> {
+    let Some(value) = storage.config.value(key)? else {
+        ensure!(is_known(key), "Unknown config key '{}'", key);
+        bail!("{} is not set", key);
+    };
+    match &value {
+        toml::Value::Table(_) => {
+            for (key, value) in flatten(key, &value) {
+                println!("{key} = {value}");
+            }
+        }
+        toml::Value::String(text) => println!("{text}"),
+        other => println!("{other}"),
+    }
+    Ok(())
+}
+
+pub fn list(storage: &Storage) -> crate::Result<()> {
+    for (key, value) in flatten("", &effective(&storage.config)?) {
+        println!("{key} = {value}");
+    }
+    Ok(())
+}
+
+/// Write a setting to `config.toml`, keeping the rest of the file as it is. A single
+/// value for a list-valued key such as `mcp.disable_prompts` becomes a one-item list.
+pub fn set(storage: &Storage, args: &crate::cli::ConfigSetArgs) -> crate::Result<()> {
+    ensure!(is_known(&args.key), "Unknown config key '{}'", args.key);
+    let mut values: Vec<toml_edit::Value> = args.values.iter().map(|v| parse_value(v)).collect();
+
+    let candidates = if args.add || args.remove {
+        let config = effective(&storage.config)?;
+        let mut items: Vec<toml_edit::Value> = match lookup(&config, &args.key) {
+            Some(toml::Value::Array(items)) => items
+                .iter()
+                .map(|item| parse_value(&item.to_string()))
+                .collect(),
+            _ => Vec::new(),
+        };
+        if args.add {
+            items.append(&mut values);
+        } else {
+            items.retain(|item| !values.iter().any(|value| same(item, value)));
+        }
+        vec![array(items)]
+    } else if values.len() == 1 && !values[0].is_array() {
+        vec![values[0].clone(), array(values)]
+    } else if values.len() == 1 {
+        values
+    } else {
+        vec![array(values)]
+    };
+
+    let path = storage.path.join("config.toml");
+    let text = std::fs::read_to_string(&path)
+        .map_err(|e| anyhow::anyhow!("Failed to read config file: {}", e))?;
+    let mut first_error = None;
+    for value in candidates {
+        let mut document = parse_document(&text)?;
+        let shown = value.to_string();
+        insert(&mut document, &args.key, value)?;
+        match validate(&document, &args.key) {
+            Ok(()) => {
+                write(&path, &document)?;
+                println!("Set {} = {}", args.key, shown.trim());
+                return Ok(());
+            }
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    Err(first_error.unwrap())
+}
+
+/// Remove a setting from `config.toml` so that its default applies again
+pub fn unset(storage: &Storage, key: &str) -> crate::Result<()> {
+    ensure!(is_known(key), "Unknown config key '{}'", key);
+    let path = storage.path.join("config.toml");
+    let text = std::fs::read_to_string(&path)
+        .map_err(|e| anyhow::anyhow!("Failed to read config file: {}", e))?;
+    let mut document = parse_document(&text)?;
+
+    let (parents, last) = match key.rsplit_once('.') {
+        Some((parents, last)) => (Some(parents), last),
+        None => (None, key),
+    };
+    let mut table: &mut dyn TableLike = document.as_table_mut();
+    for part in parents.into_iter().flat_map(|parents| parents.split('.')) {
+        table = match table.get_mut(part).and_then(Item::as_table_like_mut) {
+            Some(table) => table,
+            None => bail!("{} is not set in config.toml", key),
+        };
+    }
+    ensure!(
+        table.remove(last).is_some(),
+        "{} is not set in config.toml",
+        key
+    );
+
+    validate(&document, key)?;
+    write(&path, &document)?;
+    println!("Unset {key}");
+    Ok(())
+}
+
+/// The configuration in effect, defaults included
+fn effective(config: &Config) -> crate::Result<toml::Value> {
+    toml::Value::try_from(config).map_err(|e| anyhow::anyhow!("Failed to serialize config: {}", e))
+}
+
+fn lookup<'a>(value: &'a toml::Value, key: &str) -> Option<&'a toml::Value> {
+    key.split('.')
+        .try_fold(value, |value, part| value.get(part))
+}
+
+/// `key = value` for every setting under `value`, with tables flattened into dotted keys
+fn flatten(prefix: &str, value: &toml::Value) -> Vec<(String, String)> {
+    match value {
+        toml::Value::Table(table) => table
+            .iter()
+            .flat_map(|(key, value)| match prefix {
+                "" => flatten(key, value),
+                _ => flatten(&format!("{prefix}.{key}"), value),
+            })
+            .collect(),
+        other => vec![(prefix.to_string(), other.to_string())],
+    }
+}
+
+/// Whether the config schema has a setting at the dotted `key`
+fn is_known(key: &str) -> bool {
+    let Ok(root) = serde_json::to_value(schemars::schema_for!(Config)) else {
+        return false;
+    };
+    let mut schema = &root;
+    for part in key.split('.') {
+        let current = resolve(&root, schema);
+        schema = match current.get("properties").and_then(|p| p.get(part)) {
+            Some(property) => property,
+            None => match current.get("additionalProperties") {
+                Some(values) if values.is_object() => values,
+                _ => return false,
+            },
+        };
+    }
+    true
+}
+
+/// Follow references and optional wrappers to the schema that describes a value
+fn resolve<'a>(root: &'a Value, mut schema: &'a Value) -> &'a Value {
+    loop {
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            let name = reference.trim_start_matches("#/definitions/");
+            schema = &root["definitions"][name];
+        } else if let Some(first) = schema.get("allOf").and_then(|all| all.get(0)) {
+            schema = first;
+        } else if let Some(any) = schema.get("anyOf").and_then(Value::as_array)
+            && let Some(some) = any.iter().find(|s| s["type"] != "null")
+        {
+            schema = some;
+        } else {
+            return schema;
+        }
+    }
+}
+
+/// A TOML value, or the text as a string when it is not one
+fn parse_value(text: &str) -> toml_edit::Value {
+    let mut value = text
+        .parse::<toml_edit::Value>()
+        .unwrap_or_else(|_| text.into());
+    value.decor_mut().clear();
+    value
+}
+
+fn array(items: Vec<toml_edit::Value>) -> toml_edit::Value {
+    let mut array: toml_edit::Array = items.into_iter().collect();
+    array.fmt();
+    toml_edit::Value::Array(array)
+}
+
+fn same(a: &toml_edit::Value, b: &toml_edit::Value) -> bool {
+    a.to_string().trim() == b.to_string().trim()
+}
+
+fn parse_document(text: &str) -> crate::Result<DocumentMut> {
+    text.parse()
+        .map_err(|e| anyhow::anyhow!("Failed to parse config file: {}", e))
+}
+
+/// Put `value` at the dotted `key`, creating tables on the way
+fn insert(document: &mut DocumentMut, key: &str, value: toml_edit::Value) -> crate::Result<()> {
+    let parts: Vec<&str> = key.split('.').collect();
+    let (last, parents) = parts.split_last().unwrap();
+    let mut table: &mut dyn TableLike = document.as_table_mut();
+    for part in parents {
+        if table.get(part).is_none() {
+            table.insert(part, toml_edit::table());
+        }
+        table = match table.get_mut(part).and_then(Item::as_table_like_mut) {
+            Some(table) => table,
+            None => bail!("Cannot set {}: {} is not a table", key, part),
+        };
+    }
+    table.insert(last, Item::Value(value));
+    Ok(())
+}
+
+fn validate(document: &DocumentMut, key: &str) -> crate::Result<()> {
+    toml::from_str::<Config>(&document.to_string())
+        .map_err(|e| anyhow::anyhow!("Invalid value for {}: {}", key, e))?;
+    Ok(())
+}
+
+fn write(path: &std::path::Path, document: &DocumentMut) -> crate::Result<()> {
+    std::fs::write(path, document.to_string())
+        .map_err(|e| anyhow::anyhow!("Failed to write config file: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_set_and_unset_keep_comments() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().to_path_buf();
+        std::fs::create_dir(path.join("repo")).unwrap();
+        std::fs::write(
+            path.join("config.toml"),
+            "[agents]\n\n[mcp]\n# log_requests = false\n",
+        )
+        .unwrap();
+        let set = |key: &str, values: &[&str], add: bool, remove: bool| {
+            let args = crate::cli::ConfigSetArgs {
+                key: key.to_string(),
+                values: values.iter().map(|v| v.to_string()).collect(),
+                add,
+                remove,
+            };
+            set(&Storage::new(path.clone()).unwrap(), &args)
+        };
+        let config = || Storage::new(path.clone()).unwrap().config;
+
+        set("mcp.log_requests", &["true"], false, false).unwrap();
+        set("mcp.disable_prompts", &["secret"], false, false).unwrap();
+        set("mcp.disable_prompts", &["drafts/*", "old"], true, false).unwrap();
+        set("mcp.disable_prompts", &["old"], false, true).unwrap();
+        set("sync.branch", &["dev"], false, false).unwrap();
+        assert!(config().mcp.log_requests);
+        assert!(matches!(
+            config().mcp.disable_prompts,
+            crate::storage::DisableOption::List(names) if names == ["secret", "drafts/*"]
+        ));
+        assert_eq!(config().sync.branch, "dev");
+        assert!(
+            std::fs::read_to_string(path.join("config.toml"))
+                .unwrap()
+                .contains("# log_requests = false")
+        );
+
+        assert!(set("mcp.log_requests", &["maybe"], false, false).is_err());
+        assert!(set("mcp.nope", &["1"], false, false).is_err());
+        assert!(config().mcp.log_requests);
+
+        let storage = Storage::new(path.clone()).unwrap();
+        unset(&storage, "sync.branch").unwrap();
+        assert_eq!(config().sync.branch, "main");
+        assert!(unset(&storage, "sync.branch").is_err());
+        assert!(unset(&storage, "agents").is_err());
+        assert!(is_known("agents.claude.enabled"));
+        assert!(is_known("mcp.token"));
+        assert!(!is_known("mcp.log_requests.deep"));
+    }
+
+    #[test]
+    fn test_schema_covers_config_sections() {
+        let schema: serde_json::Value = serde_json::from_str(&schema_json().unwrap()).unwrap();
+        let properties = schema["properties"].as_object().unwrap();
+        for section in ["agents", "mcp", "extensions", "format", "limits", "updates"] {
+            assert!(properties.contains_key(section), "missing {section}");
+        }
+        assert_eq!(schema["required"], serde_json::json!(["agents"]));
+    }
+}
@@ -0,0 +1,40 @@
+use crate::agent::Agent;
+use crate::commands::apply::{self, ApplyOptions, Change};
+
+pub fn set_continue_profile(
+    storage: &crate::storage::Storage,
+    profiles: &[String],
+    options: &ApplyOptions,
+) -> crate::Result<Vec<Change>> {
+    apply::set_profile(storage, Agent::Continue, profiles, options)
+}
+
+pub fn reset_continue_profile(
+    storage: &crate::storage::Storage,
+    options: &ApplyOptions,
+) -> crate::Result<Vec<Change>> {
+    apply::reset_profile(storage, Agent::Continue, options)
+}
+
+pub fn restore_continue_profile(
+    storage: &crate::storage::Storage,
+    project: bool,
+) -> crate::Result<Vec<Change>> {
+    apply::restore_profile(storage, Agent::Continue, project)
+}
+
+pub fn append_continue_profile(
+    storage: &crate::storage::Storage,
+    profiles: &[String],
+    options: &ApplyOptions,
+) -> crate::Result<Vec<Change>> {
+    apply::append_profile(storage, Agent::Continue, profiles, options)
+}
+
+pub fn remove_continue_profile(
+    storage: &crate::storage::Storage,
+    profile: &str,
+    options: &ApplyOptions,
+) -> crate::Result<Vec<Change>> {
+    apply::remove_profile(storage, Agent::Continue, profile, options)
+}
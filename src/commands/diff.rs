@@ -0,0 +1,131 @@
+use std::fmt::Write;
+use std::path::Path;
+
+use crate::agent::Agent;
+use crate::diff::DiffOptions;
+use crate::storage::Storage;
+
+/// Show how the agent's file differs from what setting `profile` would write to it;
+/// for agents with a rules directory, the profile's rule file
+pub fn diff(storage: &Storage, args: &crate::cli::DiffArgs) -> crate::Result<()> {
+    let mut target = args.agent.target(storage, args.project)?;
+    if args.agent.has_rules_dir() {
+        target.push(crate::commands::rules::rule_file_name(&args.profile));
+    }
+    let options = DiffOptions {
+        mode: if args.word_diff {
+            crate::diff::DiffMode::Word
+        } else {
+            crate::diff::DiffMode::Line
+        },
+        color: crate::utils::use_color(),
+        ..Default::default()
+    };
+    print!(
+        "{}",
+        compare(storage, args.agent, &args.profile, &target, &options)?
+    );
+    Ok(())
+}
+
+fn compare(
+    storage: &Storage,
+    agent: Agent,
+    profile: &str,
+    target: &Path,
+    options: &DiffOptions,
+) -> crate::Result<String> {
+    let content = if agent.has_rules_dir() {
+        crate::commands::rules::render_rule(storage, profile)?
+    } else {
+        crate::commands::apply::render_profiles(storage, agent, &[profile.to_string()])?
+    };
+    let label = format!("profile {profile}");
+
+    let mut report = String::new();
+    let existing = match std::fs::read(target) {
+        Ok(existing) => existing,
+        Err(_) => {
+            writeln!(
+                report,
+                "{} does not exist; setting profile '{}' would create it",
+                target.display(),
+                profile
+            )?;
+            report.push_str(&crate::diff::render(
+                "",
+                &content,
+                "/dev/null",
+                &label,
+                options,
+            ));
+            return Ok(report);
+        }
+    };
+
+    let state = crate::state::State::load(&storage.path)?;
+    if state.has_drifted(target, &existing) {
+        let applied = state
+            .get(target)
+            .map(|applied| applied.profiles.join(" + "))
+            .unwrap_or_default();
+        writeln!(
+            report,
+            "{} was edited since pmx applied {}",
+            target.display(),
+            applied
+        )?;
+    }
+
+    let existing = String::from_utf8_lossy(&existing);
+    let rendered = crate::diff::render(
+        &existing,
+        &content,
+        &target.display().to_string(),
+        &label,
+        options,
+    );
+    if rendered.is_empty() {
+        writeln!(report, "{} matches profile '{}'", target.display(), profile)?;
+    }
+    report.push_str(&rendered);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::apply::ApplyOptions;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_compare_reports_edits_to_applied_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::initialize(temp_dir.path().join("storage")).unwrap();
+        storage.create_profile("rust", "Use cargo.\n").unwrap();
+        let target = temp_dir.path().join("CLAUDE.md");
+        let options = DiffOptions::default();
+
+        let report = compare(&storage, Agent::Claude, "rust", &target, &options).unwrap();
+        assert!(report.contains("does not exist"));
+        assert!(report.contains("+Use cargo.\n"));
+
+        crate::commands::apply::apply_profiles_to_project(
+            &storage,
+            Agent::Claude,
+            &["rust".to_string()],
+            temp_dir.path(),
+            &ApplyOptions::default(),
+        )
+        .unwrap();
+        let report = compare(&storage, Agent::Claude, "rust", &target, &options).unwrap();
+        assert!(report.ends_with("matches profile 'rust'\n"));
+
+        let applied = std::fs::read_to_string(&target).unwrap();
+        std::fs::write(&target, format!("{applied}Use clippy.\n")).unwrap();
+        let report = compare(&storage, Agent::Claude, "rust", &target, &options).unwrap();
+        assert!(report.contains("was edited since pmx applied rust\n"));
+        assert!(report.contains("-Use clippy.\n"));
+        assert!(compare(&storage, Agent::Claude, "missing", &target, &options).is_err());
+    }
+}
@@ -0,0 +1,332 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::agent::Agent;
+use crate::storage::Storage;
+
+/// A problem `pmx doctor` found, with what to do about it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub problem: String,
+    pub fix: String,
+}
+
+impl Finding {
+    fn new(problem: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            problem: problem.into(),
+            fix: fix.into(),
+        }
+    }
+}
+
+/// Findings of `pmx doctor` by area: storage, config, agents, state and extensions
+#[derive(Debug, Clone, Default)]
+pub struct Diagnosis {
+    pub areas: Vec<(&'static str, Vec<Finding>)>,
+}
+
+impl Diagnosis {
+    pub fn problems(&self) -> usize {
+        self.areas.iter().map(|(_, findings)| findings.len()).sum()
+    }
+}
+
+impl fmt::Display for Diagnosis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (area, findings) in &self.areas {
+            if findings.is_empty() {
+                writeln!(f, "{area}: ok")?;
+                continue;
+            }
+            writeln!(f, "{area}:")?;
+            for finding in findings {
+                let mut lines = finding.problem.lines();
+                writeln!(f, "  {}", lines.next().unwrap_or_default())?;
+                for line in lines {
+                    writeln!(f, "    {line}")?;
+                }
+                writeln!(f, "    fix: {}", finding.fix)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Check the storage at `path`, its configuration, agent files, state and
+/// extensions. Areas that need a readable configuration are left out when the
+/// storage or its config is broken.
+pub fn doctor(path: &Path) -> Diagnosis {
+    let mut areas = vec![("storage", check_layout(path))];
+    if !path.is_dir() {
+        return Diagnosis { areas };
+    }
+    let (findings, storage) = check_config(path);
+    areas.push(("config", findings));
+    if let Some(storage) = storage {
+        areas.push(("agents", check_agents(&storage)));
+        areas.push(("state", check_state(&storage)));
+        areas.push(("extensions", check_extensions(&storage)));
+    }
+    Diagnosis { areas }
+}
+
+fn check_layout(path: &Path) -> Vec<Finding> {
+    if !path.exists() {
+        return vec![Finding::new(
+            format!("Storage directory {} does not exist", path.display()),
+            "run `pmx init` to create it",
+        )];
+    }
+    if !path.is_dir() {
+        return vec![Finding::new(
+            format!("Storage path {} is not a directory", path.display()),
+            "move it out of the way and run `pmx init`, or pass --storage",
+        )];
+    }
+
+    let mut findings = Vec::new();
+    let repo = path.join("repo");
+    if !repo.is_dir() {
+        findings.push(Finding::new(
+            format!("Profile directory {} is missing", repo.display()),
+            format!("mkdir -p {}", repo.display()),
+        ));
+    }
+    if !path.join("config.toml").is_file() {
+        findings.push(Finding::new(
+            format!("{} is missing", path.join("config.toml").display()),
+            "write one with at least an [agents.claude] table; see `pmx init` for a commented example",
+        ));
+    }
+    findings
+}
+
+/// Parse errors of `config.toml`, which name the line and column, and the storage
+/// when it loads
+fn check_config(path: &Path) -> (Vec<Finding>, Option<Storage>) {
+    let config = path.join("config.toml");
+    if !config.is_file() || !path.join("repo").is_dir() {
+        return (Vec::new(), None);
+    }
+    match Storage::new(path.to_path_buf()) {
+        Ok(storage) => {
+            let findings = storage
+                .config
+                .extensions
+                .functions
+                .iter()
+                .filter(|name| !storage.is_extension_allowed(name))
+                .map(|name| {
+                    Finding::new(
+                        format!("Function '{name}' is not in [extensions] allowed_subcommands"),
+                        format!(
+                            "add \"{name}\" to allowed_subcommands, or remove it from functions"
+                        ),
+                    )
+                })
+                .collect();
+            (findings, Some(storage))
+        }
+        Err(e) => (
+            vec![Finding::new(
+                format!("{e}"),
+                format!("correct {} as shown", config.display()),
+            )],
+            None,
+        ),
+    }
+}
+
+/// Enabled agents whose prompt file could not be written
+fn check_agents(storage: &Storage) -> Vec<Finding> {
+    let agents = match Agent::all(storage) {
+        Ok(agents) => agents,
+        Err(e) => {
+            return vec![Finding::new(
+                format!("{e}"),
+                "fix [agents.custom] in config.toml",
+            )];
+        }
+    };
+
+    let mut findings = Vec::new();
+    for agent in agents.into_iter().filter(|agent| agent.is_enabled(storage)) {
+        let name = agent.display_name();
+        let target = match agent.global_target(storage) {
+            Ok(target) => target,
+            Err(e) => {
+                findings.push(Finding::new(
+                    format!("{name}: {e}"),
+                    format!("set a valid path under [agents.{}]", agent.name()),
+                ));
+                continue;
+            }
+        };
+        if agent.has_rules_dir() {
+            if target.exists() && !target.is_dir() {
+                findings.push(Finding::new(
+                    format!("{name}: {} is not a directory", target.display()),
+                    "move it out of the way",
+                ));
+            }
+        } else if target.is_dir() {
+            findings.push(Finding::new(
+                format!("{name}: {} is a directory", target.display()),
+                "move it out of the way",
+            ));
+        } else if target
+            .metadata()
+            .is_ok_and(|metadata| metadata.permissions().readonly())
+        {
+            findings.push(Finding::new(
+                format!("{name}: {} is read-only", target.display()),
+                format!("chmod u+w {}", target.display()),
+            ));
+        } else if let Some(dir) = nearest_existing(&target)
+            && !is_writable(&dir)
+        {
+            findings.push(Finding::new(
+                format!("{name}: cannot write to {}", dir.display()),
+                format!(
+                    "make {} writable, or disable the agent with `pmx config set agents.{}.enabled false`",
+                    dir.display(),
+                    agent.name()
+                ),
+            ));
+        }
+    }
+    findings
+}
+
+/// The target's directory, or its closest ancestor that exists, which is where
+/// pmx creates the missing directories
+fn nearest_existing(target: &Path) -> Option<PathBuf> {
+    target
+        .ancestors()
+        .skip(1)
+        .find(|dir| dir.is_dir())
+        .map(Path::to_path_buf)
+}
+
+fn is_writable(dir: &Path) -> bool {
+    tempfile::Builder::new()
+        .prefix(".pmx-doctor")
+        .tempfile_in(dir)
+        .is_ok()
+}
+
+/// Applied profiles recorded in `state.toml` that no longer exist
+fn check_state(storage: &Storage) -> Vec<Finding> {
+    let state = match crate::state::State::load(&storage.path) {
+        Ok(state) => state,
+        Err(e) => {
+            return vec![Finding::new(
+                format!("{e}"),
+                format!(
+                    "move {} aside; pmx starts a new one",
+                    storage.path.join(crate::state::STATE_FILE).display()
+                ),
+            )];
+        }
+    };
+
+    let mut findings = Vec::new();
+    for (target, applied) in &state.applied {
+        let global = Agent::named(storage, &applied.agent)
+            .and_then(|agent| agent.global_target(storage))
+            .is_ok_and(|global| global == Path::new(target));
+        let local = if global { "" } else { " --local" };
+        let project = match Path::new(target).parent() {
+            Some(dir) if !global => format!(" in {}", dir.display()),
+            _ => String::new(),
+        };
+        for profile in &applied.profiles {
+            if storage.profile_exists(profile) {
+                continue;
+            }
+            findings.push(Finding::new(
+                format!("{target} has profile '{profile}' applied, which no longer exists"),
+                format!(
+                    "run `pmx set-profile {agent} <profile>{local}` or `pmx reset-profile {agent}{local}`{project}",
+                    agent = applied.agent
+                ),
+            ));
+        }
+    }
+    findings
+}
+
+/// Allowed extensions without a `pmx-<name>` executable on `PATH`
+fn check_extensions(storage: &Storage) -> Vec<Finding> {
+    storage
+        .config
+        .extensions
+        .allowed_subcommands
+        .iter()
+        .filter(|name| !on_path(&format!("pmx-{name}")))
+        .map(|name| {
+            Finding::new(
+                format!("pmx-{name} is not on PATH"),
+                format!("install pmx-{name}, or remove \"{name}\" from allowed_subcommands"),
+            )
+        })
+        .collect()
+}
+
+fn on_path(binary: &str) -> bool {
+    let Some(path) = std::env::var_os("PATH") else {
+        return false;
+    };
+    std::env::split_paths(&path).any(|dir| {
+        dir.join(binary).is_file() || (cfg!(windows) && dir.join(format!("{binary}.exe")).is_file())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn problems(areas: &[(&str, Vec<Finding>)], area: &str) -> Vec<String> {
+        areas
+            .iter()
+            .find(|(name, _)| *name == area)
+            .map(|(_, findings)| findings.iter().map(|f| f.problem.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn test_diagnose_reports_problems() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("storage");
+        assert_eq!(problems(&doctor(&path).areas, "storage").len(), 1);
+
+        let mut storage = Storage::initialize(path.clone()).unwrap();
+        storage.create_profile("kept", "Body\n").unwrap();
+        storage.config.extensions.allowed_subcommands = vec!["surely-not-installed".to_string()];
+        storage.config.persist(&path).unwrap();
+        crate::state::update(&storage, |state| {
+            let target = temp_dir.path().join("CLAUDE.md");
+            state.record_set(Agent::Claude, &target, "kept");
+            state.record_append(Agent::Claude, &target, "gone");
+        })
+        .unwrap();
+
+        let areas = doctor(&path).areas;
+        assert!(problems(&areas, "storage").is_empty());
+        assert!(problems(&areas, "config").is_empty());
+        assert_eq!(
+            problems(&areas, "extensions"),
+            ["pmx-surely-not-installed is not on PATH"]
+        );
+        let state = problems(&areas, "state");
+        assert_eq!(state.len(), 1);
+        assert!(state[0].contains("'gone'"));
+
+        std::fs::write(path.join("config.toml"), "[agents.claude]\nenabled = yes\n").unwrap();
+        let areas = doctor(&path).areas;
+        assert!(problems(&areas, "config")[0].contains("line 2"));
+        assert!(!areas.iter().any(|(area, _)| *area == "agents"));
+    }
+}
@@ -74,14 +74,13 @@ mod tests {
         std::fs::create_dir_all(path.join("repo")).unwrap();
 
         let config = Config {
-            agents: Agents {
-                disable_claude: false,
-                disable_codex: false,
-            },
+            agents: Agents::default(),
             mcp: McpConfig::default(),
             extensions: ExtensionsConfig {
                 allowed_subcommands,
+                ..Default::default()
             },
+            ..Default::default()
         };
 
         config.persist(&path).unwrap();
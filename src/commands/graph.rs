@@ -0,0 +1,117 @@
+use std::fmt::Write;
+
+use crate::include::Graph;
+use crate::storage::Storage;
+
+/// Print the include graph of the repository, as text or Graphviz dot
+pub fn graph(storage: &Storage, dot: bool) -> crate::Result<()> {
+    let graph = Graph::build(storage)?;
+    if dot {
+        print!("{}", render_dot(&graph)?);
+    } else {
+        print!("{}", describe(&graph)?);
+    }
+    Ok(())
+}
+
+/// What each profile includes and which profiles change when it is edited
+fn describe(graph: &Graph) -> crate::Result<String> {
+    let missing = graph.missing();
+    let mut report = String::new();
+
+    for (name, includes) in &graph.edges {
+        let affected = graph.affected(name);
+        if includes.is_empty() && affected.is_empty() {
+            continue;
+        }
+
+        writeln!(report, "{name}")?;
+        for target in includes {
+            let note = if missing.contains(target.as_str()) {
+                " (missing)"
+            } else {
+                ""
+            };
+            writeln!(report, "  includes {target}{note}")?;
+        }
+        if !affected.is_empty() {
+            let affected: Vec<&str> = affected.into_iter().collect();
+            writeln!(report, "  affects {}", affected.join(", "))?;
+        }
+    }
+
+    for cycle in graph.cycles() {
+        writeln!(report, "Cycle: {}", cycle.join(" -> "))?;
+    }
+
+    if report.is_empty() {
+        report.push_str("No include directives found\n");
+    }
+    Ok(report)
+}
+
+/// Graphviz source; missing profiles and edges on a cycle are drawn in red
+fn render_dot(graph: &Graph) -> crate::Result<String> {
+    let cycle_edges: Vec<(String, String)> = graph
+        .cycles()
+        .iter()
+        .flat_map(|cycle| {
+            cycle
+                .windows(2)
+                .map(|pair| (pair[0].clone(), pair[1].clone()))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    let mut dot = String::from("digraph profiles {\n");
+    for name in graph.edges.keys() {
+        writeln!(dot, "  {name:?};")?;
+    }
+    for name in graph.missing() {
+        writeln!(dot, "  {name:?} [color=red, style=dashed];")?;
+    }
+    for (name, includes) in &graph.edges {
+        for target in includes {
+            let on_cycle = cycle_edges
+                .iter()
+                .any(|(from, to)| from == name && to == target);
+            let style = if on_cycle { " [color=red]" } else { "" };
+            writeln!(dot, "  {name:?} -> {target:?}{style};")?;
+        }
+    }
+    dot.push_str("}\n");
+    Ok(dot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_describe_and_dot() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::initialize(temp_dir.path().join("storage")).unwrap();
+        storage
+            .create_profile("shared/style", "Be concise.\n")
+            .unwrap();
+        storage
+            .create_profile("rust", "{{include: shared/style}}\n{{include: gone}}\n")
+            .unwrap();
+        storage
+            .create_profile("loop", "{{include: loop}}\n")
+            .unwrap();
+        let graph = Graph::build(&storage).unwrap();
+
+        assert_eq!(
+            describe(&graph).unwrap(),
+            "loop\n  includes loop\nrust\n  includes shared/style\n  includes gone (missing)\n\
+             shared/style\n  affects rust\nCycle: loop -> loop\n"
+        );
+
+        let dot = render_dot(&graph).unwrap();
+        assert!(dot.contains("  \"rust\" -> \"shared/style\";\n"));
+        assert!(dot.contains("  \"gone\" [color=red, style=dashed];\n"));
+        assert!(dot.contains("  \"loop\" -> \"loop\" [color=red];\n"));
+    }
+}
@@ -0,0 +1,94 @@
+use std::path::Path;
+
+use anyhow::Context;
+use dialoguer::Confirm;
+
+use crate::commands::apply::{self, ApplyOptions, Change};
+use crate::project::ProjectConfig;
+use crate::storage::Storage;
+
+/// Print the shell snippet that runs the hook on every directory change
+pub fn hook(shell: &crate::cli::HookShell) -> crate::Result<()> {
+    match shell {
+        crate::cli::HookShell::Zsh => print!("{}", include_str!("../../shell/hook.zsh")),
+        crate::cli::HookShell::Bash => print!("{}", include_str!("../../shell/hook.bash")),
+    }
+    Ok(())
+}
+
+/// Print the prompt segment, wired to this storage directory
+pub fn plugin(storage: &Storage, shell: &crate::cli::PluginShell) -> crate::Result<()> {
+    let script = match shell {
+        crate::cli::PluginShell::Zsh => include_str!("../../shell/prompt.zsh"),
+    };
+    // Substituted inside single quotes
+    let storage_path = storage.path.to_string_lossy().replace('\'', "'\\''");
+    print!("{}", script.replace("@STORAGE@", &storage_path));
+    Ok(())
+}
+
+/// Apply the current directory's `.pmx.toml`, asking once per directory (and per
+/// file revision) whether it may be trusted
+pub fn run(storage: &Storage) -> crate::Result<Vec<Change>> {
+    let dir = std::env::current_dir()
+        .map_err(|e| anyhow::anyhow!("Failed to get current directory: {}", e))?;
+    let Some(project) = ProjectConfig::load(&dir)? else {
+        return Ok(Vec::new());
+    };
+
+    if !is_trusted(storage, &dir)? {
+        return Ok(Vec::new());
+    }
+
+    let state = crate::state::State::load(&storage.path)?;
+    let mut changes = Vec::new();
+    for (agent, profiles) in project.pins()? {
+        if !agent.is_enabled(storage) {
+            continue;
+        }
+        if crate::project::is_applied(&state, &agent.global_target(storage)?, profiles) {
+            continue;
+        }
+        changes.extend(apply::apply_profiles(
+            storage,
+            agent,
+            profiles,
+            &ApplyOptions::default(),
+        )?);
+    }
+
+    Ok(changes)
+}
+
+fn is_trusted(storage: &Storage, dir: &Path) -> crate::Result<bool> {
+    let path = ProjectConfig::path(dir);
+    let content = std::fs::read(&path)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+    let hash = crate::utils::content_hash(&content);
+
+    let state = crate::state::State::load(&storage.path)?;
+    if let Some(allowed) = state.project_trust(dir, &hash) {
+        return Ok(allowed);
+    }
+
+    if !crate::utils::is_interactive() {
+        eprintln!(
+            "pmx: {} is not trusted yet; cd into it from an interactive shell to review it",
+            path.display()
+        );
+        return Ok(false);
+    }
+
+    eprintln!("pmx: found {}:", path.display());
+    eprintln!("{}", String::from_utf8_lossy(&content).trim_end());
+    let allowed = Confirm::new()
+        .with_prompt("Apply the profiles pinned in this directory?")
+        .default(false)
+        .interact()
+        .with_context(|| "Failed to get confirmation")?;
+
+    crate::state::update(storage, |state| {
+        state.record_project_trust(dir, &hash, allowed)
+    })?;
+    Ok(allowed)
+}
@@ -0,0 +1,407 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::ensure;
+
+use crate::obsidian::{MapRules, SOURCE_PREFIX};
+use crate::storage::Storage;
+
+/// Import notes from an Obsidian vault as profiles. Profiles imported earlier are
+/// recognised by their `source:` and updated; other existing profiles are left alone
+/// unless `--force` is given.
+pub fn obsidian(storage: &Storage, args: &crate::cli::ObsidianImportArgs) -> crate::Result<()> {
+    ensure!(
+        args.vault.is_dir(),
+        "Vault directory does not exist: {}",
+        args.vault.display()
+    );
+    let rules = match &args.map {
+        Some(path) => MapRules::load(path)?,
+        None => MapRules::default(),
+    };
+
+    let (mut created, mut updated, mut unchanged, mut skipped) = (0, 0, 0, 0);
+    for import in crate::obsidian::plan(&args.vault, &rules)? {
+        if let Err(e) = crate::commands::profile::validate_profile_name(&import.name) {
+            tracing::warn!("Skipping {}: {}", import.note, e);
+            skipped += 1;
+            continue;
+        }
+
+        if storage.profile_exists(&import.name) {
+            let existing = storage.get_profile_content(&import.name)?;
+            if existing == import.content {
+                unchanged += 1;
+                continue;
+            }
+            let source = crate::frontmatter::parse_metadata(&existing)
+                .ok()
+                .and_then(|metadata| metadata.source);
+            let imported_from_note =
+                source.as_deref() == Some(format!("{SOURCE_PREFIX}{}", import.note).as_str());
+            if !imported_from_note && !args.force {
+                tracing::warn!(
+                    "Skipping {}: profile '{}' already exists and was not imported from it (use --force to overwrite)",
+                    import.note,
+                    import.name
+                );
+                skipped += 1;
+                continue;
+            }
+            storage.create_profile(&import.name, &import.content)?;
+            println!("Updated {} from {}", import.name, import.note);
+            updated += 1;
+        } else {
+            storage.create_profile(&import.name, &import.content)?;
+            println!("Imported {} from {}", import.name, import.note);
+            created += 1;
+        }
+    }
+
+    println!("{created} imported, {updated} updated, {unchanged} unchanged, {skipped} skipped");
+    Ok(())
+}
+
+/// Copy a markdown file, or a directory of them, into the repository as profiles.
+/// Existing profiles with different content are reported and kept unless `--force`.
+pub fn profiles(storage: &Storage, args: &crate::cli::ProfileImportArgs) -> crate::Result<()> {
+    let (mut created, mut updated, mut unchanged, mut skipped) = (0, 0, 0, 0);
+    for (name, file) in plan(&args.path, args.name.as_deref(), args.recursive)? {
+        if let Err(e) = crate::commands::profile::validate_profile_name(&name) {
+            tracing::warn!("Skipping {}: {}", file.display(), e);
+            skipped += 1;
+            continue;
+        }
+        let content = crate::encoding::read_text(&file)?;
+
+        if storage.profile_exists(&name) {
+            if storage.get_profile_content(&name)? == content {
+                unchanged += 1;
+                continue;
+            }
+            if !args.force {
+                tracing::warn!(
+                    "Skipping {}: profile '{}' already exists with different content (use --force to overwrite)",
+                    file.display(),
+                    name
+                );
+                skipped += 1;
+                continue;
+            }
+            storage.create_profile(&name, &content)?;
+            println!("Updated {} from {}", name, file.display());
+            updated += 1;
+        } else {
+            storage.create_profile(&name, &content)?;
+            println!("Imported {} from {}", name, file.display());
+            created += 1;
+        }
+    }
+
+    println!("{created} imported, {updated} updated, {unchanged} unchanged, {skipped} skipped");
+    Ok(())
+}
+
+/// A profile found in an archive: its files relative to `repo/`, the markdown
+/// content first
+struct ArchivedProfile<'a> {
+    name: String,
+    folder: bool,
+    files: Vec<(String, &'a [u8])>,
+}
+
+/// The profiles among an archive's `repo/` files: each folder profile with all
+/// of its files, and each other markdown file on its own
+fn archived_profiles(entries: &[crate::archive::Entry]) -> Vec<ArchivedProfile<'_>> {
+    use crate::storage::{FOLDER_MARKER, INDEX_FILE};
+
+    let files: Vec<(&str, &[u8])> = entries
+        .iter()
+        .filter_map(|(path, content)| Some((path.strip_prefix("repo/")?, content.as_slice())))
+        .collect();
+    let index_of = |dir: &str| {
+        files
+            .iter()
+            .find(|(path, _)| *path == format!("{dir}/{INDEX_FILE}"))
+    };
+    let folders: Vec<&str> = files
+        .iter()
+        .filter_map(|(path, _)| path.strip_suffix(&format!("/{FOLDER_MARKER}")))
+        .filter(|dir| index_of(dir).is_some())
+        .collect();
+    let folder_of = |path: &str| {
+        folders
+            .iter()
+            .filter(|dir| path.starts_with(&format!("{dir}/")))
+            .min_by_key(|dir| dir.len())
+            .copied()
+    };
+
+    let mut profiles = Vec::new();
+    for &dir in folders.iter().filter(|dir| folder_of(dir).is_none()) {
+        let (index, content) = index_of(dir).unwrap();
+        let mut profile = ArchivedProfile {
+            name: dir.to_string(),
+            folder: true,
+            files: vec![(index.to_string(), *content)],
+        };
+        profile.files.extend(
+            files
+                .iter()
+                .filter(|(path, _)| path != index && folder_of(path) == Some(dir))
+                .map(|(path, content)| (path.to_string(), *content)),
+        );
+        profiles.push(profile);
+    }
+    for &(path, content) in files.iter().filter(|(path, _)| folder_of(path).is_none()) {
+        match path.strip_suffix(".md") {
+            Some(name) => profiles.push(ArchivedProfile {
+                name: name.to_string(),
+                folder: false,
+                files: vec![(path.to_string(), content)],
+            }),
+            None => tracing::warn!("Skipping {path}: it belongs to no profile"),
+        }
+    }
+    profiles
+}
+
+/// Restore a bundle written by `profile export`. Its profiles are saved like any
+/// other, and its `config.toml` replaces the current one only with
+/// `--with-config`, after confirmation.
+pub fn archive(
+    storage: &Storage,
+    args: &crate::cli::ProfileImportArgs,
+    yes: bool,
+) -> crate::Result<()> {
+    let entries = crate::archive::read(&args.path)?;
+    let repo = storage.path.join("repo");
+
+    let (mut created, mut updated, mut unchanged, mut skipped) = (0, 0, 0, 0);
+    for profile in archived_profiles(&entries) {
+        let name = &profile.name;
+        if let Err(e) = crate::commands::profile::validate_profile_name(name) {
+            tracing::warn!("Skipping {name}: {e}");
+            skipped += 1;
+            continue;
+        }
+        let Ok(content) = std::str::from_utf8(profile.files[0].1) else {
+            tracing::warn!("Skipping {name}: its markdown is not UTF-8");
+            skipped += 1;
+            continue;
+        };
+
+        let exists = storage.profile_exists(name);
+        if exists {
+            let same = profile
+                .files
+                .iter()
+                .all(|(path, content)| std::fs::read(repo.join(path)).is_ok_and(|c| c == *content));
+            if same {
+                unchanged += 1;
+                continue;
+            }
+            if !args.force {
+                tracing::warn!(
+                    "Skipping {name}: it already exists with different content (use --force to overwrite it)"
+                );
+                skipped += 1;
+                continue;
+            }
+        }
+
+        if profile.folder {
+            // The auxiliary files and marker go in first, so that the profile is
+            // saved as a folder profile
+            for (path, content) in &profile.files[1..] {
+                let dest = repo.join(path);
+                if let Some(parent) = dest.parent() {
+                    std::fs::create_dir_all(parent).map_err(|e| {
+                        anyhow::anyhow!("Failed to create directory {}: {}", parent.display(), e)
+                    })?;
+                }
+                std::fs::write(&dest, content)
+                    .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", dest.display(), e))?;
+            }
+            let index = repo.join(&profile.files[0].0);
+            if !index.exists() {
+                std::fs::write(&index, "")
+                    .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", index.display(), e))?;
+            }
+        }
+        storage.create_profile(name, content)?;
+        if profile.folder {
+            crate::manifest::refresh(&storage.path, &[PathBuf::from(name)])?;
+        }
+        if exists {
+            println!("Updated {name}");
+            updated += 1;
+        } else {
+            println!("Imported {name}");
+            created += 1;
+        }
+    }
+
+    if let Some((_, config)) = entries.iter().find(|(path, _)| path == "config.toml") {
+        let path = storage.path.join("config.toml");
+        if std::fs::read(&path).is_ok_and(|current| current != *config) {
+            if args.with_config {
+                let text = String::from_utf8_lossy(config);
+                toml::from_str::<crate::storage::Config>(&text).map_err(|e| {
+                    anyhow::anyhow!("Failed to parse config.toml from the archive: {}", e)
+                })?;
+                // A configuration names commands pmx runs, so only a trusted one belongs here
+                let prompt = "Replace config.toml with the archive's? It can set the editor, extensions and passphrase command pmx runs";
+                if crate::utils::confirm(prompt, yes)? {
+                    std::fs::write(&path, config)
+                        .map_err(|e| anyhow::anyhow!("Failed to write config file: {}", e))?;
+                    println!("Replaced config.toml");
+                } else {
+                    println!("Kept the existing config.toml");
+                }
+            } else {
+                tracing::warn!(
+                    "Kept the existing config.toml, which differs from the archive's (use --with-config to replace it)"
+                );
+            }
+        }
+    }
+
+    println!("{created} imported, {updated} updated, {unchanged} unchanged, {skipped} skipped");
+    Ok(())
+}
+
+/// Profile names for the markdown files at `path`: a file is named `name` or after
+/// its stem; a directory's files keep their nesting, under `name` when given, and
+/// subdirectories are only read when `recursive`
+fn plan(path: &Path, name: Option<&str>, recursive: bool) -> crate::Result<Vec<(String, PathBuf)>> {
+    if path.is_file() {
+        let name = match name {
+            Some(name) => name.to_string(),
+            None => path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .ok_or_else(|| anyhow::anyhow!("Cannot name a profile after {}", path.display()))?,
+        };
+        return Ok(vec![(name, path.to_path_buf())]);
+    }
+    ensure!(
+        path.is_dir(),
+        "No such file or directory: {}",
+        path.display()
+    );
+
+    let prefix = name.map(|name| name.trim_end_matches('/'));
+    Ok(crate::utils::visible_files(path)?
+        .into_iter()
+        .filter(|relative| relative.extension().is_some_and(|e| e == "md"))
+        .filter(|relative| recursive || relative.components().count() == 1)
+        .map(|relative| {
+            let stem = relative
+                .with_extension("")
+                .to_string_lossy()
+                .replace('\\', "/");
+            let name = match prefix {
+                Some(prefix) => format!("{prefix}/{stem}"),
+                None => stem,
+            };
+            (name, path.join(relative))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_plan_names_files_and_trees() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("prompts");
+        std::fs::create_dir_all(source.join("lang/.drafts")).unwrap();
+        std::fs::write(source.join("base.md"), "Base\n").unwrap();
+        std::fs::write(source.join("notes.txt"), "Not a profile\n").unwrap();
+        std::fs::write(source.join("lang/rust.md"), "Rust\n").unwrap();
+        std::fs::write(source.join("lang/.drafts/go.md"), "Go\n").unwrap();
+
+        let names = |name, recursive| -> Vec<String> {
+            plan(&source, name, recursive)
+                .unwrap()
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect()
+        };
+        assert_eq!(names(None, false), vec!["base"]);
+        assert_eq!(names(None, true), vec!["base", "lang/rust"]);
+        assert_eq!(
+            names(Some("team/"), true),
+            vec!["team/base", "team/lang/rust"]
+        );
+
+        let file = source.join("lang/rust.md");
+        assert_eq!(plan(&file, None, false).unwrap()[0].0, "rust");
+        assert_eq!(
+            plan(&file, Some("coding/rust"), false).unwrap()[0].0,
+            "coding/rust"
+        );
+        assert!(plan(&temp_dir.path().join("missing"), None, false).is_err());
+    }
+
+    #[test]
+    fn test_archive_saves_profiles_and_keeps_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::initialize(temp_dir.path().join("storage")).unwrap();
+        storage.create_profile("base", "Local base\n").unwrap();
+        let config = std::fs::read(storage.path.join("config.toml")).unwrap();
+
+        let bundle = temp_dir.path().join("bundle");
+        let entry = |path: &str, content: &str| (path.to_string(), content.as_bytes().to_vec());
+        crate::archive::write(
+            crate::archive::Format::Dir,
+            &bundle,
+            &[
+                entry("config.toml", "[agents]\n[editor]\ncommand = \"evil\"\n"),
+                entry("repo/base.md", "Archived base\n"),
+                entry("repo/lang/rust.md", "Rust\n"),
+                entry("repo/bad|name.md", "Bad\n"),
+                entry("repo/review/.pmx-folder", ""),
+                entry("repo/review/index.md", "Review\n"),
+                entry("repo/review/checklist.md", "- Tests\n"),
+            ],
+        )
+        .unwrap();
+
+        let args = crate::cli::ProfileImportArgs {
+            path: bundle,
+            name: None,
+            recursive: false,
+            archive: true,
+            with_config: false,
+            force: false,
+        };
+        archive(&storage, &args, false).unwrap();
+
+        let mut names = storage.list_repos().unwrap();
+        names.sort();
+        assert_eq!(names, vec!["base", "lang/rust", "review"]);
+        assert_eq!(storage.get_content("base").unwrap(), "Local base\n");
+        assert_eq!(storage.get_content("review").unwrap(), "Review\n");
+        assert_eq!(
+            storage.auxiliary_files("review").unwrap(),
+            vec![PathBuf::from("checklist.md")]
+        );
+        assert_eq!(
+            std::fs::read(storage.path.join("config.toml")).unwrap(),
+            config
+        );
+
+        // Without a terminal, replacing the configuration needs --yes
+        let args = crate::cli::ProfileImportArgs {
+            with_config: true,
+            force: true,
+            ..args
+        };
+        assert!(archive(&storage, &args, false).is_err());
+        assert_eq!(storage.get_content("base").unwrap(), "Archived base\n");
+    }
+}
@@ -0,0 +1,111 @@
+use std::path::Path;
+
+use anyhow::ensure;
+
+use crate::storage::Storage;
+
+/// Commented `config.toml` written by `pmx init`; every setting shows its default
+const CONFIG_TEMPLATE: &str = include_str!("../../init/config.toml");
+
+/// Profiles written with `--examples`, under `examples/`
+const EXAMPLES: [(&str, &str); 3] = [
+    (
+        "examples/code-review",
+        include_str!("../../init/examples/code-review.md"),
+    ),
+    (
+        "examples/concise",
+        include_str!("../../init/examples/concise.md"),
+    ),
+    (
+        "examples/project",
+        include_str!("../../init/examples/project.md"),
+    ),
+];
+
+/// Create a storage directory at `path` with a commented configuration and,
+/// optionally, a few example profiles
+pub fn init(path: &Path, examples: bool) -> crate::Result<()> {
+    ensure!(
+        !path.join("config.toml").exists(),
+        "Storage is already initialized at {}",
+        path.display()
+    );
+    if path.exists() {
+        // An empty directory (e.g. a freshly mounted volume) may be used as is
+        let empty = std::fs::read_dir(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?
+            .next()
+            .is_none();
+        ensure!(
+            empty,
+            "{} already exists and is not empty; choose another --path",
+            path.display()
+        );
+        std::fs::remove_dir(path)
+            .map_err(|e| anyhow::anyhow!("Failed to prepare {}: {}", path.display(), e))?;
+    }
+
+    Storage::initialize(path.to_path_buf())?;
+    std::fs::write(path.join("config.toml"), CONFIG_TEMPLATE)
+        .map_err(|e| anyhow::anyhow!("Failed to write config file: {}", e))?;
+    let storage = Storage::new(path.to_path_buf())?;
+    let templates = path.join(crate::template::TEMPLATES_DIR);
+    std::fs::create_dir_all(&templates)
+        .map_err(|e| anyhow::anyhow!("Failed to create {}: {}", templates.display(), e))?;
+    for (name, content) in crate::template::BUILTIN_TEMPLATES {
+        let file = templates.join(format!("{name}.md"));
+        std::fs::write(&file, content)
+            .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", file.display(), e))?;
+    }
+    println!("Initialized pmx storage at {}", path.display());
+
+    if examples {
+        for (name, content) in EXAMPLES {
+            storage.create_profile(name, content)?;
+            println!("  added profile {name}");
+        }
+    }
+
+    println!(
+        "Next: `pmx profile create <name>` (or `--template code-review`) to write a profile, then `pmx set-claude-profile <name>`"
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_init_writes_commented_config_and_examples() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("storage");
+        init(&path, true).unwrap();
+
+        let config = std::fs::read_to_string(path.join("config.toml")).unwrap();
+        assert_eq!(config, CONFIG_TEMPLATE);
+        let storage = Storage::new(path.clone()).unwrap();
+        assert!(path.join("templates/code-review.md").is_file());
+        let mut profiles = storage.list_repos().unwrap();
+        profiles.sort();
+        assert_eq!(
+            profiles,
+            vec![
+                "examples/code-review",
+                "examples/concise",
+                "examples/project"
+            ]
+        );
+
+        assert!(init(&path, false).is_err());
+        let occupied = temp_dir.path().join("occupied");
+        std::fs::create_dir_all(&occupied).unwrap();
+        std::fs::write(occupied.join("notes.txt"), "mine").unwrap();
+        assert!(init(&occupied, false).is_err());
+        let empty = temp_dir.path().join("empty");
+        std::fs::create_dir_all(&empty).unwrap();
+        init(&empty, false).unwrap();
+    }
+}
@@ -1,56 +1,707 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+
 use anyhow::Result;
 use rmcp::{
     RoleServer, ServerHandler, ServiceExt,
     model::{ErrorData as McpError, *},
-    service::RequestContext,
+    service::{NotificationContext, RequestContext},
 };
 use serde_json::Value;
 use tokio::io::{stdin, stdout};
+use tokio::sync::broadcast;
+
+use crate::cli::McpTransport;
+use crate::watch::{Debouncer, Snapshot};
+
+const COPY_TO_CLIPBOARD_TOOL: &str = "copy_to_clipboard";
+const LIST_PROFILES_TOOL: &str = "list_profiles";
+const GET_PROFILE_TOOL: &str = "get_profile";
+const CREATE_PROFILE_TOOL: &str = "create_profile";
+const SEARCH_PROFILES_TOOL: &str = "search_profiles";
+
+/// Tools that change something outside the server, offered only when listed in
+/// `[mcp] enable_tools`
+const OPT_IN_TOOLS: &[&str] = &[COPY_TO_CLIPBOARD_TOOL, CREATE_PROFILE_TOOL];
+
+/// Matching lines returned by `search_profiles`
+const MAX_SEARCH_RESULTS: usize = 100;
+
+/// Attachments larger than this are left out of prompt results
+const MAX_ATTACHMENT_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Profiles read at the same time while listing prompts
+const LIST_CONCURRENCY: usize = 16;
+
+/// Prompts per `prompts/list` page when `[mcp] page_size` is not set
+const DEFAULT_PAGE_SIZE: usize = 100;
+
+/// How often a running server checks the storage for changes
+const RELOAD_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Arguments of the `copy_to_clipboard` tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct CopyToClipboardParams {
+    /// Name of the profile to copy
+    name: String,
+    /// Values for the profile's `<{{VARIABLE}}>` placeholders
+    #[serde(default)]
+    arguments: Option<JsonObject>,
+}
+
+/// Arguments of the `list_profiles` tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct ListProfilesParams {
+    /// Only list profiles whose name starts with this, e.g. `coding/`
+    #[serde(default)]
+    prefix: Option<String>,
+}
+
+/// Arguments of the `get_profile` tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct GetProfileParams {
+    /// Name of the profile
+    name: String,
+}
+
+/// Arguments of the `create_profile` tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct CreateProfileParams {
+    /// Name of the profile, e.g. `coding/rust`
+    name: String,
+    /// Markdown content, including any front-matter
+    content: String,
+    /// Replace the profile if it already exists
+    #[serde(default)]
+    overwrite: bool,
+}
+
+/// Arguments of the `search_profiles` tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct SearchProfilesParams {
+    /// Text to look for in profile names and content, ignoring case
+    query: String,
+}
+
+/// Text an argument value stands for in a prompt: strings as they are, anything
+/// else as JSON
+fn argument_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string().trim_matches('"').to_string(),
+    }
+}
+
+/// Parse tool arguments into their parameter struct
+fn params<T: serde::de::DeserializeOwned>(arguments: Option<JsonObject>) -> Result<T, McpError> {
+    serde_json::from_value(Value::Object(arguments.unwrap_or_default()))
+        .map_err(|e| McpError::invalid_params(format!("Invalid arguments: {e}"), None))
+}
+
+/// Whether `name` is one of `patterns` or matches one as a glob; `*` spans `/`
+fn matches_any(patterns: &[String], name: &str) -> bool {
+    patterns.iter().any(|pattern| {
+        pattern == name || glob::Pattern::new(pattern).is_ok_and(|glob| glob.matches(name))
+    })
+}
+
+/// Modification time of `path`, or `None` when it cannot be read
+fn modified(path: &std::path::Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Modification time of the HEAD log of the git repository holding `file`,
+/// which git appends to on every commit, checkout or reset
+fn head_log_modified(file: &Path) -> Option<SystemTime> {
+    let dir = file
+        .ancestors()
+        .skip(1)
+        .find(|dir| dir.join(".git").exists())?;
+    let git = dir.join(".git");
+    let git_dir = if git.is_dir() {
+        git
+    } else {
+        // Submodules point to their repository from a `.git` file
+        let pointer = std::fs::read_to_string(&git).ok()?;
+        dir.join(pointer.strip_prefix("gitdir:")?.trim())
+    };
+    modified(&git_dir.join("logs").join("HEAD"))
+}
+
+/// `Snapshot::take` off the async threads, as it reads every profile's metadata
+async fn take_snapshot(storage: Arc<crate::storage::Storage>) -> Result<Snapshot> {
+    tokio::task::spawn_blocking(move || Snapshot::take(&storage)).await?
+}
+
+/// The `page_size` names after `cursor` in name order, with the cursor of the
+/// page that follows them. A cursor holds the last name of its page, so pages
+/// stay consistent while profiles are added or removed between requests.
+fn page(
+    mut names: Vec<String>,
+    cursor: Option<&str>,
+    page_size: usize,
+) -> Result<(Vec<String>, Option<String>), McpError> {
+    use base64::Engine;
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+
+    names.sort();
+    if let Some(cursor) = cursor {
+        let after = URL_SAFE_NO_PAD
+            .decode(cursor)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .ok_or_else(|| McpError::invalid_params(format!("Invalid cursor: {cursor}"), None))?;
+        names.retain(|name| *name > after);
+    }
+    if page_size == 0 || names.len() <= page_size {
+        return Ok((names, None));
+    }
+    names.truncate(page_size);
+    let next = URL_SAFE_NO_PAD.encode(names[page_size - 1].as_bytes());
+    Ok((names, Some(next)))
+}
 
+/// JSON Schema of a tool's parameter struct
+fn input_schema<T: schemars::JsonSchema>() -> JsonObject {
+    serde_json::to_value(schemars::schema_for!(T))
+        .ok()
+        .and_then(|schema| schema.as_object().cloned())
+        .unwrap_or_default()
+}
+
+/// The MCP server over the profiles. Clones, one per session or listing task,
+/// share its state.
 #[derive(Clone)]
 pub struct PmxMcpServer {
-    storage: crate::storage::Storage,
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    /// Swapped for a new one when `config.toml` changes
+    storage: RwLock<Arc<crate::storage::Storage>>,
+    /// Set when `[mcp] log_requests` is on
+    request_log: Option<crate::redact::Redactor>,
+    /// Tells every session that the prompt list changed
+    prompts_changed: broadcast::Sender<()>,
+    /// Last commit of each profile file
+    revisions: Mutex<HashMap<PathBuf, CachedRevision>>,
+}
+
+/// A file's last commit, with the modification times of the file and of its
+/// repository's HEAD log it was looked up at
+struct CachedRevision {
+    file: SystemTime,
+    head: SystemTime,
+    revision: Option<String>,
 }
 
 impl PmxMcpServer {
-    pub fn new(storage: crate::storage::Storage) -> Self {
-        Self { storage }
+    pub fn new(storage: crate::storage::Storage) -> Result<Self> {
+        let request_log = if storage.config.mcp.log_requests {
+            Some(crate::redact::Redactor::new(&storage.config.mcp.redact)?)
+        } else {
+            None
+        };
+        Ok(Self {
+            shared: Arc::new(Shared {
+                storage: RwLock::new(Arc::new(storage)),
+                request_log,
+                prompts_changed: broadcast::channel(16).0,
+                revisions: Mutex::default(),
+            }),
+        })
+    }
+
+    /// The storage as of the latest reload; requests keep the one they started
+    /// with while a reload swaps it
+    fn storage(&self) -> Arc<crate::storage::Storage> {
+        Arc::clone(&self.shared.storage.read().unwrap())
+    }
+
+    /// Poll the storage until the server stops. A changed `config.toml` is
+    /// reloaded, and sessions are told to refresh their prompt list whenever it
+    /// or a profile changes; bursts of changes are batched like `pmx watch` does.
+    async fn watch_storage(self) {
+        let storage = self.storage();
+        let config_path = storage.path.join("config.toml");
+        let mut config_stamp = modified(&config_path);
+        let Ok(snapshot) = take_snapshot(storage.clone()).await else {
+            tracing::warn!("failed to list profiles; not watching for changes");
+            return;
+        };
+        let mut debouncer = Debouncer::new(
+            snapshot,
+            Duration::from_millis(storage.config.watch.debounce_ms),
+            Duration::from_millis(storage.config.watch.max_delay_ms),
+        );
+
+        loop {
+            tokio::time::sleep(RELOAD_INTERVAL).await;
+            let mut changed = false;
+
+            let stamp = modified(&config_path);
+            if stamp != config_stamp {
+                config_stamp = stamp;
+                match crate::storage::Storage::new(storage.path.clone()) {
+                    Ok(reloaded) => {
+                        tracing::info!("reloaded config.toml");
+                        *self.shared.storage.write().unwrap() = Arc::new(reloaded);
+                        changed = true;
+                    }
+                    Err(e) => tracing::warn!(error = %e, "keeping the previous configuration"),
+                }
+            }
+
+            match take_snapshot(self.storage()).await {
+                Ok(snapshot) => {
+                    let changes = debouncer.poll(snapshot, Instant::now());
+                    if !changes.is_empty() {
+                        tracing::info!(count = changes.len(), "profiles changed");
+                        changed = true;
+                    }
+                }
+                Err(e) => tracing::warn!(error = %e, "failed to list profiles"),
+            }
+
+            if changed {
+                self.announce_prompts_changed();
+            }
+        }
     }
 
+    /// Tell every session to refresh its prompt list
+    pub(crate) fn announce_prompts_changed(&self) {
+        // No session may be connected, which is fine
+        let _ = self.shared.prompts_changed.send(());
+    }
+
+    /// Whether a profile is listed by `enable_prompts`, when set, and not by
+    /// `disable_prompts`
     fn is_prompt_enabled(&self, prompt_name: &str) -> bool {
-        match &self.storage.config.mcp.disable_prompts {
+        let mcp = &self.storage().config.mcp;
+        let enabled = mcp
+            .enable_prompts
+            .as_deref()
+            .is_none_or(|patterns| matches_any(patterns, prompt_name));
+        enabled
+            && match &mcp.disable_prompts {
+                crate::storage::DisableOption::Bool(disabled) => !disabled,
+                crate::storage::DisableOption::List(patterns) => {
+                    !matches_any(patterns, prompt_name)
+                }
+            }
+    }
+
+    fn is_tool_enabled(&self, tool_name: &str) -> bool {
+        let mcp = &self.storage().config.mcp;
+        if OPT_IN_TOOLS.contains(&tool_name) && !mcp.enable_tools.iter().any(|t| t == tool_name) {
+            return false;
+        }
+        match &mcp.disable_tools {
             crate::storage::DisableOption::Bool(true) => false,
             crate::storage::DisableOption::Bool(false) => true,
             crate::storage::DisableOption::List(disabled_list) => {
-                !disabled_list.contains(&prompt_name.to_string())
+                !disabled_list.contains(&tool_name.to_string())
             }
         }
     }
 
-    /// Extract argument templates from prompt content using <{{variable}}> pattern
-    fn extract_arguments_from_content(&self, content: &str) -> Vec<PromptArgument> {
-        use regex::Regex;
-
-        // Pattern matches <{{VARIABLE_NAME}}> where VARIABLE_NAME can contain letters, numbers, underscores
-        let re = Regex::new(r"<\{\{([A-Za-z_][A-Za-z0-9_]*)\}\}>").unwrap();
-        let mut arguments = Vec::new();
-        let mut seen = std::collections::HashSet::new();
-
-        for cap in re.captures_iter(content) {
-            if let Some(var_name) = cap.get(1) {
-                let name = var_name.as_str().to_string();
-                // Avoid duplicates
-                if seen.insert(name.clone()) {
-                    arguments.push(PromptArgument {
-                        name: name.clone(),
-                        description: Some(format!("Value for {}", name)),
-                        required: Some(true),
-                    });
+    fn tools(&self) -> Vec<Tool> {
+        [
+            Tool::new(
+                COPY_TO_CLIPBOARD_TOOL,
+                "Render a prompt with its arguments and place it on the user's clipboard",
+                input_schema::<CopyToClipboardParams>(),
+            ),
+            Tool::new(
+                LIST_PROFILES_TOOL,
+                "List the names of the stored profiles",
+                input_schema::<ListProfilesParams>(),
+            ),
+            Tool::new(
+                GET_PROFILE_TOOL,
+                "Read a profile's markdown as stored, front-matter included",
+                input_schema::<GetProfileParams>(),
+            ),
+            Tool::new(
+                CREATE_PROFILE_TOOL,
+                "Create a profile, or replace one when overwrite is set",
+                input_schema::<CreateProfileParams>(),
+            ),
+            Tool::new(
+                SEARCH_PROFILES_TOOL,
+                "Find profiles whose name or content contains some text",
+                input_schema::<SearchProfilesParams>(),
+            ),
+        ]
+        .into_iter()
+        .filter(|tool| self.is_tool_enabled(&tool.name))
+        .collect()
+    }
+
+    /// Profiles visible over MCP: those not hidden by `disable_prompts`
+    fn visible_profiles(&self) -> Result<Vec<String>, McpError> {
+        let mut profiles = self
+            .storage()
+            .list_repos()
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        profiles.retain(|name| self.is_prompt_enabled(name));
+        profiles.sort();
+        Ok(profiles)
+    }
+
+    fn list_profiles(&self, arguments: Option<JsonObject>) -> Result<CallToolResult, McpError> {
+        let params: ListProfilesParams = params(arguments)?;
+        let prefix = params.prefix.unwrap_or_default();
+        let names: Vec<String> = self
+            .visible_profiles()?
+            .into_iter()
+            .filter(|name| name.starts_with(&prefix))
+            .collect();
+        Ok(CallToolResult::success(vec![Content::text(
+            names.join("\n"),
+        )]))
+    }
+
+    fn get_profile(&self, arguments: Option<JsonObject>) -> Result<CallToolResult, McpError> {
+        let params: GetProfileParams = params(arguments)?;
+        check_profile_name(&params.name)?;
+        if !self.is_prompt_enabled(&params.name) {
+            return Err(McpError::invalid_params("Profile is disabled", None));
+        }
+        let content = self
+            .storage()
+            .get_profile_content(&params.name)
+            .map_err(|e| McpError::invalid_params(format!("Profile not found: {e}"), None))?;
+        Ok(CallToolResult::success(vec![Content::text(content)]))
+    }
+
+    fn create_profile(&self, arguments: Option<JsonObject>) -> Result<CallToolResult, McpError> {
+        let params: CreateProfileParams = params(arguments)?;
+        check_profile_name(&params.name)?;
+        if !self.is_prompt_enabled(&params.name) {
+            return Err(McpError::invalid_params("Profile is disabled", None));
+        }
+        let exists = self.storage().profile_exists(&params.name);
+        if exists && !params.overwrite {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "Profile '{}' already exists; set overwrite to replace it",
+                params.name
+            ))]));
+        }
+
+        Ok(
+            match self.storage().create_profile(&params.name, &params.content) {
+                Ok(()) => CallToolResult::success(vec![Content::text(format!(
+                    "{} profile '{}'",
+                    if exists { "Updated" } else { "Created" },
+                    params.name
+                ))]),
+                Err(e) => CallToolResult::error(vec![Content::text(format!(
+                    "Failed to save profile '{}': {e}",
+                    params.name
+                ))]),
+            },
+        )
+    }
+
+    fn search_profiles(&self, arguments: Option<JsonObject>) -> Result<CallToolResult, McpError> {
+        let params: SearchProfilesParams = params(arguments)?;
+        if params.query.is_empty() {
+            return Err(McpError::invalid_params("Query is empty", None));
+        }
+
+        let options = crate::search::SearchOptions {
+            ignore_case: true,
+            ..Default::default()
+        };
+        let results = crate::search::search(&self.storage(), &params.query, &options)
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        let mut matches = Vec::new();
+        for result in results {
+            if !self.is_prompt_enabled(&result.profile) {
+                continue;
+            }
+            if !result.name_matches.is_empty() {
+                matches.push(result.profile.clone());
+            }
+            matches.extend(
+                result.lines.iter().map(|line| {
+                    format!("{}:{}: {}", result.profile, line.number, line.text.trim())
+                }),
+            );
+        }
+
+        let text = if matches.is_empty() {
+            format!("No profiles match '{}'", params.query)
+        } else {
+            let total = matches.len();
+            matches.truncate(MAX_SEARCH_RESULTS);
+            if total > MAX_SEARCH_RESULTS {
+                matches.push(format!("... {} more", total - MAX_SEARCH_RESULTS));
+            }
+            matches.join("\n")
+        };
+        Ok(CallToolResult::success(vec![Content::text(text)]))
+    }
+
+    /// Load an enabled prompt and fill in its arguments
+    fn render_prompt(
+        &self,
+        name: &str,
+        arguments: &Option<JsonObject>,
+    ) -> Result<String, McpError> {
+        check_profile_name(name)?;
+        if !self.is_prompt_enabled(name) {
+            return Err(McpError::invalid_params("Prompt is disabled", None));
+        }
+
+        let content = self
+            .storage()
+            .get_content(name)
+            .map_err(|e| McpError::invalid_params(format!("Prompt not found: {e}"), None))?;
+
+        // Front-matter that does not parse declares no defaults, so the client must
+        // then give every placeholder a value
+        let metadata = crate::frontmatter::parse_metadata(&content).unwrap_or_default();
+        let body = crate::frontmatter::body(&content);
+        let given = |var: &str| arguments.as_ref()?.get(var).map(argument_text);
+        let resolved = crate::template::resolve(body, &metadata, given)
+            .map_err(|e| McpError::invalid_params(e.to_string(), None))?;
+        let resolved: JsonObject = resolved
+            .into_iter()
+            .map(|(var, value)| (var, Value::String(value)))
+            .collect();
+
+        // Function calls and secrets come from the stored profile only; expanding
+        // them after the client's values went in would let a value run an
+        // extension or read any secret
+        let content = crate::functions::expand(&self.storage(), body)
+            .and_then(|content| crate::secrets::resolve(&content))
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        Ok(self.substitute_arguments(&content, &Some(resolved)))
+    }
+
+    /// Auxiliary files of a folder profile as prompt content: images inline, other
+    /// files as embedded `pmx://` resources
+    fn attachments(&self, name: &str) -> Result<Vec<PromptMessageContent>, McpError> {
+        use base64::Engine;
+        use base64::engine::general_purpose::STANDARD;
+
+        let Some(dir) = self.storage().profile_folder(name) else {
+            return Ok(Vec::new());
+        };
+        let files = self
+            .storage()
+            .auxiliary_files(name)
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+        let annotations = self.annotations(name);
+        let mut contents = Vec::new();
+        for file in files {
+            let path = dir.join(&file);
+            if std::fs::metadata(&path).map_or(true, |m| m.len() > MAX_ATTACHMENT_BYTES) {
+                continue;
+            }
+            let Ok(data) = std::fs::read(&path) else {
+                continue;
+            };
+
+            let relative = file.to_string_lossy().replace('\\', "/");
+            let uri = format!("pmx://profile/{name}/{relative}");
+            let mime_type = mime_type(&file);
+            let content = if mime_type.starts_with("image/") {
+                PromptMessageContent::Image {
+                    image: RawImageContent {
+                        data: STANDARD.encode(&data),
+                        mime_type: mime_type.to_string(),
+                    }
+                    .optional_annotate(annotations.clone()),
+                }
+            } else {
+                let resource = match String::from_utf8(data) {
+                    Ok(text) => ResourceContents::TextResourceContents {
+                        uri,
+                        mime_type: Some(mime_type.to_string()),
+                        text,
+                    },
+                    Err(e) => ResourceContents::BlobResourceContents {
+                        uri,
+                        mime_type: Some(mime_type.to_string()),
+                        blob: STANDARD.encode(e.into_bytes()),
+                    },
+                };
+                PromptMessageContent::Resource {
+                    resource: RawEmbeddedResource { resource }
+                        .optional_annotate(annotations.clone()),
+                }
+            };
+            contents.push(content);
+        }
+        Ok(contents)
+    }
+
+    /// MCP annotations from the profile's `audience` and `priority` front-matter.
+    /// Audiences other than `user` and `assistant`, and priorities outside 0.0 to
+    /// 1.0, are left out.
+    fn annotations(&self, name: &str) -> Option<Annotations> {
+        let content = self.storage().get_content(name).ok()?;
+        let metadata = crate::frontmatter::parse_metadata(&content).ok()?;
+
+        let audience: Vec<Role> = metadata
+            .audience
+            .iter()
+            .filter_map(|audience| match audience.as_str() {
+                "user" => Some(Role::User),
+                "assistant" => Some(Role::Assistant),
+                _ => None,
+            })
+            .collect();
+        let priority = metadata
+            .priority
+            .filter(|priority| (0.0..=1.0).contains(priority));
+
+        if audience.is_empty() && priority.is_none() {
+            return None;
+        }
+        Some(Annotations {
+            audience: (!audience.is_empty()).then_some(audience),
+            priority,
+            timestamp: None,
+        })
+    }
+
+    fn copy_to_clipboard(&self, arguments: Option<JsonObject>) -> Result<CallToolResult, McpError> {
+        let params: CopyToClipboardParams = params(arguments)?;
+        let content = self.render_prompt(&params.name, &params.arguments)?;
+
+        let copied =
+            arboard::Clipboard::new().and_then(|mut clipboard| clipboard.set_text(content));
+        Ok(match copied {
+            Ok(()) => CallToolResult::success(vec![Content::text(format!(
+                "Copied prompt '{}' to the clipboard",
+                params.name
+            ))]),
+            Err(e) => CallToolResult::error(vec![Content::text(format!(
+                "Failed to access clipboard: {e}"
+            ))]),
+        })
+    }
+
+    /// The prompt listing entry for `profile`, reading its content for arguments
+    fn prompt(&self, profile: &str) -> Prompt {
+        let arguments = match self.storage().get_content(profile) {
+            Ok(content) => {
+                let extracted_args = self.extract_arguments_from_content(&content);
+                if extracted_args.is_empty() {
+                    None
+                } else {
+                    Some(extracted_args)
                 }
             }
+            Err(_) => None, // If we can't read the content, don't include arguments
+        };
+
+        Prompt::new(profile, Some(&self.describe_prompt(profile)), arguments)
+    }
+
+    /// Prompt description from the profile's `description:` front-matter, with its last
+    /// modification time and, when the repository is under git, the last commit touching
+    /// it, so clients can spot stale copies
+    fn describe_prompt(&self, name: &str) -> String {
+        let description = self
+            .storage()
+            .metadata(name)
+            .ok()
+            .and_then(|metadata| metadata.description)
+            .unwrap_or_else(|| format!("System prompt: {name}"));
+
+        let mut details = Vec::new();
+        if let Ok(path) = self.storage().get_repo_path(name) {
+            if let Ok(modified) = std::fs::metadata(&path).and_then(|m| m.modified()) {
+                details.push(format!(
+                    "updated {}",
+                    crate::utils::format_timestamp(modified)
+                ));
+            }
+            if let Some(revision) = self.revision(&path) {
+                details.push(format!("revision {revision}"));
+            }
         }
 
-        arguments
+        if details.is_empty() {
+            description
+        } else {
+            format!("{description} ({})", details.join(", "))
+        }
+    }
+
+    /// Short hash of the last commit touching `path`. Running git for every profile
+    /// on each `prompts/list` is slow, so the answer is kept until the file changes
+    /// or the repository gets a new commit.
+    fn revision(&self, path: &Path) -> Option<String> {
+        let (Some(file), Some(head)) = (modified(path), head_log_modified(path)) else {
+            return crate::utils::git_revision(path);
+        };
+        if let Some(cached) = self.shared.revisions.lock().unwrap().get(path)
+            && (cached.file, cached.head) == (file, head)
+        {
+            return cached.revision.clone();
+        }
+
+        let revision = crate::utils::git_revision(path);
+        let cached = CachedRevision {
+            file,
+            head,
+            revision: revision.clone(),
+        };
+        self.shared
+            .revisions
+            .lock()
+            .unwrap()
+            .insert(path.to_path_buf(), cached);
+        revision
+    }
+
+    /// Extract argument templates from prompt content using <{{variable}}> pattern,
+    /// described by what the front-matter declares under `arguments`
+    fn extract_arguments_from_content(&self, content: &str) -> Vec<PromptArgument> {
+        let metadata = crate::frontmatter::parse_metadata(content).unwrap_or_default();
+        crate::template::variables(content)
+            .into_iter()
+            .map(|name| {
+                let Some(spec) = metadata.arguments.get(&name) else {
+                    return PromptArgument {
+                        description: Some(format!("Value for {}", name)),
+                        name,
+                        required: Some(true),
+                    };
+                };
+
+                let mut description = spec
+                    .description
+                    .clone()
+                    .unwrap_or_else(|| format!("Value for {}", name));
+                let allowed: Vec<String> = spec
+                    .allowed
+                    .iter()
+                    .filter_map(crate::template::value_text)
+                    .collect();
+                if !allowed.is_empty() {
+                    description.push_str(&format!(" (one of: {})", allowed.join(", ")));
+                }
+                if let Some(default) = spec.default.as_ref().and_then(crate::template::value_text) {
+                    description.push_str(&format!(" (default: {default})"));
+                }
+                PromptArgument {
+                    description: Some(description),
+                    required: Some(spec.required.unwrap_or(spec.default.is_none())),
+                    name,
+                }
+            })
+            .collect()
     }
 
     /// Replace argument placeholders in content with provided values
@@ -59,18 +710,133 @@ impl PmxMcpServer {
             return content.to_string();
         };
 
-        use regex::Regex;
-        let re = Regex::new(r"<\{\{([A-Za-z_][A-Za-z0-9_]*)\}\}>").unwrap();
+        crate::template::substitute(content, |var_name| args.get(var_name).map(argument_text))
+    }
+
+    /// Enabled profiles as prompts, one page of them after `cursor`
+    async fn prompts(&self, cursor: Option<&str>) -> Result<ListPromptsResult, McpError> {
+        let mut profiles = self.storage().list_repos().map_err(|e| {
+            tracing::error!(error = %e, "failed to list profiles");
+            McpError::internal_error(e.to_string(), None)
+        })?;
+        profiles.retain(|profile| self.is_prompt_enabled(profile));
+        let page_size = self
+            .storage()
+            .config
+            .mcp
+            .page_size
+            .unwrap_or(DEFAULT_PAGE_SIZE);
+        let (profiles, next_cursor) = page(profiles, cursor, page_size)?;
 
-        re.replace_all(content, |caps: &regex::Captures| {
-            let var_name = &caps[1];
-            match args.get(var_name) {
-                Some(Value::String(s)) => s.clone(),
-                Some(other) => other.to_string().trim_matches('"').to_string(),
-                None => caps.get(0).unwrap().as_str().to_string(), // Keep original if not found
-            }
+        // Reading every profile one after another is slow on network filesystems, so
+        // the blocking reads run concurrently, a bounded number at a time
+        let semaphore = std::sync::Arc::new(tokio::sync::Semaphore::new(LIST_CONCURRENCY));
+        let tasks: Vec<_> = profiles
+            .into_iter()
+            .map(|profile| {
+                let server = self.clone();
+                let semaphore = semaphore.clone();
+                tokio::spawn(async move {
+                    let _permit = semaphore.acquire_owned().await;
+                    tokio::task::spawn_blocking(move || server.prompt(&profile)).await
+                })
+            })
+            .collect();
+
+        let mut prompts = Vec::with_capacity(tasks.len());
+        for task in tasks {
+            let prompt = task.await.and_then(|prompt| prompt).map_err(|e| {
+                tracing::error!(error = %e, "failed to read prompt");
+                McpError::internal_error(e.to_string(), None)
+            })?;
+            prompts.push(prompt);
+        }
+
+        tracing::debug!(count = prompts.len(), "listed prompts");
+        Ok(ListPromptsResult {
+            next_cursor,
+            prompts,
         })
-        .to_string()
+    }
+
+    /// The rendered prompt with its attachments
+    fn get_prompt_result(
+        &self,
+        name: &str,
+        arguments: &Option<JsonObject>,
+    ) -> Result<GetPromptResult, McpError> {
+        tracing::info!(prompt = %name, "get_prompt");
+        let processed_content = self.render_prompt(name, arguments).inspect_err(|e| {
+            tracing::warn!(prompt = %name, error = %e.message, "get_prompt failed");
+        })?;
+
+        let mut messages = vec![PromptMessage {
+            role: PromptMessageRole::User,
+            content: PromptMessageContent::text(processed_content),
+        }];
+        messages.extend(
+            self.attachments(name)?
+                .into_iter()
+                .map(|content| PromptMessage {
+                    role: PromptMessageRole::User,
+                    content,
+                }),
+        );
+
+        Ok(GetPromptResult {
+            description: Some(self.describe_prompt(name)),
+            messages,
+        })
+    }
+
+    fn call_tool_result(
+        &self,
+        name: &str,
+        arguments: Option<JsonObject>,
+    ) -> Result<CallToolResult, McpError> {
+        tracing::info!(tool = %name, "call_tool");
+        if !self.is_tool_enabled(name) {
+            tracing::warn!(tool = %name, "call_tool rejected: tool is disabled");
+            return Err(McpError::invalid_params("Tool is disabled", None));
+        }
+
+        match name {
+            COPY_TO_CLIPBOARD_TOOL => self.copy_to_clipboard(arguments),
+            LIST_PROFILES_TOOL => self.list_profiles(arguments),
+            GET_PROFILE_TOOL => self.get_profile(arguments),
+            CREATE_PROFILE_TOOL => self.create_profile(arguments),
+            SEARCH_PROFILES_TOOL => self.search_profiles(arguments),
+            _ => Err(McpError::invalid_params(
+                format!("Unknown tool: {name}"),
+                None,
+            )),
+        }
+    }
+
+    /// Record a request and its response when `[mcp] log_requests` is on, with
+    /// secret argument values masked
+    fn log_exchange<T: serde::Serialize>(
+        &self,
+        method: &str,
+        request: Value,
+        response: &Result<T, McpError>,
+    ) {
+        let Some(redactor) = &self.shared.request_log else {
+            return;
+        };
+        let arguments = request.get("arguments").and_then(Value::as_object);
+        let secrets = redactor.secrets(arguments.into_iter().flat_map(|a| a.values()));
+        let response = match response {
+            Ok(result) => serde_json::to_value(result),
+            Err(error) => serde_json::to_value(error),
+        }
+        .unwrap_or_default();
+        tracing::info!(
+            method,
+            request = %redactor.redact(&request, &secrets),
+            response = %redactor.redact(&response, &secrets),
+            "mcp request"
+        );
     }
 }
 
@@ -78,7 +844,11 @@ impl ServerHandler for PmxMcpServer {
     fn get_info(&self) -> ServerInfo {
         ServerInfo {
             protocol_version: ProtocolVersion::V_2024_11_05,
-            capabilities: ServerCapabilities::builder().enable_prompts().build(),
+            capabilities: ServerCapabilities::builder()
+                .enable_prompts()
+                .enable_prompts_list_changed()
+                .enable_tools()
+                .build(),
             server_info: Implementation {
                 name: "pmx-mcp-server".to_string(),
                 version: env!("CARGO_PKG_VERSION").to_string(),
@@ -87,81 +857,240 @@ impl ServerHandler for PmxMcpServer {
         }
     }
 
+    async fn on_initialized(&self, context: NotificationContext<RoleServer>) {
+        tracing::info!("client initialized");
+        let mut prompts_changed = self.shared.prompts_changed.subscribe();
+        tokio::spawn(async move {
+            loop {
+                match prompts_changed.recv().await {
+                    Ok(()) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+                // Fails once the client has gone away
+                if context.peer.notify_prompt_list_changed().await.is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
     async fn list_prompts(
         &self,
-        _request: Option<PaginatedRequestParam>,
+        request: Option<PaginatedRequestParam>,
         _: RequestContext<RoleServer>,
     ) -> Result<ListPromptsResult, McpError> {
-        let profiles = self
-            .storage
-            .list_repos()
-            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        let cursor = request.and_then(|request| request.cursor);
+        let result = self.prompts(cursor.as_deref()).await;
+        let request = cursor.map_or(Value::Null, |cursor| serde_json::json!({"cursor": cursor}));
+        self.log_exchange("prompts/list", request, &result);
+        result
+    }
 
-        let mut prompts = Vec::new();
-        for profile in profiles {
-            if self.is_prompt_enabled(&profile) {
-                // Read the content to extract arguments
-                let arguments = match self.storage.get_content(&profile) {
-                    Ok(content) => {
-                        let extracted_args = self.extract_arguments_from_content(&content);
-                        if extracted_args.is_empty() {
-                            None
-                        } else {
-                            Some(extracted_args)
-                        }
-                    }
-                    Err(_) => None, // If we can't read the content, don't include arguments
-                };
+    async fn get_prompt(
+        &self,
+        GetPromptRequestParam { name, arguments }: GetPromptRequestParam,
+        _: RequestContext<RoleServer>,
+    ) -> Result<GetPromptResult, McpError> {
+        let result = self.get_prompt_result(&name, &arguments);
+        let request = serde_json::json!({"name": name, "arguments": arguments});
+        self.log_exchange("prompts/get", request, &result);
+        result
+    }
 
-                prompts.push(Prompt::new(
-                    &profile,
-                    Some(&format!("System prompt: {profile}")),
-                    arguments,
-                ));
-            }
+    async fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, McpError> {
+        let result = Ok(ListToolsResult {
+            next_cursor: None,
+            tools: self.tools(),
+        });
+        self.log_exchange("tools/list", Value::Null, &result);
+        result
+    }
+
+    async fn call_tool(
+        &self,
+        CallToolRequestParam { name, arguments }: CallToolRequestParam,
+        _: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        let request = serde_json::json!({"name": name, "arguments": arguments});
+        let result = self.call_tool_result(&name, arguments);
+        self.log_exchange("tools/call", request, &result);
+        result
+    }
+}
+
+fn mime_type(path: &std::path::Path) -> &'static str {
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "md" => "text/markdown",
+        "txt" => "text/plain",
+        "json" => "application/json",
+        "yaml" | "yml" => "application/yaml",
+        "toml" => "application/toml",
+        "csv" => "text/csv",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Refuse a name from the client that could reach outside the profile
+/// repository, such as `../../notes`
+fn check_profile_name(name: &str) -> Result<(), McpError> {
+    crate::commands::profile::validate_profile_name(name)
+        .map_err(|e| McpError::invalid_params(e.to_string(), None))
+}
+
+/// Address the network transports listen on without `--bind` or `[mcp] bind`
+const DEFAULT_BIND: &str = "127.0.0.1:8765";
+
+/// Who may connect to the network transports: `[mcp] token` and `allowed_origins`
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Access {
+    pub(crate) token: Option<String>,
+    pub(crate) allowed_origins: Vec<String>,
+}
+
+impl Access {
+    fn new(config: &crate::storage::McpConfig) -> Self {
+        Self {
+            token: config.token.clone(),
+            allowed_origins: config.allowed_origins.clone(),
         }
+    }
 
-        Ok(ListPromptsResult {
-            next_cursor: None,
-            prompts,
+    /// Whether a client sending this `Origin` header may connect. Browsers send
+    /// one, so a page the user visits can only connect from `localhost` or an
+    /// allowed origin; other clients send none.
+    pub(crate) fn allows_origin(&self, origin: Option<&str>) -> bool {
+        origin.is_none_or(|origin| {
+            is_local_origin(origin) || self.allowed_origins.iter().any(|allowed| allowed == origin)
         })
     }
 
-    async fn get_prompt(
+    /// Whether this `Authorization` header carries the token, when one is set
+    pub(crate) fn is_authorized(&self, authorization: Option<&str>) -> bool {
+        self.token.as_ref().is_none_or(|token| {
+            authorization.is_some_and(|authorization| {
+                constant_time_eq(authorization, &format!("Bearer {token}"))
+            })
+        })
+    }
+
+    /// Why a connection with these `Origin` and `Authorization` headers is
+    /// refused, if it is
+    pub(crate) fn refusal(
         &self,
-        GetPromptRequestParam { name, arguments }: GetPromptRequestParam,
-        _: RequestContext<RoleServer>,
-    ) -> Result<GetPromptResult, McpError> {
-        if !self.is_prompt_enabled(&name) {
-            return Err(McpError::invalid_params("Prompt is disabled", None));
+        origin: Option<&str>,
+        authorization: Option<&str>,
+    ) -> Option<&'static str> {
+        if !self.allows_origin(origin) {
+            Some("Origin is not allowed")
+        } else if !self.is_authorized(authorization) {
+            Some("Missing or invalid bearer token")
+        } else {
+            None
         }
+    }
+}
 
-        let content = self
-            .storage
-            .get_content(&name)
-            .map_err(|e| McpError::invalid_params(format!("Prompt not found: {e}"), None))?;
+/// Compare the digests of `a` and `b` in a time that depends on neither, so
+/// timing a wrong guess says nothing about the token or its length. ring's
+/// `verify_slices_are_equal` is deprecated for use outside ring.
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    use sha2::{Digest, Sha256};
 
-        // Substitute arguments in the content
-        let processed_content = self.substitute_arguments(&content, &arguments);
+    let (a, b) = (Sha256::digest(a), Sha256::digest(b));
+    a.iter()
+        .zip(b.iter())
+        .fold(0, |diff, (x, y)| diff | (x ^ y))
+        == 0
+}
 
-        Ok(GetPromptResult {
-            description: None,
-            messages: vec![PromptMessage {
-                role: PromptMessageRole::User,
-                content: PromptMessageContent::text(processed_content),
-            }],
-        })
+/// Whether `origin` is a page served from this machine, e.g. `http://localhost:3000`
+fn is_local_origin(origin: &str) -> bool {
+    let Some((_, host)) = origin.split_once("://") else {
+        return false;
+    };
+    let host = match host.strip_prefix('[') {
+        Some(ipv6) => ipv6.split(']').next().unwrap_or_default(),
+        None => host.split(':').next().unwrap_or_default(),
+    };
+    host == "localhost"
+        || host
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback())
+}
+
+/// Refuse to serve other machines without a token
+fn check_exposure(address: std::net::SocketAddr, access: &Access) -> Result<()> {
+    anyhow::ensure!(
+        address.ip().is_loopback() || access.token.is_some(),
+        "Refusing to listen on {} without a token; set [mcp] token in config.toml or bind to 127.0.0.1",
+        address
+    );
+    Ok(())
+}
+
+/// `bind`, else `[mcp] bind`, else the default, with its port replaced by `port`
+fn listen_address(
+    storage: &crate::storage::Storage,
+    bind: Option<&str>,
+    port: Option<u16>,
+) -> String {
+    let bind = bind
+        .or(storage.config.mcp.bind.as_deref())
+        .unwrap_or(DEFAULT_BIND);
+    match port {
+        Some(port) => {
+            let host = bind.rsplit_once(':').map_or(bind, |(host, _)| host);
+            format!("{host}:{port}")
+        }
+        None => bind.to_string(),
     }
 }
 
-pub fn run_mcp_server(storage: crate::storage::Storage) -> Result<()> {
+pub fn run_mcp_server(
+    storage: crate::storage::Storage,
+    transport: McpTransport,
+    bind: Option<&str>,
+    port: Option<u16>,
+) -> Result<()> {
+    let bind = listen_address(&storage, bind, port);
+    let access = Access::new(&storage.config.mcp);
     tokio::runtime::Builder::new_current_thread()
         .enable_all()
         .build()?
         .block_on(async {
-            let service = PmxMcpServer::new(storage);
+            let service = PmxMcpServer::new(storage)?;
+            tokio::spawn(service.clone().watch_storage());
+            if transport != McpTransport::Stdio {
+                let listener = tokio::net::TcpListener::bind(&bind)
+                    .await
+                    .map_err(|e| anyhow::anyhow!("Failed to listen on {}: {}", bind, e))?;
+                let address = listener.local_addr()?;
+                check_exposure(address, &access)?;
+                if transport == McpTransport::Http {
+                    tracing::info!(url = %format!("http://{address}/mcp"), "serving MCP over HTTP");
+                    return super::mcp_http::serve(service, listener, access).await;
+                }
+                tracing::info!(url = %format!("ws://{address}"), "serving MCP over WebSocket");
+                return super::mcp_ws::serve(service, listener, access).await;
+            }
+
+            tracing::info!("starting MCP server on stdio");
             let server = service.serve((stdin(), stdout())).await?;
-            server.waiting().await?;
+            let reason = server.waiting().await?;
+            tracing::info!(?reason, "MCP server stopped");
             Ok(())
         })
 }
@@ -179,19 +1108,18 @@ mod tests {
         crate::storage::Storage::initialize(path.clone()).unwrap();
 
         let config = crate::storage::Config {
-            agents: crate::storage::Agents {
-                disable_claude: false,
-                disable_codex: false,
-            },
+            agents: crate::storage::Agents::default(),
             mcp: crate::storage::McpConfig {
                 disable_prompts: crate::storage::DisableOption::Bool(false),
                 disable_tools: crate::storage::DisableOption::Bool(false),
+                ..Default::default()
             },
             extensions: crate::storage::ExtensionsConfig::default(),
+            ..Default::default()
         };
         config.persist(&path).unwrap();
         let storage = crate::storage::Storage::new(path).unwrap();
-        let server = PmxMcpServer::new(storage);
+        let server = PmxMcpServer::new(storage).unwrap();
 
         assert!(server.is_prompt_enabled("test_prompt"));
     }
@@ -201,7 +1129,7 @@ mod tests {
         let temp_dir = TempDir::new().unwrap();
         let path = temp_dir.path().join("test_storage");
         let storage = crate::storage::Storage::initialize(path).unwrap();
-        let server = PmxMcpServer::new(storage);
+        let server = PmxMcpServer::new(storage).unwrap();
 
         // Test extracting single argument
         let content1 = "Please visit <{{URL}}> for more information.";
@@ -237,12 +1165,47 @@ mod tests {
         assert_eq!(args5.len(), 0);
     }
 
+    #[test]
+    fn test_prompt_arguments_from_front_matter() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("test_storage");
+        let storage = crate::storage::Storage::initialize(path).unwrap();
+        storage
+            .create_profile(
+                "review",
+                "---\narguments:\n  TONE:\n    description: Tone of the review\n    enum: [formal, casual]\n    default: formal\n  TARGET:\n    description: File to review\n---\nReview <{{TARGET}}> in a <{{TONE}}> tone.\n",
+            )
+            .unwrap();
+        let server = PmxMcpServer::new(storage).unwrap();
+
+        let content = server.storage().get_content("review").unwrap();
+        let args = server.extract_arguments_from_content(&content);
+        assert_eq!(args[0].name, "TARGET");
+        assert_eq!(args[0].description, Some("File to review".to_string()));
+        assert_eq!(args[0].required, Some(true));
+        assert_eq!(
+            args[1].description,
+            Some("Tone of the review (one of: formal, casual) (default: formal)".to_string())
+        );
+        assert_eq!(args[1].required, Some(false));
+
+        let mut given = serde_json::Map::new();
+        given.insert("TARGET".to_string(), json!("main.rs"));
+        let rendered = server
+            .render_prompt("review", &Some(given.clone()))
+            .unwrap();
+        assert!(rendered.ends_with("Review main.rs in a formal tone.\n"));
+
+        given.insert("TONE".to_string(), json!("rude"));
+        assert!(server.render_prompt("review", &Some(given)).is_err());
+    }
+
     #[test]
     fn test_substitute_arguments() {
         let temp_dir = TempDir::new().unwrap();
         let path = temp_dir.path().join("test_storage");
         let storage = crate::storage::Storage::initialize(path).unwrap();
-        let server = PmxMcpServer::new(storage);
+        let server = PmxMcpServer::new(storage).unwrap();
 
         // Test basic substitution
         let content = "Please visit <{{URL}}> for more information.";
@@ -281,19 +1244,18 @@ mod tests {
         crate::storage::Storage::initialize(path.clone()).unwrap();
 
         let config = crate::storage::Config {
-            agents: crate::storage::Agents {
-                disable_claude: false,
-                disable_codex: false,
-            },
+            agents: crate::storage::Agents::default(),
             mcp: crate::storage::McpConfig {
                 disable_prompts: crate::storage::DisableOption::Bool(true),
                 disable_tools: crate::storage::DisableOption::Bool(false),
+                ..Default::default()
             },
             extensions: crate::storage::ExtensionsConfig::default(),
+            ..Default::default()
         };
         config.persist(&path).unwrap();
         let storage = crate::storage::Storage::new(path).unwrap();
-        let server = PmxMcpServer::new(storage);
+        let server = PmxMcpServer::new(storage).unwrap();
 
         assert!(!server.is_prompt_enabled("test_prompt"));
     }
@@ -305,32 +1267,507 @@ mod tests {
         crate::storage::Storage::initialize(path.clone()).unwrap();
 
         let config = crate::storage::Config {
-            agents: crate::storage::Agents {
-                disable_claude: false,
-                disable_codex: false,
-            },
+            agents: crate::storage::Agents::default(),
             mcp: crate::storage::McpConfig {
                 disable_prompts: crate::storage::DisableOption::List(vec![
                     "disabled_prompt".to_string(),
                 ]),
                 disable_tools: crate::storage::DisableOption::Bool(false),
+                ..Default::default()
             },
             extensions: crate::storage::ExtensionsConfig::default(),
+            ..Default::default()
         };
         config.persist(&path).unwrap();
         let storage = crate::storage::Storage::new(path).unwrap();
-        let server = PmxMcpServer::new(storage);
+        let server = PmxMcpServer::new(storage).unwrap();
 
         assert!(!server.is_prompt_enabled("disabled_prompt"));
         assert!(server.is_prompt_enabled("enabled_prompt"));
     }
 
+    #[test]
+    fn test_is_prompt_enabled_with_patterns() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("test_storage");
+        let mut storage = crate::storage::Storage::initialize(path).unwrap();
+        storage.config.mcp.enable_prompts = Some(vec!["work/*".to_string(), "base".to_string()]);
+        storage.config.mcp.disable_prompts =
+            crate::storage::DisableOption::List(vec!["work/drafts/*".to_string()]);
+        let server = PmxMcpServer::new(storage).unwrap();
+
+        assert!(server.is_prompt_enabled("base"));
+        assert!(server.is_prompt_enabled("work/review"));
+        assert!(server.is_prompt_enabled("work/rust/style"));
+        assert!(!server.is_prompt_enabled("work/drafts/idea"));
+        assert!(!server.is_prompt_enabled("personal/journal"));
+        assert!(!server.is_prompt_enabled("basement"));
+    }
+
+    #[test]
+    fn test_tools_respect_disable_list() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("test_storage");
+        crate::storage::Storage::initialize(path.clone()).unwrap();
+
+        let config = crate::storage::Config {
+            mcp: crate::storage::McpConfig {
+                disable_prompts: crate::storage::DisableOption::Bool(false),
+                disable_tools: crate::storage::DisableOption::List(vec![
+                    COPY_TO_CLIPBOARD_TOOL.to_string(),
+                ]),
+                enable_tools: vec![COPY_TO_CLIPBOARD_TOOL.to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        config.persist(&path).unwrap();
+        let server = PmxMcpServer::new(crate::storage::Storage::new(path).unwrap()).unwrap();
+        assert!(
+            server
+                .tools()
+                .iter()
+                .all(|tool| tool.name != COPY_TO_CLIPBOARD_TOOL)
+        );
+
+        let mut storage =
+            crate::storage::Storage::initialize(temp_dir.path().join("other")).unwrap();
+        let server = PmxMcpServer::new(storage.clone()).unwrap();
+        let tools = server.tools();
+        assert_eq!(tools.len(), 3);
+        assert!(
+            tools
+                .iter()
+                .all(|tool| !OPT_IN_TOOLS.contains(&tool.name.as_ref()))
+        );
+        assert!(server.call_tool_result(CREATE_PROFILE_TOOL, None).is_err());
+        assert!(
+            server
+                .call_tool_result(COPY_TO_CLIPBOARD_TOOL, None)
+                .is_err()
+        );
+
+        storage.config.mcp.enable_tools = vec![COPY_TO_CLIPBOARD_TOOL.to_string()];
+        let tools = PmxMcpServer::new(storage).unwrap().tools();
+        assert_eq!(tools.len(), 4);
+        assert_eq!(tools[0].name, COPY_TO_CLIPBOARD_TOOL);
+        assert!(tools[0].input_schema["properties"].get("name").is_some());
+    }
+
+    #[test]
+    fn test_profile_tools() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut storage =
+            crate::storage::Storage::initialize(temp_dir.path().join("storage")).unwrap();
+        storage
+            .create_profile("coding/rust", "# Rust\nRun clippy.\n")
+            .unwrap();
+        storage.config.mcp.enable_tools = vec![CREATE_PROFILE_TOOL.to_string()];
+        storage.config.mcp.disable_prompts =
+            crate::storage::DisableOption::List(vec!["drafts/*".to_string()]);
+        let server = PmxMcpServer::new(storage).unwrap();
+        let call = |name: &str, arguments: Value| {
+            let result = server
+                .call_tool_result(name, arguments.as_object().cloned())
+                .unwrap();
+            let text = result.content[0].as_text().unwrap().text.clone();
+            (result.is_error == Some(true), text)
+        };
+
+        let created = call(
+            CREATE_PROFILE_TOOL,
+            serde_json::json!({"name": "writing", "content": "Be clear.\n"}),
+        );
+        assert_eq!(created, (false, "Created profile 'writing'".to_string()));
+        assert!(
+            call(
+                CREATE_PROFILE_TOOL,
+                serde_json::json!({"name": "writing", "content": "x"})
+            )
+            .0
+        );
+
+        assert_eq!(
+            call(LIST_PROFILES_TOOL, serde_json::json!({})).1,
+            "coding/rust\nwriting"
+        );
+        assert_eq!(
+            call(LIST_PROFILES_TOOL, serde_json::json!({"prefix": "coding/"})).1,
+            "coding/rust"
+        );
+        assert_eq!(
+            call(GET_PROFILE_TOOL, serde_json::json!({"name": "writing"})).1,
+            "Be clear.\n"
+        );
+        assert_eq!(
+            call(SEARCH_PROFILES_TOOL, serde_json::json!({"query": "CLIPPY"})).1,
+            "coding/rust:2: Run clippy."
+        );
+        assert!(
+            server
+                .call_tool_result(
+                    CREATE_PROFILE_TOOL,
+                    serde_json::json!({"name": "../escape", "content": "x"})
+                        .as_object()
+                        .cloned()
+                )
+                .is_err()
+        );
+        assert!(
+            server
+                .call_tool_result(
+                    CREATE_PROFILE_TOOL,
+                    serde_json::json!({"name": "drafts/idea", "content": "x"})
+                        .as_object()
+                        .cloned()
+                )
+                .is_err()
+        );
+        assert!(!server.storage().profile_exists("drafts/idea"));
+    }
+
+    #[test]
+    fn test_names_outside_the_repo_are_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = crate::storage::Storage::initialize(temp_dir.path().join("storage")).unwrap();
+        std::fs::write(temp_dir.path().join("notes.md"), "Private.\n").unwrap();
+        let server = PmxMcpServer::new(storage).unwrap();
+
+        let escape = serde_json::json!({"name": "../../notes"});
+        let result = server.call_tool_result(GET_PROFILE_TOOL, escape.as_object().cloned());
+        assert!(result.unwrap_err().message.contains(".."));
+        assert!(server.render_prompt("../../notes", &None).is_err());
+    }
+
+    #[test]
+    fn test_render_prompt() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("test_storage");
+        let storage = crate::storage::Storage::initialize(path).unwrap();
+        storage
+            .create_profile("greet", "Hello <{{NAME}}>!")
+            .unwrap();
+        let server = PmxMcpServer::new(storage).unwrap();
+
+        let mut args = serde_json::Map::new();
+        args.insert("NAME".to_string(), json!("world"));
+        assert_eq!(
+            server.render_prompt("greet", &Some(args)).unwrap(),
+            "Hello world!"
+        );
+        assert!(server.render_prompt("missing", &None).is_err());
+    }
+
+    #[test]
+    fn test_render_prompt_leaves_out_front_matter() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("test_storage");
+        let storage = crate::storage::Storage::initialize(path).unwrap();
+        storage
+            .create_profile(
+                "greet",
+                "---\ndescription: Greets\narguments:\n  NAME:\n    default: world\n---\n\nHello <{{NAME}}>!",
+            )
+            .unwrap();
+        let server = PmxMcpServer::new(storage).unwrap();
+
+        assert_eq!(
+            server.render_prompt("greet", &None).unwrap(),
+            "Hello world!"
+        );
+        for (name, content) in [
+            ("toml", "+++\ndescription = \"Greets\"\n+++\nHello!"),
+            (
+                "json",
+                "---json\n{\"description\": \"Greets\"}\n---\nHello!",
+            ),
+        ] {
+            server.storage().create_profile(name, content).unwrap();
+            assert_eq!(server.render_prompt(name, &None).unwrap(), "Hello!");
+        }
+        let result = server.get_prompt_result("greet", &None).unwrap();
+        assert_eq!(
+            result
+                .description
+                .as_deref()
+                .map(|d| d.starts_with("Greets")),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn test_render_prompt_does_not_call_functions_from_arguments() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("test_storage");
+        let storage = crate::storage::Storage::initialize(path).unwrap();
+        storage
+            .create_profile("review", "Review <{{TARGET}}> carefully.")
+            .unwrap();
+        let server = PmxMcpServer::new(storage).unwrap();
+
+        let mut args = serde_json::Map::new();
+        args.insert("TARGET".to_string(), json!("<{{fn:jira --any-flag}}>"));
+        assert_eq!(
+            server.render_prompt("review", &Some(args)).unwrap(),
+            "Review <{{fn:jira --any-flag}}> carefully."
+        );
+    }
+
+    #[test]
+    fn test_render_prompt_does_not_resolve_secrets_from_arguments() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("test_storage");
+        let storage = crate::storage::Storage::initialize(path).unwrap();
+        storage
+            .create_profile("review", "Review <{{TARGET}}> carefully.")
+            .unwrap();
+        let server = PmxMcpServer::new(storage).unwrap();
+
+        unsafe { std::env::set_var("PMX_TEST_MCP_SUPER_SECRET", "hunter2") };
+        let mut args = serde_json::Map::new();
+        args.insert(
+            "TARGET".to_string(),
+            json!("<{{secret:PMX_TEST_MCP_SUPER_SECRET}}>"),
+        );
+        assert_eq!(
+            server.render_prompt("review", &Some(args)).unwrap(),
+            "Review <{{secret:PMX_TEST_MCP_SUPER_SECRET}}> carefully."
+        );
+    }
+
+    #[test]
+    fn test_attachments_for_folder_profile() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("test_storage");
+        let storage = crate::storage::Storage::initialize(path.clone()).unwrap();
+        let folder = path.join("repo").join("review");
+        std::fs::create_dir_all(&folder).unwrap();
+        std::fs::write(folder.join("index.md"), "Review this.").unwrap();
+        std::fs::write(folder.join(crate::storage::FOLDER_MARKER), "").unwrap();
+        std::fs::write(folder.join("diagram.png"), [0x89, b'P', b'N', b'G']).unwrap();
+        std::fs::write(folder.join("example.json"), "{}").unwrap();
+        let server = PmxMcpServer::new(storage).unwrap();
+
+        let attachments = server.attachments("review").unwrap();
+        assert_eq!(attachments.len(), 2);
+        match &attachments[0] {
+            PromptMessageContent::Image { image } => {
+                assert_eq!(image.mime_type, "image/png");
+                assert_eq!(image.data, "iVBORw==");
+            }
+            other => panic!("expected image, got {other:?}"),
+        }
+        match &attachments[1] {
+            PromptMessageContent::Resource { resource } => match &resource.resource {
+                ResourceContents::TextResourceContents { uri, text, .. } => {
+                    assert_eq!(uri, "pmx://profile/review/example.json");
+                    assert_eq!(text, "{}");
+                }
+                other => panic!("expected text resource, got {other:?}"),
+            },
+            other => panic!("expected resource, got {other:?}"),
+        }
+
+        assert!(server.attachments("missing").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_annotations_from_frontmatter() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("test_storage");
+        let storage = crate::storage::Storage::initialize(path.clone()).unwrap();
+        storage
+            .create_profile(
+                "ranked",
+                "---\naudience: [assistant, robot]\npriority: 0.8\n---\nBody\n",
+            )
+            .unwrap();
+        storage
+            .create_profile("bad_priority", "---\npriority: 3\n---\nBody\n")
+            .unwrap();
+        storage.create_profile("plain", "Body\n").unwrap();
+        let server = PmxMcpServer::new(storage).unwrap();
+
+        assert_eq!(
+            server.annotations("ranked"),
+            Some(Annotations {
+                audience: Some(vec![Role::Assistant]),
+                priority: Some(0.8),
+                timestamp: None,
+            })
+        );
+        assert_eq!(server.annotations("bad_priority"), None);
+        assert_eq!(server.annotations("plain"), None);
+    }
+
+    #[test]
+    fn test_describe_prompt_from_frontmatter() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("test_storage");
+        let storage = crate::storage::Storage::initialize(path.clone()).unwrap();
+        storage
+            .create_profile(
+                "described",
+                "---\ndescription: Rust review rules\n---\nBody\n",
+            )
+            .unwrap();
+        storage.create_profile("plain", "Body\n").unwrap();
+        let server = PmxMcpServer::new(storage).unwrap();
+
+        assert!(
+            server
+                .describe_prompt("described")
+                .starts_with("Rust review rules (updated ")
+        );
+        assert!(
+            server
+                .describe_prompt("plain")
+                .starts_with("System prompt: plain (updated ")
+        );
+    }
+
+    #[test]
+    fn test_revision_is_cached_until_a_commit() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = crate::storage::Storage::initialize(temp_dir.path().join("storage")).unwrap();
+        let repo = storage.path.join("repo");
+        std::fs::write(repo.join("rust.md"), "First\n").unwrap();
+        crate::history::commit(&repo, Path::new("rust.md"), "Create rust").unwrap();
+        let server = PmxMcpServer::new(storage).unwrap();
+        let file = repo.join("rust.md");
+
+        let first = server.revision(&file).unwrap();
+        assert_eq!(crate::utils::git_revision(&file).unwrap(), first);
+        assert!(server.shared.revisions.lock().unwrap().contains_key(&file));
+        assert_eq!(server.revision(&file).unwrap(), first);
+
+        std::fs::write(&file, "Second\n").unwrap();
+        crate::history::commit(&repo, Path::new("rust.md"), "Update rust").unwrap();
+        let second = server.revision(&file).unwrap();
+        assert_ne!(second, first);
+        assert_eq!(crate::utils::git_revision(&file).unwrap(), second);
+    }
+
+    #[test]
+    fn test_list_prompts_in_pages() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("test_storage");
+        let mut storage = crate::storage::Storage::initialize(path).unwrap();
+        for name in ["e", "c", "a", "d", "b"] {
+            storage.create_profile(name, "Body\n").unwrap();
+        }
+        storage.config.mcp.page_size = Some(2);
+        let server = PmxMcpServer::new(storage).unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+
+        let mut pages = Vec::new();
+        let mut cursor = None;
+        loop {
+            let result = runtime.block_on(server.prompts(cursor.as_deref())).unwrap();
+            let names: Vec<String> = result.prompts.into_iter().map(|p| p.name).collect();
+            pages.push(names);
+            cursor = result.next_cursor;
+            if cursor.is_none() {
+                break;
+            }
+        }
+        assert_eq!(pages, [vec!["a", "b"], vec!["c", "d"], vec!["e"]]);
+
+        // A cursor still works after the profile it points at is removed
+        let (_, cursor) = page(vec!["a".into(), "b".into(), "c".into()], None, 1).unwrap();
+        let (names, _) = page(vec!["c".into()], cursor.as_deref(), 1).unwrap();
+        assert_eq!(names, ["c"]);
+
+        let (names, cursor) = page(vec!["a".into(), "b".into()], None, 0).unwrap();
+        assert_eq!((names.len(), cursor), (2, None));
+        assert!(page(Vec::new(), Some("not base64!"), 1).is_err());
+    }
+
+    #[test]
+    fn test_clones_share_the_server_state() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage =
+            crate::storage::Storage::initialize(temp_dir.path().join("test_storage")).unwrap();
+        let server = PmxMcpServer::new(storage).unwrap();
+        let clone = server.clone();
+        assert!(Arc::ptr_eq(&server.shared, &clone.shared));
+        assert!(Arc::ptr_eq(&server.storage(), &clone.storage()));
+
+        // A reload seen by one clone is seen by all of them
+        let mut reloaded = (*server.storage()).clone();
+        reloaded.config.mcp.disable_prompts = crate::storage::DisableOption::Bool(true);
+        *server.shared.storage.write().unwrap() = Arc::new(reloaded);
+        assert!(!clone.is_prompt_enabled("anything"));
+    }
+
+    #[tokio::test]
+    async fn test_watch_storage_reloads_and_notifies() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("test_storage");
+        let mut storage = crate::storage::Storage::initialize(path.clone()).unwrap();
+        storage.config.watch.debounce_ms = 0;
+        let server = PmxMcpServer::new(storage).unwrap();
+        let mut prompts_changed = server.shared.prompts_changed.subscribe();
+        tokio::spawn(server.clone().watch_storage());
+        tokio::time::sleep(RELOAD_INTERVAL).await;
+        let next = Duration::from_secs(10);
+
+        server.storage().create_profile("added", "Body\n").unwrap();
+        tokio::time::timeout(next, prompts_changed.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(server.is_prompt_enabled("added"));
+
+        let mut config = server.storage().config.clone();
+        config.mcp.disable_prompts = crate::storage::DisableOption::Bool(true);
+        config.persist(&path).unwrap();
+        tokio::time::timeout(next, prompts_changed.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!server.is_prompt_enabled("added"));
+    }
+
+    #[test]
+    fn test_access_checks_origin_and_token() {
+        let open = Access::default();
+        assert_eq!(open.refusal(None, None), None);
+        assert_eq!(open.refusal(Some("http://localhost:3000"), None), None);
+        assert_eq!(open.refusal(Some("http://127.0.0.1"), None), None);
+        assert_eq!(open.refusal(Some("http://[::1]:8080"), None), None);
+        assert!(open.refusal(Some("https://evil.example"), None).is_some());
+        assert!(
+            open.refusal(Some("http://localhost.evil.example"), None)
+                .is_some()
+        );
+
+        let guarded = Access {
+            token: Some("secret".to_string()),
+            allowed_origins: vec!["https://inspector.example".to_string()],
+        };
+        assert!(guarded.refusal(None, None).is_some());
+        assert!(guarded.refusal(None, Some("Bearer wrong")).is_some());
+        assert!(guarded.refusal(None, Some("Bearer secrets")).is_some());
+        assert!(guarded.refusal(None, None).is_some());
+        assert_eq!(guarded.refusal(None, Some("Bearer secret")), None);
+        assert_eq!(
+            guarded.refusal(Some("https://inspector.example"), Some("Bearer secret")),
+            None
+        );
+
+        let anywhere = "0.0.0.0:8765".parse().unwrap();
+        assert!(check_exposure(anywhere, &open).is_err());
+        assert!(check_exposure(anywhere, &guarded).is_ok());
+        assert!(check_exposure("127.0.0.1:8765".parse().unwrap(), &open).is_ok());
+    }
+
     #[test]
     fn test_server_info() {
         let temp_dir = TempDir::new().unwrap();
         let path = temp_dir.path().join("test_storage");
         let storage = crate::storage::Storage::initialize(path).unwrap();
-        let server = PmxMcpServer::new(storage);
+        let server = PmxMcpServer::new(storage).unwrap();
 
         let info = server.get_info();
         assert_eq!(info.server_info.name, "pmx-mcp-server");
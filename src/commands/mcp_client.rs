@@ -0,0 +1,110 @@
+use anyhow::{Context, Result};
+use rmcp::{
+    ServiceExt,
+    model::{CallToolRequestParam, GetPromptRequestParam, JsonObject},
+    transport::TokioChildProcess,
+};
+use serde_json::Value;
+
+use crate::cli::{McpCallArgs, McpMethod};
+
+/// Spawn an MCP server, send a single request and print the JSON result
+pub fn call(storage: &crate::storage::Storage, args: &McpCallArgs) -> Result<()> {
+    let arguments = parse_arguments(&args.args)?;
+    let needs_name = matches!(args.method, McpMethod::PromptsGet | McpMethod::ToolsCall);
+    anyhow::ensure!(
+        args.name.is_some() || !needs_name,
+        "A prompt or tool name is required for this request"
+    );
+
+    let command = server_command(storage, args.server.as_deref())?;
+
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?
+        .block_on(async {
+            let transport =
+                TokioChildProcess::new(command).context("Failed to start the MCP server")?;
+            let client = ().serve(transport).await.context("Failed to initialize the MCP session")?;
+
+            let name = args.name.clone().unwrap_or_default();
+            let result = match args.method {
+                McpMethod::PromptsList => serde_json::to_value(client.list_all_prompts().await?)?,
+                McpMethod::PromptsGet => serde_json::to_value(
+                    client
+                        .get_prompt(GetPromptRequestParam { name, arguments })
+                        .await?,
+                )?,
+                McpMethod::ToolsList => serde_json::to_value(client.list_all_tools().await?)?,
+                McpMethod::ToolsCall => serde_json::to_value(
+                    client
+                        .call_tool(CallToolRequestParam {
+                            name: name.into(),
+                            arguments,
+                        })
+                        .await?,
+                )?,
+            };
+            client.cancel().await?;
+
+            println!("{}", serde_json::to_string_pretty(&result)?);
+            Ok(())
+        })
+}
+
+/// `--server` split shell-style, or this executable's own `mcp` subcommand
+fn server_command(
+    storage: &crate::storage::Storage,
+    server: Option<&str>,
+) -> Result<tokio::process::Command> {
+    if let Some(server) = server {
+        let words = shlex::split(server)
+            .filter(|words| !words.is_empty())
+            .ok_or_else(|| anyhow::anyhow!("Failed to parse server command: {}", server))?;
+        let mut command = tokio::process::Command::new(&words[0]);
+        command.args(&words[1..]);
+        return Ok(command);
+    }
+
+    let exe = std::env::current_exe()
+        .map_err(|e| anyhow::anyhow!("Failed to locate the pmx executable: {}", e))?;
+    let mut command = tokio::process::Command::new(exe);
+    command.arg("--storage").arg(&storage.path).arg("mcp");
+    Ok(command)
+}
+
+/// Turn `KEY=VALUE` pairs into request arguments
+fn parse_arguments(pairs: &[String]) -> Result<Option<JsonObject>> {
+    if pairs.is_empty() {
+        return Ok(None);
+    }
+
+    pairs
+        .iter()
+        .map(|pair| {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("Expected KEY=VALUE, got '{}'", pair))?;
+            Ok((key.to_string(), Value::String(value.to_string())))
+        })
+        .collect::<Result<JsonObject>>()
+        .map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_arguments() {
+        assert_eq!(parse_arguments(&[]).unwrap(), None);
+
+        let arguments = parse_arguments(&["LANGUAGE=Rust".to_string(), "X=a=b".to_string()])
+            .unwrap()
+            .unwrap();
+        assert_eq!(arguments["LANGUAGE"], "Rust");
+        assert_eq!(arguments["X"], "a=b");
+
+        assert!(parse_arguments(&["missing".to_string()]).is_err());
+    }
+}
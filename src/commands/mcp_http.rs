@@ -0,0 +1,311 @@
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use http_body_util::{BodyExt, Full, Limited, combinators::BoxBody};
+use hyper::body::{Bytes, Incoming};
+use hyper::header::{self, HeaderValue};
+use hyper::{Request, Response, StatusCode};
+use hyper_util::rt::{TokioIo, TokioTimer};
+use rmcp::transport::streamable_http_server::session::local::{LocalSessionManager, SessionConfig};
+use rmcp::transport::streamable_http_server::{StreamableHttpServerConfig, StreamableHttpService};
+use tokio::net::TcpListener;
+use tower_http::timeout::TimeoutBody;
+
+use super::mcp::{Access, PmxMcpServer};
+
+/// Path clients send their JSON-RPC messages to
+const ENDPOINT: &str = "/mcp";
+/// Largest request body accepted
+const MAX_BODY_BYTES: usize = 4 * 1024 * 1024;
+/// Most headers a request may carry
+const MAX_HEADERS: usize = 64;
+/// Largest request line and headers accepted
+const MAX_HEAD_BYTES: usize = 64 * 1024;
+/// How long a client may take to send a request's headers, or keep a
+/// connection open without sending one
+const HEAD_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long a client may pause while sending a request body
+const BODY_READ_TIMEOUT: Duration = Duration::from_secs(30);
+/// Sessions without requests or an open event stream for this long are ended
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+type Mcp = StreamableHttpService<PmxMcpServer, LocalSessionManager>;
+type Body = BoxBody<Bytes, Infallible>;
+
+/// Accept Streamable HTTP clients that `access` allows on `ENDPOINT`. Each client
+/// that sends `initialize` gets a session of its own, named by the
+/// `Mcp-Session-Id` header, which ends after `IDLE_TIMEOUT` without requests.
+pub(crate) async fn serve(
+    server: PmxMcpServer,
+    listener: TcpListener,
+    access: Access,
+) -> Result<()> {
+    let sessions = LocalSessionManager {
+        sessions: Default::default(),
+        session_config: SessionConfig {
+            keep_alive: Some(IDLE_TIMEOUT),
+            ..Default::default()
+        },
+    };
+    let mcp: Mcp = StreamableHttpService::new(
+        move || Ok(server.clone()),
+        Arc::new(sessions),
+        StreamableHttpServerConfig::default(),
+    );
+    let access = Arc::new(access);
+
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let mcp = mcp.clone();
+        let access = access.clone();
+        tokio::spawn(async move {
+            let service = hyper::service::service_fn(move |request| {
+                let mcp = mcp.clone();
+                let access = access.clone();
+                async move { Ok::<_, Infallible>(handle(&mcp, &access, request).await) }
+            });
+            let connection = hyper::server::conn::http1::Builder::new()
+                .timer(TokioTimer::new())
+                .header_read_timeout(HEAD_TIMEOUT)
+                .max_headers(MAX_HEADERS)
+                .max_buf_size(MAX_HEAD_BYTES)
+                .serve_connection(TokioIo::new(stream), service);
+            // Clients that go quiet end up here too, so this is not worth a warning
+            if let Err(e) = connection.await {
+                tracing::debug!(%peer, error = %e, "HTTP connection closed");
+            }
+        });
+    }
+}
+
+/// Refuse requests `access` does not allow before handing them to the MCP service
+async fn handle(mcp: &Mcp, access: &Access, request: Request<Incoming>) -> Response<Body> {
+    if request.uri().path() != ENDPOINT {
+        return text(
+            StatusCode::NOT_FOUND,
+            format!("MCP is served at {ENDPOINT}"),
+        );
+    }
+    // Checking the origin keeps web pages, including ones reached through DNS
+    // rebinding, from talking to a local server
+    let header = |name| {
+        request
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+    };
+    if !access.allows_origin(header(header::ORIGIN)) {
+        return text(StatusCode::FORBIDDEN, "Origin is not allowed");
+    }
+    if !access.is_authorized(header(header::AUTHORIZATION)) {
+        let mut response = text(StatusCode::UNAUTHORIZED, "Missing or invalid bearer token");
+        response
+            .headers_mut()
+            .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+        return response;
+    }
+    let too_large = header(header::CONTENT_LENGTH)
+        .and_then(|length| length.parse::<usize>().ok())
+        .is_some_and(|length| length > MAX_BODY_BYTES);
+    if too_large {
+        return text(StatusCode::PAYLOAD_TOO_LARGE, "Request body is too large");
+    }
+
+    // A body sent without a length is cut off at the same limit
+    let request =
+        request.map(|body| Limited::new(TimeoutBody::new(BODY_READ_TIMEOUT, body), MAX_BODY_BYTES));
+    mcp.handle(request).await
+}
+
+fn text(status: StatusCode, body: impl Into<String>) -> Response<Body> {
+    let mut response = Response::new(Full::new(Bytes::from(body.into())).boxed());
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("text/plain; charset=utf-8"),
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{Value, json};
+    use tempfile::TempDir;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    fn initialize_request() -> Value {
+        json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "initialize",
+            "params": {
+                "protocolVersion": "2025-03-26",
+                "capabilities": {},
+                "clientInfo": {"name": "test", "version": "0.0.0"}
+            }
+        })
+    }
+
+    /// Send one request and return the status, the session header and the body
+    async fn send(
+        address: std::net::SocketAddr,
+        method: &'static str,
+        headers: &[(&str, &str)],
+        body: &Value,
+    ) -> (u16, Option<String>, String) {
+        let headers: Vec<(String, String)> = headers
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        let body = body.to_string();
+        tokio::task::spawn_blocking(move || {
+            let mut request = ureq::request(method, &format!("http://{address}{ENDPOINT}"))
+                .set("Content-Type", "application/json")
+                .set("Accept", "application/json, text/event-stream");
+            for (name, value) in &headers {
+                request = request.set(name, value);
+            }
+            let response = match request.send_string(&body) {
+                Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+                Err(e) => panic!("request failed: {e}"),
+            };
+            let session = response.header("mcp-session-id").map(str::to_string);
+            (response.status(), session, response.into_string().unwrap())
+        })
+        .await
+        .unwrap()
+    }
+
+    /// The JSON-RPC message carried by a server-sent event stream
+    fn event(body: &str) -> Value {
+        let data = body
+            .lines()
+            .find_map(|line| line.strip_prefix("data:"))
+            .unwrap_or_else(|| panic!("no event in {body:?}"));
+        serde_json::from_str(data.trim()).unwrap()
+    }
+
+    async fn start(access: Access) -> (TempDir, PmxMcpServer, std::net::SocketAddr) {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = crate::storage::Storage::initialize(temp_dir.path().join("storage")).unwrap();
+        storage.create_profile("rust", "Use cargo.\n").unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = PmxMcpServer::new(storage).unwrap();
+        tokio::spawn(serve(server.clone(), listener, access));
+        (temp_dir, server, address)
+    }
+
+    #[tokio::test]
+    async fn test_sessions_and_bearer_token() {
+        let access = Access {
+            token: Some("secret".to_string()),
+            allowed_origins: Vec::new(),
+        };
+        let (_temp_dir, _, address) = start(access).await;
+
+        let ping = json!({"jsonrpc": "2.0", "id": 2, "method": "ping"});
+        let (status, _, _) = send(address, "POST", &[], &initialize_request()).await;
+        assert_eq!(status, 401);
+        let auth = ("Authorization", "Bearer secret");
+        let (status, _, _) = send(
+            address,
+            "POST",
+            &[("Authorization", "Bearer secreT")],
+            &ping,
+        )
+        .await;
+        assert_eq!(status, 401);
+
+        let (status, _, _) = send(address, "POST", &[auth], &ping).await;
+        assert_eq!(status, 422);
+        let origin = ("Origin", "http://rebound.example");
+        let (status, _, _) = send(address, "POST", &[auth, origin], &initialize_request()).await;
+        assert_eq!(status, 403);
+
+        let (status, session, body) = send(address, "POST", &[auth], &initialize_request()).await;
+        assert_eq!(status, 200);
+        assert_eq!(
+            event(&body)["result"]["serverInfo"]["name"],
+            "pmx-mcp-server"
+        );
+        let session = session.unwrap();
+
+        let initialized = json!({"jsonrpc": "2.0", "method": "notifications/initialized"});
+        let session_header = ("Mcp-Session-Id", session.as_str());
+        let (status, _, _) = send(address, "POST", &[auth, session_header], &initialized).await;
+        assert_eq!(status, 202);
+
+        let list = json!({"jsonrpc": "2.0", "id": 3, "method": "prompts/list"});
+        let (status, _, body) = send(address, "POST", &[auth, session_header], &list).await;
+        assert_eq!(status, 200);
+        let reply = event(&body);
+        assert_eq!(reply["id"], 3);
+        assert_eq!(reply["result"]["prompts"][0]["name"], "rust");
+
+        let (status, _, _) = send(address, "DELETE", &[auth, session_header], &Value::Null).await;
+        assert_eq!(status, 202);
+        let (status, _, _) = send(address, "POST", &[auth, session_header], &ping).await;
+        assert_eq!(status, 401);
+    }
+
+    #[tokio::test]
+    async fn test_event_stream_carries_server_messages() {
+        let (_temp_dir, server, address) = start(Access::default()).await;
+
+        let (_, session, _) = send(address, "POST", &[], &initialize_request()).await;
+        let session = session.unwrap();
+        let initialized = json!({"jsonrpc": "2.0", "method": "notifications/initialized"});
+        let session_header = ("Mcp-Session-Id", session.as_str());
+        send(address, "POST", &[session_header], &initialized).await;
+
+        let mut stream = TcpStream::connect(address).await.unwrap();
+        let request = format!(
+            "GET {ENDPOINT} HTTP/1.1\r\nHost: localhost\r\nAccept: text/event-stream\r\nMcp-Session-Id: {session}\r\n\r\n"
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+
+        let mut received = String::new();
+        let mut buffer = [0; 1024];
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+        while !received.contains("notifications/prompts/list_changed") {
+            // The session subscribes once it handled `initialized`, so keep announcing
+            server.announce_prompts_changed();
+            if let Ok(read) =
+                tokio::time::timeout(Duration::from_millis(50), stream.read(&mut buffer)).await
+            {
+                received.push_str(&String::from_utf8_lossy(&buffer[..read.unwrap()]));
+            }
+            assert!(
+                tokio::time::Instant::now() < deadline,
+                "no event in {received:?}"
+            );
+        }
+        assert!(received.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(received.contains("content-type: text/event-stream"));
+    }
+
+    #[tokio::test]
+    async fn test_request_limits() {
+        let (_temp_dir, _, address) = start(Access::default()).await;
+
+        let names: Vec<String> = (0..=MAX_HEADERS).map(|n| format!("X-Extra-{n}")).collect();
+        let headers: Vec<(&str, &str)> = names.iter().map(|name| (name.as_str(), "1")).collect();
+        let (status, _, _) = send(address, "POST", &headers, &initialize_request()).await;
+        assert_eq!(status, 431);
+
+        let mut stream = TcpStream::connect(address).await.unwrap();
+        let request = format!(
+            "POST {ENDPOINT} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY_BYTES + 1
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut reply = [0; 64];
+        let read = stream.read(&mut reply).await.unwrap();
+        assert!(String::from_utf8_lossy(&reply[..read]).starts_with("HTTP/1.1 413"));
+    }
+}
@@ -0,0 +1,187 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::bail;
+use serde_json::{Map, Value, json};
+
+use crate::cli::{McpClient, McpInstallArgs};
+use crate::storage::Storage;
+
+/// Add or update the pmx server entry in an MCP client's configuration file
+pub fn install(storage: &Storage, args: &McpInstallArgs) -> crate::Result<()> {
+    let path = match &args.file {
+        Some(file) => file.clone(),
+        None => config_path(args.client, args.global)?,
+    };
+
+    match update_config(&path, &args.name, entry(storage, args)?)? {
+        Some(outcome) => println!("{outcome} MCP server '{}' in {}", args.name, path.display()),
+        None => println!(
+            "MCP server '{}' is already up to date in {}",
+            args.name,
+            path.display()
+        ),
+    }
+    Ok(())
+}
+
+/// Where each client reads its MCP servers from
+fn config_path(client: McpClient, global: bool) -> crate::Result<PathBuf> {
+    let cwd = || {
+        std::env::current_dir()
+            .map_err(|e| anyhow::anyhow!("Failed to get current directory: {}", e))
+    };
+    Ok(match (client, global) {
+        (McpClient::ClaudeDesktop, _) => claude_desktop_dir()?.join("claude_desktop_config.json"),
+        (McpClient::ClaudeCode, false) => cwd()?.join(".mcp.json"),
+        (McpClient::ClaudeCode, true) => crate::utils::home_dir()?.join(".claude.json"),
+        (McpClient::Cursor, false) => cwd()?.join(".cursor").join("mcp.json"),
+        (McpClient::Cursor, true) => crate::utils::home_dir()?.join(".cursor").join("mcp.json"),
+    })
+}
+
+fn claude_desktop_dir() -> crate::Result<PathBuf> {
+    if cfg!(target_os = "macos") {
+        return Ok(crate::utils::home_dir()?.join("Library/Application Support/Claude"));
+    }
+    if cfg!(windows) {
+        let appdata =
+            std::env::var_os("APPDATA").ok_or_else(|| anyhow::anyhow!("APPDATA is not set"))?;
+        return Ok(PathBuf::from(appdata).join("Claude"));
+    }
+    Ok(crate::utils::home_dir()?.join(".config").join("Claude"))
+}
+
+/// The server entry to install. A project's file is shared with everyone working
+/// on it, so it leaves out this machine's paths and lets each run their own pmx.
+fn entry(storage: &Storage, args: &McpInstallArgs) -> crate::Result<Value> {
+    if args.file.is_none() && !args.global && args.client != McpClient::ClaudeDesktop {
+        return Ok(shared_entry());
+    }
+    let exe = std::env::current_exe()
+        .map_err(|e| anyhow::anyhow!("Failed to locate the pmx executable: {}", e))?;
+    Ok(server_entry(&exe, &storage.path))
+}
+
+/// Command that starts this pmx's MCP server on the given storage
+fn server_entry(exe: &Path, storage_path: &Path) -> Value {
+    json!({
+        "command": exe.to_string_lossy(),
+        "args": ["--storage", storage_path.to_string_lossy(), "mcp"],
+    })
+}
+
+/// Command that starts the MCP server of whichever pmx is on `PATH`, on its own storage
+fn shared_entry() -> Value {
+    json!({
+        "command": "pmx",
+        "args": ["mcp"],
+    })
+}
+
+/// Set `mcpServers.<name>` in the JSON file at `path`, keeping everything else;
+/// `None` if the entry was already there as given
+fn update_config(path: &Path, name: &str, entry: Value) -> crate::Result<Option<&'static str>> {
+    let mut config = if path.exists() {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+        if content.trim().is_empty() {
+            Value::Object(Map::new())
+        } else {
+            serde_json::from_str(&content)
+                .map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", path.display(), e))?
+        }
+    } else {
+        Value::Object(Map::new())
+    };
+    let Value::Object(root) = &mut config else {
+        bail!("{} does not contain a JSON object", path.display());
+    };
+    let servers = root
+        .entry("mcpServers")
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .ok_or_else(|| anyhow::anyhow!("mcpServers in {} is not an object", path.display()))?;
+
+    let outcome = match servers.get(name) {
+        Some(existing) if *existing == entry => return Ok(None),
+        Some(_) => "Updated",
+        None => "Added",
+    };
+    servers.insert(name.to_string(), entry);
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| anyhow::anyhow!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let content = serde_json::to_string_pretty(&config)
+        .map_err(|e| anyhow::anyhow!("Failed to serialize {}: {}", path.display(), e))?;
+    // Written in one step: a client may be reading the file, and ~/.claude.json
+    // also holds Claude Code's own settings
+    crate::utils::write_atomic(path, format!("{content}\n"))
+        .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))?;
+    Ok(Some(outcome))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_update_config_keeps_other_settings() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(".cursor").join("mcp.json");
+        let entry = server_entry(Path::new("/bin/pmx"), Path::new("/home/me/.config/pmx"));
+
+        assert_eq!(
+            update_config(&path, "pmx", entry.clone()).unwrap(),
+            Some("Added")
+        );
+        let config: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(
+            config,
+            json!({"mcpServers": {"pmx": {
+                "command": "/bin/pmx",
+                "args": ["--storage", "/home/me/.config/pmx", "mcp"]
+            }}})
+        );
+        assert_eq!(update_config(&path, "pmx", entry.clone()).unwrap(), None);
+
+        std::fs::write(
+            &path,
+            r#"{"theme": "dark", "mcpServers": {"other": {"command": "x"}, "pmx": {"command": "old"}}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            update_config(&path, "pmx", entry.clone()).unwrap(),
+            Some("Updated")
+        );
+        let config: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(config["theme"], "dark");
+        assert_eq!(config["mcpServers"]["other"]["command"], "x");
+        assert_eq!(config["mcpServers"]["pmx"], entry);
+
+        std::fs::write(&path, "[1, 2]").unwrap();
+        assert!(update_config(&path, "pmx", entry).is_err());
+    }
+
+    #[test]
+    fn test_project_entry_has_no_local_paths() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::initialize(temp_dir.path().join("storage")).unwrap();
+        let mut args = McpInstallArgs {
+            client: McpClient::ClaudeCode,
+            name: "pmx".to_string(),
+            global: false,
+            file: None,
+        };
+
+        assert_eq!(
+            entry(&storage, &args).unwrap(),
+            json!({"command": "pmx", "args": ["mcp"]})
+        );
+        args.global = true;
+        let global = entry(&storage, &args).unwrap();
+        assert_eq!(global["args"][1], storage.path.to_string_lossy().as_ref());
+    }
+}
@@ -0,0 +1,182 @@
+use std::future::ready;
+
+use anyhow::Result;
+use futures::{SinkExt, StreamExt};
+use rmcp::{
+    RoleServer, ServiceExt,
+    service::{RxJsonRpcMessage, TxJsonRpcMessage},
+};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http::{StatusCode, header};
+
+use super::mcp::{Access, PmxMcpServer};
+
+/// Accept WebSocket connections that `access` allows, serving each one as its
+/// own MCP session
+pub(crate) async fn serve(
+    server: PmxMcpServer,
+    listener: TcpListener,
+    access: Access,
+) -> Result<()> {
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let server = server.clone();
+        let access = access.clone();
+        tokio::spawn(async move {
+            tracing::info!(%peer, "WebSocket client connected");
+            match session(server, stream, &access).await {
+                Ok(()) => tracing::info!(%peer, "WebSocket client disconnected"),
+                Err(e) => tracing::warn!(%peer, error = %e, "WebSocket session failed"),
+            }
+        });
+    }
+}
+
+async fn session(server: PmxMcpServer, stream: TcpStream, access: &Access) -> Result<()> {
+    // The error type is the one tungstenite's handshake callback takes
+    #[allow(clippy::result_large_err)]
+    let check = |request: &Request, response: Response| -> Result<Response, ErrorResponse> {
+        let header = |name| {
+            request
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+        };
+        match access.refusal(header(header::ORIGIN), header(header::AUTHORIZATION)) {
+            None => Ok(response),
+            Some(reason) => {
+                let mut refused = ErrorResponse::new(Some(reason.to_string()));
+                *refused.status_mut() = StatusCode::FORBIDDEN;
+                Err(refused)
+            }
+        }
+    };
+    let socket = tokio_tungstenite::accept_hdr_async(stream, check)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to accept WebSocket connection: {}", e))?;
+    let (sink, stream) = socket.split();
+
+    let sink = sink
+        .sink_map_err(std::io::Error::other)
+        .with(|message: TxJsonRpcMessage<RoleServer>| ready(encode(&message)));
+    // The session ends when the client closes the socket or the connection breaks
+    let stream = stream
+        .take_while(|frame| ready(frame.as_ref().is_ok_and(|frame| !frame.is_close())))
+        .filter_map(|frame| ready(frame.ok().and_then(decode)));
+
+    let server = server.serve((Box::pin(sink), Box::pin(stream))).await?;
+    server.waiting().await?;
+    Ok(())
+}
+
+/// Each JSON-RPC message travels as one text frame
+fn encode(message: &TxJsonRpcMessage<RoleServer>) -> std::io::Result<Message> {
+    serde_json::to_string(message)
+        .map(Message::text)
+        .map_err(std::io::Error::other)
+}
+
+/// Parse a client frame; control frames and malformed messages are skipped
+fn decode(frame: Message) -> Option<RxJsonRpcMessage<RoleServer>> {
+    let payload = match &frame {
+        Message::Text(text) => text.as_bytes(),
+        Message::Binary(data) => data.as_slice(),
+        _ => return None,
+    };
+    serde_json::from_slice(payload)
+        .inspect_err(|e| tracing::warn!(error = %e, "ignoring malformed WebSocket message"))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{Value, json};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_decode_skips_control_and_malformed_frames() {
+        let ping = json!({"jsonrpc": "2.0", "id": 1, "method": "ping"});
+        assert!(decode(Message::text(ping.to_string())).is_some());
+        assert!(decode(Message::binary(ping.to_string().into_bytes())).is_some());
+        assert!(decode(Message::text("not json")).is_none());
+        assert!(decode(Message::Ping(Vec::new())).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_serve_answers_initialize() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = crate::storage::Storage::initialize(temp_dir.path().join("storage")).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(serve(
+            PmxMcpServer::new(storage).unwrap(),
+            listener,
+            Access::default(),
+        ));
+
+        let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{address}"))
+            .await
+            .unwrap();
+        let initialize = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "initialize",
+            "params": {
+                "protocolVersion": "2025-03-26",
+                "capabilities": {},
+                "clientInfo": {"name": "test", "version": "0.0.0"}
+            }
+        });
+        socket
+            .send(Message::text(initialize.to_string()))
+            .await
+            .unwrap();
+
+        let reply = socket.next().await.unwrap().unwrap();
+        let reply: Value = serde_json::from_str(reply.to_text().unwrap()).unwrap();
+        assert_eq!(reply["id"], 1);
+        assert_eq!(reply["result"]["serverInfo"]["name"], "pmx-mcp-server");
+    }
+
+    #[tokio::test]
+    async fn test_serve_refuses_foreign_origins_and_missing_token() {
+        use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+
+        let temp_dir = TempDir::new().unwrap();
+        let storage = crate::storage::Storage::initialize(temp_dir.path().join("storage")).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let access = Access {
+            token: Some("secret".to_string()),
+            allowed_origins: Vec::new(),
+        };
+        tokio::spawn(serve(PmxMcpServer::new(storage).unwrap(), listener, access));
+
+        let request = |headers: &[(&'static str, &'static str)]| {
+            let mut request = format!("ws://{address}").into_client_request().unwrap();
+            for (name, value) in headers {
+                request.headers_mut().insert(*name, value.parse().unwrap());
+            }
+            request
+        };
+        let auth = ("Authorization", "Bearer secret");
+        assert!(
+            tokio_tungstenite::connect_async(request(&[]))
+                .await
+                .is_err()
+        );
+        assert!(
+            tokio_tungstenite::connect_async(request(&[auth, ("Origin", "https://evil.example")]))
+                .await
+                .is_err()
+        );
+        assert!(
+            tokio_tungstenite::connect_async(request(&[auth, ("Origin", "http://localhost:3000")]))
+                .await
+                .is_ok()
+        );
+    }
+}
@@ -0,0 +1,381 @@
+use std::path::Path;
+
+use anyhow::{bail, ensure};
+
+use crate::pack::{CHECKSUMS_FILE, InstalledPack, InstalledPacks, PackManifest};
+use crate::storage::Storage;
+
+/// Install a pack from a local directory or git URL into `repo/<name>/`,
+/// recording content hashes and optionally checking the publisher's signature
+pub fn install(storage: &Storage, args: &crate::cli::PackInstallArgs) -> crate::Result<()> {
+    let checkout;
+    let source = if crate::utils::is_git_url(&args.source) {
+        checkout = tempfile::TempDir::new()
+            .map_err(|e| anyhow::anyhow!("Failed to create temporary directory: {}", e))?;
+        crate::utils::git_clone(&args.source, checkout.path())?;
+        checkout.path().to_path_buf()
+    } else {
+        Path::new(&args.source).to_path_buf()
+    };
+    ensure!(
+        source.is_dir(),
+        "Pack source is not a directory: {}",
+        source.display()
+    );
+    let manifest = PackManifest::load(&source)?;
+
+    let name = match (&args.name, &manifest) {
+        (Some(name), _) => name.clone(),
+        (None, Some(manifest)) => manifest.pack.name.clone(),
+        (None, None) => default_name(&args.source)?,
+    };
+    ensure!(
+        !name.is_empty()
+            && !name
+                .split(['/', '\\'])
+                .any(|part| part == ".." || part.is_empty()),
+        "Invalid pack name: {}",
+        name
+    );
+    let dest = crate::pack::pack_dir(storage, &name);
+    ensure!(
+        args.force || !dest.exists(),
+        "{} already exists (use --force to replace it)",
+        dest.display()
+    );
+
+    let files: Vec<_> = crate::utils::visible_files(&source)?
+        .into_iter()
+        .filter(|file| !is_checksums_file(file))
+        .collect();
+    let signature = verify_signature(&source, args)?;
+    if signature.is_some() || source.join(CHECKSUMS_FILE).is_file() {
+        check_published_sums(&source, &crate::pack::hash_files(&source, &files)?)?;
+    }
+
+    // The manifest, README and tests describe the pack; only its profiles are installed
+    let files: Vec<_> = files
+        .into_iter()
+        .filter(|file| manifest.is_none() || !crate::pack::is_pack_metadata(file))
+        .collect();
+    let hashes = crate::pack::hash_files(&source, &files)?;
+
+    if dest.exists() {
+        std::fs::remove_dir_all(&dest)
+            .map_err(|e| anyhow::anyhow!("Failed to remove {}: {}", dest.display(), e))?;
+    }
+    crate::utils::copy_files(&source, &dest, &files)?;
+    crate::manifest::refresh(&storage.path, &[name.clone().into()])?;
+
+    let mut packs = InstalledPacks::load(&storage.path)?;
+    packs.packs.insert(
+        name.clone(),
+        InstalledPack {
+            source: args.source.clone(),
+            signature: signature.clone(),
+            files: hashes,
+        },
+    );
+    packs.persist(&storage.path)?;
+
+    println!(
+        "Installed pack '{}' ({} file(s)) into {}{}",
+        name,
+        files.len(),
+        dest.display(),
+        signature
+            .map(|method| format!(", {method} signature verified"))
+            .unwrap_or_default()
+    );
+    Ok(())
+}
+
+/// Scaffold a new pack: manifest, README, an example profile and a tests directory
+pub fn new(args: &crate::cli::PackNewArgs) -> crate::Result<()> {
+    let dir = &args.path;
+    ensure!(
+        !dir.exists()
+            || std::fs::read_dir(dir)
+                .map(|mut entries| entries.next().is_none())
+                .unwrap_or(false),
+        "{} already exists and is not empty",
+        dir.display()
+    );
+    let name = match &args.name {
+        Some(name) => name.clone(),
+        None => default_name(&dir.to_string_lossy())?,
+    };
+
+    let manifest = PackManifest {
+        pack: crate::pack::PackInfo {
+            name: name.clone(),
+            version: "0.1.0".to_string(),
+            description: Some(format!("Prompts from {name}")),
+            license: Some("CC-BY-4.0".to_string()),
+            authors: Vec::new(),
+        },
+    };
+    let manifest = toml::to_string(&manifest)
+        .map_err(|e| anyhow::anyhow!("Failed to serialize pack manifest: {}", e))?;
+
+    let files = [
+        (crate::pack::MANIFEST_FILE.to_string(), manifest),
+        ("README.md".to_string(), readme(&name)),
+        ("example.md".to_string(), EXAMPLE_PROFILE.to_string()),
+        ("tests/example.toml".to_string(), EXAMPLE_TEST.to_string()),
+    ];
+    for (file, content) in &files {
+        let path = dir.join(file);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                anyhow::anyhow!("Failed to create directory {}: {}", parent.display(), e)
+            })?;
+        }
+        std::fs::write(&path, content)
+            .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))?;
+    }
+
+    println!("Created pack '{}' in {}", name, dir.display());
+    for (file, _) in &files {
+        println!("  {file}");
+    }
+    Ok(())
+}
+
+const EXAMPLE_PROFILE: &str = "---
+title: Example
+description: Reviews code in a given language
+license: CC-BY-4.0
+tags: [example]
+---
+# Code Reviewer
+
+You are reviewing <{{LANGUAGE}}> code. Point out bugs before style issues.
+";
+
+const EXAMPLE_TEST: &str = "# Arguments to render example.md with when checking the pack
+profile = \"example\"
+
+[arguments]
+LANGUAGE = \"Rust\"
+";
+
+fn readme(name: &str) -> String {
+    format!(
+        "# {name}
+
+Prompt pack for [pmx](https://github.com/NishantJoshi00/pmx).
+
+## Install
+
+```bash
+pmx pack install <git-url-or-path>
+```
+
+## Profiles
+
+- `example`: reviews code in a given language (`LANGUAGE` argument)
+"
+    )
+}
+
+/// Report installed pack files that were modified, removed or added since install,
+/// returning how many were found
+pub fn verify(storage: &Storage) -> crate::Result<usize> {
+    let packs = InstalledPacks::load(&storage.path)?;
+    let mut problems = 0;
+    for (name, pack) in &packs.packs {
+        let deviations = crate::pack::verify(&crate::pack::pack_dir(storage, name), pack)?;
+        if deviations.is_empty() {
+            println!("{name}: ok");
+            continue;
+        }
+        println!("{name}:");
+        for deviation in &deviations {
+            println!("  {deviation}");
+        }
+        problems += deviations.len();
+    }
+    Ok(problems)
+}
+
+fn default_name(source: &str) -> crate::Result<String> {
+    let name = source
+        .trim_end_matches('/')
+        .rsplit(['/', ':'])
+        .next()
+        .unwrap_or_default()
+        .trim_end_matches(".git");
+    ensure!(
+        !name.is_empty() && !name.starts_with('.'),
+        "Cannot derive a pack name from '{}'; pass --name",
+        source
+    );
+    Ok(name.to_string())
+}
+
+/// `SHA256SUMS` and its signatures, which are not installed as pack content
+fn is_checksums_file(file: &Path) -> bool {
+    let file = file.to_string_lossy();
+    file == CHECKSUMS_FILE || file.starts_with(&format!("{CHECKSUMS_FILE}."))
+}
+
+/// Check the detached signature over `SHA256SUMS` with minisign or gpg, as requested
+fn verify_signature(
+    source: &Path,
+    args: &crate::cli::PackInstallArgs,
+) -> crate::Result<Option<String>> {
+    let sums = source.join(CHECKSUMS_FILE);
+    let (method, mut command) = if let Some(key) = &args.minisign_key {
+        let mut command = std::process::Command::new("minisign");
+        command.arg("-Vm").arg(&sums).arg("-P").arg(key);
+        ("minisign", command)
+    } else if args.gpg {
+        let mut command = std::process::Command::new("gpg");
+        command
+            .arg("--verify")
+            .arg(source.join(format!("{CHECKSUMS_FILE}.asc")))
+            .arg(&sums);
+        ("gpg", command)
+    } else {
+        return Ok(None);
+    };
+
+    ensure!(
+        sums.is_file(),
+        "Pack has no {} file to verify the signature against",
+        CHECKSUMS_FILE
+    );
+    let output = command
+        .output()
+        .map_err(|e| anyhow::anyhow!("Failed to run {}: {}", method, e))?;
+    if !output.status.success() {
+        bail!(
+            "{} signature verification failed: {}",
+            method,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(Some(method.to_string()))
+}
+
+/// Ensure the files match the publisher's `SHA256SUMS` exactly
+fn check_published_sums(
+    source: &Path,
+    hashes: &std::collections::BTreeMap<String, String>,
+) -> crate::Result<()> {
+    let content = std::fs::read_to_string(source.join(CHECKSUMS_FILE))
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", CHECKSUMS_FILE, e))?;
+    let published = crate::pack::parse_checksums(&content)?;
+
+    for (path, hash) in hashes {
+        match published.get(path) {
+            Some(expected) if expected == hash => {}
+            Some(_) => bail!("Checksum mismatch for {}", path),
+            None => bail!("{} is not listed in {}", path, CHECKSUMS_FILE),
+        }
+    }
+    for path in published.keys() {
+        ensure!(
+            hashes.contains_key(path),
+            "{} is listed in {} but missing from the pack",
+            path,
+            CHECKSUMS_FILE
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn install_args(source: &Path) -> crate::cli::PackInstallArgs {
+        crate::cli::PackInstallArgs {
+            source: source.to_string_lossy().to_string(),
+            name: None,
+            force: false,
+            minisign_key: None,
+            gpg: false,
+        }
+    }
+
+    #[test]
+    fn test_install_records_hashes_and_checks_sums() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::initialize(temp_dir.path().join("storage")).unwrap();
+        let source = temp_dir.path().join("team-prompts");
+        std::fs::create_dir_all(&source).unwrap();
+        std::fs::write(source.join("review.md"), "Review.\n").unwrap();
+        let hash = crate::utils::content_hash(b"Review.\n");
+        std::fs::write(source.join(CHECKSUMS_FILE), format!("{hash}  review.md\n")).unwrap();
+
+        install(&storage, &install_args(&source)).unwrap();
+
+        assert!(storage.profile_exists("team-prompts/review"));
+        assert!(!storage.path.join("repo/team-prompts/SHA256SUMS").exists());
+        let packs = InstalledPacks::load(&storage.path).unwrap();
+        assert_eq!(packs.packs["team-prompts"].files["review.md"], hash);
+
+        // Reinstalling requires --force
+        assert!(install(&storage, &install_args(&source)).is_err());
+    }
+
+    #[test]
+    fn test_install_rejects_tampered_pack() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::initialize(temp_dir.path().join("storage")).unwrap();
+        let source = temp_dir.path().join("pack");
+        std::fs::create_dir_all(&source).unwrap();
+        std::fs::write(source.join("review.md"), "Tampered.\n").unwrap();
+        let hash = crate::utils::content_hash(b"Review.\n");
+        std::fs::write(source.join(CHECKSUMS_FILE), format!("{hash}  review.md\n")).unwrap();
+
+        let err = install(&storage, &install_args(&source)).unwrap_err();
+        assert!(err.to_string().contains("Checksum mismatch for review.md"));
+        assert!(!storage.path.join("repo/pack").exists());
+    }
+
+    #[test]
+    fn test_new_pack_installs_only_profiles() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::initialize(temp_dir.path().join("storage")).unwrap();
+        let pack_path = temp_dir.path().join("my-pack");
+        new(&crate::cli::PackNewArgs {
+            path: pack_path.clone(),
+            name: None,
+        })
+        .unwrap();
+
+        let manifest = PackManifest::load(&pack_path).unwrap().unwrap();
+        assert_eq!(manifest.pack.name, "my-pack");
+        assert!(pack_path.join("tests/example.toml").exists());
+        assert!(
+            new(&crate::cli::PackNewArgs {
+                path: pack_path.clone(),
+                name: None,
+            })
+            .is_err()
+        );
+
+        install(&storage, &install_args(&pack_path)).unwrap();
+        let mut profiles = storage.list_repos().unwrap();
+        profiles.sort();
+        assert_eq!(profiles, vec!["my-pack/example"]);
+        let metadata =
+            crate::frontmatter::parse_metadata(&storage.get_content("my-pack/example").unwrap())
+                .unwrap();
+        assert_eq!(metadata.license.as_deref(), Some("CC-BY-4.0"));
+    }
+
+    #[test]
+    fn test_default_name() {
+        assert_eq!(
+            default_name("https://github.com/team/prompts.git").unwrap(),
+            "prompts"
+        );
+        assert_eq!(default_name("./local/pack/").unwrap(), "pack");
+        assert!(default_name(".").is_err());
+    }
+}
@@ -0,0 +1,104 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::agent::Agent;
+use crate::paths::StoragePath;
+
+/// Where pmx reads and writes its files
+#[derive(Debug, Clone)]
+pub struct Paths {
+    pub storage: StoragePath,
+    /// Whether the storage directory exists yet
+    pub exists: bool,
+    pub config: PathBuf,
+    pub profiles: PathBuf,
+    pub state: PathBuf,
+    pub backups: PathBuf,
+    pub trash: PathBuf,
+    /// Each agent's user-wide prompt file, or why it cannot be resolved
+    pub agents: Vec<(Agent, Result<PathBuf, String>)>,
+}
+
+/// Locate the storage files and agent targets; needs no storage directory
+pub fn paths(resolved: &StoragePath) -> crate::Result<Paths> {
+    let storage = &resolved.path;
+
+    // Without a readable config the default targets are reported
+    let loaded = crate::storage::Storage::new(storage.clone()).ok();
+    let agents = match &loaded {
+        Some(loaded) => Agent::all(loaded)?,
+        None => Agent::ALL.to_vec(),
+    };
+    let agents = agents
+        .into_iter()
+        .map(|agent| {
+            let target = match &loaded {
+                Some(loaded) => agent.global_target(loaded),
+                None => agent.default_target(),
+            };
+            (agent, target.map_err(|e| e.to_string()))
+        })
+        .collect();
+
+    Ok(Paths {
+        storage: resolved.clone(),
+        exists: storage.exists(),
+        config: storage.join("config.toml"),
+        profiles: storage.join("repo"),
+        state: storage.join(crate::state::STATE_FILE),
+        backups: storage.join(crate::backups::BACKUPS_DIR),
+        trash: storage.join(crate::trash::TRASH_DIR),
+        agents,
+    })
+}
+
+impl fmt::Display for Paths {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let missing = if self.exists {
+            ""
+        } else {
+            " (not created yet)"
+        };
+        writeln!(
+            f,
+            "Storage:  {} (from {}){missing}",
+            self.storage.path.display(),
+            self.storage.source
+        )?;
+        writeln!(f, "Config:   {}", self.config.display())?;
+        writeln!(f, "Profiles: {}", self.profiles.display())?;
+        writeln!(f, "State:    {}", self.state.display())?;
+        writeln!(f, "Backups:  {}", self.backups.display())?;
+        writeln!(f, "Trash:    {}", self.trash.display())?;
+        for (agent, target) in &self.agents {
+            let target = match target {
+                Ok(target) => target.display().to_string(),
+                Err(e) => format!("unavailable ({e})"),
+            };
+            writeln!(f, "{:<9} {target}", format!("{}:", agent.display_name()))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paths::Source;
+
+    #[test]
+    fn test_describe_reports_source_and_missing_storage() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let resolved = StoragePath {
+            path: temp_dir.path().join("pmx"),
+            source: Source::Home,
+        };
+
+        let report = paths(&resolved).unwrap().to_string();
+        assert!(report.contains("(from $PMX_HOME) (not created yet)"));
+        assert!(report.contains(&format!(
+            "Profiles: {}",
+            temp_dir.path().join("pmx/repo").display()
+        )));
+    }
+}
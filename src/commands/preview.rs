@@ -0,0 +1,105 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::Context;
+use pulldown_cmark::{Options, Parser};
+
+use crate::storage::Storage;
+
+const STYLE: &str = "body{max-width:50rem;margin:2rem auto;padding:0 1rem;\
+font-family:system-ui,sans-serif;line-height:1.5}\
+pre,code{background:#f4f4f4;border-radius:4px}pre{padding:.75rem;overflow-x:auto}\
+table{border-collapse:collapse}th,td{border:1px solid #ccc;padding:.25rem .5rem}";
+
+/// Print the profile as HTML, or open it in the default browser when `browser` is set
+pub fn preview(storage: &Storage, name: &str, browser: bool) -> crate::Result<()> {
+    let content = storage.get_profile_content(name)?;
+    let metadata = crate::frontmatter::parse_metadata(&content)?;
+    let title = metadata.title.as_deref().unwrap_or(name);
+    let html = render_html(title, crate::frontmatter::split(&content).1);
+
+    if !browser {
+        print!("{html}");
+        return Ok(());
+    }
+
+    let path = preview_path(name);
+    std::fs::write(&path, html)
+        .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))?;
+    open_in_browser(&path)?;
+    println!("Opened {} in your browser", path.display());
+    Ok(())
+}
+
+/// Render markdown as a standalone HTML page
+fn render_html(title: &str, markdown: &str) -> String {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_FOOTNOTES;
+    let mut body = String::new();
+    pulldown_cmark::html::push_html(&mut body, Parser::new_ext(markdown, options));
+
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+         <style>{STYLE}</style>\n</head>\n<body>\n{body}</body>\n</html>\n",
+        escape_html(title)
+    )
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// A stable file per profile in the temp directory, so repeated previews reuse one tab
+fn preview_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("pmx-preview-{}.html", name.replace('/', "-")))
+}
+
+/// Open `path` with `$BROWSER` or the platform's default handler
+fn open_in_browser(path: &Path) -> crate::Result<()> {
+    let mut command = match std::env::var("BROWSER") {
+        Ok(browser) if !browser.is_empty() => {
+            let words = shlex::split(&browser)
+                .filter(|words| !words.is_empty())
+                .ok_or_else(|| anyhow::anyhow!("Failed to parse $BROWSER: {}", browser))?;
+            let mut command = Command::new(&words[0]);
+            command.args(&words[1..]);
+            command
+        }
+        _ if cfg!(target_os = "macos") => Command::new("open"),
+        _ if cfg!(windows) => {
+            let mut command = Command::new("cmd");
+            command.args(["/C", "start", ""]);
+            command
+        }
+        _ => Command::new("xdg-open"),
+    };
+
+    let status = command
+        .arg(path)
+        .status()
+        .with_context(|| "Failed to launch a browser")?;
+    anyhow::ensure!(status.success(), "Browser command exited with {}", status);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_html() {
+        let html = render_html(
+            "A & B",
+            "# Title\n\n| a | b |\n|---|---|\n| 1 | 2 |\n\n- [x] done\n",
+        );
+        assert!(html.contains("<title>A &amp; B</title>"));
+        assert!(html.contains("<h1>Title</h1>"));
+        assert!(html.contains("<table>"));
+        assert!(html.contains("type=\"checkbox\""));
+    }
+}
@@ -1,101 +1,219 @@
 use anyhow::{Context, anyhow};
-use dialoguer::Confirm;
-use std::env;
 use std::fs;
-use std::process::Command;
 
-pub fn edit(storage: &crate::storage::Storage, name: &str) -> crate::Result<()> {
+use crate::editor::Editor;
+
+pub fn edit(
+    storage: &crate::storage::Storage,
+    name: &str,
+    editor: Option<&str>,
+) -> crate::Result<()> {
     // Check if profile exists
+    let before = storage.get_profile_content(name)?;
+    if storage.shared_layer(name).is_some() {
+        // Edit a personal copy rather than the shared base
+        storage.create_profile(name, &before)?;
+    }
     let profile_path = storage.get_repo_path(name)?;
+    let editor = Editor::resolve(storage, editor)?;
 
-    // Get editor from environment or use default
-    let editor = get_editor()?;
+    let after = if crate::crypto::is_encrypted(&crate::encoding::read_text(&profile_path)?) {
+        // Edit the plaintext in a private temporary file; saving encrypts it again
+        let temp_file = tempfile::Builder::new()
+            .suffix(".md")
+            .tempfile()
+            .with_context(|| "Failed to create temporary file")?;
+        fs::write(temp_file.path(), &before)
+            .with_context(|| "Failed to write profile to temporary file")?;
+        editor.open(temp_file.path())?;
+        crate::encoding::read_text(temp_file.path())?
+    } else {
+        editor.open(&profile_path)?;
+        storage.get_profile_content(name)?
+    };
 
-    // Open profile in editor
-    let status = Command::new(&editor)
-        .arg(&profile_path)
-        .status()
-        .with_context(|| format!("Failed to execute editor: {editor}"))?;
-
-    if !status.success() {
-        return Err(anyhow!("Editor exited with non-zero status"));
+    // Record the edit like any other change to the repository
+    if after != before {
+        storage.create_profile(name, &after)?;
     }
-
-    println!("Profile '{name}' edited successfully");
     Ok(())
 }
 
-pub fn delete(storage: &crate::storage::Storage, name: &str) -> crate::Result<()> {
+/// Move a profile to the trash after showing it and asking, unless `yes`.
+/// Returns whether it was deleted.
+pub fn delete(storage: &crate::storage::Storage, name: &str, yes: bool) -> crate::Result<bool> {
     // Check if profile exists
     let profile_path = storage.get_repo_path(name)?;
 
-    // Show profile content before deletion
-    let content = fs::read_to_string(&profile_path)
-        .with_context(|| format!("Failed to read profile: {name}"))?;
+    // Show profile content before asking, on stderr with the question
+    if !yes && crate::utils::is_interactive() {
+        let content = crate::encoding::read_text(&profile_path)
+            .with_context(|| format!("Failed to read profile: {name}"))?;
+        eprintln!("Profile '{name}' contents:");
+        eprintln!("{content}");
+        eprintln!();
+    }
 
-    println!("Profile '{name}' contents:");
-    println!("{content}");
-    println!();
+    if !crate::utils::confirm(&format!("Delete profile '{name}'?"), yes)? {
+        return Ok(false);
+    }
+    storage.delete_profile(name)?;
+    Ok(true)
+}
 
-    // Ask for confirmation
-    let confirmed = Confirm::new()
-        .with_prompt(format!("Delete profile '{name}'?"))
-        .default(false)
-        .interact()
-        .with_context(|| "Failed to get confirmation")?;
+pub fn trash_list(storage: &crate::storage::Storage) -> crate::Result<Vec<crate::trash::Trashed>> {
+    crate::trash::list(storage)
+}
+
+pub fn trash_restore(
+    storage: &crate::storage::Storage,
+    name: &str,
+) -> crate::Result<crate::trash::Trashed> {
+    storage.restore_profile(name)
+}
 
-    if !confirmed {
-        println!("Deletion cancelled");
-        return Ok(());
+/// Remove deleted profiles for good, after confirmation unless `yes`. Returns
+/// how many were removed, or `None` when the purge was cancelled.
+pub fn trash_purge(
+    storage: &crate::storage::Storage,
+    older_than_days: Option<u64>,
+    yes: bool,
+) -> crate::Result<Option<usize>> {
+    let prompt = match older_than_days {
+        Some(days) => format!("Permanently remove profiles deleted more than {days} day(s) ago?"),
+        None => "Permanently remove every profile in the trash?".to_string(),
+    };
+    if !crate::utils::confirm(&prompt, yes)? {
+        return Ok(None);
     }
+    crate::trash::purge(storage, older_than_days).map(Some)
+}
 
-    // Delete the profile
-    storage.delete_profile(name)?;
-    println!("Profile '{name}' deleted successfully");
-    Ok(())
+/// How a command saved a profile
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveKind {
+    Created,
+    /// A profile of that name was replaced
+    Overwritten,
+    /// Text was added at the end of the existing profile
+    Appended,
 }
 
-pub fn create(storage: &crate::storage::Storage, name: &str) -> crate::Result<()> {
+/// A profile written by `create` or `paste`, under the name it was stored as,
+/// which differs from the one asked for when `[naming] slugify` changed it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Saved {
+    pub name: String,
+    pub kind: SaveKind,
+}
+
+/// The name a new profile is stored under: `requested`, slugified when `[naming]` asks for it
+fn new_profile_name(storage: &crate::storage::Storage, requested: &str) -> crate::Result<String> {
+    if !storage.config.naming.slugify {
+        return Ok(requested.to_string());
+    }
+    let slug = slugify(requested);
+    if slug != requested {
+        anyhow::ensure!(
+            !slug.is_empty(),
+            "Profile name '{}' has no usable characters",
+            requested
+        );
+    }
+    Ok(slug)
+}
+
+/// Create a profile from a file, stdin, a template or the editor. Returns `None`
+/// when nothing was written in the editor.
+pub fn create(
+    storage: &crate::storage::Storage,
+    args: &crate::cli::CreateArgs,
+) -> crate::Result<Option<Saved>> {
+    let requested = args.name.as_str();
+    let name = new_profile_name(storage, requested)?;
+    let name = name.as_str();
+
+    let input = if let Some(path) = &args.from_file {
+        Some(crate::encoding::read_text(path)?)
+    } else if args.stdin {
+        Some(
+            std::io::read_to_string(std::io::stdin())
+                .with_context(|| "Failed to read profile content from stdin")?,
+        )
+    } else if let Some(template) = &args.template {
+        let content = crate::template::load_template(storage, template)?;
+        Some(crate::template::fill_template(
+            &content,
+            &args.vars,
+            ask_placeholder,
+        )?)
+    } else {
+        None
+    };
+
     // Check if profile already exists
-    if storage.profile_exists(name) {
-        return Err(anyhow!(
-            "Profile '{}' already exists. Use 'edit' to modify it.",
-            name
-        ));
+    let exists = storage.profile_exists(name);
+    if exists && !args.force {
+        return Err(match input {
+            Some(_) => anyhow!(
+                "Profile '{}' already exists. Use --force to overwrite it.",
+                name
+            ),
+            None => anyhow!(
+                "Profile '{}' already exists. Use 'edit' to modify it.",
+                name
+            ),
+        });
     }
 
     // Validate profile name
     validate_profile_name(name)?;
 
+    if let Some(content) = input {
+        anyhow::ensure!(
+            !content.trim().is_empty(),
+            "No content given for profile '{}'",
+            name
+        );
+        storage.create_profile(name, &content)?;
+        return Ok(Some(Saved {
+            name: name.to_string(),
+            kind: if exists {
+                SaveKind::Overwritten
+            } else {
+                SaveKind::Created
+            },
+        }));
+    }
+
     // Create temporary file for editing
     let temp_file =
         tempfile::NamedTempFile::new().with_context(|| "Failed to create temporary file")?;
 
-    // Write initial template content
-    let template = format!("# {name}\n\n<!-- Add your profile content here -->\n");
+    // Write initial template content, keeping the original spelling as the title
+    let title = requested.rsplit('/').next().unwrap_or(requested);
+    let frontmatter = if name == requested {
+        String::new()
+    } else {
+        let yaml = serde_yaml::to_string(&std::collections::BTreeMap::from([("title", title)]))
+            .map_err(|e| anyhow!("Failed to serialize front-matter: {}", e))?;
+        format!("---\n{yaml}---\n\n")
+    };
+    let heading = if name == requested { name } else { title };
+    let template = format!("{frontmatter}# {heading}\n\n<!-- Add your profile content here -->\n");
     fs::write(temp_file.path(), template)
         .with_context(|| "Failed to write template to temporary file")?;
 
-    // Get editor from environment or use default
-    let editor = get_editor()?;
-
     // Open temporary file in editor
-    let status = Command::new(&editor)
-        .arg(temp_file.path())
-        .status()
-        .with_context(|| format!("Failed to execute editor: {editor}"))?;
-
-    if !status.success() {
-        return Err(anyhow!("Editor exited with non-zero status"));
-    }
+    Editor::resolve(storage, args.editor.as_deref())?.open(temp_file.path())?;
 
     // Read the content back from temporary file
     let content = fs::read_to_string(temp_file.path())
         .with_context(|| "Failed to read content from temporary file")?;
 
     // Check if the content is effectively empty (only whitespace, comments, or original template)
-    let trimmed_content = content.trim();
-    let template_header = format!("# {name}");
+    let trimmed_content = crate::frontmatter::split(&content).1.trim();
+    let template_header = format!("# {heading}");
     let is_empty = trimmed_content.is_empty()
         || trimmed_content == template_header
         || trimmed_content
@@ -106,69 +224,620 @@ pub fn create(storage: &crate::storage::Storage, name: &str) -> crate::Result<()
         });
 
     if is_empty {
-        println!("Profile creation cancelled - no content added");
-        return Ok(());
+        return Ok(None);
     }
 
     // Create the profile
     storage.create_profile(name, &content)?;
-    println!("Profile '{name}' created successfully");
-    Ok(())
+    Ok(Some(Saved {
+        name: name.to_string(),
+        kind: SaveKind::Created,
+    }))
 }
 
-pub fn show(storage: &crate::storage::Storage, name: &str) -> crate::Result<()> {
+/// Value for a template placeholder typed at the terminal; nothing without one
+fn ask_placeholder(var: &str) -> crate::Result<Option<String>> {
+    if !crate::utils::is_interactive() {
+        return Ok(None);
+    }
+    let value: String = dialoguer::Input::new()
+        .with_prompt(format!("{var} (empty to keep <{{{{{var}}}}}>)"))
+        .allow_empty(true)
+        .interact_text()
+        .with_context(|| format!("Failed to read a value for {var}"))?;
+    Ok(Some(value))
+}
+
+/// The profile as stored, with secrets masked unless `reveal`
+pub fn show(storage: &crate::storage::Storage, name: &str, reveal: bool) -> crate::Result<String> {
     let content = storage.get_profile_content(name)?;
-    println!("{content}");
-    Ok(())
+    reveal_secrets(&content, reveal)
 }
 
-pub fn copy(storage: &crate::storage::Storage, name: &str) -> crate::Result<()> {
-    // Reuse the existing copy_profile functionality
-    crate::commands::utils::copy_profile(name, storage)
+/// `content` with its secrets filled in when `reveal` is set, else masked
+fn reveal_secrets(content: &str, reveal: bool) -> crate::Result<String> {
+    if reveal {
+        crate::secrets::resolve(content)
+    } else {
+        Ok(crate::secrets::mask(content))
+    }
 }
 
-fn get_editor() -> crate::Result<String> {
-    // Try $EDITOR first
-    if let Ok(editor) = env::var("EDITOR")
-        && !editor.is_empty()
-    {
-        return Ok(editor);
+/// Only the front-matter (`--metadata`) or only the body (`--body`) of a profile
+pub fn show_part(
+    storage: &crate::storage::Storage,
+    args: &crate::cli::ShowArgs,
+) -> crate::Result<String> {
+    use crate::cli::MetadataFormat;
+
+    let content = storage.get_profile_content(&args.name)?;
+    if args.body {
+        let body = crate::frontmatter::split(&content).1;
+        return reveal_secrets(body, args.reveal);
     }
 
-    // Try $VISUAL as fallback
-    if let Ok(editor) = env::var("VISUAL")
-        && !editor.is_empty()
-    {
-        return Ok(editor);
+    let metadata = crate::frontmatter::parse_metadata(&content)?;
+    match args.metadata.unwrap_or(MetadataFormat::Text) {
+        MetadataFormat::Text => Ok(describe_metadata(&metadata)),
+        MetadataFormat::Toml => metadata_toml(&metadata),
+        MetadataFormat::Json => serde_json::to_string_pretty(&metadata)
+            .map(|json| json + "\n")
+            .map_err(|e| anyhow::anyhow!("Failed to serialize metadata: {}", e)),
     }
+}
 
-    // Platform-specific defaults
-    #[cfg(unix)]
-    {
-        // Try common editors on Unix systems
-        for editor in &["vi", "nano", "emacs"] {
-            if Command::new("which")
-                .arg(editor)
-                .output()
-                .map(|o| o.status.success())
-                .unwrap_or(false)
-            {
-                return Ok(editor.to_string());
+/// A profile with what editors and launchers building on pmx need, as printed
+/// by `pmx profile show --json`
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ProfileDetails {
+    pub name: String,
+    /// Content as stored, with secrets masked unless revealed
+    pub content: String,
+    /// Profiles its include directives pull in, directly or through others
+    pub includes: Vec<String>,
+    /// `<{{NAME}}>` placeholders of the profile and what it extends or includes
+    pub arguments: Vec<String>,
+    pub metadata: crate::frontmatter::ProfileMetadata,
+    /// Size of the stored file in bytes
+    pub size: u64,
+    /// Last modification of the stored file, as an RFC 3339 UTC timestamp
+    pub modified: Option<String>,
+}
+
+pub fn details(
+    storage: &crate::storage::Storage,
+    name: &str,
+    reveal: bool,
+) -> crate::Result<ProfileDetails> {
+    fn collect_includes(
+        storage: &crate::storage::Storage,
+        name: &str,
+        includes: &mut Vec<String>,
+    ) -> crate::Result<()> {
+        let content = crate::extends::resolve(storage, name)?;
+        for target in crate::include::directives(&content) {
+            if target != name && !includes.contains(&target) {
+                includes.push(target.clone());
+                collect_includes(storage, &target, includes)?;
             }
         }
+        Ok(())
     }
 
-    #[cfg(windows)]
-    {
-        return Ok("notepad".to_string());
+    let content = storage.get_profile_content(name)?;
+    let file = std::fs::metadata(storage.get_repo_path(name)?)
+        .map_err(|e| anyhow::anyhow!("Failed to read profile '{}': {}", name, e))?;
+    let mut includes = Vec::new();
+    collect_includes(storage, name, &mut includes)?;
+
+    Ok(ProfileDetails {
+        name: name.to_string(),
+        content: reveal_secrets(&content, reveal)?,
+        includes,
+        arguments: crate::template::variables(&storage.get_content(name)?),
+        metadata: crate::frontmatter::parse_metadata(&content)?,
+        size: file.len(),
+        modified: file.modified().ok().map(crate::utils::format_timestamp),
+    })
+}
+
+/// Front-matter as a TOML document; YAML nulls are dropped since TOML has none
+fn metadata_toml(metadata: &crate::frontmatter::ProfileMetadata) -> crate::Result<String> {
+    fn drop_nulls(value: &mut serde_yaml::Value) {
+        match value {
+            serde_yaml::Value::Mapping(map) => {
+                map.retain(|_, value| !value.is_null());
+                map.values_mut().for_each(drop_nulls);
+            }
+            serde_yaml::Value::Sequence(items) => {
+                items.retain(|value| !value.is_null());
+                items.iter_mut().for_each(drop_nulls);
+            }
+            _ => {}
+        }
+    }
+
+    let mut metadata = metadata.clone();
+    metadata.extra.retain(|_, value| !value.is_null());
+    metadata.extra.values_mut().for_each(drop_nulls);
+    toml::to_string(&metadata).map_err(|e| anyhow::anyhow!("Failed to serialize metadata: {}", e))
+}
+
+/// One `key: value` line per front-matter field, well-known fields first
+fn describe_metadata(metadata: &crate::frontmatter::ProfileMetadata) -> String {
+    if metadata.is_empty() {
+        return "No metadata\n".to_string();
+    }
+
+    let fields = [
+        ("title", metadata.title.clone()),
+        ("description", metadata.description.clone()),
+        ("author", metadata.author.clone()),
+        ("license", metadata.license.clone()),
+        ("source", metadata.source.clone()),
+        (
+            "tags",
+            (!metadata.tags.is_empty()).then(|| metadata.tags.join(", ")),
+        ),
+        (
+            "agents",
+            (!metadata.agents.is_empty()).then(|| metadata.agents.join(", ")),
+        ),
+        (
+            "audience",
+            (!metadata.audience.is_empty()).then(|| metadata.audience.join(", ")),
+        ),
+        ("priority", metadata.priority.map(|p| p.to_string())),
+        (
+            "arguments",
+            (!metadata.arguments.is_empty()).then(|| {
+                let names: Vec<&str> = metadata.arguments.keys().map(String::as_str).collect();
+                names.join(", ")
+            }),
+        ),
+    ];
+
+    let mut output = String::new();
+    for (key, value) in fields {
+        if let Some(value) = value {
+            output.push_str(&format!("{key}: {value}\n"));
+        }
+    }
+    for (key, value) in &metadata.extra {
+        let value = serde_yaml::to_string(value).unwrap_or_default();
+        output.push_str(&format!("{key}: {}\n", value.trim_end()));
+    }
+    output
+}
+
+pub fn copy_many(
+    storage: &crate::storage::Storage,
+    args: &crate::cli::CopyArgs,
+) -> crate::Result<()> {
+    crate::commands::utils::copy_profiles(&args.names, args.separator.as_deref(), storage)
+}
+
+/// Copy a profile under a new name, which is returned
+pub fn duplicate(
+    storage: &crate::storage::Storage,
+    args: &crate::cli::DuplicateArgs,
+) -> crate::Result<String> {
+    let to = new_profile_name(storage, &args.to)?;
+    validate_profile_name(&to)?;
+    storage.duplicate_profile(&args.from, &to)?;
+    Ok(to)
+}
+
+/// Save the clipboard's text as a profile, or add it to the end of one with `--append`
+pub fn paste(
+    storage: &crate::storage::Storage,
+    args: &crate::cli::PasteArgs,
+) -> crate::Result<Saved> {
+    let text = arboard::Clipboard::new()
+        .and_then(|mut clipboard| clipboard.get_text())
+        .map_err(|e| anyhow!("Failed to read the clipboard: {}", e))?;
+    let name = new_profile_name(storage, &args.name)?;
+    save_pasted(storage, &name, &text, args)
+}
+
+fn save_pasted(
+    storage: &crate::storage::Storage,
+    name: &str,
+    text: &str,
+    args: &crate::cli::PasteArgs,
+) -> crate::Result<Saved> {
+    anyhow::ensure!(!text.trim().is_empty(), "The clipboard holds no text");
+    let exists = storage.profile_exists(name);
+
+    if args.append && exists {
+        let mut content = storage.get_profile_content(name)?;
+        content.truncate(content.trim_end().len());
+        content.push_str("\n\n");
+        content.push_str(text.trim_end());
+        content.push('\n');
+        storage.create_profile(name, &content)?;
+        return Ok(Saved {
+            name: name.to_string(),
+            kind: SaveKind::Appended,
+        });
+    }
+
+    anyhow::ensure!(
+        !exists || args.force,
+        "Profile '{}' already exists. Use --append to add to it or --force to overwrite it.",
+        name
+    );
+    validate_profile_name(name)?;
+    let mut content = text.trim_end().to_string();
+    content.push('\n');
+    storage.create_profile(name, &content)?;
+    Ok(Saved {
+        name: name.to_string(),
+        kind: if exists {
+            SaveKind::Overwritten
+        } else {
+            SaveKind::Created
+        },
+    })
+}
+
+/// The profile as an agent receives it, with template values filled in; it is
+/// also put on the clipboard with `--copy`
+pub fn render(
+    storage: &crate::storage::Storage,
+    args: &crate::cli::RenderArgs,
+) -> crate::Result<String> {
+    let values = crate::template::Values {
+        vars: args.vars.clone(),
+        env: args.env,
+    };
+    let (content, missing) = crate::template::render(storage, &args.name, &values)?;
+    if !missing.is_empty() {
+        let missing: Vec<String> = missing
+            .iter()
+            .map(|var| format!("<{{{{{var}}}}}>"))
+            .collect();
+        tracing::warn!("No value for {}", missing.join(", "));
+    }
+
+    if args.copy {
+        let mut clipboard = arboard::Clipboard::new()?;
+        clipboard.set_text(&content)?;
+    }
+    Ok(content)
+}
+
+/// Format one profile or all of them, returning the ones that were not formatted
+/// already. With `--check` nothing is written.
+pub fn fmt(
+    storage: &crate::storage::Storage,
+    args: &crate::cli::FmtArgs,
+) -> crate::Result<Vec<String>> {
+    let names = match &args.name {
+        Some(name) => vec![name.clone()],
+        None => storage.list_repos()?,
+    };
+
+    let options = crate::format::FormatOptions {
+        wrap_width: args.width.or(storage.config.format.wrap_width),
+    };
+
+    let mut unformatted = Vec::new();
+    for name in &names {
+        let content = storage.get_profile_content(name)?;
+        let formatted = crate::format::format_profile(&content, &options);
+        if formatted == content {
+            continue;
+        }
+
+        if !args.check {
+            storage.create_profile(name, &formatted)?;
+        }
+        unformatted.push(name.clone());
+    }
+    Ok(unformatted)
+}
+
+/// A problem `crate::lint` found in a profile
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    pub profile: String,
+    pub diagnostic: crate::lint::Diagnostic,
+}
+
+impl std::fmt::Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.profile, self.diagnostic)
+    }
+}
+
+/// The problems `crate::lint` finds in one profile or all of them
+pub fn lint(
+    storage: &crate::storage::Storage,
+    args: &crate::cli::LintArgs,
+) -> crate::Result<Vec<Problem>> {
+    let names = match &args.name {
+        Some(name) => vec![name.clone()],
+        None => storage.list_repos()?,
+    };
+
+    let mut problems = Vec::new();
+    for name in names {
+        for diagnostic in crate::lint::lint_profile(storage, &name)? {
+            problems.push(Problem {
+                profile: name.clone(),
+                diagnostic,
+            });
+        }
+    }
+    Ok(problems)
+}
+
+/// `problems` as the JSON array `pmx profile lint --json` prints
+pub fn problems_json(problems: &[Problem]) -> crate::Result<String> {
+    let problems: Vec<_> = problems
+        .iter()
+        .map(|problem| {
+            serde_json::json!({
+                "profile": problem.profile,
+                "severity": problem.diagnostic.severity,
+                "line": problem.diagnostic.line,
+                "message": problem.diagnostic.message,
+            })
+        })
+        .collect();
+    Ok(serde_json::to_string_pretty(&problems)?)
+}
+
+/// Characters, words and estimated tokens of some text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Size {
+    pub chars: usize,
+    pub words: usize,
+    pub tokens: usize,
+}
+
+impl Size {
+    fn measure(text: &str, tokenizer: crate::tokens::Tokenizer) -> Self {
+        Self {
+            chars: text.chars().count(),
+            words: text.split_whitespace().count(),
+            tokens: tokenizer.count(text),
+        }
+    }
+}
+
+/// Sizes of profiles and agent files, by name or path, as `pmx profile stats`
+/// reports them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stats {
+    pub profiles: Vec<(String, Size)>,
+    /// Agent files pmx can write that exist; empty for a single profile
+    pub files: Vec<(String, Size)>,
+}
+
+impl std::fmt::Display for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", size_table("PROFILE", &self.profiles))?;
+        if !self.files.is_empty() {
+            write!(f, "\n{}", size_table("AGENT FILE", &self.files))?;
+        }
+        Ok(())
+    }
+}
+
+/// The size of one profile, or of every profile and the agent files pmx can
+/// write, as their content is handed to agents
+pub fn stats(
+    storage: &crate::storage::Storage,
+    args: &crate::cli::StatsArgs,
+) -> crate::Result<Stats> {
+    let tokenizer = args.tokenizer.unwrap_or(storage.config.tokens.tokenizer);
+    let names = match &args.name {
+        Some(name) => vec![name.clone()],
+        None => storage.list_repos()?,
+    };
+
+    let mut profiles = Vec::new();
+    for name in names {
+        let content = storage.get_content(&name)?;
+        let body = crate::frontmatter::body(&content);
+        profiles.push((name, Size::measure(body, tokenizer)));
+    }
+
+    let mut files = Vec::new();
+    if args.name.is_none() {
+        for agent in crate::agent::Agent::all(storage)? {
+            if !agent.is_enabled(storage) {
+                continue;
+            }
+            for project in [false, true] {
+                if let Ok(target) = agent.target(storage, project)
+                    && let Ok(content) = fs::read_to_string(&target)
+                {
+                    files.push((
+                        target.display().to_string(),
+                        Size::measure(&content, tokenizer),
+                    ));
+                }
+            }
+        }
+    }
+    Ok(Stats { profiles, files })
+}
+
+fn size_table(heading: &str, rows: &[(String, Size)]) -> String {
+    let width = rows
+        .iter()
+        .map(|(name, _)| name.chars().count())
+        .chain([heading.len()])
+        .max()
+        .unwrap_or_default();
+
+    let mut table = format!(
+        "{heading:<width$}  {:>8}  {:>8}  {:>8}\n",
+        "CHARS", "WORDS", "TOKENS"
+    );
+    for (name, size) in rows {
+        table.push_str(&format!(
+            "{name:<width$}  {:>8}  {:>8}  {:>8}\n",
+            size.chars, size.words, size.tokens
+        ));
+    }
+    table
+}
+
+/// Merge two profiles, saving the result with `--output`. Conflicts are marked
+/// in the content; they only stop it from being saved without `--force`.
+pub fn merge(
+    storage: &crate::storage::Storage,
+    args: &crate::cli::MergeArgs,
+) -> crate::Result<crate::merge::MergeResult> {
+    let ours = storage.get_profile_content(&args.ours)?;
+    let theirs = storage.get_profile_content(&args.theirs)?;
+    let base = args
+        .base
+        .as_deref()
+        .map(|base| storage.get_profile_content(base))
+        .transpose()?;
+
+    let labels = crate::merge::MergeLabels {
+        ours: &args.ours,
+        theirs: &args.theirs,
+    };
+    let result = crate::merge::merge3(base.as_deref(), &ours, &theirs, labels);
+
+    if let Some(output) = &args.output {
+        validate_profile_name(output)?;
+        anyhow::ensure!(
+            result.conflicts == 0 || args.force,
+            "Merge produced {} conflict(s); '{}' was not saved (use --force to save it with conflict markers)",
+            result.conflicts,
+            output
+        );
+        storage.create_profile(output, &result.content)?;
+    }
+    Ok(result)
+}
+
+pub fn history(
+    storage: &crate::storage::Storage,
+    name: &str,
+) -> crate::Result<Vec<crate::history::Entry>> {
+    storage.history(name)
+}
+
+pub fn revert(
+    storage: &crate::storage::Storage,
+    args: &crate::cli::RevertArgs,
+) -> crate::Result<()> {
+    storage.revert(&args.name, &args.to)
+}
+
+pub fn search(
+    storage: &crate::storage::Storage,
+    args: &crate::cli::SearchArgs,
+) -> crate::Result<Vec<crate::search::ProfileMatch>> {
+    let options = crate::search::SearchOptions {
+        regex: args.regex,
+        ignore_case: args.ignore_case,
+        context: args.context,
+    };
+    crate::search::search(storage, &args.query, &options)
+}
+
+/// Search results as `pmx profile search` prints them, grep style, with the
+/// matches in bold red when `color` is set
+pub fn describe_matches(results: &[crate::search::ProfileMatch], color: bool) -> String {
+    let mut output = String::new();
+    for result in results {
+        output.push_str(&highlight(
+            &result.profile,
+            &result.name_matches,
+            color,
+            true,
+        ));
+        output.push('\n');
+        let mut previous = None;
+        for line in &result.lines {
+            if previous.is_some_and(|n| line.number > n + 1) {
+                output.push_str("  --\n");
+            }
+            let separator = if line.matches.is_empty() { '-' } else { ':' };
+            output.push_str(&format!(
+                "  {}{separator} {}\n",
+                line.number,
+                highlight(&line.text, &line.matches, color, false)
+            ));
+            previous = Some(line.number);
+        }
     }
+    output
+}
+
+/// `text` with the `matches` ranges in bold red when `color` is set
+fn highlight(text: &str, matches: &[std::ops::Range<usize>], color: bool, bold: bool) -> String {
+    if !color {
+        return text.to_string();
+    }
+    let base = if bold { "\x1b[1m" } else { "" };
+    let mut out = String::from(base);
+    let mut last = 0;
+    for range in matches {
+        out.push_str(&text[last..range.start]);
+        out.push_str("\x1b[1;31m");
+        out.push_str(&text[range.clone()]);
+        out.push_str("\x1b[0m");
+        out.push_str(base);
+        last = range.end;
+    }
+    out.push_str(&text[last..]);
+    if bold || !matches.is_empty() {
+        out.push_str("\x1b[0m");
+    }
+    out
+}
+
+/// A diff between two profiles; empty when they are identical
+pub fn diff(
+    storage: &crate::storage::Storage,
+    args: &crate::cli::ProfileDiffArgs,
+) -> crate::Result<String> {
+    let old = storage.get_profile_content(&args.old)?;
+    let new = storage.get_profile_content(&args.new)?;
+
+    let options = crate::diff::DiffOptions {
+        mode: if args.word_diff {
+            crate::diff::DiffMode::Word
+        } else {
+            crate::diff::DiffMode::Line
+        },
+        color: crate::utils::use_color(),
+        ..Default::default()
+    };
 
-    Err(anyhow!(
-        "No editor found. Please set the EDITOR environment variable."
+    Ok(crate::diff::render(
+        &old, &new, &args.old, &args.new, &options,
     ))
 }
 
-fn validate_profile_name(name: &str) -> crate::Result<()> {
+/// Lowercase each path component, turn whitespace into dashes and drop other punctuation
+pub(crate) fn slugify(name: &str) -> String {
+    name.split('/')
+        .map(|component| {
+            component
+                .to_lowercase()
+                .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .filter(|word| !word.is_empty())
+                .collect::<Vec<_>>()
+                .join("-")
+        })
+        .filter(|component| !component.is_empty())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+pub(crate) fn validate_profile_name(name: &str) -> crate::Result<()> {
     if name.is_empty() {
         return Err(anyhow!("Profile name cannot be empty"));
     }
@@ -223,12 +892,10 @@ mod tests {
         fs::create_dir(&repo_dir).unwrap();
 
         let config = Config {
-            agents: Agents {
-                disable_claude: false,
-                disable_codex: false,
-            },
+            agents: Agents::default(),
             mcp: crate::storage::McpConfig::default(),
             extensions: crate::storage::ExtensionsConfig::default(),
+            ..Default::default()
         };
 
         let config_content = toml::to_string(&config).unwrap();
@@ -242,6 +909,14 @@ mod tests {
         (temp_dir, storage)
     }
 
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("My Cool Prompt!"), "my-cool-prompt");
+        assert_eq!(slugify("Work/Code Review (v2)"), "work/code-review-v2");
+        assert_eq!(slugify("already-fine_name"), "already-fine_name");
+        assert_eq!(slugify("!!!"), "");
+    }
+
     #[test]
     fn test_validate_profile_name_valid() {
         assert!(validate_profile_name("valid_name").is_ok());
@@ -265,36 +940,328 @@ mod tests {
         assert!(validate_profile_name("invalid/..").is_err()); // dotdot component
     }
 
+    #[test]
+    fn test_create_from_file() {
+        let (temp_dir, storage) = create_test_storage();
+        let source = temp_dir.path().join("prompt.md");
+        fs::write(&source, "# Review\nBe thorough.\n").unwrap();
+        let args = |name: &str, force: bool| crate::cli::CreateArgs {
+            name: name.to_string(),
+            editor: None,
+            from_file: Some(source.clone()),
+            stdin: false,
+            template: None,
+            vars: Vec::new(),
+            force,
+        };
+
+        assert_eq!(
+            create(&storage, &args("review", false)).unwrap(),
+            Some(Saved {
+                name: "review".to_string(),
+                kind: SaveKind::Created
+            })
+        );
+        assert_eq!(
+            storage.get_profile_content("review").unwrap(),
+            "# Review\nBe thorough.\n"
+        );
+        assert!(create(&storage, &args("test_profile", false)).is_err());
+        let saved = create(&storage, &args("test_profile", true)).unwrap();
+        assert_eq!(saved.unwrap().kind, SaveKind::Overwritten);
+        assert_eq!(
+            storage.get_profile_content("test_profile").unwrap(),
+            "# Review\nBe thorough.\n"
+        );
+
+        fs::write(&source, "\n").unwrap();
+        assert!(create(&storage, &args("empty", false)).is_err());
+    }
+
+    #[test]
+    fn test_save_pasted() {
+        let (_temp_dir, storage) = create_test_storage();
+        let args = |append: bool, force: bool| crate::cli::PasteArgs {
+            name: "chat".to_string(),
+            append,
+            force,
+        };
+
+        save_pasted(&storage, "chat", "Be concise.\n\n", &args(false, false)).unwrap();
+        assert_eq!(
+            storage.get_profile_content("chat").unwrap(),
+            "Be concise.\n"
+        );
+        assert!(save_pasted(&storage, "chat", "Other", &args(false, false)).is_err());
+        let saved = save_pasted(&storage, "chat", "Cite sources.", &args(true, false)).unwrap();
+        assert_eq!(saved.kind, SaveKind::Appended);
+        assert_eq!(
+            storage.get_profile_content("chat").unwrap(),
+            "Be concise.\n\nCite sources.\n"
+        );
+        save_pasted(&storage, "chat", "Start over.", &args(false, true)).unwrap();
+        assert_eq!(
+            storage.get_profile_content("chat").unwrap(),
+            "Start over.\n"
+        );
+        assert!(save_pasted(&storage, "chat", " \n", &args(true, false)).is_err());
+    }
+
     #[test]
     fn test_show_existing_profile() {
         let (_temp_dir, storage) = create_test_storage();
-        let result = show(&storage, "test_profile");
-        assert!(result.is_ok());
+        assert_eq!(
+            show(&storage, "test_profile", false).unwrap(),
+            "# Test Profile\nThis is a test profile."
+        );
     }
 
     #[test]
     fn test_show_nonexistent_profile() {
         let (_temp_dir, storage) = create_test_storage();
-        let result = show(&storage, "nonexistent");
+        let result = show(&storage, "nonexistent", false);
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_details() {
+        let (_temp_dir, storage) = create_test_storage();
+        storage
+            .create_profile("shared/tone", "Be <{{TONE}}>.\n{{include: shared/style}}\n")
+            .unwrap();
+        storage
+            .create_profile("shared/style", "Use <{{STYLE}}>.\n")
+            .unwrap();
+        storage
+            .create_profile(
+                "review",
+                "---\ntitle: Review\n---\nReview for <{{LANG}}>.\n{{include: shared/tone}}\nKey <{{secret:API_KEY}}>\n",
+            )
+            .unwrap();
+
+        let details = details(&storage, "review", false).unwrap();
+        assert_eq!(details.includes, ["shared/tone", "shared/style"]);
+        assert_eq!(details.arguments, ["LANG", "TONE", "STYLE"]);
+        assert_eq!(details.metadata.title.as_deref(), Some("Review"));
+        assert!(details.content.ends_with("Key ********\n"));
+        assert_eq!(
+            details.size,
+            storage.get_profile_content("review").unwrap().len() as u64
+        );
+        assert!(details.modified.is_some());
+    }
+
+    #[test]
+    fn test_describe_metadata() {
+        let metadata = crate::frontmatter::parse_metadata(
+            "---\nsource: https://example.com\nlicense: MIT\ntags: [a, b]\n---\nBody\n",
+        )
+        .unwrap();
+        assert_eq!(
+            describe_metadata(&metadata),
+            "license: MIT\nsource: https://example.com\ntags: a, b\n"
+        );
+        assert_eq!(describe_metadata(&Default::default()), "No metadata\n");
+    }
+
+    #[test]
+    fn test_metadata_toml() {
+        let metadata = crate::frontmatter::parse_metadata(
+            "---\ntitle: Review\ntags: [a, b]\nreviewed: ~\nlimits:\n  tokens: 10\n---\nBody\n",
+        )
+        .unwrap();
+        assert_eq!(
+            metadata_toml(&metadata).unwrap(),
+            "title = \"Review\"\ntags = [\"a\", \"b\"]\n\n[limits]\ntokens = 10\n"
+        );
+    }
+
     #[test]
     #[ignore = "Clipboard tests require display environment"]
     fn test_copy_existing_profile() {
         let (_temp_dir, storage) = create_test_storage();
-        let result = copy(&storage, "test_profile");
+        let args = crate::cli::CopyArgs {
+            names: vec!["test_profile".to_string()],
+            separator: None,
+        };
+        let result = copy_many(&storage, &args);
         assert!(result.is_ok());
     }
 
     #[test]
-    fn test_get_editor_with_env() {
-        unsafe {
-            env::set_var("EDITOR", "test-editor");
-            let result = get_editor();
-            assert!(result.is_ok());
-            assert_eq!(result.unwrap(), "test-editor");
-            env::remove_var("EDITOR");
-        }
+    fn test_fmt_check_and_write() {
+        let (_temp_dir, storage) = create_test_storage();
+        let args = crate::cli::FmtArgs {
+            name: Some("test_profile".to_string()),
+            all: false,
+            check: true,
+            width: None,
+        };
+
+        // The fixture lacks a trailing newline and a blank line after its heading
+        assert_eq!(fmt(&storage, &args).unwrap(), ["test_profile"]);
+        assert_eq!(
+            storage.get_profile_content("test_profile").unwrap(),
+            "# Test Profile\nThis is a test profile."
+        );
+
+        let args = crate::cli::FmtArgs {
+            check: false,
+            ..args
+        };
+        assert_eq!(fmt(&storage, &args).unwrap(), ["test_profile"]);
+        assert_eq!(
+            storage.get_profile_content("test_profile").unwrap(),
+            "# Test Profile\n\nThis is a test profile.\n"
+        );
+
+        let args = crate::cli::FmtArgs {
+            check: true,
+            ..args
+        };
+        assert!(fmt(&storage, &args).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_size_table() {
+        let size = Size::measure("Use cargo fmt.\n", crate::tokens::Tokenizer::Chars);
+        assert_eq!(
+            size,
+            Size {
+                chars: 15,
+                words: 3,
+                tokens: 4
+            }
+        );
+        assert_eq!(
+            size_table("PROFILE", &[("rust".to_string(), size)]),
+            "PROFILE     CHARS     WORDS    TOKENS\nrust           15         3         4\n"
+        );
+    }
+
+    #[test]
+    fn test_merge_to_output_profile() {
+        let (_temp_dir, storage) = create_test_storage();
+        storage
+            .create_profile("base", "# Test Profile\nshared\n")
+            .unwrap();
+        storage
+            .create_profile("theirs", "# Test Profile\nshared\nextra\n")
+            .unwrap();
+        storage
+            .create_profile("ours", "# Our Profile\nshared\n")
+            .unwrap();
+
+        let args = crate::cli::MergeArgs {
+            ours: "ours".to_string(),
+            theirs: "theirs".to_string(),
+            base: Some("base".to_string()),
+            output: Some("merged/result".to_string()),
+            force: false,
+        };
+        let result = merge(&storage, &args).unwrap();
+        assert_eq!(result.conflicts, 0);
+        assert_eq!(
+            storage.get_profile_content("merged/result").unwrap(),
+            "# Our Profile\nshared\nextra\n"
+        );
+    }
+
+    #[test]
+    fn test_merge_with_conflicts_is_not_saved() {
+        let (_temp_dir, storage) = create_test_storage();
+        storage.create_profile("base", "line\n").unwrap();
+        storage.create_profile("ours", "ours\n").unwrap();
+        storage.create_profile("theirs", "theirs\n").unwrap();
+
+        let mut args = crate::cli::MergeArgs {
+            ours: "ours".to_string(),
+            theirs: "theirs".to_string(),
+            base: Some("base".to_string()),
+            output: Some("merged".to_string()),
+            force: false,
+        };
+        let error = merge(&storage, &args).unwrap_err();
+        assert!(error.to_string().contains("was not saved"));
+        assert!(!storage.profile_exists("merged"));
+
+        args.force = true;
+        let result = merge(&storage, &args).unwrap();
+        assert_eq!(result.conflicts, 1);
+        assert_eq!(
+            storage.get_profile_content("merged").unwrap(),
+            result.content
+        );
+        assert!(result.content.contains("<<<<<<<"));
+    }
+
+    #[test]
+    fn test_trash_list_and_restore() {
+        let (_temp_dir, storage) = create_test_storage();
+        assert!(trash_list(&storage).unwrap().is_empty());
+
+        assert!(delete(&storage, "test_profile", true).unwrap());
+        let trashed = trash_list(&storage).unwrap();
+        assert_eq!(trashed.len(), 1);
+        assert_eq!(trashed[0].name, "test_profile");
+        assert!(!storage.profile_exists("test_profile"));
+
+        let restored = trash_restore(&storage, "test_profile").unwrap();
+        assert_eq!(restored.deleted, trashed[0].deleted);
+        assert!(storage.profile_exists("test_profile"));
+        assert_eq!(trash_purge(&storage, None, true).unwrap(), Some(0));
+    }
+
+    #[test]
+    fn test_stats() {
+        let (_temp_dir, storage) = create_test_storage();
+        let args = crate::cli::StatsArgs {
+            name: Some("test_profile".to_string()),
+            tokenizer: None,
+        };
+        let stats = stats(&storage, &args).unwrap();
+        assert_eq!(stats.profiles.len(), 1);
+        assert_eq!(stats.profiles[0].0, "test_profile");
+        assert_eq!(stats.profiles[0].1.words, 8);
+        assert!(stats.files.is_empty());
+        assert_eq!(stats.to_string(), size_table("PROFILE", &stats.profiles));
+    }
+
+    #[test]
+    fn test_lint_problems() {
+        let (_temp_dir, storage) = create_test_storage();
+        storage
+            .create_profile("broken", "{{include: missing}}\n")
+            .unwrap();
+        let args = crate::cli::LintArgs {
+            name: Some("broken".to_string()),
+            json: true,
+        };
+        let problems = lint(&storage, &args).unwrap();
+        assert!(!problems.is_empty());
+        assert!(problems.iter().all(|problem| problem.profile == "broken"));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&problems_json(&problems).unwrap()).unwrap();
+        assert_eq!(json.as_array().unwrap().len(), problems.len());
+        assert_eq!(json[0]["profile"], "broken");
+    }
+
+    #[test]
+    fn test_describe_matches() {
+        let (_temp_dir, storage) = create_test_storage();
+        let args = crate::cli::SearchArgs {
+            query: "test profile".to_string(),
+            regex: false,
+            ignore_case: true,
+            context: 0,
+        };
+        let results = search(&storage, &args).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].profile, "test_profile");
+        assert_eq!(
+            describe_matches(&results, false),
+            "test_profile\n  1: # Test Profile\n  2: This is a test profile.\n"
+        );
     }
 }
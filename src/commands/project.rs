@@ -0,0 +1,407 @@
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use crate::agent::Agent;
+use crate::commands::apply::{self, ApplyOptions, Change};
+use crate::project::ProjectConfig;
+use crate::storage::Storage;
+
+/// What `pmx apply` did with the profiles pinned in a `.pmx.toml`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Applied {
+    /// The `.pmx.toml` that was applied
+    pub file: PathBuf,
+    pub changes: Vec<Change>,
+}
+
+impl std::fmt::Display for Applied {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Applying {}", self.file.display())?;
+        for change in &self.changes {
+            writeln!(f, "{change}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Apply every profile pinned in the nearest `.pmx.toml`
+pub fn apply(storage: &Storage, options: &ApplyOptions) -> crate::Result<Applied> {
+    let (dir, project) = find_project()?;
+
+    let mut changes = Vec::new();
+    for (agent, profiles) in project.pins()? {
+        if !agent.is_enabled(storage) {
+            changes.push(Change::Skipped(agent));
+            continue;
+        }
+        changes.extend(apply::apply_profiles(storage, agent, profiles, options)?);
+    }
+    Ok(Applied {
+        file: ProjectConfig::path(&dir),
+        changes,
+    })
+}
+
+/// Whether an agent's file still holds what pmx last applied to it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileState {
+    /// pmx applied no profile to the file
+    None,
+    Missing,
+    Modified,
+    Clean,
+    /// The file is a symbolic link to this path, so it follows its profile
+    Linked(PathBuf),
+}
+
+/// The profiles pmx last applied to an enabled agent's file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AgentStatus {
+    pub agent: Agent,
+    pub target: PathBuf,
+    /// Empty when no profile was applied
+    pub profiles: Vec<String>,
+    pub file: FileState,
+}
+
+/// Status of each enabled built-in agent's global file
+pub fn agents(storage: &Storage) -> crate::Result<Vec<AgentStatus>> {
+    agent_statuses(storage, |agent| agent.global_target(storage))
+}
+
+fn agent_statuses(
+    storage: &Storage,
+    target_of: impl Fn(Agent) -> crate::Result<PathBuf>,
+) -> crate::Result<Vec<AgentStatus>> {
+    let state = crate::state::State::load(&storage.path)?;
+
+    let mut statuses = Vec::new();
+    for agent in Agent::ALL {
+        if !agent.is_enabled(storage) {
+            continue;
+        }
+        let target = target_of(agent)?;
+        let (profiles, file) = if let Some(link) = crate::utils::symlink_target(&target) {
+            let applied = state.get(&target);
+            let profiles = applied.map(|a| a.profiles.clone()).unwrap_or_default();
+            (profiles, FileState::Linked(link))
+        } else if agent.has_rules_dir() {
+            match state.get(&target) {
+                None => (Vec::new(), FileState::None),
+                Some(applied) => (
+                    applied.profiles.clone(),
+                    crate::commands::rules::file_state(&target)?,
+                ),
+            }
+        } else {
+            match (state.get(&target), std::fs::read(&target)) {
+                (None, _) => (Vec::new(), FileState::None),
+                (Some(applied), Err(_)) => (applied.profiles.clone(), FileState::Missing),
+                (Some(applied), Ok(content)) if state.has_drifted(&target, &content) => {
+                    (applied.profiles.clone(), FileState::Modified)
+                }
+                (Some(applied), Ok(_)) => (applied.profiles.clone(), FileState::Clean),
+            }
+        };
+        statuses.push(AgentStatus {
+            agent,
+            target,
+            profiles,
+            file,
+        });
+    }
+    Ok(statuses)
+}
+
+/// Report of the profiles applied to each agent and, inside a project, how the
+/// agent files deviate from the nearest `.pmx.toml`
+pub fn status(storage: &Storage) -> crate::Result<String> {
+    let mut report = describe_agents(storage, |agent| agent.global_target(storage))?;
+
+    let cwd = std::env::current_dir()
+        .map_err(|e| anyhow::anyhow!("Failed to get current directory: {}", e))?;
+    if let Some((dir, project)) = ProjectConfig::find(&cwd)? {
+        report.push('\n');
+        report.push_str(&describe(storage, &dir, &project, |agent| {
+            agent.global_target(storage)
+        })?);
+    }
+    Ok(report)
+}
+
+/// Which profiles were last applied to each agent's file, whether the file changed
+/// since, and whether the MCP server exposes anything
+fn describe_agents(
+    storage: &Storage,
+    target_of: impl Fn(Agent) -> crate::Result<PathBuf>,
+) -> crate::Result<String> {
+    let statuses = agent_statuses(storage, target_of)?;
+
+    let mut report = String::new();
+    for agent in Agent::ALL {
+        let Some(status) = statuses.iter().find(|status| status.agent == agent) else {
+            writeln!(report, "{}: disabled", agent.name())?;
+            continue;
+        };
+        let file = match &status.file {
+            FileState::None => {
+                writeln!(report, "{}: no profile applied", agent.name())?;
+                continue;
+            }
+            FileState::Missing => "missing".to_string(),
+            FileState::Modified => "modified since applied".to_string(),
+            FileState::Clean => "unchanged since applied".to_string(),
+            FileState::Linked(link) => format!("linked to {}", link.display()),
+        };
+        let profiles = if status.profiles.is_empty() {
+            "no profile applied".to_string()
+        } else {
+            status.profiles.join(" + ")
+        };
+        writeln!(
+            report,
+            "{}: {profiles}, {file} ({})",
+            agent.name(),
+            status.target.display()
+        )?;
+    }
+
+    let mcp = if storage.is_mcp_enabled() {
+        "enabled"
+    } else {
+        "disabled"
+    };
+    writeln!(report, "MCP server: {mcp}")?;
+    Ok(report)
+}
+
+/// `<agent>\t<profiles>\t<state>` for each enabled agent, for scripts and shell
+/// prompts; profiles are joined with `+` (`-` when none were applied) and the
+/// state is `clean`, `modified`, `missing`, `linked` or `none`
+pub fn porcelain(storage: &Storage) -> crate::Result<String> {
+    describe_porcelain(storage, |agent| agent.global_target(storage))
+}
+
+fn describe_porcelain(
+    storage: &Storage,
+    target_of: impl Fn(Agent) -> crate::Result<PathBuf>,
+) -> crate::Result<String> {
+    let mut report = String::new();
+    for status in agent_statuses(storage, target_of)? {
+        let profiles = if status.profiles.is_empty() {
+            "-".to_string()
+        } else {
+            status.profiles.join("+")
+        };
+        let file = match &status.file {
+            FileState::None => "none",
+            FileState::Missing => "missing",
+            FileState::Modified => "modified",
+            FileState::Clean => "clean",
+            FileState::Linked(_) => "linked",
+        };
+        writeln!(
+            report,
+            "{}\t{profiles}\t{file}\t{}",
+            status.agent.name(),
+            status.target.display()
+        )?;
+    }
+    Ok(report)
+}
+
+/// Whether `actual` is what applying `profiles` would write. Profiles calling
+/// template functions are not rendered again, as that would run extensions; the
+/// target only has to be as pmx last wrote it with the same profiles.
+fn in_sync(
+    storage: &Storage,
+    state: &crate::state::State,
+    agent: Agent,
+    profiles: &[String],
+    target: &Path,
+    actual: &str,
+) -> crate::Result<bool> {
+    let live = profiles.iter().any(|profile| {
+        storage
+            .get_content(profile)
+            .is_ok_and(|content| !crate::functions::calls(&content).is_empty())
+    });
+    if live {
+        return Ok(state
+            .get(target)
+            .is_some_and(|applied| applied.profiles == profiles)
+            && !state.has_drifted(target, actual.as_bytes()));
+    }
+    Ok(actual == apply::render_profiles(storage, agent, profiles)?)
+}
+
+fn find_project() -> crate::Result<(PathBuf, ProjectConfig)> {
+    let cwd = std::env::current_dir()
+        .map_err(|e| anyhow::anyhow!("Failed to get current directory: {}", e))?;
+    ProjectConfig::find(&cwd)?.ok_or_else(|| {
+        anyhow::anyhow!(
+            "No {} found in {} or any parent directory",
+            crate::project::PROJECT_FILE,
+            cwd.display()
+        )
+    })
+}
+
+fn describe(
+    storage: &Storage,
+    dir: &Path,
+    project: &ProjectConfig,
+    target_of: impl Fn(Agent) -> crate::Result<PathBuf>,
+) -> crate::Result<String> {
+    let state = crate::state::State::load(&storage.path)?;
+
+    let mut report = String::new();
+    writeln!(report, "Project: {}", ProjectConfig::path(dir).display())?;
+    for (agent, profiles) in project.pins()? {
+        let pinned = profiles.join(" + ");
+        if !agent.is_enabled(storage) {
+            writeln!(report, "{}: disabled ({pinned})", agent.name())?;
+            continue;
+        }
+
+        let target = target_of(agent)?;
+        let status = match std::fs::read_to_string(&target) {
+            Err(_) => "missing".to_string(),
+            Ok(actual) if in_sync(storage, &state, agent, profiles, &target, &actual)? => {
+                "in sync".to_string()
+            }
+            Ok(_) => match state.get(&target) {
+                Some(applied) if applied.profiles != profiles => {
+                    format!("differs (applied: {})", applied.profiles.join(" + "))
+                }
+                Some(_) => "modified since applied".to_string(),
+                None => "differs (not applied by pmx)".to_string(),
+            },
+        };
+        writeln!(report, "{}: {status} ({pinned})", agent.name())?;
+        for profile in profiles {
+            if let Some(staleness) = storage.staleness(profile) {
+                writeln!(report, "  {profile}: {staleness}")?;
+            }
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_describe_reports_deviation() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::initialize(temp_dir.path().join("storage")).unwrap();
+        storage.create_profile("base", "Be helpful.\n").unwrap();
+        storage.create_profile("other", "Be terse.\n").unwrap();
+        storage
+            .create_profile("old", "---\nexpires: 2000-01-01\n---\nOutdated.\n")
+            .unwrap();
+        let project =
+            ProjectConfig::parse("[profiles]\nclaude = [\"base\"]\ncodex = [\"base\", \"old\"]\n")
+                .unwrap();
+        let target_of = |agent: Agent| Ok(temp_dir.path().join(agent.file_name()));
+
+        let report = describe(&storage, temp_dir.path(), &project, target_of).unwrap();
+        assert!(report.contains("claude: missing (base)\n"));
+
+        let rendered =
+            apply::render_profiles(&storage, Agent::Claude, &["base".to_string()]).unwrap();
+        std::fs::write(temp_dir.path().join("CLAUDE.md"), rendered).unwrap();
+        std::fs::write(temp_dir.path().join("AGENTS.md"), "Be terse.\n").unwrap();
+        crate::state::update(&storage, |state| {
+            state.record_set(Agent::Codex, &temp_dir.path().join("AGENTS.md"), "other")
+        })
+        .unwrap();
+
+        let report = describe(&storage, temp_dir.path(), &project, target_of).unwrap();
+        assert!(report.contains("claude: in sync (base)\n"));
+        assert!(
+            report.contains(
+                "codex: differs (applied: other) (base + old)\n  old: expired 2000-01-01\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_describe_agents() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::initialize(temp_dir.path().join("storage")).unwrap();
+        let target_of = |agent: Agent| Ok(temp_dir.path().join(agent.file_name()));
+        let claude = temp_dir.path().join("CLAUDE.md");
+        let codex = temp_dir.path().join("AGENTS.md");
+
+        std::fs::write(&claude, "Base\n").unwrap();
+        std::fs::write(&codex, "Writing\n").unwrap();
+        crate::state::update(&storage, |state| {
+            state.record_set(Agent::Claude, &claude, "base");
+            state.record_append(Agent::Claude, &claude, "rust");
+            state.record_written(&claude, b"Base\n");
+            state.record_set(Agent::Codex, &codex, "writing");
+            state.record_written(&codex, b"Before\n");
+        })
+        .unwrap();
+
+        assert_eq!(
+            describe_agents(&storage, target_of).unwrap(),
+            format!(
+                "claude: base + rust, unchanged since applied ({})\n\
+                 codex: writing, modified since applied ({})\n\
+                 gemini: no profile applied\n\
+                 cursor: no profile applied\n\
+                 continue: no profile applied\n\
+                 MCP server: enabled\n",
+                claude.display(),
+                codex.display()
+            )
+        );
+    }
+
+    #[test]
+    fn test_describe_porcelain() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::initialize(temp_dir.path().join("storage")).unwrap();
+        let target_of = |agent: Agent| Ok(temp_dir.path().join(agent.file_name()));
+        let claude = temp_dir.path().join("CLAUDE.md");
+        let codex = temp_dir.path().join("AGENTS.md");
+
+        let states = |report: String| -> String {
+            report
+                .lines()
+                .map(|line| line.rsplit_once('\t').unwrap().0.to_string() + "\n")
+                .collect()
+        };
+        assert_eq!(
+            states(describe_porcelain(&storage, target_of).unwrap()),
+            "claude\t-\tnone\ncodex\t-\tnone\ngemini\t-\tnone\ncursor\t-\tnone\ncontinue\t-\tnone\n"
+        );
+
+        std::fs::write(&claude, "Base\n").unwrap();
+        crate::state::update(&storage, |state| {
+            state.record_set(Agent::Claude, &claude, "base");
+            state.record_append(Agent::Claude, &claude, "rust");
+            state.record_written(&claude, b"Base\n");
+            state.record_set(Agent::Codex, &codex, "writing");
+        })
+        .unwrap();
+        assert_eq!(
+            states(describe_porcelain(&storage, target_of).unwrap()),
+            "claude\tbase+rust\tclean\ncodex\twriting\tmissing\ngemini\t-\tnone\ncursor\t-\tnone\ncontinue\t-\tnone\n"
+        );
+
+        std::fs::write(&claude, "Edited\n").unwrap();
+        assert!(
+            describe_porcelain(&storage, target_of)
+                .unwrap()
+                .starts_with(&format!(
+                    "claude\tbase+rust\tmodified\t{}\n",
+                    claude.display()
+                ))
+        );
+    }
+}
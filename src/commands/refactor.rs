@@ -0,0 +1,125 @@
+use anyhow::ensure;
+use is_terminal::IsTerminal;
+use regex::Regex;
+
+use crate::cli::RenameVarArgs;
+use crate::storage::Storage;
+
+/// Rename `<{{OLD}}>` to `<{{NEW}}>` in every matching profile, after showing the
+/// diff and asking for confirmation unless `yes`
+pub fn rename_var(storage: &Storage, args: &RenameVarArgs, yes: bool) -> crate::Result<()> {
+    let valid = Regex::new(r"^[A-Za-z_][A-Za-z0-9_]*$").unwrap();
+    for name in [&args.old, &args.new] {
+        ensure!(
+            valid.is_match(name),
+            "Invalid placeholder name '{}': use letters, digits and '_'",
+            name
+        );
+    }
+    let filter = args
+        .filter
+        .as_deref()
+        .map(glob::Pattern::new)
+        .transpose()
+        .map_err(|e| anyhow::anyhow!("Invalid filter: {}", e))?;
+
+    let mut changes = Vec::new();
+    for name in storage.list_repos()? {
+        if filter.as_ref().is_some_and(|filter| !filter.matches(&name)) {
+            continue;
+        }
+        let content = storage.get_profile_content(&name)?;
+        let renamed = rename_placeholder(&content, &args.old, &args.new);
+        if renamed != content {
+            changes.push((name, content, renamed));
+        }
+    }
+
+    if changes.is_empty() {
+        println!("No profiles use <{{{{{}}}}}>", args.old);
+        return Ok(());
+    }
+
+    let options = crate::diff::DiffOptions {
+        color: std::io::stdout().is_terminal(),
+        ..Default::default()
+    };
+    for (name, content, renamed) in &changes {
+        print!(
+            "{}",
+            crate::diff::render(content, renamed, name, name, &options)
+        );
+    }
+
+    let prompt = format!(
+        "Rename <{{{{{}}}}}> to <{{{{{}}}}}> in {} profile(s)?",
+        args.old,
+        args.new,
+        changes.len()
+    );
+    if !crate::utils::confirm(&prompt, yes)? {
+        println!("Rename cancelled");
+        return Ok(());
+    }
+
+    for (name, _, renamed) in &changes {
+        storage.create_profile(name, renamed)?;
+    }
+    println!(
+        "Renamed <{{{{{}}}}}> to <{{{{{}}}}}> in {} profile(s)",
+        args.old,
+        args.new,
+        changes.len()
+    );
+    Ok(())
+}
+
+fn rename_placeholder(content: &str, old: &str, new: &str) -> String {
+    content.replace(&format!("<{{{{{old}}}}}>"), &format!("<{{{{{new}}}}}>"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_rename_placeholder() {
+        assert_eq!(
+            rename_placeholder(
+                "Use <{{LANG}}> and <{{LANGUAGE}}>, not {{LANG}}.",
+                "LANG",
+                "LANGUAGE"
+            ),
+            "Use <{{LANGUAGE}}> and <{{LANGUAGE}}>, not {{LANG}}."
+        );
+    }
+
+    #[test]
+    fn test_rename_var_respects_filter() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::initialize(temp_dir.path().join("storage")).unwrap();
+        storage
+            .create_profile("coding/rust", "Write <{{LANG}}>.\n")
+            .unwrap();
+        storage
+            .create_profile("writing", "Translate to <{{LANG}}>.\n")
+            .unwrap();
+
+        let args = RenameVarArgs {
+            old: "LANG".to_string(),
+            new: "LANGUAGE".to_string(),
+            filter: Some("coding/*".to_string()),
+        };
+        rename_var(&storage, &args, true).unwrap();
+
+        assert_eq!(
+            storage.get_profile_content("coding/rust").unwrap(),
+            "Write <{{LANGUAGE}}>.\n"
+        );
+        assert_eq!(
+            storage.get_profile_content("writing").unwrap(),
+            "Translate to <{{LANG}}>.\n"
+        );
+    }
+}
@@ -0,0 +1,539 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, ensure};
+
+use crate::agent::Agent;
+use crate::commands::apply::{self, ApplyOptions, Change};
+use crate::commands::project::FileState;
+use crate::storage::Storage;
+
+/// File in a rules directory listing the rule files pmx wrote there
+pub(crate) const MANIFEST_FILE: &str = ".pmx-manifest.toml";
+
+/// The rule files pmx wrote to a rules directory, keyed by file name
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+struct Manifest {
+    #[serde(default)]
+    rules: BTreeMap<String, Rule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+struct Rule {
+    profile: String,
+    /// Hash of the content pmx wrote, to notice edits made to the file since
+    hash: String,
+}
+
+impl Manifest {
+    fn load(dir: &Path) -> crate::Result<Self> {
+        let path = dir.join(MANIFEST_FILE);
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            return Ok(Self::default());
+        };
+        toml::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", path.display(), e))
+    }
+
+    /// Write the manifest to `dir`, or remove it once no rule is left
+    fn persist(&self, dir: &Path) -> crate::Result<()> {
+        let path = dir.join(MANIFEST_FILE);
+        if self.rules.is_empty() {
+            return match std::fs::remove_file(&path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(anyhow::anyhow!(
+                    "Failed to remove {}: {}",
+                    path.display(),
+                    e
+                )),
+                _ => Ok(()),
+            };
+        }
+        let content = toml::to_string(self)
+            .map_err(|e| anyhow::anyhow!("Failed to serialize {}: {}", path.display(), e))?;
+        std::fs::write(&path, content)
+            .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))
+    }
+
+    /// Name of the rule file written for `profile`
+    fn file_of(&self, profile: &str) -> Option<String> {
+        self.rules
+            .iter()
+            .find(|(_, rule)| rule.profile == profile)
+            .map(|(name, _)| name.clone())
+    }
+}
+
+/// Front-matter of a rule file
+#[derive(serde::Serialize)]
+struct RuleHeader<'a> {
+    name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+}
+
+/// Name of the rule file for `profile`, e.g. `coding-rust.md` for `coding/rust`
+pub fn rule_file_name(profile: &str) -> String {
+    format!("{}.md", profile.replace('/', "-"))
+}
+
+/// The rule file written for `profile`: its rendered body under a front-matter
+/// naming the rule after the profile
+pub fn render_rule(storage: &Storage, profile: &str) -> crate::Result<String> {
+    apply::check_profile(storage, profile)?;
+    let body = storage.render(profile)?;
+    let header = RuleHeader {
+        name: profile,
+        description: storage.metadata(profile).unwrap_or_default().description,
+    };
+    let header = serde_yaml::to_string(&header)
+        .map_err(|e| anyhow::anyhow!("Failed to write the rule header: {}", e))?;
+    Ok(format!("---\n{header}---\n{body}"))
+}
+
+/// Whether the rule file at `path` holds something other than what pmx wrote
+fn is_edited(path: &Path, rule: &Rule) -> bool {
+    std::fs::read(path).is_ok_and(|content| crate::utils::content_hash(&content) != rule.hash)
+}
+
+fn remove_rule(path: &Path) -> crate::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(anyhow::anyhow!(
+            "Failed to remove {}: {}",
+            path.display(),
+            e
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Back up `files` in `dir` and the manifest before pmx writes or removes them,
+/// so that `pmx restore-profile` can put the rules back as they were
+fn back_up(
+    storage: &Storage,
+    agent: Agent,
+    dir: &Path,
+    mut files: Vec<PathBuf>,
+) -> crate::Result<()> {
+    files.push(dir.join(MANIFEST_FILE));
+    crate::backups::save_files(storage, agent, dir, &files)
+}
+
+/// Write one rule file per profile into `dir`. Setting (`replace`) also removes
+/// the rules pmx wrote before that are not among `profiles`; appending keeps them.
+pub fn write(
+    storage: &Storage,
+    agent: Agent,
+    profiles: &[String],
+    dir: &Path,
+    options: &ApplyOptions,
+    replace: bool,
+) -> crate::Result<Vec<Change>> {
+    ensure!(!profiles.is_empty(), "No profile to apply");
+    ensure!(
+        !options.link,
+        "{} rules are written one file per profile and cannot be linked",
+        agent.display_name()
+    );
+    let mut manifest = Manifest::load(dir)?;
+
+    let mut rules: Vec<(String, &String, String)> = Vec::new();
+    for profile in profiles {
+        let name = rule_file_name(profile);
+        let taken = rules
+            .iter()
+            .map(|(name, profile, _)| (name, profile.as_str()))
+            .chain(
+                manifest
+                    .rules
+                    .iter()
+                    .filter(|_| !replace)
+                    .map(|(name, rule)| (name, rule.profile.as_str())),
+            )
+            .find(|(taken, other)| **taken == name && *other != profile);
+        if let Some((_, other)) = taken {
+            bail!(
+                "Profiles '{}' and '{}' would both be written to {}",
+                other,
+                profile,
+                dir.join(&name).display()
+            );
+        }
+        if rules.iter().any(|(taken, _, _)| *taken == name) {
+            continue;
+        }
+        let content = render_rule(storage, profile)?;
+        apply::warn_if_stale(storage, profile);
+        rules.push((name, profile, content));
+    }
+
+    // Rules that are already up to date are left alone, edits are not discarded
+    // without --force and hand-written rules are protected like any agent file
+    let mut changes = Vec::new();
+    let mut writes = Vec::new();
+    for (name, profile, content) in &rules {
+        let path = dir.join(name);
+        if !options.force
+            && std::fs::read(&path).is_ok_and(|existing| existing == content.as_bytes())
+        {
+            continue;
+        }
+        match manifest.rules.get(name) {
+            Some(rule) => ensure!(
+                options.force || !is_edited(&path, rule),
+                "{} was edited since pmx wrote it; rerun with --force to overwrite it",
+                path.display()
+            ),
+            None => changes.extend(apply::protect_unmanaged(storage, &path, options)?),
+        }
+        apply::check_size_limits(storage, &path, content)?;
+        writes.push((path, *profile, content));
+    }
+    let stale: Vec<String> = manifest
+        .rules
+        .keys()
+        .filter(|name| replace && !rules.iter().any(|(kept, _, _)| kept == *name))
+        .cloned()
+        .collect();
+    for name in &stale {
+        let path = dir.join(name);
+        ensure!(
+            options.force || !is_edited(&path, &manifest.rules[name]),
+            "{} was edited since pmx wrote it; rerun with --force to remove it",
+            path.display()
+        );
+    }
+
+    let label = apply::describe_profiles(profiles);
+    let up_to_date = writes.is_empty() && stale.is_empty();
+    if options.dry_run {
+        for (path, profile, content) in &writes {
+            changes.push(apply::preview_write(
+                path,
+                content,
+                &format!("profile '{profile}'"),
+            ));
+        }
+        changes.extend(stale.iter().map(|name| Change::WouldRemove(dir.join(name))));
+        if up_to_date {
+            changes.push(Change::UpToDate {
+                target: dir.to_path_buf(),
+                label,
+            });
+        }
+        return Ok(changes);
+    }
+    let written: Vec<String> = writes
+        .iter()
+        .map(|(_, profile, _)| profile.to_string())
+        .collect();
+    if !apply::confirm_sensitive(storage, &written, dir, options)? {
+        changes.push(Change::Unchanged(dir.to_path_buf()));
+        return Ok(changes);
+    }
+
+    let changed: Vec<PathBuf> = writes
+        .iter()
+        .map(|(path, _, _)| path.clone())
+        .chain(stale.iter().map(|name| dir.join(name)))
+        .collect();
+    if !changed.is_empty() {
+        back_up(storage, agent, dir, changed)?;
+    }
+    std::fs::create_dir_all(dir)
+        .map_err(|e| anyhow::anyhow!("Failed to create directory {}: {}", dir.display(), e))?;
+    for (path, _, content) in &writes {
+        std::fs::write(path, content)
+            .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))?;
+    }
+    for name in &stale {
+        remove_rule(&dir.join(name))?;
+        if let Some(removed) = manifest.rules.remove(name) {
+            changes.push(Change::Removed {
+                target: dir.join(name),
+                profile: removed.profile,
+            });
+        }
+    }
+    for (name, profile, content) in &rules {
+        manifest.rules.insert(
+            name.clone(),
+            Rule {
+                profile: profile.to_string(),
+                hash: crate::utils::content_hash(content.as_bytes()),
+            },
+        );
+    }
+    manifest.persist(dir)?;
+
+    crate::state::update(storage, |state| {
+        if replace {
+            state.record_set(agent, dir, &profiles[0]);
+        }
+        for profile in &profiles[usize::from(replace)..] {
+            let applied = state
+                .get(dir)
+                .is_some_and(|applied| applied.profiles.contains(profile));
+            if !applied {
+                state.record_append(agent, dir, profile);
+            }
+        }
+    })?;
+
+    let target = dir.to_path_buf();
+    changes.push(match up_to_date {
+        true => Change::UpToDate { target, label },
+        false => Change::Applied { target, label },
+    });
+    Ok(changes)
+}
+
+/// Remove the rule file pmx wrote for `profile`; an edited one is kept on disk
+pub fn remove(
+    storage: &Storage,
+    agent: Agent,
+    profile: &str,
+    dir: &Path,
+    options: &ApplyOptions,
+) -> crate::Result<Vec<Change>> {
+    let mut manifest = Manifest::load(dir)?;
+    let Some(name) = manifest.file_of(profile) else {
+        bail!("Profile '{}' has no rule in {}", profile, dir.display());
+    };
+    let path = dir.join(&name);
+    let edited = is_edited(&path, &manifest.rules[&name]);
+    if options.dry_run {
+        return Ok(vec![match edited {
+            true => Change::WouldKeep(path),
+            false => Change::WouldRemove(path),
+        }]);
+    }
+
+    back_up(
+        storage,
+        agent,
+        dir,
+        [path.clone()].into_iter().filter(|_| !edited).collect(),
+    )?;
+    if !edited {
+        remove_rule(&path)?;
+    }
+    manifest.rules.remove(&name);
+    manifest.persist(dir)?;
+    crate::state::update(storage, |state| state.record_remove(dir, profile))?;
+    Ok(vec![match edited {
+        true => Change::Kept(path),
+        false => Change::Removed {
+            target: dir.to_path_buf(),
+            profile: profile.to_string(),
+        },
+    }])
+}
+
+/// Remove every rule file pmx wrote to `dir`, keeping the ones edited since and
+/// leaving other rules alone
+pub fn reset(
+    storage: &Storage,
+    agent: Agent,
+    dir: &Path,
+    options: &ApplyOptions,
+) -> crate::Result<Vec<Change>> {
+    let manifest = Manifest::load(dir)?;
+    if manifest.rules.is_empty() {
+        if !options.dry_run {
+            crate::state::update(storage, |state| state.clear(dir))?;
+        }
+        return Ok(vec![Change::AlreadyReset {
+            agent,
+            target: dir.to_path_buf(),
+        }]);
+    }
+
+    if !options.dry_run {
+        let removed = manifest
+            .rules
+            .iter()
+            .filter(|(name, rule)| !is_edited(&dir.join(name), rule))
+            .map(|(name, _)| dir.join(name))
+            .collect();
+        back_up(storage, agent, dir, removed)?;
+    }
+    let mut changes = Vec::new();
+    for (name, rule) in &manifest.rules {
+        let path = dir.join(name);
+        let edited = is_edited(&path, rule);
+        if options.dry_run {
+            changes.push(match edited {
+                true => Change::WouldKeep(path),
+                false => Change::WouldRemove(path),
+            });
+        } else if edited {
+            changes.push(Change::Kept(path));
+        } else {
+            remove_rule(&path)?;
+        }
+    }
+    if options.dry_run {
+        return Ok(changes);
+    }
+
+    Manifest::default().persist(dir)?;
+    crate::state::update(storage, |state| state.clear(dir))?;
+    // Only the kept rules are listed so far
+    changes.push(Change::RulesReset {
+        agent,
+        dir: dir.to_path_buf(),
+        removed: manifest.rules.len() - changes.len(),
+    });
+    Ok(changes)
+}
+
+/// Whether the rule files pmx wrote to `dir` are all there and unchanged
+pub(crate) fn file_state(dir: &Path) -> crate::Result<FileState> {
+    let manifest = Manifest::load(dir)?;
+    if manifest.rules.is_empty() {
+        return Ok(FileState::Missing);
+    }
+    let mut file = FileState::Clean;
+    for (name, rule) in &manifest.rules {
+        let path = dir.join(name);
+        if !path.exists() {
+            return Ok(FileState::Missing);
+        }
+        if is_edited(&path, rule) {
+            file = FileState::Modified;
+        }
+    }
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_set_append_and_reset_rules() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::initialize(temp_dir.path().join("storage")).unwrap();
+        storage
+            .create_profile(
+                "coding/rust",
+                "---\ndescription: Rust style\n---\nUse clippy.\n",
+            )
+            .unwrap();
+        storage.create_profile("base", "Be helpful.\n").unwrap();
+        storage.create_profile("extra", "Be brief.\n").unwrap();
+        let dir = temp_dir.path().join("rules");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("mine.md"), "Hand-written.\n").unwrap();
+        let options = ApplyOptions::default();
+        let profiles = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+
+        write(
+            &storage,
+            Agent::Continue,
+            &profiles(&["base", "coding/rust"]),
+            &dir,
+            &options,
+            true,
+        )
+        .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.join("coding-rust.md")).unwrap(),
+            "---\nname: coding/rust\ndescription: Rust style\n---\nUse clippy.\n"
+        );
+        write(
+            &storage,
+            Agent::Continue,
+            &profiles(&["extra"]),
+            &dir,
+            &options,
+            false,
+        )
+        .unwrap();
+        assert_eq!(file_state(&dir).unwrap(), FileState::Clean);
+
+        // Setting replaces what pmx wrote, but not edits
+        std::fs::write(dir.join("base.md"), "Edited.\n").unwrap();
+        assert_eq!(file_state(&dir).unwrap(), FileState::Modified);
+        assert!(
+            write(
+                &storage,
+                Agent::Continue,
+                &profiles(&["extra"]),
+                &dir,
+                &options,
+                true
+            )
+            .is_err()
+        );
+        std::fs::write(dir.join("base.md"), render_rule(&storage, "base").unwrap()).unwrap();
+        let changes = write(
+            &storage,
+            Agent::Continue,
+            &profiles(&["extra"]),
+            &dir,
+            &options,
+            true,
+        )
+        .unwrap();
+        assert_eq!(
+            changes,
+            [
+                Change::Removed {
+                    target: dir.join("base.md"),
+                    profile: "base".to_string(),
+                },
+                Change::Removed {
+                    target: dir.join("coding-rust.md"),
+                    profile: "coding/rust".to_string(),
+                },
+                Change::Applied {
+                    target: dir.clone(),
+                    label: "profile 'extra'".to_string(),
+                },
+            ]
+        );
+        assert!(!dir.join("base.md").exists());
+        let state = crate::state::State::load(&storage.path).unwrap();
+        assert_eq!(state.get(&dir).unwrap().profiles, ["extra"]);
+
+        let changes = reset(&storage, Agent::Continue, &dir, &options).unwrap();
+        assert_eq!(
+            changes,
+            [Change::RulesReset {
+                agent: Agent::Continue,
+                dir: dir.clone(),
+                removed: 1,
+            }]
+        );
+        let mut left: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        left.sort();
+        assert_eq!(left, ["mine.md"]);
+        assert!(
+            crate::state::State::load(&storage.path)
+                .unwrap()
+                .get(&dir)
+                .is_none()
+        );
+
+        // Each change is backed up, so the last one can be undone
+        crate::backups::restore(&storage, &dir).unwrap().unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.join("extra.md")).unwrap(),
+            render_rule(&storage, "extra").unwrap()
+        );
+        assert_eq!(file_state(&dir).unwrap(), FileState::Clean);
+        let state = crate::state::State::load(&storage.path).unwrap();
+        assert_eq!(state.get(&dir).unwrap().profiles, ["extra"]);
+
+        crate::backups::restore(&storage, &dir).unwrap().unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.join("base.md")).unwrap(),
+            render_rule(&storage, "base").unwrap()
+        );
+    }
+}
@@ -0,0 +1,80 @@
+use anyhow::ensure;
+
+use crate::cli::SyncCommand;
+use crate::storage::Storage;
+
+/// Exchange the repository with the `[sync]` remote and update its shared sections
+pub fn sync(storage: &Storage, args: &crate::cli::SyncArgs) -> crate::Result<()> {
+    ensure!(
+        args.command.is_some() || args.submodules,
+        "Nothing to sync; use `pmx sync clone|pull|push`, or pass --submodules to update shared sections of the repository"
+    );
+
+    match &args.command {
+        Some(SyncCommand::Clone(clone)) => {
+            crate::remote::clone(storage, clone.url.as_deref())?;
+            println!(
+                "Cloned the remote into {}",
+                storage.path.join("repo").display()
+            );
+        }
+        Some(SyncCommand::Pull) => {
+            let changed = crate::remote::pull(storage)?;
+            if changed.is_empty() {
+                println!("Already up to date");
+            }
+            for file in &changed {
+                println!("Updated {file}");
+            }
+        }
+        Some(SyncCommand::Push) => {
+            if crate::remote::push(storage)? {
+                println!("Pushed to {}", storage.config.sync.branch);
+            } else {
+                println!("Remote is up to date");
+            }
+        }
+        None => {}
+    }
+    if args.submodules {
+        submodules(storage)?;
+    }
+    Ok(())
+}
+
+/// Bring every submodule of the repository to the tip of its tracked branch
+fn submodules(storage: &Storage) -> crate::Result<()> {
+    let repo = storage.path.join("repo");
+    let modules = crate::submodules::paths(&repo)?;
+    if modules.is_empty() {
+        println!(
+            "No submodules declared in {}",
+            repo.join(".gitmodules").display()
+        );
+        return Ok(());
+    }
+
+    let revision = |module: &str| {
+        crate::submodules::is_checked_out(&repo, module)
+            .then(|| crate::utils::git(&repo.join(module), &["rev-parse", "--short", "HEAD"]))
+            .and_then(Result::ok)
+            .map(|output| output.trim().to_string())
+    };
+    let before: Vec<_> = modules.iter().map(|module| revision(module)).collect();
+
+    crate::submodules::update(&repo)?;
+    let paths: Vec<_> = modules.iter().map(Into::into).collect();
+    crate::manifest::refresh(&storage.path, &paths)?;
+
+    for (module, before) in modules.iter().zip(before) {
+        match (before, revision(module)) {
+            (_, None) => println!("{module}: not checked out"),
+            (None, Some(after)) => println!("{module}: checked out at {after}"),
+            (Some(before), Some(after)) if before == after => {
+                println!("{module}: up to date ({after})")
+            }
+            (Some(before), Some(after)) => println!("{module}: updated {before} -> {after}"),
+        }
+    }
+    Ok(())
+}
@@ -1,46 +1,45 @@
-pub fn list(storage: &crate::storage::Storage) -> crate::Result<()> {
-    use is_terminal::IsTerminal;
-    use std::collections::BTreeMap;
-    use std::io;
-
-    let profile_list = storage.list_repos()?;
+/// The profiles and bundles `pmx profile list` shows
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProfileList {
+    /// Each profile with the note shown after it: its description, and whether
+    /// it is encrypted or past its review or expiry date
+    pub profiles: Vec<(String, String)>,
+    /// Directories that are submodules, which hold shared profiles
+    pub shared: Vec<String>,
+    pub bundles: Vec<String>,
+}
 
-    if profile_list.is_empty() {
-        println!("No profiles found.");
-        return Ok(());
+impl ProfileList {
+    /// Profile names alone, one per line, as printed when stdout is piped
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.profiles.iter().map(|(name, _)| name.as_str())
     }
+}
 
-    // If output is piped, use the simple format
-    if !io::stdout().is_terminal() {
-        profile_list
-            .iter()
-            .for_each(|profile| println!("{profile}"));
-        return Ok(());
-    }
+impl std::fmt::Display for ProfileList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use std::collections::BTreeMap;
 
-    // For terminal output, create a tree-like structure
-    let mut tree: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        if self.profiles.is_empty() {
+            return writeln!(f, "No profiles found.");
+        }
 
-    for profile in &profile_list {
-        if let Some(slash_pos) = profile.find('/') {
-            let (dir, file) = profile.split_at(slash_pos);
-            let file = &file[1..]; // Remove the leading '/'
-            tree.entry(dir.to_string())
-                .or_default()
-                .push(file.to_string());
-        } else {
-            tree.entry(String::new()).or_default().push(profile.clone());
+        // Group profiles by directory into a tree
+        let mut tree: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+        for (profile, note) in &self.profiles {
+            match profile.split_once('/') {
+                Some((dir, file)) => tree.entry(dir).or_default().push(format!("{file}{note}")),
+                None => tree.entry("").or_default().push(format!("{profile}{note}")),
+            }
         }
-    }
 
-    // Print the tree
-    let dirs: Vec<_> = tree.keys().collect();
-    for (i, dir) in dirs.iter().enumerate() {
-        let is_last_dir = i == dirs.len() - 1;
+        let dirs: Vec<_> = tree.keys().collect();
+        for (i, dir) in dirs.iter().enumerate() {
+            let is_last_dir = i == dirs.len() - 1;
+            let files = &tree[**dir];
 
-        if dir.is_empty() {
-            // Root level files
-            if let Some(files) = tree.get(*dir) {
+            if dir.is_empty() {
+                // Root level files
                 for (j, file) in files.iter().enumerate() {
                     let is_last_file = j == files.len() - 1 && is_last_dir;
                     let prefix = if is_last_file {
@@ -48,19 +47,22 @@ pub fn list(storage: &crate::storage::Storage) -> crate::Result<()> {
                     } else {
                         "├── "
                     };
-                    println!("{prefix}{file}");
+                    writeln!(f, "{prefix}{file}")?;
                 }
-            }
-        } else {
-            // Directory
-            let dir_prefix = if is_last_dir {
-                "└── "
             } else {
-                "├── "
-            };
-            println!("{dir_prefix}{dir}/");
+                // Directory
+                let dir_prefix = if is_last_dir {
+                    "└── "
+                } else {
+                    "├── "
+                };
+                let shared = if self.shared.iter().any(|module| module == *dir) {
+                    " (shared)"
+                } else {
+                    ""
+                };
+                writeln!(f, "{dir_prefix}{dir}/{shared}")?;
 
-            if let Some(files) = tree.get(*dir) {
                 for (j, file) in files.iter().enumerate() {
                     let is_last_file = j == files.len() - 1;
                     let file_prefix = if is_last_dir {
@@ -74,46 +76,200 @@ pub fn list(storage: &crate::storage::Storage) -> crate::Result<()> {
                     } else {
                         "│   ├── "
                     };
-                    println!("{file_prefix}{file}");
+                    writeln!(f, "{file_prefix}{file}")?;
                 }
             }
         }
+
+        if !self.bundles.is_empty() {
+            writeln!(f, "\nBundles:")?;
+            for bundle in &self.bundles {
+                writeln!(f, "  {bundle}")?;
+            }
+        }
+        Ok(())
     }
+}
 
-    Ok(())
+/// The profiles in the repository, with a warning for each submodule that is
+/// not checked out
+pub fn list(storage: &crate::storage::Storage) -> crate::Result<ProfileList> {
+    let profile_list = storage.list_repos()?;
+    let repo = storage.path.join("repo");
+    let shared = crate::submodules::paths(&repo)?;
+    for module in &shared {
+        if !crate::submodules::is_checked_out(&repo, module) {
+            tracing::warn!("Submodule {module} is not checked out; run `pmx sync --submodules`");
+        }
+    }
+
+    let mut profiles = Vec::new();
+    for profile in profile_list {
+        // Describing an encrypted profile would take the passphrase
+        let encrypted = crate::crypto::is_profile_encrypted(storage, &profile);
+        let metadata = if encrypted {
+            Default::default()
+        } else {
+            storage.metadata(&profile).unwrap_or_default()
+        };
+        let mut note = metadata
+            .description
+            .as_ref()
+            .map(|description| format!(" - {description}"))
+            .unwrap_or_default();
+        if encrypted {
+            note.push_str(" (encrypted)");
+        }
+        // Flag profiles past their review or expiry date
+        if let Some(staleness) = metadata.staleness(&crate::utils::today()) {
+            note.push_str(&format!(" ({staleness})"));
+        }
+        profiles.push((profile, note));
+    }
+
+    let bundles = if profiles.is_empty() {
+        Vec::new()
+    } else {
+        crate::commands::bundle::describe(storage)
+    };
+    Ok(ProfileList {
+        profiles,
+        shared,
+        bundles,
+    })
 }
 
 pub fn copy_profile(path: &str, storage: &crate::storage::Storage) -> crate::Result<()> {
+    copy_profiles(&[path.to_string()], None, storage)
+}
+
+/// Copy several profiles to the clipboard as one text, joined by `separator`
+/// (or `clipboard.separator` from config.toml)
+pub fn copy_profiles(
+    paths: &[String],
+    separator: Option<&str>,
+    storage: &crate::storage::Storage,
+) -> crate::Result<()> {
     use arboard::Clipboard;
-    use std::fs;
 
-    let profile_path = storage.get_repo_path(path)?;
-    let content = fs::read_to_string(&profile_path)?;
+    let content = join_profiles(paths, separator, storage)?;
 
     let mut clipboard = Clipboard::new()?;
     clipboard.set_text(content)?;
-
-    println!("Profile content copied to clipboard: {path}");
     Ok(())
 }
 
+fn join_profiles(
+    paths: &[String],
+    separator: Option<&str>,
+    storage: &crate::storage::Storage,
+) -> crate::Result<String> {
+    let separator = unescape(separator.unwrap_or(&storage.config.clipboard.separator));
+    let contents = paths
+        .iter()
+        .map(|path| storage.render(path))
+        .collect::<crate::Result<Vec<_>>>()?;
+    Ok(contents.join(&separator))
+}
+
+/// Expand `\n` and `\t` so separators can be given on the command line
+fn unescape(text: &str) -> String {
+    text.replace("\\n", "\n").replace("\\t", "\t")
+}
+
+const ZSH_COMPLETION: &str = include_str!("../../completions/_pmx");
+
+/// Directory under the home directory where `completion install` puts the zsh script
+const ZSH_COMPLETION_DIR: &str = ".zsh/completions";
+
 pub fn completion(shell: &crate::cli::Shell) -> crate::Result<()> {
+    match shell {
+        crate::cli::Shell::Zsh => print!("{ZSH_COMPLETION}"),
+    }
+    Ok(())
+}
+
+/// Write the completion script for `shell` (or `$SHELL`) and explain any rc file changes
+pub fn install_completion(shell: Option<crate::cli::Shell>) -> crate::Result<()> {
+    let shell = match shell {
+        Some(shell) => shell,
+        None => detect_shell()?,
+    };
+    let home = crate::utils::home_dir()?;
+
     match shell {
         crate::cli::Shell::Zsh => {
-            const ZSH_COMPLETION: &str = include_str!("../../completions/_pmx");
-            print!("{ZSH_COMPLETION}");
+            let dir = home.join(ZSH_COMPLETION_DIR);
+            std::fs::create_dir_all(&dir)
+                .map_err(|e| anyhow::anyhow!("Failed to create {}: {}", dir.display(), e))?;
+            let script = dir.join("_pmx");
+            std::fs::write(&script, ZSH_COMPLETION)
+                .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", script.display(), e))?;
+            println!("Installed zsh completions to {}", script.display());
+
+            let zdotdir = std::env::var_os("ZDOTDIR").map(std::path::PathBuf::from);
+            let zshrc = zdotdir.unwrap_or(home).join(".zshrc");
+            let rc = std::fs::read_to_string(&zshrc).unwrap_or_default();
+            match zsh_rc_lines(&rc) {
+                Some(lines) => {
+                    println!(
+                        "Add these lines to {} and restart your shell; zsh only picks up\n\
+                         completions from directories in fpath when compinit runs, so the\n\
+                         fpath line must come before it:\n",
+                        zshrc.display()
+                    );
+                    println!("{lines}");
+                }
+                None => println!("Restart your shell (or run `exec zsh`) to load them."),
+            }
         }
     }
     Ok(())
 }
 
+fn detect_shell() -> crate::Result<crate::cli::Shell> {
+    let shell = std::env::var("SHELL").unwrap_or_default();
+    let name = std::path::Path::new(&shell)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    match name {
+        "zsh" => Ok(crate::cli::Shell::Zsh),
+        "" => Err(anyhow::anyhow!(
+            "Could not detect your shell from $SHELL; pass --shell"
+        )),
+        other => Err(anyhow::anyhow!(
+            "Completions for '{}' are not available yet; supported shells: zsh",
+            other
+        )),
+    }
+}
+
+/// Lines `.zshrc` needs so zsh finds the installed script, or `None` when it already has them.
+/// The `fpath` line has to run before `compinit`, which only scans `fpath` once.
+fn zsh_rc_lines(rc: &str) -> Option<String> {
+    if rc.contains(ZSH_COMPLETION_DIR) {
+        return None;
+    }
+    let fpath = format!("fpath=(~/{ZSH_COMPLETION_DIR} $fpath)");
+    Some(if rc.contains("compinit") {
+        format!("# above the line that runs compinit\n{fpath}")
+    } else {
+        format!("{fpath}\nautoload -U compinit && compinit")
+    })
+}
+
 pub fn internal_completion(
     storage: &crate::storage::Storage,
     completion_cmd: &crate::cli::InternalCompletionCommand,
 ) -> crate::Result<()> {
     match completion_cmd {
         crate::cli::InternalCompletionCommand::ClaudeProfiles => {
-            if !storage.config.agents.disable_claude {
+            if storage
+                .config
+                .agents
+                .is_enabled(crate::agent::Agent::Claude)
+            {
                 let profile_list = storage.list_repos()?;
                 profile_list
                     .iter()
@@ -121,28 +277,147 @@ pub fn internal_completion(
             }
         }
         crate::cli::InternalCompletionCommand::CodexProfiles => {
-            if !storage.config.agents.disable_codex {
+            if storage.config.agents.is_enabled(crate::agent::Agent::Codex) {
                 let profile_list = storage.list_repos()?;
                 profile_list
                     .iter()
                     .for_each(|profile| println!("{profile}"));
             }
         }
+        crate::cli::InternalCompletionCommand::GeminiProfiles => {
+            if storage
+                .config
+                .agents
+                .is_enabled(crate::agent::Agent::Gemini)
+            {
+                let profile_list = storage.list_repos()?;
+                profile_list
+                    .iter()
+                    .for_each(|profile| println!("{profile}"));
+            }
+        }
+        crate::cli::InternalCompletionCommand::CursorProfiles => {
+            if storage
+                .config
+                .agents
+                .is_enabled(crate::agent::Agent::Cursor)
+            {
+                let profile_list = storage.list_repos()?;
+                profile_list
+                    .iter()
+                    .for_each(|profile| println!("{profile}"));
+            }
+        }
+        crate::cli::InternalCompletionCommand::ContinueProfiles => {
+            if storage
+                .config
+                .agents
+                .is_enabled(crate::agent::Agent::Continue)
+            {
+                let profile_list = storage.list_repos()?;
+                profile_list
+                    .iter()
+                    .for_each(|profile| println!("{profile}"));
+            }
+        }
+        crate::cli::InternalCompletionCommand::TemplateNames => {
+            for name in crate::template::template_names(storage) {
+                println!("{name}");
+            }
+        }
+        crate::cli::InternalCompletionCommand::BundleNames => {
+            for name in storage.config.bundles.keys() {
+                println!("{name}");
+            }
+        }
+        crate::cli::InternalCompletionCommand::AgentNames => {
+            for agent in crate::agent::Agent::all(storage)? {
+                if agent.is_enabled(storage) {
+                    println!("{}", agent.name());
+                }
+            }
+        }
         crate::cli::InternalCompletionCommand::EnabledCommands => {
             // Always available commands
             println!("profile");
             println!("completion");
+            println!("config");
+            println!("hook");
+            println!("shell-plugin");
+            println!("apply");
+            println!("status");
+            println!("bootstrap");
+            println!("init");
+            println!("pack");
+            println!("import");
+            println!("sync");
+            println!("verify");
+            println!("which");
+            println!("diff");
+            println!("alias");
+            println!("refactor");
+            println!("vars");
+            println!("paths");
+            println!("doctor");
+            println!("watch");
+            println!("bundle");
+            println!("set-profile");
+            println!("reset-profile");
+            println!("restore-profile");
+            println!("append-profile");
+            println!("remove-profile");
 
             // Agent-specific commands
-            if !storage.config.agents.disable_claude {
+            if storage
+                .config
+                .agents
+                .is_enabled(crate::agent::Agent::Claude)
+            {
                 println!("set-claude-profile");
                 println!("reset-claude-profile");
+                println!("restore-claude-profile");
                 println!("append-claude-profile");
+                println!("remove-claude-profile");
             }
-            if !storage.config.agents.disable_codex {
+            if storage.config.agents.is_enabled(crate::agent::Agent::Codex) {
                 println!("set-codex-profile");
                 println!("reset-codex-profile");
+                println!("restore-codex-profile");
                 println!("append-codex-profile");
+                println!("remove-codex-profile");
+            }
+            if storage
+                .config
+                .agents
+                .is_enabled(crate::agent::Agent::Gemini)
+            {
+                println!("set-gemini-profile");
+                println!("reset-gemini-profile");
+                println!("restore-gemini-profile");
+                println!("append-gemini-profile");
+                println!("remove-gemini-profile");
+            }
+            if storage
+                .config
+                .agents
+                .is_enabled(crate::agent::Agent::Cursor)
+            {
+                println!("set-cursor-profile");
+                println!("reset-cursor-profile");
+                println!("restore-cursor-profile");
+                println!("append-cursor-profile");
+                println!("remove-cursor-profile");
+            }
+            if storage
+                .config
+                .agents
+                .is_enabled(crate::agent::Agent::Continue)
+            {
+                println!("set-continue-profile");
+                println!("reset-continue-profile");
+                println!("restore-continue-profile");
+                println!("append-continue-profile");
+                println!("remove-continue-profile");
             }
 
             // MCP command (only if prompts or tools are enabled)
@@ -150,16 +425,41 @@ pub fn internal_completion(
                 println!("mcp");
             }
         }
-        crate::cli::InternalCompletionCommand::ProfileNames => {
+        crate::cli::InternalCompletionCommand::ProfileNames { prefix: None } => {
             let profile_list = storage.list_repos()?;
             profile_list
                 .iter()
                 .for_each(|profile| println!("{profile}"));
         }
+        crate::cli::InternalCompletionCommand::ProfileNames {
+            prefix: Some(prefix),
+        } => {
+            for candidate in nested_candidates(&storage.list_repos()?, prefix) {
+                println!("{candidate}");
+            }
+        }
     }
     Ok(())
 }
 
+/// Completions for `prefix` that go one directory deeper: profiles directly in
+/// the directory being typed, and its subdirectories with a trailing `/`
+fn nested_candidates(profiles: &[String], prefix: &str) -> Vec<String> {
+    let directory = &prefix[..prefix.rfind('/').map_or(0, |slash| slash + 1)];
+    let mut candidates = std::collections::BTreeSet::new();
+    for profile in profiles
+        .iter()
+        .filter(|profile| profile.starts_with(prefix))
+    {
+        let rest = &profile[directory.len()..];
+        match rest.split_once('/') {
+            Some((subdirectory, _)) => candidates.insert(format!("{directory}{subdirectory}/")),
+            None => candidates.insert(profile.clone()),
+        };
+    }
+    candidates.into_iter().collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -177,13 +477,14 @@ mod tests {
 
         fs::create_dir(&repo_dir).unwrap();
 
+        let mut agents = Agents::default();
+        agents.agents.get_mut("claude").unwrap().enabled = !disable_claude;
+        agents.agents.get_mut("codex").unwrap().enabled = !disable_codex;
         let config = Config {
-            agents: Agents {
-                disable_claude,
-                disable_codex,
-            },
+            agents,
             mcp: crate::storage::McpConfig::default(),
             extensions: crate::storage::ExtensionsConfig::default(),
+            ..Default::default()
         };
 
         let config_content = toml::to_string(&config).unwrap();
@@ -197,6 +498,28 @@ mod tests {
         (temp_dir, storage)
     }
 
+    #[test]
+    fn test_list() {
+        let (_temp_dir, storage) = create_test_storage(false, false);
+        storage
+            .create_profile(
+                "work/review",
+                "---\ndescription: Code review\n---\nBe thorough.\n",
+            )
+            .unwrap();
+
+        let list = list(&storage).unwrap();
+        assert_eq!(
+            list.names().collect::<Vec<_>>(),
+            ["test_profile", "work/review"]
+        );
+        assert_eq!(
+            list.to_string(),
+            "├── test_profile\n└── work/\n    └── review - Code review\n"
+        );
+        assert_eq!(ProfileList::default().to_string(), "No profiles found.\n");
+    }
+
     #[test]
     fn test_internal_completion_claude_profiles_enabled() {
         let (_temp_dir, storage) = create_test_storage(false, false);
@@ -233,6 +556,33 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_internal_completion_gemini_profiles_enabled() {
+        let (_temp_dir, storage) = create_test_storage(false, false);
+
+        let cmd = crate::cli::InternalCompletionCommand::GeminiProfiles;
+        let result = internal_completion(&storage, &cmd);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_internal_completion_cursor_profiles_enabled() {
+        let (_temp_dir, storage) = create_test_storage(false, false);
+
+        let cmd = crate::cli::InternalCompletionCommand::CursorProfiles;
+        let result = internal_completion(&storage, &cmd);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_internal_completion_continue_profiles_enabled() {
+        let (_temp_dir, storage) = create_test_storage(false, false);
+
+        let cmd = crate::cli::InternalCompletionCommand::ContinueProfiles;
+        let result = internal_completion(&storage, &cmd);
+        assert!(result.is_ok());
+    }
+
     #[test]
     fn test_internal_completion_enabled_commands_all_enabled() {
         let (_temp_dir, storage) = create_test_storage(false, false);
@@ -280,16 +630,19 @@ mod tests {
         let repo_dir = temp_dir.path().join("repo");
         fs::create_dir(&repo_dir).unwrap();
 
+        let mut agents = crate::storage::Agents::default();
+        for agent in agents.agents.values_mut() {
+            agent.enabled = false;
+        }
         let config = crate::storage::Config {
-            agents: crate::storage::Agents {
-                disable_claude: true,
-                disable_codex: true,
-            },
+            agents,
             mcp: crate::storage::McpConfig {
                 disable_prompts: crate::storage::DisableOption::Bool(true),
                 disable_tools: crate::storage::DisableOption::Bool(true),
+                ..Default::default()
             },
             extensions: crate::storage::ExtensionsConfig::default(),
+            ..Default::default()
         };
 
         let config_content = toml::to_string(&config).unwrap();
@@ -300,4 +653,65 @@ mod tests {
         // Since we can't easily capture stdout in unit tests, we'll test the logic directly
         assert!(!storage.is_mcp_enabled());
     }
+
+    #[test]
+    fn test_join_profiles_with_separator() {
+        let (_temp_dir, storage) = create_test_storage(false, false);
+        storage.create_profile("second", "Second.").unwrap();
+        let names = vec!["test_profile".to_string(), "second".to_string()];
+
+        assert_eq!(
+            join_profiles(&names, None, &storage).unwrap(),
+            "# Test Profile\nThis is a test profile.\n\nSecond."
+        );
+        assert_eq!(
+            join_profiles(&names, Some("\\n---\\n"), &storage).unwrap(),
+            "# Test Profile\nThis is a test profile.\n---\nSecond."
+        );
+        assert!(join_profiles(&["missing".to_string()], None, &storage).is_err());
+    }
+
+    #[test]
+    fn test_nested_candidates() {
+        let profiles: Vec<String> = [
+            "base",
+            "design",
+            "design/plan",
+            "design/ui/colors",
+            "devops",
+        ]
+        .map(String::from)
+        .to_vec();
+
+        assert_eq!(
+            nested_candidates(&profiles, ""),
+            ["base", "design", "design/", "devops"]
+        );
+        assert_eq!(
+            nested_candidates(&profiles, "de"),
+            ["design", "design/", "devops"]
+        );
+        assert_eq!(
+            nested_candidates(&profiles, "design/"),
+            ["design/plan", "design/ui/"]
+        );
+        assert_eq!(
+            nested_candidates(&profiles, "design/ui/c"),
+            ["design/ui/colors"]
+        );
+        assert!(nested_candidates(&profiles, "nope").is_empty());
+    }
+
+    #[test]
+    fn test_zsh_rc_lines() {
+        assert_eq!(
+            zsh_rc_lines("").unwrap(),
+            "fpath=(~/.zsh/completions $fpath)\nautoload -U compinit && compinit"
+        );
+        assert_eq!(
+            zsh_rc_lines("autoload -Uz compinit; compinit\n").unwrap(),
+            "# above the line that runs compinit\nfpath=(~/.zsh/completions $fpath)"
+        );
+        assert!(zsh_rc_lines("fpath=(~/.zsh/completions $fpath)\n").is_none());
+    }
 }
@@ -0,0 +1,102 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::frontmatter::ArgumentSpec;
+use crate::storage::Storage;
+
+/// Profiles using a placeholder, with what each one declares about it
+type Usage = BTreeMap<String, Vec<(String, Option<ArgumentSpec>)>>;
+
+/// Print every `<{{VARIABLE}}>` placeholder with the profiles using it
+pub fn list(storage: &Storage) -> crate::Result<()> {
+    let mut usage = Usage::new();
+
+    for name in storage.list_repos()? {
+        let content = storage.get_profile_content(&name)?;
+        // Placeholders of a profile whose front-matter does not parse are still
+        // listed, only without their declarations
+        let metadata = crate::frontmatter::parse_metadata(&content).unwrap_or_default();
+
+        for var in crate::template::variables(&content) {
+            let spec = metadata.arguments.get(&var).cloned();
+            usage.entry(var).or_default().push((name.clone(), spec));
+        }
+    }
+
+    print!("{}", describe(&usage)?);
+    Ok(())
+}
+
+fn describe(usage: &Usage) -> crate::Result<String> {
+    if usage.is_empty() {
+        return Ok("No placeholders found\n".to_string());
+    }
+
+    let mut report = String::new();
+    for (var, profiles) in usage {
+        let count = match profiles.len() {
+            1 => "1 profile".to_string(),
+            n => format!("{n} profiles"),
+        };
+        writeln!(report, "{var} ({count})")?;
+
+        for (profile, spec) in profiles {
+            let mut declared = Vec::new();
+            if let Some(spec) = spec {
+                if let Some(default) = &spec.default {
+                    declared.push(format!("default {}", serde_json::to_string(default)?));
+                }
+                if let Some(kind) = &spec.kind {
+                    declared.push(format!("type {kind}"));
+                }
+                if !spec.allowed.is_empty() {
+                    declared.push(format!("enum {}", serde_json::to_string(&spec.allowed)?));
+                }
+                if spec.required == Some(true) {
+                    declared.push("required".to_string());
+                }
+                if spec.description.is_some() {
+                    declared.push("described".to_string());
+                }
+            }
+
+            if declared.is_empty() {
+                writeln!(report, "  {profile}")?;
+            } else {
+                writeln!(report, "  {profile} ({})", declared.join(", "))?;
+            }
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_groups_profiles_by_placeholder() {
+        let spec = ArgumentSpec {
+            description: Some("Language to write in".to_string()),
+            default: Some(serde_yaml::Value::String("Rust".to_string())),
+            kind: Some("string".to_string()),
+            ..Default::default()
+        };
+        let mut usage = Usage::new();
+        usage.insert(
+            "LANGUAGE".to_string(),
+            vec![
+                ("coding/go".to_string(), None),
+                ("coding/rust".to_string(), Some(spec)),
+            ],
+        );
+        usage.insert("NAME".to_string(), vec![("greeting".to_string(), None)]);
+
+        assert_eq!(
+            describe(&usage).unwrap(),
+            "LANGUAGE (2 profiles)\n  coding/go\n  coding/rust (default \"Rust\", type string, described)\n\
+             NAME (1 profile)\n  greeting\n"
+        );
+        assert_eq!(describe(&Usage::new()).unwrap(), "No placeholders found\n");
+    }
+}
@@ -0,0 +1,41 @@
+use anyhow::ensure;
+
+use crate::manifest::{MANIFEST_FILE, Manifest};
+use crate::storage::Storage;
+
+/// Check the repository against its checksum manifest and installed packs against
+/// their install records, or record the current repository with `update`
+pub fn verify(storage: &Storage, update: bool) -> crate::Result<()> {
+    if update {
+        let manifest = Manifest::scan(&storage.path)?;
+        manifest.persist(&storage.path)?;
+        println!(
+            "Recorded {} file(s) in {}",
+            manifest.files.len(),
+            MANIFEST_FILE
+        );
+        return Ok(());
+    }
+
+    let mut problems = match crate::manifest::verify(&storage.path)? {
+        None => {
+            println!("repo: no manifest recorded yet (run `pmx verify --update`)");
+            0
+        }
+        Some(deviations) if deviations.is_empty() => {
+            println!("repo: ok");
+            0
+        }
+        Some(deviations) => {
+            println!("repo:");
+            for deviation in &deviations {
+                println!("  {deviation}");
+            }
+            deviations.len()
+        }
+    };
+    problems += super::pack::verify(storage)?;
+
+    ensure!(problems == 0, "{} file(s) failed verification", problems);
+    Ok(())
+}
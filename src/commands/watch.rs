@@ -0,0 +1,80 @@
+use std::time::{Duration, Instant};
+
+use crate::lint::Severity;
+use crate::storage::Storage;
+use crate::watch::{Change, Debouncer, Snapshot};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Report profile changes until interrupted, linting changed profiles when `lint` is set.
+/// Changes are batched until the repository has been quiet for `debounce`
+/// (`[watch] debounce_ms` when not given).
+pub fn watch(storage: &Storage, lint: bool, debounce: Option<Duration>) -> crate::Result<()> {
+    let config = &storage.config.watch;
+    let mut debouncer = Debouncer::new(
+        Snapshot::take(storage)?,
+        debounce.unwrap_or(Duration::from_millis(config.debounce_ms)),
+        Duration::from_millis(config.max_delay_ms),
+    );
+    println!(
+        "Watching {} for changes (Ctrl-C to stop)",
+        storage.path.join("repo").display()
+    );
+
+    if lint {
+        let mut problems = 0;
+        for name in storage.list_repos()? {
+            problems += report(storage, &name, false);
+        }
+        println!("Linted all profiles: {problems} with problems");
+    }
+
+    loop {
+        std::thread::sleep(POLL_INTERVAL);
+        let changes = debouncer.poll(Snapshot::take(storage)?, Instant::now());
+        if changes.len() > 1 {
+            println!("{} profiles changed", changes.len());
+        }
+        for change in changes {
+            match &change {
+                Change::Removed(name) => println!("removed: {name}"),
+                Change::Added(name) | Change::Modified(name) if lint => {
+                    report(storage, name, true);
+                }
+                Change::Added(name) => println!("added: {name}"),
+                Change::Modified(name) => println!("modified: {name}"),
+            }
+        }
+    }
+}
+
+/// Print the diagnostics for `name`; returns 1 when it has any, for counting
+fn report(storage: &Storage, name: &str, announce_clean: bool) -> usize {
+    let diagnostics = match crate::lint::lint_profile(storage, name) {
+        Ok(diagnostics) => diagnostics,
+        Err(e) => {
+            println!("{name}: error: {e}");
+            return 1;
+        }
+    };
+
+    if diagnostics.is_empty() {
+        if announce_clean {
+            println!("{name}: ok");
+        }
+        return 0;
+    }
+
+    let errors = diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .count();
+    println!(
+        "{name}: {errors} error(s), {} warning(s)",
+        diagnostics.len() - errors
+    );
+    for diagnostic in diagnostics {
+        println!("  {diagnostic}");
+    }
+    1
+}
@@ -0,0 +1,92 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::agent::Agent;
+use crate::storage::Storage;
+
+/// Where an agent's prompt file lives and what pmx applied to it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Which {
+    pub agent: Agent,
+    /// Whether the agent is enabled in the configuration
+    pub enabled: bool,
+    pub target: PathBuf,
+    pub exists: bool,
+    /// Profiles pmx last wrote to the target, in order; empty when it wrote none
+    pub profiles: Vec<String>,
+}
+
+/// Resolve the agent's user-wide file, or the project-local one with `project`
+pub fn which(storage: &Storage, agent: Agent, project: bool) -> crate::Result<Which> {
+    resolve(storage, agent, agent.target(storage, project)?)
+}
+
+fn resolve(storage: &Storage, agent: Agent, target: PathBuf) -> crate::Result<Which> {
+    let state = crate::state::State::load(&storage.path)?;
+    Ok(Which {
+        agent,
+        enabled: agent.is_enabled(storage),
+        exists: target.exists(),
+        profiles: state
+            .get(&target)
+            .map(|applied| applied.profiles.clone())
+            .unwrap_or_default(),
+        target,
+    })
+}
+
+impl fmt::Display for Which {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let enabled = if self.enabled {
+            ""
+        } else {
+            " (disabled in config)"
+        };
+        writeln!(f, "Agent:   {}{enabled}", self.agent.name())?;
+        writeln!(f, "Target:  {}", self.target.display())?;
+        writeln!(f, "Exists:  {}", if self.exists { "yes" } else { "no" })?;
+        if self.profiles.is_empty() {
+            writeln!(f, "Profile: none applied by pmx")
+        } else {
+            writeln!(f, "Profile: {}", self.profiles.join(" + "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_describe_unmanaged_target() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::initialize(temp_dir.path().join("storage")).unwrap();
+        let target = temp_dir.path().join("CLAUDE.md");
+
+        let report = resolve(&storage, Agent::Claude, target)
+            .unwrap()
+            .to_string();
+        assert!(report.contains("Agent:   claude\n"));
+        assert!(report.contains("Exists:  no\n"));
+        assert!(report.contains("Profile: none applied by pmx\n"));
+    }
+
+    #[test]
+    fn test_describe_applied_target() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::initialize(temp_dir.path().join("storage")).unwrap();
+        let target = temp_dir.path().join("AGENTS.md");
+        std::fs::write(&target, "content").unwrap();
+
+        crate::state::update(&storage, |state| {
+            state.record_set(Agent::Codex, &target, "base");
+            state.record_append(Agent::Codex, &target, "rust");
+        })
+        .unwrap();
+
+        let report = resolve(&storage, Agent::Codex, target).unwrap().to_string();
+        assert!(report.contains("Exists:  yes\n"));
+        assert!(report.contains("Profile: base + rust\n"));
+    }
+}
@@ -0,0 +1,248 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::num::NonZeroU32;
+use std::sync::Mutex;
+
+use anyhow::{anyhow, bail, ensure};
+use base64::Engine;
+use ring::aead::{Aad, CHACHA20_POLY1305, LessSafeKey, NONCE_LEN, Nonce, UnboundKey};
+use ring::rand::{SecureRandom, SystemRandom};
+
+use crate::storage::Storage;
+
+/// First line of an encrypted profile file
+const HEADER: &str = "-----BEGIN PMX ENCRYPTED PROFILE-----";
+/// Last line of an encrypted profile file
+const FOOTER: &str = "-----END PMX ENCRYPTED PROFILE-----";
+/// Environment variable holding the passphrase of sensitive profiles
+pub const PASSPHRASE_ENV: &str = "PMX_PASSPHRASE";
+/// Layout of the payload, stored as its first byte
+const VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+/// PBKDF2-HMAC-SHA256 rounds turning the passphrase into a key
+const ITERATIONS: u32 = 200_000;
+
+/// The passphrase once it was found, so it is asked for at most once
+static PASSPHRASE: Mutex<Option<String>> = Mutex::new(None);
+/// Keys derived so far, by salt
+static KEYS: Mutex<BTreeMap<[u8; SALT_LEN], [u8; KEY_LEN]>> = Mutex::new(BTreeMap::new());
+
+/// Whether `content`, as stored under `repo/`, is an encrypted profile
+pub fn is_encrypted(content: &str) -> bool {
+    content.starts_with(HEADER)
+}
+
+/// Whether profile `name` is stored encrypted; needs no passphrase
+pub fn is_profile_encrypted(storage: &Storage, name: &str) -> bool {
+    storage
+        .get_repo_path(name)
+        .and_then(|path| crate::encoding::read_text(&path))
+        .is_ok_and(|content| is_encrypted(&content))
+}
+
+/// Whether writing profile `name` out would reveal sensitive content: it is
+/// encrypted or declares `sensitive: true`, or so does a profile it extends or
+/// includes. Needs no passphrase.
+pub fn is_sensitive(storage: &Storage, name: &str) -> bool {
+    is_sensitive_in(storage, name, &mut BTreeSet::new())
+}
+
+fn is_sensitive_in(storage: &Storage, name: &str, seen: &mut BTreeSet<String>) -> bool {
+    if !seen.insert(name.to_string()) {
+        return false;
+    }
+    let Ok(content) = storage
+        .get_repo_path(name)
+        .and_then(|path| crate::encoding::read_text(&path))
+    else {
+        return false;
+    };
+    if is_encrypted(&content) {
+        return true;
+    }
+    let metadata = crate::frontmatter::parse_metadata(&content).unwrap_or_default();
+    metadata.sensitive
+        || metadata
+            .extends
+            .into_iter()
+            .chain(crate::include::directives(&content))
+            .any(|other| is_sensitive_in(storage, &other, seen))
+}
+
+/// The passphrase from `PMX_PASSPHRASE`, `[encryption] passphrase_command` or,
+/// on a terminal, a prompt. A new passphrase (`confirm`) is asked for twice.
+fn passphrase(storage: &Storage, confirm: bool) -> crate::Result<String> {
+    let mut cached = PASSPHRASE.lock().unwrap();
+    if let Some(passphrase) = cached.as_ref() {
+        return Ok(passphrase.clone());
+    }
+
+    let passphrase = if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV)
+        && !passphrase.is_empty()
+    {
+        passphrase
+    } else if let Some(command) = &storage.config.encryption.passphrase_command {
+        run_passphrase_command(command)?
+    } else if crate::utils::is_interactive() {
+        let prompt = dialoguer::Password::new().with_prompt("Passphrase for sensitive profiles");
+        let prompt = if confirm {
+            prompt.with_confirmation("Repeat the passphrase", "The passphrases do not match")
+        } else {
+            prompt
+        };
+        prompt
+            .interact()
+            .map_err(|e| anyhow!("Failed to read the passphrase: {}", e))?
+    } else {
+        bail!(
+            "No passphrase for sensitive profiles; set {} or passphrase_command under [encryption] in config.toml",
+            PASSPHRASE_ENV
+        );
+    };
+    ensure!(!passphrase.is_empty(), "The passphrase is empty");
+    *cached = Some(passphrase.clone());
+    Ok(passphrase)
+}
+
+/// First line printed by `command`, e.g. `pass show pmx`
+fn run_passphrase_command(command: &str) -> crate::Result<String> {
+    let words = shlex::split(command)
+        .filter(|words| !words.is_empty())
+        .ok_or_else(|| anyhow!("Invalid passphrase_command: {}", command))?;
+    let output = std::process::Command::new(&words[0])
+        .args(&words[1..])
+        .stderr(std::process::Stdio::inherit())
+        .output()
+        .map_err(|e| anyhow!("Failed to run passphrase_command '{}': {}", words[0], e))?;
+    ensure!(
+        output.status.success(),
+        "passphrase_command '{}' failed with {}",
+        command,
+        output.status
+    );
+    let stdout = String::from_utf8(output.stdout)
+        .map_err(|_| anyhow!("passphrase_command printed something other than UTF-8"))?;
+    Ok(stdout.lines().next().unwrap_or_default().to_string())
+}
+
+fn key(storage: &Storage, salt: &[u8; SALT_LEN], confirm: bool) -> crate::Result<LessSafeKey> {
+    let known = KEYS.lock().unwrap().get(salt).copied();
+    let bytes = match known {
+        Some(bytes) => bytes,
+        None => {
+            let mut bytes = [0; KEY_LEN];
+            ring::pbkdf2::derive(
+                ring::pbkdf2::PBKDF2_HMAC_SHA256,
+                NonZeroU32::new(ITERATIONS).unwrap(),
+                salt,
+                passphrase(storage, confirm)?.as_bytes(),
+                &mut bytes,
+            );
+            KEYS.lock().unwrap().insert(*salt, bytes);
+            bytes
+        }
+    };
+    let key = UnboundKey::new(&CHACHA20_POLY1305, &bytes)
+        .map_err(|_| anyhow!("Failed to set up the encryption key"))?;
+    Ok(LessSafeKey::new(key))
+}
+
+/// `plaintext` encrypted with the passphrase, in the text form stored under `repo/`
+pub fn encrypt(storage: &Storage, plaintext: &str) -> crate::Result<String> {
+    let rng = SystemRandom::new();
+    let mut salt = [0; SALT_LEN];
+    let mut nonce = [0; NONCE_LEN];
+    rng.fill(&mut salt)
+        .and_then(|_| rng.fill(&mut nonce))
+        .map_err(|_| anyhow!("Failed to generate random bytes"))?;
+
+    let mut sealed = plaintext.as_bytes().to_vec();
+    key(storage, &salt, true)?
+        .seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(HEADER),
+            &mut sealed,
+        )
+        .map_err(|_| anyhow!("Failed to encrypt the profile"))?;
+
+    let mut payload = vec![VERSION];
+    payload.extend_from_slice(&salt);
+    payload.extend_from_slice(&nonce);
+    payload.extend_from_slice(&sealed);
+    let encoded = base64::engine::general_purpose::STANDARD.encode(payload);
+    let lines: Vec<&str> = encoded
+        .as_bytes()
+        .chunks(64)
+        .map(|chunk| std::str::from_utf8(chunk).unwrap())
+        .collect();
+    Ok(format!("{HEADER}\n{}\n{FOOTER}\n", lines.join("\n")))
+}
+
+/// The plaintext of an encrypted profile as `encrypt` wrote it
+pub fn decrypt(storage: &Storage, content: &str) -> crate::Result<String> {
+    let encoded: String = content
+        .lines()
+        .skip(1)
+        .take_while(|line| line.trim() != FOOTER)
+        .map(str::trim)
+        .collect();
+    let payload = base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|e| anyhow!("damaged encrypted profile: {}", e))?;
+    ensure!(
+        payload.len() > 1 + SALT_LEN + NONCE_LEN && payload[0] == VERSION,
+        "unsupported encrypted profile format"
+    );
+
+    let (salt, rest) = payload[1..].split_at(SALT_LEN);
+    let (nonce, sealed) = rest.split_at(NONCE_LEN);
+    let mut opened = sealed.to_vec();
+    let plaintext = key(storage, salt.try_into().unwrap(), false)?
+        .open_in_place(
+            Nonce::try_assume_unique_for_key(nonce).unwrap(),
+            Aad::from(HEADER),
+            &mut opened,
+        )
+        .map_err(|_| anyhow!("wrong passphrase or damaged file"))?;
+    String::from_utf8(plaintext.to_vec()).map_err(|_| anyhow!("decrypted content is not UTF-8"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_sensitive_profiles_are_stored_encrypted() {
+        *PASSPHRASE.lock().unwrap() = Some("correct horse".to_string());
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::initialize(temp_dir.path().join("storage")).unwrap();
+        let secret = "---\nsensitive: true\n---\nProprietary context.\n";
+        storage.create_profile("secret", secret).unwrap();
+        storage
+            .create_profile("public", "Be kind.\n{{include: secret}}\n")
+            .unwrap();
+        storage.create_profile("plain", "Be brief.\n").unwrap();
+
+        let stored = std::fs::read_to_string(storage.get_repo_path("secret").unwrap()).unwrap();
+        assert!(is_encrypted(&stored));
+        assert!(!stored.contains("Proprietary"));
+        assert_eq!(storage.get_profile_content("secret").unwrap(), secret);
+        assert!(
+            storage
+                .get_content("public")
+                .unwrap()
+                .contains("Proprietary context.")
+        );
+
+        assert!(is_sensitive(&storage, "secret"));
+        assert!(is_sensitive(&storage, "public"));
+        assert!(!is_sensitive(&storage, "plain"));
+
+        // Any change to the ciphertext is caught
+        let mut damaged = stored.into_bytes();
+        let at = HEADER.len() + 40;
+        damaged[at] = if damaged[at] == b'A' { b'B' } else { b'A' };
+        assert!(decrypt(&storage, &String::from_utf8(damaged).unwrap()).is_err());
+    }
+}
@@ -0,0 +1,233 @@
+use similar::{ChangeTag, DiffTag, TextDiff};
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const CYAN: &str = "\x1b[36m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DiffMode {
+    /// Unified diff of whole lines
+    #[default]
+    Line,
+    /// Inline diff of the words that changed within each line, like `git diff --word-diff`
+    Word,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DiffOptions {
+    pub mode: DiffMode,
+    /// Emit ANSI colors instead of plain-text markers
+    pub color: bool,
+    /// Unchanged lines shown around each change
+    pub context: usize,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            mode: DiffMode::Line,
+            color: false,
+            context: 3,
+        }
+    }
+}
+
+/// Render the difference between `old` and `new`, or an empty string when equal.
+pub fn render(
+    old: &str,
+    new: &str,
+    old_label: &str,
+    new_label: &str,
+    options: &DiffOptions,
+) -> String {
+    if old == new {
+        return String::new();
+    }
+
+    let diff = TextDiff::from_lines(old, new);
+    match options.mode {
+        DiffMode::Line => {
+            let unified = diff
+                .unified_diff()
+                .context_radius(options.context)
+                .header(old_label, new_label)
+                .to_string();
+            if options.color {
+                colorize_unified(&unified)
+            } else {
+                unified
+            }
+        }
+        DiffMode::Word => render_words(&diff, old_label, new_label, options),
+    }
+}
+
+fn colorize_unified(unified: &str) -> String {
+    unified
+        .split_inclusive('\n')
+        .map(|line| {
+            let color = if line.starts_with("---") || line.starts_with("+++") {
+                BOLD
+            } else if line.starts_with("@@") {
+                CYAN
+            } else if line.starts_with('-') {
+                RED
+            } else if line.starts_with('+') {
+                GREEN
+            } else {
+                return line.to_string();
+            };
+            let (text, newline) = split_newline(line);
+            format!("{color}{text}{RESET}{newline}")
+        })
+        .collect()
+}
+
+fn render_words(
+    diff: &TextDiff<'_, '_, '_, str>,
+    old_label: &str,
+    new_label: &str,
+    options: &DiffOptions,
+) -> String {
+    let mut output = paint(
+        &format!("--- {old_label}\n+++ {new_label}\n"),
+        BOLD,
+        options,
+    );
+
+    for group in diff.grouped_ops(options.context) {
+        let (Some(first), Some(last)) = (group.first(), group.last()) else {
+            continue;
+        };
+        let old_start = first.old_range().start;
+        let new_start = first.new_range().start;
+        let header = format!(
+            "@@ -{},{} +{},{} @@\n",
+            old_start + 1,
+            last.old_range().end - old_start,
+            new_start + 1,
+            last.new_range().end - new_start,
+        );
+        output.push_str(&paint(&header, CYAN, options));
+
+        for op in &group {
+            let old_text: String = diff.old_slices()[op.old_range()].concat();
+            let new_text: String = diff.new_slices()[op.new_range()].concat();
+            match op.tag() {
+                DiffTag::Equal => output.push_str(&old_text),
+                DiffTag::Delete => output.push_str(&mark(&old_text, ChangeTag::Delete, options)),
+                DiffTag::Insert => output.push_str(&mark(&new_text, ChangeTag::Insert, options)),
+                DiffTag::Replace => {
+                    let words = TextDiff::from_words(old_text.as_str(), new_text.as_str());
+                    let mut run = String::new();
+                    let mut run_tag = ChangeTag::Equal;
+                    for change in words.iter_all_changes() {
+                        if change.tag() != run_tag {
+                            output.push_str(&mark(&run, run_tag, options));
+                            run.clear();
+                            run_tag = change.tag();
+                        }
+                        run.push_str(change.value());
+                    }
+                    output.push_str(&mark(&run, run_tag, options));
+                }
+            }
+            if !output.ends_with('\n') {
+                output.push('\n');
+            }
+        }
+    }
+
+    output
+}
+
+/// Wrap a run of changed text in color codes or `[-…-]`/`{+…+}` markers.
+/// Trailing newlines stay outside the markers so lines remain intact.
+fn mark(text: &str, tag: ChangeTag, options: &DiffOptions) -> String {
+    if text.is_empty() || tag == ChangeTag::Equal {
+        return text.to_string();
+    }
+
+    text.split_inclusive('\n')
+        .map(|line| {
+            let (text, newline) = split_newline(line);
+            if text.is_empty() {
+                return newline.to_string();
+            }
+            match (tag, options.color) {
+                (ChangeTag::Delete, true) => format!("{RED}{text}{RESET}{newline}"),
+                (ChangeTag::Insert, true) => format!("{GREEN}{text}{RESET}{newline}"),
+                (ChangeTag::Delete, false) => format!("[-{text}-]{newline}"),
+                (_, false) => format!("{{+{text}+}}{newline}"),
+                (ChangeTag::Equal, true) => line.to_string(),
+            }
+        })
+        .collect()
+}
+
+fn paint(text: &str, color: &str, options: &DiffOptions) -> String {
+    if !options.color {
+        return text.to_string();
+    }
+    text.split_inclusive('\n')
+        .map(|line| {
+            let (text, newline) = split_newline(line);
+            format!("{color}{text}{RESET}{newline}")
+        })
+        .collect()
+}
+
+fn split_newline(line: &str) -> (&str, &str) {
+    match line.strip_suffix('\n') {
+        Some(text) => (text, "\n"),
+        None => (line, ""),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_identical_is_empty() {
+        assert_eq!(render("a\n", "a\n", "a", "b", &DiffOptions::default()), "");
+    }
+
+    #[test]
+    fn test_render_line_diff() {
+        let output = render("a\nb\n", "a\nc\n", "old", "new", &DiffOptions::default());
+        assert_eq!(output, "--- old\n+++ new\n@@ -1,2 +1,2 @@\n a\n-b\n+c\n");
+    }
+
+    #[test]
+    fn test_render_word_diff() {
+        let options = DiffOptions {
+            mode: DiffMode::Word,
+            ..Default::default()
+        };
+        let output = render(
+            "Be concise.\nAlways cite sources.\n",
+            "Be concise.\nAlways cite primary sources.\n",
+            "old",
+            "new",
+            &options,
+        );
+        assert_eq!(
+            output,
+            "--- old\n+++ new\n@@ -1,2 +1,2 @@\nBe concise.\nAlways cite {+primary +}sources.\n"
+        );
+    }
+
+    #[test]
+    fn test_render_word_diff_colored() {
+        let options = DiffOptions {
+            mode: DiffMode::Word,
+            color: true,
+            ..Default::default()
+        };
+        let output = render("use tabs\n", "use spaces\n", "old", "new", &options);
+        assert!(output.contains(&format!("use {RED}tabs{RESET}{GREEN}spaces{RESET}\n")));
+    }
+}
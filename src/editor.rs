@@ -0,0 +1,192 @@
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+use anyhow::{Context, anyhow};
+
+use crate::storage::Storage;
+
+/// GUI editors that return immediately unless told to wait, with the flag that makes them block
+const WAIT_FLAGS: &[(&str, &str)] = &[
+    ("code", "--wait"),
+    ("code-insiders", "--wait"),
+    ("codium", "--wait"),
+    ("cursor", "--wait"),
+    ("windsurf", "--wait"),
+    ("zed", "--wait"),
+    ("atom", "--wait"),
+    ("subl", "--wait"),
+    ("mate", "-w"),
+    ("gvim", "-f"),
+    ("mvim", "-f"),
+];
+
+/// An editor program and the arguments placed before the file to edit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Editor {
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+impl Editor {
+    /// Resolve the editor from `editor_override`, the `[editor]` configuration,
+    /// `$EDITOR`, `$VISUAL` or a platform default
+    pub fn resolve(storage: &Storage, editor_override: Option<&str>) -> crate::Result<Self> {
+        if let Some(editor) = editor_override {
+            return Self::parse(editor);
+        }
+        let config = &storage.config.editor;
+        match &config.command {
+            Some(command) => Self::with_args(command, &config.args),
+            None => Self::parse(&get_editor()?),
+        }
+    }
+
+    /// Split an editor command line shell-style, e.g. `emacsclient -t` or `"my editor" --wait`
+    pub fn parse(command: &str) -> crate::Result<Self> {
+        Self::with_args(command, &[])
+    }
+
+    /// Split `command` like `parse` and add `extra` arguments after its own
+    fn with_args(command: &str, extra: &[String]) -> crate::Result<Self> {
+        let mut words = shlex::split(command)
+            .ok_or_else(|| anyhow!("Failed to parse editor command: {}", command))?
+            .into_iter();
+        let program = words
+            .next()
+            .ok_or_else(|| anyhow!("Editor command is empty"))?;
+        let mut editor = Self {
+            program,
+            args: words.chain(extra.iter().cloned()).collect(),
+        };
+
+        if let Some(flag) = editor.missing_wait_flag() {
+            editor.args.push(flag.to_string());
+        }
+        Ok(editor)
+    }
+
+    /// The wait flag a known GUI editor needs but was not given
+    fn missing_wait_flag(&self) -> Option<&'static str> {
+        let name = Path::new(&self.program).file_stem()?.to_str()?;
+        let (_, flag) = WAIT_FLAGS.iter().find(|(editor, _)| *editor == name)?;
+        let has_flag = self
+            .args
+            .iter()
+            .any(|arg| arg == flag || arg == "-w" || arg == "--wait" || arg == "-f");
+        (!has_flag).then_some(*flag)
+    }
+
+    /// Open `path` and wait for the editor to exit successfully
+    pub fn open(&self, path: &Path) -> crate::Result<()> {
+        let status = Command::new(&self.program)
+            .args(&self.args)
+            .arg(path)
+            .status()
+            .with_context(|| format!("Failed to execute editor: {}", self.program))?;
+
+        if !status.success() {
+            return Err(anyhow!("Editor exited with non-zero status"));
+        }
+        Ok(())
+    }
+}
+
+fn get_editor() -> crate::Result<String> {
+    // Try $EDITOR first
+    if let Ok(editor) = env::var("EDITOR")
+        && !editor.is_empty()
+    {
+        return Ok(editor);
+    }
+
+    // Try $VISUAL as fallback
+    if let Ok(editor) = env::var("VISUAL")
+        && !editor.is_empty()
+    {
+        return Ok(editor);
+    }
+
+    // Platform-specific defaults: the first common editor found on the PATH
+    let (lookup, editors, fallback) = if cfg!(windows) {
+        ("where", &["nvim", "vim", "nano"][..], Some("notepad"))
+    } else {
+        ("which", &["vi", "nano", "emacs"][..], None)
+    };
+    for editor in editors {
+        if Command::new(lookup)
+            .arg(editor)
+            .output()
+            .map(|o| o.status.success())
+            .unwrap_or(false)
+        {
+            return Ok(editor.to_string());
+        }
+    }
+
+    fallback
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("No editor found. Please set the EDITOR environment variable."))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_editor_with_env() {
+        unsafe {
+            env::set_var("EDITOR", "test-editor");
+            let result = get_editor();
+            assert!(result.is_ok());
+            assert_eq!(result.unwrap(), "test-editor");
+            env::remove_var("EDITOR");
+        }
+    }
+
+    #[test]
+    fn test_parse_splits_arguments() {
+        let editor = Editor::parse("emacsclient -t").unwrap();
+        assert_eq!(editor.program, "emacsclient");
+        assert_eq!(editor.args, vec!["-t"]);
+
+        let editor = Editor::parse("'/opt/My Editor/bin/edit' --new-window").unwrap();
+        assert_eq!(editor.program, "/opt/My Editor/bin/edit");
+        assert_eq!(editor.args, vec!["--new-window"]);
+
+        assert!(Editor::parse("").is_err());
+        assert!(Editor::parse("vim 'unterminated").is_err());
+    }
+
+    #[test]
+    fn test_parse_adds_wait_flag_for_gui_editors() {
+        assert_eq!(Editor::parse("code").unwrap().args, vec!["--wait"]);
+        assert_eq!(
+            Editor::parse("/usr/local/bin/subl -n").unwrap().args,
+            vec!["-n", "--wait"]
+        );
+        assert_eq!(Editor::parse("code -w").unwrap().args, vec!["-w"]);
+        assert!(Editor::parse("vim").unwrap().args.is_empty());
+    }
+
+    #[test]
+    fn test_resolve_prefers_configured_editor() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("storage");
+        std::fs::create_dir_all(path.join("repo")).unwrap();
+        std::fs::write(
+            path.join("config.toml"),
+            "[agents]\n\n[editor]\ncommand = \"code\"\nargs = [\"--wait\", \"--new-window\"]\n",
+        )
+        .unwrap();
+        let storage = Storage::new(path).unwrap();
+
+        let editor = Editor::resolve(&storage, None).unwrap();
+        assert_eq!(editor.program, "code");
+        assert_eq!(editor.args, vec!["--wait", "--new-window"]);
+        assert_eq!(
+            Editor::resolve(&storage, Some("vim")).unwrap().program,
+            "vim"
+        );
+    }
+}
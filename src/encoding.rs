@@ -0,0 +1,127 @@
+use std::path::Path;
+
+/// Text encodings recognized when reading profiles
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    /// UTF-8 with a leading byte order mark, which is dropped
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    /// Fallback for bytes that are not valid UTF-8, as produced by many Windows tools
+    Windows1252,
+}
+
+impl std::fmt::Display for Encoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf8Bom => "UTF-8 with BOM",
+            Encoding::Utf16Le => "UTF-16LE",
+            Encoding::Utf16Be => "UTF-16BE",
+            Encoding::Windows1252 => "Windows-1252",
+        })
+    }
+}
+
+/// Characters for bytes 0x80..=0x9F in Windows-1252; the rest match Latin-1
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8D}', 'Ž', '\u{8F}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9D}', 'ž', 'Ÿ',
+];
+
+/// Read a text file as UTF-8, dropping a byte order mark and transcoding other encodings.
+///
+/// A note naming the file is printed to stderr when it was not UTF-8.
+pub fn read_text(path: &Path) -> crate::Result<String> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+    let (text, encoding) = decode(&bytes)
+        .map_err(|e| anyhow::anyhow!("Failed to decode {}: {}", path.display(), e))?;
+
+    if !matches!(encoding, Encoding::Utf8 | Encoding::Utf8Bom) {
+        tracing::warn!(
+            "{} is encoded as {encoding}; converted it to UTF-8",
+            path.display()
+        );
+    }
+    Ok(text)
+}
+
+/// Decode `bytes`, detecting the encoding from a byte order mark or UTF-8 validity
+pub fn decode(bytes: &[u8]) -> crate::Result<(String, Encoding)> {
+    if let Some(rest) = bytes.strip_prefix(b"\xEF\xBB\xBF") {
+        let text = std::str::from_utf8(rest)
+            .map_err(|e| anyhow::anyhow!("invalid UTF-8 after byte order mark: {}", e))?;
+        return Ok((text.to_string(), Encoding::Utf8Bom));
+    }
+    if let Some(rest) = bytes.strip_prefix(b"\xFF\xFE") {
+        return Ok((decode_utf16(rest, u16::from_le_bytes)?, Encoding::Utf16Le));
+    }
+    if let Some(rest) = bytes.strip_prefix(b"\xFE\xFF") {
+        return Ok((decode_utf16(rest, u16::from_be_bytes)?, Encoding::Utf16Be));
+    }
+
+    match std::str::from_utf8(bytes) {
+        Ok(text) => Ok((text.to_string(), Encoding::Utf8)),
+        Err(_) => Ok((decode_windows_1252(bytes), Encoding::Windows1252)),
+    }
+}
+
+fn decode_utf16(bytes: &[u8], unit: fn([u8; 2]) -> u16) -> crate::Result<String> {
+    anyhow::ensure!(bytes.len().is_multiple_of(2), "truncated UTF-16 text");
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| unit([pair[0], pair[1]]))
+        .collect();
+    String::from_utf16(&units).map_err(|e| anyhow::anyhow!("invalid UTF-16: {}", e))
+}
+
+fn decode_windows_1252(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&byte| match byte {
+            0x80..=0x9F => WINDOWS_1252_HIGH[usize::from(byte - 0x80)],
+            _ => char::from(byte),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_utf8_and_bom() {
+        assert_eq!(
+            decode("# Plain ✓\n".as_bytes()).unwrap(),
+            ("# Plain ✓\n".to_string(), Encoding::Utf8)
+        );
+        assert_eq!(
+            decode(b"\xEF\xBB\xBF# Title\n").unwrap(),
+            ("# Title\n".to_string(), Encoding::Utf8Bom)
+        );
+    }
+
+    #[test]
+    fn test_decode_utf16() {
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend("Hé".encode_utf16().flat_map(u16::to_le_bytes));
+        assert_eq!(
+            decode(&bytes).unwrap(),
+            ("Hé".to_string(), Encoding::Utf16Le)
+        );
+
+        assert!(decode(b"\xFE\xFF\x00").is_err());
+    }
+
+    #[test]
+    fn test_decode_windows_1252() {
+        // “Smart quotes”, an em dash and café as saved by Windows tools
+        let bytes = b"\x93Smart quotes\x94 \x97 caf\xE9";
+        assert_eq!(
+            decode(bytes).unwrap(),
+            ("“Smart quotes” — café".to_string(), Encoding::Windows1252)
+        );
+    }
+}
@@ -0,0 +1,167 @@
+use anyhow::ensure;
+use regex::Regex;
+
+use crate::storage::Storage;
+
+/// Content of a profile with its `extends:` chain resolved.
+///
+/// The profile keeps its own front-matter; its body is composed onto the body of
+/// the profile it extends, which is resolved the same way first.
+pub fn resolve(storage: &Storage, name: &str) -> crate::Result<String> {
+    resolve_chain(storage, name, &mut Vec::new())
+}
+
+fn resolve_chain(storage: &Storage, name: &str, chain: &mut Vec<String>) -> crate::Result<String> {
+    if chain.iter().any(|seen| seen == name) {
+        chain.push(name.to_string());
+        anyhow::bail!("Profile inheritance cycle: {}", chain.join(" -> "));
+    }
+
+    let content = storage.get_profile_content(name)?;
+    // Front-matter that does not parse has no `extends`, so the profile is used
+    // on its own
+    let parent = crate::frontmatter::parse_metadata(&content)
+        .ok()
+        .and_then(|metadata| metadata.extends);
+    let Some(parent) = parent else {
+        return Ok(content);
+    };
+    ensure!(
+        storage.get_repo_path(&parent).is_ok(),
+        "Profile '{}' extends '{}', which does not exist",
+        name,
+        parent
+    );
+
+    chain.push(name.to_string());
+    let parent_content = resolve_chain(storage, &parent, chain)?;
+    chain.pop();
+
+    let (_, parent_body) = crate::frontmatter::split(&parent_content);
+    let (_, body) = crate::frontmatter::split(&content);
+    let frontmatter = &content[..content.len() - body.len()];
+    Ok(format!("{frontmatter}{}", compose(parent_body, body)))
+}
+
+/// Compose a child body onto its parent's: a child section replaces the parent
+/// section with the same heading, other child sections are appended, and text
+/// before the child's first heading follows the parent's
+pub fn compose(parent: &str, child: &str) -> String {
+    let (parent_intro, mut sections) = split_sections(parent);
+    let (child_intro, child_sections) = split_sections(child);
+
+    for (heading, text) in child_sections {
+        match sections
+            .iter_mut()
+            .find(|(existing, _)| *existing == heading)
+        {
+            Some(section) => section.1 = text,
+            None => sections.push((heading, text)),
+        }
+    }
+
+    let blocks: Vec<&str> = [parent_intro, child_intro]
+        .into_iter()
+        .chain(sections.iter().map(|(_, text)| *text))
+        .map(str::trim_end)
+        .filter(|block| !block.trim().is_empty())
+        .collect();
+    if blocks.is_empty() {
+        return String::new();
+    }
+    format!("{}\n", blocks.join("\n\n"))
+}
+
+/// Text before the first heading, then each heading line with the text up to the
+/// next heading. Headings inside fenced code blocks do not count.
+pub(crate) fn split_sections(body: &str) -> (&str, Vec<(String, &str)>) {
+    let heading = Regex::new(r"^ {0,3}#{1,6}(?:[ \t]|$)").unwrap();
+    let mut starts = Vec::new();
+    let mut fence: Option<&str> = None;
+    let mut offset = 0;
+
+    for line in body.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+        } else if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+        } else if heading.is_match(line) {
+            starts.push((offset, line.trim().to_string()));
+        }
+        offset += line.len();
+    }
+
+    let intro_end = starts.first().map_or(body.len(), |(start, _)| *start);
+    let sections = starts
+        .iter()
+        .enumerate()
+        .map(|(i, (start, heading))| {
+            let end = starts.get(i + 1).map_or(body.len(), |(next, _)| *next);
+            (heading.clone(), &body[*start..end])
+        })
+        .collect();
+    (&body[..intro_end], sections)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_compose_overrides_and_appends_sections() {
+        let parent = "Base rules.\n\n# Style\nBe terse.\n\n# Tools\nUse cargo.\n";
+        let child =
+            "Project X.\n\n# Tools\nUse bazel.\n```sh\n# not a heading\n```\n\n# Extra\nMore.\n";
+        assert_eq!(
+            compose(parent, child),
+            "Base rules.\n\nProject X.\n\n# Style\nBe terse.\n\n# Tools\nUse bazel.\n```sh\n# not a heading\n```\n\n# Extra\nMore.\n"
+        );
+    }
+
+    #[test]
+    fn test_resolve_chain_and_cycles() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::initialize(temp_dir.path().join("storage")).unwrap();
+        storage
+            .create_profile(
+                "base/engineering",
+                "---\ntitle: Base\n---\n# Style\nBe terse.\n",
+            )
+            .unwrap();
+        storage
+            .create_profile(
+                "rust",
+                "---\nextends: base/engineering\n---\n# Tools\nUse cargo.\n",
+            )
+            .unwrap();
+        storage
+            .create_profile(
+                "rust/strict",
+                "---\nextends: rust\n---\n# Style\nBe exact.\n",
+            )
+            .unwrap();
+
+        assert_eq!(
+            resolve(&storage, "rust/strict").unwrap(),
+            "---\nextends: rust\n---\n# Style\nBe exact.\n\n# Tools\nUse cargo.\n"
+        );
+
+        storage
+            .create_profile("a", "---\nextends: b\n---\nA\n")
+            .unwrap();
+        storage
+            .create_profile("b", "---\nextends: a\n---\nB\n")
+            .unwrap();
+        let error = resolve(&storage, "a").unwrap_err();
+        assert_eq!(error.to_string(), "Profile inheritance cycle: a -> b -> a");
+
+        storage
+            .create_profile("orphan", "---\nextends: gone\n---\nO\n")
+            .unwrap();
+        assert!(resolve(&storage, "orphan").is_err());
+    }
+}
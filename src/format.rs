@@ -0,0 +1,321 @@
+use regex::Regex;
+
+use crate::frontmatter::Syntax;
+
+#[derive(Debug, Clone, Default)]
+pub struct FormatOptions {
+    /// Wrap lines longer than this many characters; `None` disables wrapping
+    pub wrap_width: Option<usize>,
+}
+
+/// Normalize a profile's markdown.
+///
+/// - YAML front-matter keys are put in a canonical order
+/// - headings use the ATX style (`# Title`) and never skip a level
+/// - trailing whitespace and repeated blank lines are removed, except a hard
+///   line break (two trailing spaces) inside a paragraph
+/// - long paragraph and list lines are wrapped to `wrap_width`, never starting
+///   a line with a word that would turn it into a list item, heading or quote
+/// - the file ends with exactly one newline
+///
+/// Fenced code blocks are left untouched. Formatting is idempotent.
+pub fn format_profile(content: &str, options: &FormatOptions) -> String {
+    let content = content.replace("\r\n", "\n");
+    let (frontmatter, body) = crate::frontmatter::split_block(&content);
+
+    let mut output = String::new();
+    if let Some((syntax, frontmatter)) = frontmatter {
+        let frontmatter = match syntax {
+            Syntax::Yaml => crate::frontmatter::normalize_key_order(frontmatter),
+            Syntax::Toml | Syntax::Json => frontmatter.trim_end().to_string(),
+        };
+        output.push_str(&syntax.wrap(&frontmatter));
+    }
+
+    let body = format_body(body, options);
+    if !body.is_empty() {
+        if frontmatter.is_some() {
+            output.push('\n');
+        }
+        output.push_str(&body);
+        output.push('\n');
+    }
+
+    output
+}
+
+fn format_body(body: &str, options: &FormatOptions) -> String {
+    let atx = Regex::new(r"^ {0,3}(#{1,6})(?:[ \t]+(.*?))?(?:[ \t]+#+)?[ \t]*$").unwrap();
+    let setext = Regex::new(r"^ {0,3}(=+|-+)[ \t]*$").unwrap();
+
+    let mut lines: Vec<String> = Vec::new();
+    let mut fence: Option<String> = None;
+    let mut last_level = 0;
+    // Whether the previous line is paragraph text that a setext underline could apply to
+    let mut in_paragraph = false;
+
+    let raw_lines: Vec<&str> = body.lines().collect();
+    for (i, raw) in raw_lines.iter().copied().enumerate() {
+        if let Some(marker) = &fence {
+            if raw.trim_start().starts_with(marker.as_str()) {
+                fence = None;
+            }
+            lines.push(raw.to_string());
+            continue;
+        }
+
+        let line = raw.trim_end();
+        if let Some(marker) = fence_marker(line) {
+            fence = Some(marker);
+            in_paragraph = false;
+            lines.push(line.to_string());
+            continue;
+        }
+
+        if line.is_empty() {
+            in_paragraph = false;
+            if lines.last().is_some_and(|last| !last.is_empty()) {
+                lines.push(String::new());
+            }
+            continue;
+        }
+
+        let heading = if let Some(caps) = atx.captures(line) {
+            let text = caps.get(2).map(|m| m.as_str().trim()).unwrap_or_default();
+            Some((caps[1].len(), text.to_string()))
+        } else if in_paragraph && setext.is_match(line) {
+            let level = if line.trim_start().starts_with('=') {
+                1
+            } else {
+                2
+            };
+            let text = lines.pop().unwrap_or_default();
+            Some((level, text.trim().to_string()))
+        } else {
+            None
+        };
+
+        if let Some((level, text)) = heading {
+            let level = if last_level == 0 {
+                level
+            } else {
+                level.min(last_level + 1)
+            };
+            last_level = level;
+            in_paragraph = false;
+
+            if lines.last().is_some_and(|last| !last.is_empty()) {
+                lines.push(String::new());
+            }
+            let hashes = "#".repeat(level);
+            if text.is_empty() {
+                lines.push(hashes);
+            } else {
+                lines.push(format!("{hashes} {text}"));
+            }
+            lines.push(String::new());
+            continue;
+        }
+
+        in_paragraph = is_paragraph_text(line);
+        let mut wrapped = match options.wrap_width {
+            Some(width) if width > 0 && is_wrappable(line) => wrap_line(line, width),
+            _ => vec![line.to_string()],
+        };
+        // A hard line break only means something when more text follows
+        let continues = raw_lines.get(i + 1).is_some_and(|next| {
+            !next.trim().is_empty() && !atx.is_match(next) && fence_marker(next).is_none()
+        });
+        if raw.ends_with("  ")
+            && continues
+            && let Some(last) = wrapped.last_mut()
+        {
+            last.push_str("  ");
+        }
+        lines.extend(wrapped);
+    }
+
+    while lines.last().is_some_and(|last| last.is_empty()) {
+        lines.pop();
+    }
+    let first_content = lines.iter().position(|line| !line.is_empty());
+    lines.drain(..first_content.unwrap_or(lines.len()));
+
+    lines.join("\n")
+}
+
+fn fence_marker(line: &str) -> Option<String> {
+    let trimmed = line.trim_start();
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    ["```", "~~~"]
+        .into_iter()
+        .find(|marker| trimmed.starts_with(marker))
+        .map(str::to_string)
+}
+
+fn is_paragraph_text(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    !(line.starts_with("    ")
+        || line.starts_with('\t')
+        || trimmed.starts_with(['|', '<', '>'])
+        || list_marker_width(trimmed).is_some())
+}
+
+fn is_wrappable(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    if line.starts_with('\t') || trimmed.starts_with(['|', '<']) {
+        return false;
+    }
+    let indent = line.len() - trimmed.len();
+    indent < 4 || list_marker_width(trimmed).is_some()
+}
+
+/// Width of a list marker (`- `, `* `, `+ `, `1. `) at the start of `text`
+fn list_marker_width(text: &str) -> Option<usize> {
+    if text.starts_with("- ") || text.starts_with("* ") || text.starts_with("+ ") {
+        return Some(2);
+    }
+    let digits = text.chars().take_while(|c| c.is_ascii_digit()).count();
+    let rest = &text[digits..];
+    (digits > 0 && (rest.starts_with(". ") || rest.starts_with(") "))).then_some(digits + 2)
+}
+
+/// Whether a line starting with `word` would begin a list item, heading, quote,
+/// fence or setext underline instead of continuing a paragraph
+fn starts_block(word: &str) -> bool {
+    let digits = word.chars().take_while(|c| c.is_ascii_digit()).count();
+    word.starts_with(['#', '>'])
+        || word.starts_with("```")
+        || word.starts_with("~~~")
+        || word
+            .chars()
+            .all(|c| matches!(c, '-' | '*' | '+' | '=' | '_'))
+        || (digits > 0 && matches!(&word[digits..], "." | ")"))
+}
+
+fn wrap_line(line: &str, width: usize) -> Vec<String> {
+    if line.chars().count() <= width {
+        return vec![line.to_string()];
+    }
+
+    let trimmed = line.trim_start();
+    let indent = &line[..line.len() - trimmed.len()];
+    let (first_prefix, rest_prefix, text) = if let Some(quoted) = trimmed.strip_prefix("> ") {
+        (format!("{indent}> "), format!("{indent}> "), quoted)
+    } else if let Some(marker) = list_marker_width(trimmed) {
+        (
+            format!("{indent}{}", &trimmed[..marker]),
+            format!("{indent}{}", " ".repeat(marker)),
+            &trimmed[marker..],
+        )
+    } else {
+        (indent.to_string(), indent.to_string(), trimmed)
+    };
+
+    let mut wrapped = Vec::new();
+    let mut current = first_prefix.clone();
+    let mut current_has_words = false;
+    for word in text.split_whitespace() {
+        let needed =
+            current.chars().count() + usize::from(current_has_words) + word.chars().count();
+        if current_has_words && needed > width && !starts_block(word) {
+            wrapped.push(std::mem::replace(&mut current, rest_prefix.clone()));
+            current_has_words = false;
+        }
+        if current_has_words {
+            current.push(' ');
+        }
+        current.push_str(word);
+        current_has_words = true;
+    }
+    wrapped.push(current);
+    wrapped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fmt(content: &str) -> String {
+        format_profile(content, &FormatOptions::default())
+    }
+
+    #[test]
+    fn test_format_headings() {
+        let content = "Title\n=====\nintro\n\n###  Skipped level ##\nSub\n---\ntext";
+        assert_eq!(
+            fmt(content),
+            "# Title\n\nintro\n\n## Skipped level\n\n## Sub\n\ntext\n"
+        );
+    }
+
+    #[test]
+    fn test_format_whitespace_and_trailing_newline() {
+        let content = "\n\nline one   \n\n\n\nline two";
+        assert_eq!(fmt(content), "line one\n\nline two\n");
+    }
+
+    #[test]
+    fn test_format_preserves_code_blocks() {
+        let content = "```\n# not a heading   \n\n\n```\n";
+        assert_eq!(fmt(content), content);
+    }
+
+    #[test]
+    fn test_format_frontmatter() {
+        let content = "---\ntags: [a]\ntitle: T\n---\n# Body";
+        assert_eq!(fmt(content), "---\ntitle: T\ntags: [a]\n---\n\n# Body\n");
+
+        let content = "+++\ntags = [\"a\"]\ntitle = \"T\"\n+++\n# Body";
+        assert_eq!(
+            fmt(content),
+            "+++\ntags = [\"a\"]\ntitle = \"T\"\n+++\n\n# Body\n"
+        );
+        let content = "{\n  \"title\": \"T\"\n}\n# Body";
+        assert_eq!(fmt(content), "{\n  \"title\": \"T\"\n}\n\n# Body\n");
+    }
+
+    #[test]
+    fn test_format_wraps_lines() {
+        let options = FormatOptions {
+            wrap_width: Some(20),
+        };
+        let content = "- one two three four five six\n| table row that is long |";
+        assert_eq!(
+            format_profile(content, &options),
+            "- one two three four\n  five six\n| table row that is long |\n"
+        );
+    }
+
+    #[test]
+    fn test_format_wrap_keeps_paragraphs() {
+        let options = FormatOptions {
+            wrap_width: Some(12),
+        };
+        let content = "see item - one\nstep 1. then # two\ngo to item 2. now\n";
+        let formatted = format_profile(content, &options);
+        assert_eq!(
+            formatted,
+            "see item -\none\nstep 1. then #\ntwo\ngo to item 2.\nnow\n"
+        );
+        assert_eq!(format_profile(&formatted, &options), formatted);
+
+        let content = "first line  \nsecond line that wraps  \n\nend  \n";
+        assert_eq!(
+            format_profile(content, &options),
+            "first line  \nsecond line\nthat wraps\n\nend\n"
+        );
+    }
+
+    #[test]
+    fn test_format_is_idempotent() {
+        let options = FormatOptions {
+            wrap_width: Some(30),
+        };
+        let content = "Intro\n===\n\n> a quoted line that goes on for quite a while\n\n1. numbered item that is rather long too\n";
+        let once = format_profile(content, &options);
+        assert_eq!(format_profile(&once, &options), once);
+    }
+}
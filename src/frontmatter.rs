@@ -0,0 +1,439 @@
+/// Syntax of a front-matter block, announced by its opening line: `---` for YAML,
+/// `+++` for TOML and `---json` for JSON, closed by a `---` line. A JSON fence
+/// must be explicit so that a body starting with `{` is not taken for metadata.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Syntax {
+    Yaml,
+    Toml,
+    Json,
+}
+
+impl Syntax {
+    const ALL: [Syntax; 3] = [Syntax::Yaml, Syntax::Toml, Syntax::Json];
+
+    fn delimiters(self) -> (&'static str, &'static str) {
+        match self {
+            Syntax::Yaml => ("---", "---"),
+            Syntax::Toml => ("+++", "+++"),
+            Syntax::Json => ("---json", "---"),
+        }
+    }
+
+    /// Line that closes the block
+    pub fn closing(self) -> &'static str {
+        self.delimiters().1
+    }
+
+    /// Syntax whose opening line starts `content`, whether or not the block is closed
+    pub fn detect(content: &str) -> Option<Self> {
+        let first = content.lines().next()?.trim_end();
+        Self::ALL
+            .into_iter()
+            .find(|syntax| syntax.delimiters().0 == first)
+    }
+
+    /// A block as returned by [`split_block`], with its delimiters and trailing newline
+    pub fn wrap(self, block: &str) -> String {
+        let (opening, closing) = self.delimiters();
+        if block.is_empty() {
+            format!("{opening}\n{closing}\n")
+        } else {
+            format!("{opening}\n{block}\n{closing}\n")
+        }
+    }
+}
+
+/// Preferred ordering of well-known front-matter keys. Keys not listed here
+/// are placed after these, sorted alphabetically.
+pub(crate) const KEY_ORDER: &[&str] = &[
+    "title",
+    "extends",
+    "description",
+    "author",
+    "license",
+    "source",
+    "tags",
+    "agents",
+    "audience",
+    "priority",
+    "review_by",
+    "expires",
+    "arguments",
+];
+
+/// Accepted values of the `audience` field, matching MCP roles
+pub const AUDIENCES: &[&str] = &["user", "assistant"];
+
+/// Well-known front-matter fields of a profile; anything else is kept in `extra`
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ProfileMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Profile whose body this one builds on, section by section
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// License the prompt is shared under, e.g. `CC-BY-4.0`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    /// Where the prompt was copied from, for attribution
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub agents: Vec<String>,
+    /// Who the prompt is meant for over MCP: `user`, `assistant` or both
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub audience: Vec<String>,
+    /// Importance between 0.0 (optional) and 1.0 (essential), passed on to MCP clients
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<f32>,
+    /// Date (`YYYY-MM-DD`) after which the prompt should be looked at again
+    #[serde(
+        default,
+        deserialize_with = "date",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub review_by: Option<String>,
+    /// Date (`YYYY-MM-DD`) after which the prompt should no longer be used
+    #[serde(
+        default,
+        deserialize_with = "date",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub expires: Option<String>,
+    /// Store the profile encrypted and ask before writing it to an agent file
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub sensitive: bool,
+    /// Declarations for the profile's `<{{NAME}}>` placeholders, keyed by name
+    #[serde(default, skip_serializing_if = "std::collections::BTreeMap::is_empty")]
+    pub arguments: std::collections::BTreeMap<String, ArgumentSpec>,
+    #[serde(flatten)]
+    pub extra: std::collections::BTreeMap<String, serde_yaml::Value>,
+}
+
+/// What a profile declares about one of its placeholders
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ArgumentSpec {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Value used when the argument is not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<serde_yaml::Value>,
+    /// Expected kind of value, e.g. `string` or `number`
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    /// Whether a value must be given; by default, when there is no `default`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub required: Option<bool>,
+    /// Values the argument is limited to
+    #[serde(default, rename = "enum", skip_serializing_if = "Vec::is_empty")]
+    pub allowed: Vec<serde_yaml::Value>,
+}
+
+impl ProfileMetadata {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Whether the profile has expired or is due for review on `today` (`YYYY-MM-DD`)
+    pub fn staleness(&self, today: &str) -> Option<Staleness> {
+        let past = |date: &Option<String>| {
+            date.as_ref()
+                .filter(|date| is_date(date) && date.as_str() <= today)
+                .cloned()
+        };
+        past(&self.expires)
+            .map(Staleness::Expired)
+            .or_else(|| past(&self.review_by).map(Staleness::ReviewDue))
+    }
+}
+
+/// Why a profile needs attention
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Staleness {
+    Expired(String),
+    ReviewDue(String),
+}
+
+impl std::fmt::Display for Staleness {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Staleness::Expired(date) => write!(f, "expired {date}"),
+            Staleness::ReviewDue(date) => write!(f, "review due {date}"),
+        }
+    }
+}
+
+/// Whether `value` is a `YYYY-MM-DD` date
+pub fn is_date(value: &str) -> bool {
+    let bytes = value.as_bytes();
+    bytes.len() == 10
+        && bytes.iter().enumerate().all(|(i, b)| match i {
+            4 | 7 => *b == b'-',
+            _ => b.is_ascii_digit(),
+        })
+}
+
+/// Dates are strings in YAML and JSON but a datetime type in TOML
+fn date<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    enum Date {
+        Text(String),
+        Toml(toml::value::Datetime),
+    }
+
+    Ok(
+        <Option<Date> as serde::Deserialize>::deserialize(deserializer)?.map(|date| match date {
+            Date::Text(text) => text,
+            Date::Toml(datetime) => datetime.to_string(),
+        }),
+    )
+}
+
+/// Parse the front-matter of profile content, in any supported syntax; content
+/// without one has empty metadata
+pub fn parse_metadata(content: &str) -> crate::Result<ProfileMetadata> {
+    let (Some((syntax, frontmatter)), _) = split_block(content) else {
+        return Ok(ProfileMetadata::default());
+    };
+    if frontmatter.trim().is_empty() {
+        return Ok(ProfileMetadata::default());
+    }
+
+    let metadata = match syntax {
+        Syntax::Yaml => serde_yaml::from_str(frontmatter).map_err(anyhow::Error::from),
+        Syntax::Toml => toml::from_str(frontmatter).map_err(anyhow::Error::from),
+        Syntax::Json => serde_json::from_str(frontmatter).map_err(anyhow::Error::from),
+    };
+    metadata.map_err(|e| anyhow::anyhow!("Failed to parse front-matter: {}", e))
+}
+
+/// Split profile content into its front-matter block (without delimiters) and body.
+///
+/// Returns `None` for the front-matter when the content does not start with an
+/// opening delimiter or the block is never closed.
+pub fn split(content: &str) -> (Option<&str>, &str) {
+    let (frontmatter, body) = split_block(content);
+    (frontmatter.map(|(_, frontmatter)| frontmatter), body)
+}
+
+/// Profile content as agents and MCP clients receive it: the body without the
+/// front-matter block, in any syntax, or the blank lines after it
+pub fn body(content: &str) -> &str {
+    match split_block(content) {
+        (Some(_), body) => body.trim_start_matches(['\n', '\r']),
+        (None, body) => body,
+    }
+}
+
+/// Like [`split`], also telling which syntax the block uses
+pub fn split_block(content: &str) -> (Option<(Syntax, &str)>, &str) {
+    let Some(syntax) = Syntax::detect(content) else {
+        return (None, content);
+    };
+    let (opening, closing) = syntax.delimiters();
+    let Some(rest) = strip_delimiter_line(content, opening) else {
+        return (None, content);
+    };
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == closing {
+            let frontmatter = rest[..offset].trim_end_matches(['\n', '\r']);
+            let body = &rest[offset + line.len()..];
+            return (Some((syntax, frontmatter)), body);
+        }
+        offset += line.len();
+    }
+
+    (None, content)
+}
+
+fn strip_delimiter_line<'a>(content: &'a str, delimiter: &str) -> Option<&'a str> {
+    let rest = content.strip_prefix(delimiter)?;
+    rest.strip_prefix('\n')
+        .or_else(|| rest.strip_prefix("\r\n"))
+}
+
+/// Reorder the top-level keys of a YAML front-matter block.
+///
+/// Each top-level key is moved together with its nested lines and any comment
+/// lines directly preceding it, so the block stays semantically unchanged.
+pub fn normalize_key_order(frontmatter: &str) -> String {
+    let mut entries: Vec<(String, Vec<&str>)> = Vec::new();
+    let mut pending: Vec<&str> = Vec::new();
+
+    for line in frontmatter.lines() {
+        if let Some(key) = top_level_key(line) {
+            let mut lines = std::mem::take(&mut pending);
+            lines.push(line);
+            entries.push((key.to_string(), lines));
+        } else if line.trim_start().starts_with('#') || line.trim().is_empty() {
+            pending.push(line);
+        } else if let Some((_, lines)) = entries.last_mut() {
+            lines.append(&mut pending);
+            lines.push(line);
+        } else {
+            pending.push(line);
+        }
+    }
+
+    entries.sort_by_key(|(key, _)| key_rank(key));
+
+    let mut output: Vec<&str> = entries
+        .iter()
+        .flat_map(|(_, lines)| lines.iter().copied())
+        .collect();
+    output.extend(pending.iter().filter(|line| !line.trim().is_empty()));
+    output.join("\n")
+}
+
+fn top_level_key(line: &str) -> Option<&str> {
+    if line.starts_with([' ', '\t', '#', '-']) {
+        return None;
+    }
+    let (key, _) = line.split_once(':')?;
+    let key = key.trim().trim_matches(['"', '\'']);
+    (!key.is_empty()).then_some(key)
+}
+
+fn key_rank(key: &str) -> (usize, String) {
+    let position = KEY_ORDER
+        .iter()
+        .position(|known| *known == key)
+        .unwrap_or(KEY_ORDER.len());
+    let tiebreak = if position == KEY_ORDER.len() {
+        key.to_string()
+    } else {
+        String::new()
+    };
+    (position, tiebreak)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_with_frontmatter() {
+        let content = "---\ntitle: Review\ntags: [a]\n---\n# Body\n";
+        let (frontmatter, body) = split(content);
+        assert_eq!(frontmatter, Some("title: Review\ntags: [a]"));
+        assert_eq!(body, "# Body\n");
+    }
+
+    #[test]
+    fn test_split_without_frontmatter() {
+        let content = "# Body\n---\nmore\n";
+        assert_eq!(split(content), (None, content));
+
+        let unterminated = "---\ntitle: Review\n";
+        assert_eq!(split(unterminated), (None, unterminated));
+    }
+
+    #[test]
+    fn test_parse_metadata() {
+        let content = "---\ntitle: Review\nlicense: CC-BY-4.0\nsource: https://example.com/prompts\ntags: [a, b]\nreviewed: true\n---\nBody\n";
+        let metadata = parse_metadata(content).unwrap();
+        assert_eq!(metadata.title.as_deref(), Some("Review"));
+        assert_eq!(metadata.license.as_deref(), Some("CC-BY-4.0"));
+        assert_eq!(
+            metadata.source.as_deref(),
+            Some("https://example.com/prompts")
+        );
+        assert_eq!(metadata.tags, vec!["a", "b"]);
+        assert_eq!(metadata.extra["reviewed"], serde_yaml::Value::Bool(true));
+
+        assert!(parse_metadata("# Just a body\n").unwrap().is_empty());
+        assert!(parse_metadata("---\ntags: {\n---\n").is_err());
+    }
+
+    #[test]
+    fn test_toml_and_json_frontmatter() {
+        let toml = "+++\ntitle = \"Review\"\ntags = [\"a\"]\npriority = 0.5\n+++\n# Body\n";
+        assert_eq!(
+            split_block(toml),
+            (
+                Some((
+                    Syntax::Toml,
+                    "title = \"Review\"\ntags = [\"a\"]\npriority = 0.5"
+                )),
+                "# Body\n"
+            )
+        );
+        let metadata = parse_metadata(toml).unwrap();
+        assert_eq!(metadata.title.as_deref(), Some("Review"));
+        assert_eq!(metadata.tags, vec!["a"]);
+        assert_eq!(metadata.priority, Some(0.5));
+
+        let json = "---json\n{\n  \"title\": \"Review\",\n  \"reviewed\": true\n}\n---\n# Body\n";
+        assert_eq!(
+            split(json),
+            (
+                Some("{\n  \"title\": \"Review\",\n  \"reviewed\": true\n}"),
+                "# Body\n"
+            )
+        );
+        let metadata = parse_metadata(json).unwrap();
+        assert_eq!(metadata.title.as_deref(), Some("Review"));
+        assert_eq!(metadata.extra["reviewed"], serde_yaml::Value::Bool(true));
+
+        assert!(parse_metadata("+++\ntitle = \n+++\n").is_err());
+        assert_eq!(Syntax::detect("+++\ntitle = 1\n"), Some(Syntax::Toml));
+        assert_eq!(Syntax::Json.wrap("{}"), "---json\n{}\n---\n");
+
+        // A body that happens to start with a JSON object has no front-matter
+        let body = "{\n  \"example\": true\n}\nRespond in this shape.\n";
+        assert_eq!(split(body), (None, body));
+        assert_eq!(parse_metadata(body).unwrap(), ProfileMetadata::default());
+        assert_eq!(Syntax::Toml.wrap("a = 1"), "+++\na = 1\n+++\n");
+    }
+
+    #[test]
+    fn test_body_leaves_out_every_syntax() {
+        assert_eq!(body("---\ntitle: T\n---\n\n# Body\n"), "# Body\n");
+        assert_eq!(body("+++\ntitle = \"T\"\n+++\n# Body\n"), "# Body\n");
+        assert_eq!(
+            body("---json\n{\"title\": \"T\"}\n---\n# Body\n"),
+            "# Body\n"
+        );
+        assert_eq!(body("\n# Body\n"), "\n# Body\n");
+    }
+
+    #[test]
+    fn test_staleness() {
+        let yaml =
+            parse_metadata("---\nreview_by: 2026-03-01\nexpires: 2026-06-01\n---\n").unwrap();
+        assert_eq!(yaml.staleness("2026-02-28"), None);
+        assert_eq!(
+            yaml.staleness("2026-03-01"),
+            Some(Staleness::ReviewDue("2026-03-01".to_string()))
+        );
+        assert_eq!(
+            yaml.staleness("2026-07-01"),
+            Some(Staleness::Expired("2026-06-01".to_string()))
+        );
+
+        let toml = parse_metadata("+++\nexpires = 2026-06-01\n+++\n").unwrap();
+        assert_eq!(toml.expires.as_deref(), Some("2026-06-01"));
+
+        let invalid = parse_metadata("---\nexpires: soon\n---\n").unwrap();
+        assert_eq!(invalid.staleness("2030-01-01"), None);
+        assert!(!is_date("soon") && is_date("2026-06-01"));
+    }
+
+    #[test]
+    fn test_normalize_key_order() {
+        let frontmatter = "zeta: 1\ntags:\n  - a\n  - b\n# who wrote it\nauthor: me\ntitle: T";
+        assert_eq!(
+            normalize_key_order(frontmatter),
+            "title: T\n# who wrote it\nauthor: me\ntags:\n  - a\n  - b\nzeta: 1"
+        );
+    }
+}
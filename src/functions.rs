@@ -0,0 +1,113 @@
+use std::process::{Command, Stdio};
+
+use anyhow::ensure;
+use regex::Regex;
+
+use crate::storage::Storage;
+
+/// A template function call: `<{{fn:NAME ARGS...}}>` runs `pmx-NAME ARGS...`
+pub const CALL_PATTERN: &str = r"<\{\{fn:([A-Za-z0-9_-]+)([^}]*)\}\}>";
+
+/// Function name and whitespace-separated arguments of each call in `content`
+pub fn calls(content: &str) -> Vec<(String, Vec<String>)> {
+    Regex::new(CALL_PATTERN)
+        .unwrap()
+        .captures_iter(content)
+        .map(|captures| {
+            let args = captures[2].split_whitespace().map(str::to_string).collect();
+            (captures[1].to_string(), args)
+        })
+        .collect()
+}
+
+/// Replace each function call with the output of its extension, trailing
+/// whitespace trimmed. Calls run in order of appearance; any failure aborts.
+pub fn expand(storage: &Storage, content: &str) -> crate::Result<String> {
+    let re = Regex::new(CALL_PATTERN).unwrap();
+    let mut expanded = String::with_capacity(content.len());
+    let mut last = 0;
+    for captures in re.captures_iter(content) {
+        let whole = captures.get(0).unwrap();
+        expanded.push_str(&content[last..whole.start()]);
+        expanded.push_str(&call(
+            storage,
+            &captures[1],
+            captures[2].split_whitespace(),
+        )?);
+        last = whole.end();
+    }
+    expanded.push_str(&content[last..]);
+    Ok(expanded)
+}
+
+fn call<'a>(
+    storage: &Storage,
+    name: &str,
+    args: impl Iterator<Item = &'a str>,
+) -> crate::Result<String> {
+    ensure!(
+        storage.is_function_allowed(name),
+        "Template function '{}' is not allowed. Add it to both 'allowed_subcommands' and 'functions' under [extensions] in config.toml",
+        name
+    );
+
+    let binary = format!("pmx-{name}");
+    let output = Command::new(&binary)
+        .args(args)
+        .env("PMX_FUNCTION", name)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| anyhow::anyhow!("Failed to run template function '{}': {}", binary, e))?;
+    ensure!(
+        output.status.success(),
+        "Template function '{}' failed: {}",
+        name,
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .trim_end()
+        .to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{Config, ExtensionsConfig};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_calls() {
+        let content = "Ticket: <{{fn:jira TICKET-123}}>\nNow: <{{fn:clock}}> for <{{NAME}}>\n";
+        assert_eq!(
+            calls(content),
+            vec![
+                ("jira".to_string(), vec!["TICKET-123".to_string()]),
+                ("clock".to_string(), Vec::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_expand_requires_both_allowlists() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("storage");
+        Storage::initialize(path.clone()).unwrap();
+        Config {
+            extensions: ExtensionsConfig {
+                allowed_subcommands: vec!["jira".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .persist(&path)
+        .unwrap();
+        let storage = Storage::new(path).unwrap();
+
+        assert_eq!(
+            expand(&storage, "Hello <{{NAME}}>\n").unwrap(),
+            "Hello <{{NAME}}>\n"
+        );
+        let error = expand(&storage, "<{{fn:jira TICKET-123}}>").unwrap_err();
+        assert!(error.to_string().contains("'jira' is not allowed"));
+    }
+}
@@ -0,0 +1,174 @@
+use std::path::Path;
+
+use anyhow::ensure;
+
+/// Identity used for history commits when git has none configured
+const FALLBACK_IDENTITY: [&str; 4] = ["-c", "user.name=pmx", "-c", "user.email=pmx@localhost"];
+
+/// One commit touching a profile
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub revision: String,
+    pub date: String,
+    pub subject: String,
+}
+
+/// Whether `repo/` has its own git repository
+pub fn is_enabled(repo: &Path) -> bool {
+    repo.join(".git").exists()
+}
+
+/// Commit the current state of `path` (relative to `repo/`), creating the
+/// repository on first use. Nothing is committed when `path` is unchanged. A
+/// path in a checked-out submodule is committed to the submodule, since the
+/// outer repository only records which commit of it to use.
+pub fn commit(repo: &Path, path: &Path, message: &str) -> crate::Result<()> {
+    let modules = crate::submodules::paths(repo)?;
+    let name = path.to_string_lossy().replace('\\', "/");
+    if let Some(module) = crate::submodules::containing(&modules, &name)
+        && crate::submodules::is_checked_out(repo, module)
+    {
+        let inner = path.strip_prefix(module).unwrap_or(path);
+        return commit(&repo.join(module), inner, message);
+    }
+
+    if !is_enabled(repo) {
+        crate::utils::git(repo, &["init", "--quiet"])?;
+    }
+
+    let path = path.to_string_lossy();
+    let status = crate::utils::git(repo, &["status", "--porcelain", "--", &path])?;
+    if status.trim().is_empty() {
+        return Ok(());
+    }
+
+    crate::utils::git(repo, &["add", "--all", "--", &path])?;
+    let mut args: Vec<&str> = identity(repo);
+    args.extend([
+        "commit",
+        "--quiet",
+        "--no-verify",
+        "-m",
+        message,
+        "--",
+        &path,
+    ]);
+    crate::utils::git(repo, &args)?;
+    Ok(())
+}
+
+/// Arguments that give git an identity to commit with when none is configured
+pub(crate) fn identity(repo: &Path) -> Vec<&'static str> {
+    if crate::utils::git(repo, &["config", "user.email"]).is_ok() {
+        Vec::new()
+    } else {
+        FALLBACK_IDENTITY.to_vec()
+    }
+}
+
+/// Commits touching `path`, newest first
+pub fn log(repo: &Path, path: &Path) -> crate::Result<Vec<Entry>> {
+    let output = crate::utils::git(
+        repo,
+        &[
+            "log",
+            "--format=%h%x09%ad%x09%s",
+            "--date=short",
+            "--",
+            &path.to_string_lossy(),
+        ],
+    )?;
+    Ok(output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            Some(Entry {
+                revision: fields.next()?.to_string(),
+                date: fields.next()?.to_string(),
+                subject: fields.next().unwrap_or_default().to_string(),
+            })
+        })
+        .collect())
+}
+
+/// Whether `path` existed at `revision`
+pub fn exists_at(repo: &Path, revision: &str, path: &Path) -> bool {
+    let object = format!("{revision}:{}", path.to_string_lossy().replace('\\', "/"));
+    crate::utils::git(repo, &["cat-file", "-e", &object]).is_ok()
+}
+
+/// Put `path` back as it was at `revision`
+pub fn restore(repo: &Path, revision: &str, path: &Path) -> crate::Result<()> {
+    ensure!(
+        exists_at(repo, revision, path),
+        "{} does not exist at revision '{}'",
+        path.display(),
+        revision
+    );
+    crate::utils::git(repo, &["checkout", revision, "--", &path.to_string_lossy()])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_commit_log_and_restore() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = temp_dir.path();
+        let file = Path::new("rust.md");
+
+        std::fs::write(repo.join(file), "First\n").unwrap();
+        commit(repo, file, "Create rust").unwrap();
+        assert!(is_enabled(repo));
+        std::fs::write(repo.join(file), "Second\n").unwrap();
+        commit(repo, file, "Update rust").unwrap();
+        commit(repo, file, "Nothing changed").unwrap();
+
+        let entries = log(repo, file).unwrap();
+        let subjects: Vec<_> = entries.iter().map(|e| e.subject.as_str()).collect();
+        assert_eq!(subjects, vec!["Update rust", "Create rust"]);
+
+        restore(repo, &entries[1].revision, file).unwrap();
+        assert_eq!(std::fs::read_to_string(repo.join(file)).unwrap(), "First\n");
+        assert!(restore(repo, &entries[1].revision, Path::new("gone.md")).is_err());
+    }
+
+    #[test]
+    fn test_commit_in_submodule() {
+        let temp_dir = TempDir::new().unwrap();
+        let shared = temp_dir.path().join("shared");
+        std::fs::create_dir(&shared).unwrap();
+        std::fs::write(shared.join("review.md"), "Review\n").unwrap();
+        commit(&shared, Path::new("review.md"), "Add review").unwrap();
+
+        let repo = temp_dir.path().join("repo");
+        std::fs::create_dir(&repo).unwrap();
+        std::fs::write(repo.join("base.md"), "Base\n").unwrap();
+        commit(&repo, Path::new("base.md"), "Add base").unwrap();
+        crate::utils::git(
+            &repo,
+            &[
+                "-c",
+                "protocol.file.allow=always",
+                "submodule",
+                "add",
+                "--quiet",
+                &shared.to_string_lossy(),
+                "team",
+            ],
+        )
+        .unwrap();
+
+        std::fs::write(repo.join("team/review.md"), "Review carefully\n").unwrap();
+        commit(&repo, Path::new("team/review.md"), "Update review").unwrap();
+        let subjects: Vec<_> = log(&repo.join("team"), Path::new("review.md"))
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.subject)
+            .collect();
+        assert_eq!(subjects, ["Update review", "Add review"]);
+    }
+}
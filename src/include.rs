@@ -0,0 +1,237 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{bail, ensure};
+use regex::Regex;
+
+use crate::storage::Storage;
+
+fn directive() -> Regex {
+    Regex::new(r"\{\{\s*include:\s*([^}\s]+)\s*\}\}").unwrap()
+}
+
+/// Names referenced by `{{include: other/profile}}` directives, in order of appearance
+pub fn directives(content: &str) -> Vec<String> {
+    directive()
+        .captures_iter(content)
+        .map(|captures| captures[1].to_string())
+        .collect()
+}
+
+/// Replace the include directives in `content`, the content of profile `name`, with
+/// the bodies of the profiles they name, which are expanded the same way first.
+/// Cycles, missing profiles and nesting deeper than `[includes] max_depth` are errors.
+pub fn expand(storage: &Storage, name: &str, content: &str) -> crate::Result<String> {
+    expand_chain(storage, content, &mut vec![name.to_string()])
+}
+
+fn expand_chain(
+    storage: &Storage,
+    content: &str,
+    chain: &mut Vec<String>,
+) -> crate::Result<String> {
+    let max_depth = storage.config.includes.max_depth;
+    let mut expanded = String::new();
+    let mut copied = 0;
+    for captures in directive().captures_iter(content) {
+        let target = &captures[1];
+        if chain.iter().any(|seen| seen == target) {
+            chain.push(target.to_string());
+            bail!("Profile include cycle: {}", chain.join(" -> "));
+        }
+        ensure!(
+            chain.len() <= max_depth,
+            "Includes nested deeper than {} levels: {} -> {}",
+            max_depth,
+            chain.join(" -> "),
+            target
+        );
+        ensure!(
+            storage.get_repo_path(target).is_ok(),
+            "Profile '{}' includes '{}', which does not exist",
+            chain.last().unwrap(),
+            target
+        );
+
+        let included = crate::extends::resolve(storage, target)?;
+        let (_, body) = crate::frontmatter::split(&included);
+        chain.push(target.to_string());
+        let body = expand_chain(storage, body, chain)?;
+        chain.pop();
+
+        let directive = captures.get(0).unwrap();
+        expanded.push_str(&content[copied..directive.start()]);
+        expanded.push_str(body.trim_end_matches('\n'));
+        copied = directive.end();
+    }
+    expanded.push_str(&content[copied..]);
+    Ok(expanded)
+}
+
+/// Which profiles include which, as declared by their include directives
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Graph {
+    /// Included profiles per profile; every profile in the repository has an entry
+    pub edges: BTreeMap<String, Vec<String>>,
+}
+
+impl Graph {
+    pub fn build(storage: &Storage) -> crate::Result<Self> {
+        let mut edges = BTreeMap::new();
+        for name in storage.list_repos()? {
+            let content = storage.get_profile_content(&name)?;
+            let mut includes = directives(&content);
+            includes.dedup();
+            edges.insert(name, includes);
+        }
+        Ok(Self { edges })
+    }
+
+    /// Included names that are not profiles in the repository
+    pub fn missing(&self) -> BTreeSet<&str> {
+        self.edges
+            .values()
+            .flatten()
+            .filter(|target| !self.edges.contains_key(*target))
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Include cycles, each as the path that returns to its first profile
+    pub fn cycles(&self) -> Vec<Vec<String>> {
+        fn visit<'a>(
+            graph: &'a Graph,
+            node: &'a str,
+            path: &mut Vec<&'a str>,
+            done: &mut BTreeSet<&'a str>,
+            cycles: &mut Vec<Vec<String>>,
+        ) {
+            if let Some(start) = path.iter().position(|n| *n == node) {
+                let mut cycle: Vec<String> = path[start..].iter().map(|n| n.to_string()).collect();
+                cycle.push(node.to_string());
+                cycles.push(cycle);
+                return;
+            }
+            if done.contains(node) {
+                return;
+            }
+
+            path.push(node);
+            for target in graph.edges.get(node).into_iter().flatten() {
+                visit(graph, target, path, done, cycles);
+            }
+            path.pop();
+            done.insert(node);
+        }
+
+        let mut cycles = Vec::new();
+        let mut done = BTreeSet::new();
+        for node in self.edges.keys() {
+            visit(self, node, &mut Vec::new(), &mut done, &mut cycles);
+        }
+        cycles
+    }
+
+    /// Profiles that include `name` directly or through other profiles
+    pub fn affected(&self, name: &str) -> BTreeSet<&str> {
+        let mut affected = BTreeSet::new();
+        let mut pending = vec![name];
+        while let Some(current) = pending.pop() {
+            for (profile, includes) in &self.edges {
+                if includes.iter().any(|target| target == current)
+                    && profile != name
+                    && affected.insert(profile.as_str())
+                {
+                    pending.push(profile);
+                }
+            }
+        }
+        affected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_expand_nested_includes() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::initialize(temp_dir.path().join("storage")).unwrap();
+        storage
+            .create_profile("shared/tone", "---\ntitle: Tone\n---\nBe kind.\n")
+            .unwrap();
+        storage
+            .create_profile("shared/style", "Be terse.\n{{include: shared/tone}}\n")
+            .unwrap();
+        storage
+            .create_profile("rust", "# Rust\n{{ include: shared/style }}\nUse cargo.\n")
+            .unwrap();
+        assert_eq!(
+            storage.get_content("rust").unwrap(),
+            "# Rust\nBe terse.\nBe kind.\nUse cargo.\n"
+        );
+
+        storage.create_profile("a", "{{include: b}}\n").unwrap();
+        storage.create_profile("b", "{{include: a}}\n").unwrap();
+        let error = storage.get_content("a").unwrap_err();
+        assert_eq!(error.to_string(), "Profile include cycle: a -> b -> a");
+
+        storage
+            .create_profile("orphan", "{{include: gone}}\n")
+            .unwrap();
+        assert!(storage.get_content("orphan").is_err());
+
+        let mut shallow = storage.clone();
+        shallow.config.includes.max_depth = 1;
+        assert!(shallow.get_content("rust").is_err());
+        assert!(shallow.get_content("shared/style").is_ok());
+    }
+
+    fn graph(edges: &[(&str, &[&str])]) -> Graph {
+        Graph {
+            edges: edges
+                .iter()
+                .map(|(name, includes)| {
+                    (
+                        name.to_string(),
+                        includes.iter().map(|s| s.to_string()).collect(),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_directives() {
+        let content = "# Rust\n{{include: shared/style}}\nText\n{{ include:shared/tone }}\n";
+        assert_eq!(directives(content), vec!["shared/style", "shared/tone"]);
+        assert!(directives("Hello <{{NAME}}>").is_empty());
+    }
+
+    #[test]
+    fn test_missing_and_affected() {
+        let graph = graph(&[
+            ("base", &["shared/style"]),
+            ("rust", &["base", "shared/gone"]),
+            ("shared/style", &[]),
+            ("solo", &[]),
+        ]);
+        assert_eq!(graph.missing(), BTreeSet::from(["shared/gone"]));
+        assert_eq!(
+            graph.affected("shared/style"),
+            BTreeSet::from(["base", "rust"])
+        );
+        assert!(graph.affected("solo").is_empty());
+        assert!(graph.cycles().is_empty());
+    }
+
+    #[test]
+    fn test_cycles() {
+        let graph = graph(&[("a", &["b"]), ("b", &["c"]), ("c", &["a"]), ("d", &["d"])]);
+        assert_eq!(
+            graph.cycles(),
+            vec![vec!["a", "b", "c", "a"], vec!["d", "d"]]
+        );
+    }
+}
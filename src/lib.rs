@@ -1,6 +1,74 @@
+//! pmx manages prompt profiles for AI coding agents. Besides the `pmx` binary,
+//! the crate can be embedded: open a [`storage::Storage`], read its
+//! [`storage::Config`] and render profiles. The report, profile, list and
+//! apply commands return data the caller can inspect or print: applying a
+//! profile yields a [`commands::apply::Change`] per file touched. The remaining
+//! commands (sync, import, export, config and the like) still print their
+//! results themselves.
+//!
+//! ```no_run
+//! use pmx::agent::Agent;
+//! use pmx::storage::Storage;
+//!
+//! fn main() -> pmx::Result<()> {
+//!     let storage = Storage::auto()?;
+//!     println!("Storage at {}", storage.path().display());
+//!     println!("MCP logging: {}", storage.config().mcp().log_requests);
+//!
+//!     for name in storage.list_repos()? {
+//!         let rendered = storage.render(&name)?;
+//!         println!("{name}: {} bytes", rendered.len());
+//!     }
+//!     for status in pmx::commands::project::agents(&storage)? {
+//!         println!("{}: {:?}", status.agent.name(), status.file);
+//!     }
+//!     let which = pmx::commands::which::which(&storage, Agent::Claude, false)?;
+//!     println!("{}", which.target.display());
+//!     let profiles = ["rust".to_string()];
+//!     let options = pmx::commands::apply::ApplyOptions::default();
+//!     for change in pmx::commands::apply::set_profile(&storage, Agent::Claude, &profiles, &options)? {
+//!         println!("{change}");
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+pub mod agent;
+pub mod archive;
+pub mod backups;
 pub mod cli;
 pub mod commands;
+pub mod crypto;
+pub mod diff;
+pub mod editor;
+pub mod encoding;
+pub mod extends;
+pub mod format;
+pub mod frontmatter;
+pub mod functions;
+pub mod history;
+pub mod include;
+pub mod lint;
+pub mod logging;
+pub mod manifest;
+pub mod markers;
+pub mod merge;
+pub mod obsidian;
+pub mod pack;
+pub mod paths;
+pub mod project;
+pub mod redact;
+pub mod remote;
+pub mod search;
+pub mod secrets;
+pub mod state;
 pub mod storage;
+pub mod submodules;
+pub mod template;
+pub mod tokens;
+pub mod trash;
+pub mod update;
 pub mod utils;
+pub mod watch;
 
-pub(crate) type Result<T> = anyhow::Result<T>;
+pub type Result<T> = anyhow::Result<T>;
@@ -0,0 +1,329 @@
+use regex::Regex;
+
+use crate::storage::Storage;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A problem found in a profile
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// 1-based line number, when the problem has a location
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        match self.line {
+            Some(line) => write!(f, "line {line}: {severity}: {}", self.message),
+            None => write!(f, "{severity}: {}", self.message),
+        }
+    }
+}
+
+impl Diagnostic {
+    fn error(line: Option<usize>, message: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            line,
+            message,
+        }
+    }
+
+    fn warning(line: Option<usize>, message: String) -> Self {
+        Self {
+            severity: Severity::Warning,
+            line,
+            message,
+        }
+    }
+}
+
+/// Check a profile's front-matter, `<{{VARIABLE}}>` placeholders and whitespace
+pub fn lint(content: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut declared = None;
+
+    let (frontmatter, body) = crate::frontmatter::split(content);
+    if let (None, Some(syntax)) = (frontmatter, crate::frontmatter::Syntax::detect(content)) {
+        diagnostics.push(Diagnostic::warning(
+            Some(1),
+            format!(
+                "front-matter block is never closed with '{}'",
+                syntax.closing()
+            ),
+        ));
+    }
+    match crate::frontmatter::parse_metadata(content) {
+        Ok(metadata) => {
+            if !metadata.arguments.is_empty() {
+                declared = Some(metadata.arguments.clone());
+            }
+            for audience in &metadata.audience {
+                if !crate::frontmatter::AUDIENCES.contains(&audience.as_str()) {
+                    diagnostics.push(Diagnostic::warning(
+                        Some(1),
+                        format!("unknown audience '{audience}'; expected 'user' or 'assistant'"),
+                    ));
+                }
+            }
+            for (key, date) in [
+                ("review_by", &metadata.review_by),
+                ("expires", &metadata.expires),
+            ] {
+                if date
+                    .as_deref()
+                    .is_some_and(|date| !crate::frontmatter::is_date(date))
+                {
+                    diagnostics.push(Diagnostic::warning(
+                        Some(1),
+                        format!("{key} must be a date in YYYY-MM-DD form"),
+                    ));
+                }
+            }
+            if metadata.priority.is_some_and(|p| !(0.0..=1.0).contains(&p)) {
+                diagnostics.push(Diagnostic::warning(
+                    Some(1),
+                    "priority must be between 0.0 and 1.0".to_string(),
+                ));
+            }
+        }
+        Err(e) => diagnostics.push(Diagnostic::error(Some(1), e.to_string())),
+    }
+
+    if body.trim().is_empty() {
+        diagnostics.push(Diagnostic::warning(
+            None,
+            "profile has no content".to_string(),
+        ));
+    }
+
+    let placeholder = Regex::new(&format!(
+        r"^(?:<\{{\{{[A-Za-z_][A-Za-z0-9_]*\}}\}}>|{})",
+        crate::functions::CALL_PATTERN
+    ))
+    .unwrap();
+    let bare = Regex::new(r"\{\{([A-Za-z_][A-Za-z0-9_]*)\}\}").unwrap();
+    let mut undeclared: Vec<String> = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let number = Some(index + 1);
+
+        if line.ends_with([' ', '\t']) {
+            diagnostics.push(Diagnostic::warning(
+                number,
+                "trailing whitespace".to_string(),
+            ));
+        }
+
+        // Once a profile declares its arguments, every placeholder should be declared
+        if let Some(declared) = &declared {
+            for var in crate::template::variables(line) {
+                if !declared.contains_key(&var) && !undeclared.contains(&var) {
+                    diagnostics.push(Diagnostic::warning(
+                        number,
+                        format!("placeholder <{{{{{var}}}}}> is not declared under 'arguments'"),
+                    ));
+                    undeclared.push(var);
+                }
+            }
+        }
+
+        for (start, _) in line.match_indices("<{{") {
+            if !placeholder.is_match(&line[start..]) {
+                diagnostics.push(Diagnostic::error(
+                    number,
+                    "malformed placeholder; expected <{{NAME}}> with letters, digits and '_'"
+                        .to_string(),
+                ));
+            }
+        }
+
+        for captures in bare.captures_iter(line) {
+            let whole = captures.get(0).unwrap();
+            let wrapped =
+                line[..whole.start()].ends_with('<') && line[whole.end()..].starts_with('>');
+            if !wrapped {
+                diagnostics.push(Diagnostic::warning(
+                    number,
+                    format!(
+                        "'{}' is not a placeholder; did you mean '<{}>'?",
+                        whole.as_str(),
+                        whole.as_str()
+                    ),
+                ));
+            }
+        }
+    }
+
+    diagnostics
+}
+
+/// `lint` for a stored profile, also checking that it is valid UTF-8, that its
+/// includes resolve and that it fits the `[lint]` budgets
+pub fn lint_profile(storage: &Storage, name: &str) -> crate::Result<Vec<Diagnostic>> {
+    let path = storage.get_repo_path(name)?;
+    let bytes = std::fs::read(&path)
+        .map_err(|e| anyhow::anyhow!("Failed to read profile '{}': {}", name, e))?;
+
+    let mut diagnostics = Vec::new();
+    if let Err(e) = std::str::from_utf8(&bytes) {
+        let offset = e.valid_up_to();
+        let line = bytes[..offset]
+            .iter()
+            .filter(|&&byte| byte == b'\n')
+            .count()
+            + 1;
+        diagnostics.push(Diagnostic::error(
+            Some(line),
+            format!("invalid UTF-8 at byte {offset}"),
+        ));
+    }
+
+    let content = storage.get_profile_content(name)?;
+    diagnostics.extend(lint(&content));
+
+    let mut missing_include = false;
+    for (index, line) in content.lines().enumerate() {
+        for target in crate::include::directives(line) {
+            if storage.get_repo_path(&target).is_err() {
+                diagnostics.push(Diagnostic::error(
+                    Some(index + 1),
+                    format!("included profile '{target}' does not exist"),
+                ));
+                missing_include = true;
+            }
+        }
+    }
+
+    match storage.get_content(name) {
+        Ok(expanded) => {
+            let config = &storage.config.lint;
+            let chars = expanded.chars().count();
+            if let Some(max_chars) = config.max_chars
+                && chars > max_chars
+            {
+                diagnostics.push(Diagnostic::warning(
+                    None,
+                    format!("profile is {chars} characters, above lint.max_chars ({max_chars})"),
+                ));
+            }
+            let tokens = storage.config.tokens.tokenizer.count(&expanded);
+            if let Some(max_tokens) = config.max_tokens
+                && tokens > max_tokens
+            {
+                diagnostics.push(Diagnostic::warning(
+                    None,
+                    format!("profile is ~{tokens} tokens, above lint.max_tokens ({max_tokens})"),
+                ));
+            }
+        }
+        // Cycles, nesting too deep and broken `extends:` chains
+        Err(e) if !missing_include => diagnostics.push(Diagnostic::error(None, e.to_string())),
+        Err(_) => {}
+    }
+    Ok(diagnostics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lint_clean_profile() {
+        let content = "---\ntitle: Greeting\n---\nHello <{{NAME}}>, welcome to <{{PLACE}}>.\n<{{fn:jira T-1}}>\n";
+        assert!(lint(content).is_empty());
+    }
+
+    #[test]
+    fn test_lint_placeholders() {
+        let content = "# Title\nHello <{{first name}}> and {{LAST}}.\n";
+        let diagnostics = lint(content);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(diagnostics[0].line, Some(2));
+        assert_eq!(diagnostics[1].severity, Severity::Warning);
+        assert!(diagnostics[1].message.contains("'<{{LAST}}>'"));
+    }
+
+    #[test]
+    fn test_lint_frontmatter() {
+        let diagnostics = lint("---\ntitle: [unclosed\n---\nBody\n");
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+
+        let diagnostics = lint("---\ntitle: x\nBody\n");
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert!(diagnostics[0].message.contains("never closed"));
+
+        let diagnostics = lint("---\naudience: [assistant, robot]\npriority: 2\n---\nBody\n");
+        assert_eq!(diagnostics.len(), 2);
+        assert!(diagnostics[0].message.contains("'robot'"));
+        assert!(diagnostics[1].message.contains("priority"));
+
+        let diagnostics = lint("---\nreview_by: next month\nexpires: 2026-06-01\n---\nBody\n");
+        assert_eq!(diagnostics.len(), 1);
+        assert!(
+            diagnostics[0]
+                .message
+                .starts_with("review_by must be a date")
+        );
+    }
+
+    #[test]
+    fn test_lint_whitespace_and_undeclared_placeholders() {
+        let content = "---\narguments:\n  NAME:\n    default: you\n---\nHello <{{NAME}}> \nfrom <{{TEAM}}> and <{{TEAM}}>.\n";
+        let diagnostics = lint(content);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].line, Some(6));
+        assert_eq!(diagnostics[0].message, "trailing whitespace");
+        assert_eq!(diagnostics[1].line, Some(7));
+        assert!(
+            diagnostics[1]
+                .message
+                .contains("<{{TEAM}}> is not declared")
+        );
+    }
+
+    #[test]
+    fn test_lint_profile() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("storage");
+        std::fs::create_dir_all(path.join("repo")).unwrap();
+        std::fs::write(
+            path.join("config.toml"),
+            "[agents]\n\n[lint]\nmax_chars = 20\n",
+        )
+        .unwrap();
+        let storage = Storage::new(path.clone()).unwrap();
+
+        storage.create_profile("base", "Use cargo.\n").unwrap();
+        storage
+            .create_profile("rust", "{{include: base}}\n{{include: gone}}\n")
+            .unwrap();
+        let diagnostics = lint_profile(&storage, "rust").unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line, Some(2));
+        assert!(diagnostics[0].message.contains("'gone' does not exist"));
+
+        storage
+            .create_profile("long", "{{include: base}}\nUse clippy too.\n")
+            .unwrap();
+        let diagnostics = lint_profile(&storage, "long").unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].message.contains("above lint.max_chars (20)"));
+
+        std::fs::write(path.join("repo/latin1.md"), b"Caf\xe9\n").unwrap();
+        let diagnostics = lint_profile(&storage, "latin1").unwrap();
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(diagnostics[0].message, "invalid UTF-8 at byte 3");
+    }
+}
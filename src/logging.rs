@@ -0,0 +1,151 @@
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Environment variable overriding the configured log level, e.g. `PMX_LOG=debug`
+const LOG_ENV: &str = "PMX_LOG";
+
+/// Target of the events recording each command and its outcome; they belong in
+/// the log file only, since a failing command already reports its error
+pub const COMMAND_TARGET: &str = "pmx::command";
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// How log lines are written
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+    schemars::JsonSchema,
+    clap::ValueEnum,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// One readable line per event
+    Text,
+    /// One JSON object per event
+    Json,
+}
+
+/// How much is reported on stderr, from `-v` and `-q`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Verbosity {
+    pub verbose: u8,
+    pub quiet: bool,
+}
+
+impl Verbosity {
+    /// Level of the events shown on stderr: warnings by default, errors only
+    /// when quiet, and more detail with each `-v`
+    fn level(self) -> &'static str {
+        if self.quiet {
+            return "error";
+        }
+        match self.verbose {
+            0 => "warn",
+            1 => "info",
+            2 => "debug",
+            _ => "trace",
+        }
+    }
+}
+
+/// Whether `--quiet` was given; notices printed without going through logging
+/// check it
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+fn filter(level: &str) -> crate::Result<tracing_subscriber::EnvFilter> {
+    use tracing_subscriber::EnvFilter;
+
+    EnvFilter::try_from_env(LOG_ENV)
+        .or_else(|_| EnvFilter::try_new(level))
+        .map_err(|e| anyhow::anyhow!("Invalid log level '{}': {}", level, e))
+}
+
+/// Report warnings and errors on stderr, more or less of them as `verbosity`
+/// asks, and send tracing output at `level` to `file`, appending to it.
+///
+/// Stdout is never used: it belongs to command output (and to the protocol when
+/// running as an MCP server). Lines are text on stderr and JSON in the file
+/// unless `format` says otherwise.
+pub fn init(
+    file: Option<&Path>,
+    level: &str,
+    format: Option<LogFormat>,
+    verbosity: Verbosity,
+) -> crate::Result<()> {
+    use tracing_subscriber::Layer;
+    use tracing_subscriber::filter::filter_fn;
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::util::SubscriberInitExt;
+
+    QUIET.store(verbosity.quiet, Ordering::Relaxed);
+
+    let stderr = tracing_subscriber::fmt::layer()
+        .with_writer(std::io::stderr)
+        .with_target(false);
+    let stderr = match format.unwrap_or(LogFormat::Text) {
+        LogFormat::Text => {
+            use is_terminal::IsTerminal;
+
+            let color = std::env::var_os("NO_COLOR").is_none() && std::io::stderr().is_terminal();
+            stderr.without_time().with_ansi(color).boxed()
+        }
+        LogFormat::Json => stderr.json().boxed(),
+    }
+    .with_filter(filter(verbosity.level())?)
+    .with_filter(filter_fn(|metadata| metadata.target() != COMMAND_TARGET));
+
+    let file = match file {
+        Some(file) => {
+            if let Some(parent) = file.parent().filter(|p| !p.as_os_str().is_empty()) {
+                std::fs::create_dir_all(parent)
+                    .map_err(|e| anyhow::anyhow!("Failed to create log directory: {}", e))?;
+            }
+            let writer = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(file)
+                .map_err(|e| {
+                    anyhow::anyhow!("Failed to open log file {}: {}", file.display(), e)
+                })?;
+
+            let layer = tracing_subscriber::fmt::layer()
+                .with_writer(std::sync::Mutex::new(writer))
+                .with_ansi(false);
+            let layer = match format.unwrap_or(LogFormat::Json) {
+                LogFormat::Text => layer.boxed(),
+                LogFormat::Json => layer.json().boxed(),
+            };
+            Some(layer.with_filter(filter(level)?))
+        }
+        None => None,
+    };
+
+    tracing_subscriber::registry()
+        .with(stderr)
+        .with(file)
+        .try_init()
+        .map_err(|e| anyhow::anyhow!("Failed to initialize logging: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verbosity_level() {
+        let level = |verbose, quiet| Verbosity { verbose, quiet }.level();
+        assert_eq!(level(0, false), "warn");
+        assert_eq!(level(1, false), "info");
+        assert_eq!(level(2, false), "debug");
+        assert_eq!(level(5, false), "trace");
+        assert_eq!(level(0, true), "error");
+        assert_eq!(level(2, true), "error");
+    }
+}
@@ -1,64 +1,628 @@
-use std::path::PathBuf;
-
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches};
 use pmx::cli;
+use pmx::commands::profile::SaveKind;
 
 fn main() -> anyhow::Result<()> {
-    let args = cli::Arg::parse();
-    let storage = args
-        .config
-        .or_else(|| std::env::var("PMX_CONFIG_FILE").ok().map(PathBuf::from))
-        .map(pmx::storage::Storage::new)
-        .unwrap_or_else(pmx::storage::Storage::auto)?;
-
-    match args.command {
+    let matches = cli::Arg::command().get_matches();
+    let command = cli::command_name(&matches);
+    let args = cli::Arg::from_arg_matches(&matches)
+        .unwrap_or_else(|e| e.format(&mut cli::Arg::command()).exit());
+    let verbosity = pmx::logging::Verbosity {
+        verbose: args.verbose,
+        quiet: args.quiet,
+    };
+
+    // Bootstrap creates its own storage and must work without a home directory
+    if let cli::Command::Bootstrap(bootstrap) = &args.command {
+        pmx::logging::init(args.log_file.as_deref(), "info", args.log_format, verbosity)?;
+        let storage_path = pmx::paths::explicit(args.storage.as_deref()).map(|p| p.path);
+        return log_result(pmx::commands::bootstrap::bootstrap(bootstrap, storage_path));
+    }
+
+    let resolved = pmx::paths::resolve(args.storage.as_deref())?;
+
+    // Reporting the locations must not create or require the storage directory
+    if let cli::Command::Paths = &args.command {
+        print!("{}", pmx::commands::paths::paths(&resolved)?);
+        return Ok(());
+    }
+    // Diagnosing a broken storage must not fail on loading it
+    if let cli::Command::Doctor = &args.command {
+        let diagnosis = pmx::commands::doctor::doctor(&resolved.path);
+        print!("{diagnosis}");
+        anyhow::ensure!(
+            diagnosis.problems() == 0,
+            "{} problem(s) found",
+            diagnosis.problems()
+        );
+        return Ok(());
+    }
+    if let cli::Command::Init(init) = &args.command {
+        let path = init.path.as_deref().unwrap_or(&resolved.path);
+        return pmx::commands::init::init(path, init.examples);
+    }
+
+    let storage = pmx::storage::Storage::open(&resolved)?;
+
+    let (config_log_file, log_level, log_format) = storage.logging();
+    pmx::logging::init(
+        args.log_file.as_deref().or(config_log_file),
+        log_level,
+        args.log_format.or(log_format),
+        verbosity,
+    )?;
+    tracing::info!(target: pmx::logging::COMMAND_TARGET, command = %command, "running command");
+
+    log_result(run(args.command, storage, args.dry_run, args.yes))
+}
+
+/// Tell the user when `[naming] slugify` stored a profile under another name
+fn note_renamed(requested: &str, name: &str) {
+    if requested != name {
+        println!("Using profile name '{name}'");
+    }
+}
+
+/// Print what an apply command did, a line per change
+fn print_changes(changes: Vec<pmx::commands::apply::Change>) {
+    for change in changes {
+        println!("{change}");
+    }
+}
+
+fn log_result(result: anyhow::Result<()>) -> anyhow::Result<()> {
+    if let Err(e) = &result {
+        tracing::error!(target: pmx::logging::COMMAND_TARGET, error = format!("{e:#}"), "command failed");
+    }
+    result
+}
+
+fn run(
+    command: cli::Command,
+    storage: pmx::storage::Storage,
+    dry_run: bool,
+    yes: bool,
+) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        !dry_run
+            || matches!(
+                command,
+                cli::Command::SetClaudeProfile(_)
+                    | cli::Command::AppendClaudeProfile(_)
+                    | cli::Command::ResetClaudeProfile(_)
+                    | cli::Command::RemoveClaudeProfile(_)
+                    | cli::Command::SetCodexProfile(_)
+                    | cli::Command::AppendCodexProfile(_)
+                    | cli::Command::ResetCodexProfile(_)
+                    | cli::Command::RemoveCodexProfile(_)
+                    | cli::Command::SetGeminiProfile(_)
+                    | cli::Command::AppendGeminiProfile(_)
+                    | cli::Command::ResetGeminiProfile(_)
+                    | cli::Command::RemoveGeminiProfile(_)
+                    | cli::Command::SetCursorProfile(_)
+                    | cli::Command::AppendCursorProfile(_)
+                    | cli::Command::ResetCursorProfile(_)
+                    | cli::Command::RemoveCursorProfile(_)
+                    | cli::Command::SetContinueProfile(_)
+                    | cli::Command::AppendContinueProfile(_)
+                    | cli::Command::ResetContinueProfile(_)
+                    | cli::Command::RemoveContinueProfile(_)
+                    | cli::Command::SetProfile(_)
+                    | cli::Command::AppendProfile(_)
+                    | cli::Command::ResetProfile(_)
+                    | cli::Command::RemoveProfile(_)
+                    | cli::Command::Apply(_)
+                    | cli::Command::Bundle(_)
+            ),
+        "--dry-run is only supported when setting, appending, removing or resetting agent profiles and by `pmx apply` and `pmx bundle apply`"
+    );
+    // --yes answers the prompts to back up hand-written files and to write sensitive profiles
+    let with_global_flags =
+        |options: pmx::commands::apply::ApplyOptions| pmx::commands::apply::ApplyOptions {
+            dry_run,
+            backup: options.backup || yes,
+            allow_sensitive: options.allow_sensitive || yes,
+            ..options
+        };
+
+    let notify_updates = !matches!(
+        command,
+        cli::Command::InternalCompletion(_)
+            | cli::Command::InternalUpdateCheck
+            | cli::Command::Completion(_)
+            | cli::Command::Hook(_)
+            | cli::Command::InternalHook
+            | cli::Command::Config(_)
+    );
+
+    match command {
         // utils
+        cli::Command::Completion(cli::CompletionArgs {
+            command: Some(cli::CompletionCommand::Install(args)),
+            ..
+        }) => {
+            pmx::commands::utils::install_completion(args.shell)?;
+        }
         cli::Command::Completion(completion) => {
-            pmx::commands::utils::completion(&completion.shell)?;
+            if let Some(shell) = &completion.shell {
+                pmx::commands::utils::completion(shell)?;
+            }
         }
 
+        cli::Command::Hook(args) => {
+            pmx::commands::hook::hook(&args.shell)?;
+        }
+        cli::Command::InternalHook => {
+            print_changes(pmx::commands::hook::run(&storage)?);
+        }
+        cli::Command::ShellPlugin(args) => {
+            pmx::commands::hook::plugin(&storage, &args.shell)?;
+        }
+
+        cli::Command::Config(config_cmd) => match config_cmd {
+            cli::ConfigCommand::Schema => {
+                pmx::commands::config::schema()?;
+            }
+            cli::ConfigCommand::Get(args) => {
+                pmx::commands::config::get(&storage, &args.key)?;
+            }
+            cli::ConfigCommand::Set(args) => {
+                pmx::commands::config::set(&storage, &args)?;
+            }
+            cli::ConfigCommand::Unset(args) => {
+                pmx::commands::config::unset(&storage, &args.key)?;
+            }
+            cli::ConfigCommand::List => {
+                pmx::commands::config::list(&storage)?;
+            }
+        },
+
         // profile management
         cli::Command::Profile(profile_cmd) => match profile_cmd {
             cli::ProfileCommand::List => {
-                pmx::commands::utils::list(&storage)?;
+                use is_terminal::IsTerminal;
+
+                let list = pmx::commands::utils::list(&storage)?;
+                if std::io::stdout().is_terminal() {
+                    print!("{list}");
+                } else {
+                    // Piped output gets the plain names
+                    list.names().for_each(|name| println!("{name}"));
+                }
             }
             cli::ProfileCommand::Edit(args) => {
-                pmx::commands::profile::edit(&storage, &args.name)?;
+                pmx::commands::profile::edit(&storage, &args.name, args.editor.as_deref())?;
+                println!("Profile '{}' edited successfully", args.name);
             }
             cli::ProfileCommand::Delete(args) => {
-                pmx::commands::profile::delete(&storage, &args.name)?;
+                if pmx::commands::profile::delete(&storage, &args.name, yes)? {
+                    println!(
+                        "Profile '{}' moved to the trash; `pmx profile trash restore {}` brings it back",
+                        args.name, args.name
+                    );
+                } else {
+                    println!("Deletion cancelled");
+                }
+            }
+            cli::ProfileCommand::Trash(cli::TrashCommand::List) => {
+                let trashed = pmx::commands::profile::trash_list(&storage)?;
+                if trashed.is_empty() {
+                    println!("The trash is empty");
+                }
+                for trashed in trashed {
+                    println!("{}  {}", trashed.deleted, trashed.name);
+                }
+            }
+            cli::ProfileCommand::Trash(cli::TrashCommand::Restore(args)) => {
+                let trashed = pmx::commands::profile::trash_restore(&storage, &args.name)?;
+                println!(
+                    "Profile '{}' restored (deleted {})",
+                    args.name, trashed.deleted
+                );
+            }
+            cli::ProfileCommand::Trash(cli::TrashCommand::Purge(args)) => {
+                match pmx::commands::profile::trash_purge(&storage, args.older_than, yes)? {
+                    Some(purged) => println!("Removed {purged} profile(s) from the trash"),
+                    None => println!("Purge cancelled"),
+                }
             }
             cli::ProfileCommand::Create(args) => {
-                pmx::commands::profile::create(&storage, &args.name)?;
+                match pmx::commands::profile::create(&storage, &args)? {
+                    Some(saved) => {
+                        note_renamed(&args.name, &saved.name);
+                        match saved.kind {
+                            SaveKind::Overwritten => {
+                                println!("Profile '{}' overwritten", saved.name)
+                            }
+                            _ => println!("Profile '{}' created successfully", saved.name),
+                        }
+                    }
+                    None => println!("Profile creation cancelled - no content added"),
+                }
+            }
+            cli::ProfileCommand::Show(args) if args.json => {
+                let details = pmx::commands::profile::details(&storage, &args.name, args.reveal)?;
+                println!("{}", serde_json::to_string_pretty(&details)?);
+            }
+            cli::ProfileCommand::Show(args) if args.metadata.is_some() || args.body => {
+                print!("{}", pmx::commands::profile::show_part(&storage, &args)?);
             }
             cli::ProfileCommand::Show(args) => {
-                pmx::commands::profile::show(&storage, &args.name)?;
+                println!(
+                    "{}",
+                    pmx::commands::profile::show(&storage, &args.name, args.reveal)?
+                );
+            }
+            cli::ProfileCommand::Graph(args) => {
+                pmx::commands::graph::graph(&storage, args.dot)?;
+            }
+            cli::ProfileCommand::Preview(args) => {
+                pmx::commands::preview::preview(&storage, &args.name, args.browser)?;
+            }
+            cli::ProfileCommand::Clip(args) => {
+                pmx::commands::profile::copy_many(&storage, &args)?;
+                println!(
+                    "Profile content copied to clipboard: {}",
+                    args.names.join(", ")
+                );
+            }
+            cli::ProfileCommand::Duplicate(args) => {
+                let to = pmx::commands::profile::duplicate(&storage, &args)?;
+                note_renamed(&args.to, &to);
+                println!("Copied profile '{}' to '{}'", args.from, to);
+            }
+            cli::ProfileCommand::Paste(args) => {
+                let saved = pmx::commands::profile::paste(&storage, &args)?;
+                note_renamed(&args.name, &saved.name);
+                let name = &saved.name;
+                match saved.kind {
+                    SaveKind::Appended => {
+                        println!("Pasted clipboard text at the end of profile '{name}'")
+                    }
+                    SaveKind::Overwritten => {
+                        println!("Profile '{name}' overwritten with the clipboard text")
+                    }
+                    SaveKind::Created => println!("Profile '{name}' created from the clipboard"),
+                }
+            }
+            cli::ProfileCommand::Render(args) => {
+                let content = pmx::commands::profile::render(&storage, &args)?;
+                if args.copy {
+                    println!("Rendered profile copied to clipboard: {}", args.name);
+                } else {
+                    print!("{content}");
+                }
+            }
+            cli::ProfileCommand::Fmt(args) => {
+                let unformatted = pmx::commands::profile::fmt(&storage, &args)?;
+                for name in &unformatted {
+                    if args.check {
+                        println!("{name}");
+                    } else {
+                        println!("Formatted profile '{name}'");
+                    }
+                }
+                anyhow::ensure!(
+                    !args.check || unformatted.is_empty(),
+                    "{} profile(s) need formatting. Run 'pmx profile fmt' to fix them.",
+                    unformatted.len()
+                );
+                if unformatted.is_empty() {
+                    println!("All profiles are formatted");
+                }
+            }
+            cli::ProfileCommand::Lint(args) => {
+                let problems = pmx::commands::profile::lint(&storage, &args)?;
+                if args.json {
+                    println!("{}", pmx::commands::profile::problems_json(&problems)?);
+                } else if problems.is_empty() {
+                    println!("No problems found");
+                } else {
+                    problems.iter().for_each(|problem| println!("{problem}"));
+                }
+                let errors = problems
+                    .iter()
+                    .filter(|problem| problem.diagnostic.severity == pmx::lint::Severity::Error)
+                    .count();
+                anyhow::ensure!(errors == 0, "Found {} error(s) in profiles", errors);
+            }
+            cli::ProfileCommand::Stats(args) => {
+                print!("{}", pmx::commands::profile::stats(&storage, &args)?);
+            }
+            cli::ProfileCommand::Merge(args) => {
+                let result = pmx::commands::profile::merge(&storage, &args)?;
+                match &args.output {
+                    Some(output) => println!("Merged profile saved as '{output}'"),
+                    None => print!("{}", result.content),
+                }
+                anyhow::ensure!(
+                    result.conflicts == 0,
+                    "Merge produced {} conflict(s); resolve the marked sections before applying",
+                    result.conflicts
+                );
+            }
+            cli::ProfileCommand::Diff(args) => {
+                let diff = pmx::commands::profile::diff(&storage, &args)?;
+                if diff.is_empty() {
+                    println!("Profiles '{}' and '{}' are identical", args.old, args.new);
+                } else {
+                    print!("{diff}");
+                }
+            }
+            cli::ProfileCommand::History(args) => {
+                let entries = pmx::commands::profile::history(&storage, &args.name)?;
+                if entries.is_empty() {
+                    println!("No recorded changes to profile '{}'", args.name);
+                }
+                for entry in entries {
+                    println!("{}  {}  {}", entry.revision, entry.date, entry.subject);
+                }
+            }
+            cli::ProfileCommand::Revert(args) => {
+                pmx::commands::profile::revert(&storage, &args)?;
+                println!("Profile '{}' reverted to {}", args.name, args.to);
+            }
+            cli::ProfileCommand::Search(args) => {
+                let results = pmx::commands::profile::search(&storage, &args)?;
+                if results.is_empty() {
+                    println!("No profiles match '{}'", args.query);
+                } else {
+                    print!(
+                        "{}",
+                        pmx::commands::profile::describe_matches(&results, pmx::utils::use_color())
+                    );
+                }
             }
-            cli::ProfileCommand::Copy(args) => {
-                pmx::commands::profile::copy(&storage, &args.name)?;
+            cli::ProfileCommand::Import(args) if args.archive => {
+                pmx::commands::import::archive(&storage, &args, yes)?;
+            }
+            cli::ProfileCommand::Import(args) => {
+                pmx::commands::import::profiles(&storage, &args)?;
+            }
+            cli::ProfileCommand::Export(args) => {
+                pmx::commands::export::export(&storage, &args)?;
             }
         },
 
+        cli::Command::Bundle(cli::BundleCommand::Apply(args)) => {
+            print_changes(pmx::commands::bundle::apply(
+                &storage,
+                &args.name,
+                &args.agent,
+                &with_global_flags(args.apply_options()),
+            )?);
+        }
+        cli::Command::Pack(pack_cmd) => match pack_cmd {
+            cli::PackCommand::Install(args) => {
+                pmx::commands::pack::install(&storage, &args)?;
+            }
+            cli::PackCommand::New(args) => {
+                pmx::commands::pack::new(&args)?;
+            }
+        },
+        cli::Command::Import(cli::ImportCommand::Obsidian(args)) => {
+            pmx::commands::import::obsidian(&storage, &args)?;
+        }
+        cli::Command::Sync(args) => {
+            pmx::commands::sync::sync(&storage, &args)?;
+        }
+        cli::Command::Verify(args) => {
+            pmx::commands::verify::verify(&storage, args.update)?;
+        }
+
         // claude_code
         cli::Command::SetClaudeProfile(profile) => {
-            pmx::commands::claude_code::set_claude_profile(&storage, &profile.path)?;
+            print_changes(pmx::commands::claude_code::set_claude_profile(
+                &storage,
+                &profile.profiles,
+                &with_global_flags(profile.apply_options()),
+            )?);
+        }
+        cli::Command::ResetClaudeProfile(args) => {
+            print_changes(pmx::commands::claude_code::reset_claude_profile(
+                &storage,
+                &with_global_flags(args.apply_options()),
+            )?);
+        }
+        cli::Command::RemoveClaudeProfile(args) => {
+            print_changes(pmx::commands::claude_code::remove_claude_profile(
+                &storage,
+                &args.name,
+                &with_global_flags(args.apply_options()),
+            )?);
         }
-        cli::Command::ResetClaudeProfile => {
-            pmx::commands::claude_code::reset_claude_profile(&storage)?;
+        cli::Command::RestoreClaudeProfile(args) => {
+            print_changes(pmx::commands::claude_code::restore_claude_profile(
+                &storage,
+                args.project,
+            )?);
         }
         cli::Command::AppendClaudeProfile(profile) => {
-            pmx::commands::claude_code::append_claude_profile(&storage, &profile.path)?;
+            print_changes(pmx::commands::claude_code::append_claude_profile(
+                &storage,
+                &profile.profiles,
+                &with_global_flags(profile.apply_options()),
+            )?);
         }
 
         // openai_codex
         cli::Command::SetCodexProfile(profile) => {
-            pmx::commands::openai_codex::set_codex_profile(&storage, &profile.path)?;
+            print_changes(pmx::commands::openai_codex::set_codex_profile(
+                &storage,
+                &profile.profiles,
+                &with_global_flags(profile.apply_options()),
+            )?);
         }
-        cli::Command::ResetCodexProfile => {
-            pmx::commands::openai_codex::reset_codex_profile(&storage)?;
+        cli::Command::ResetCodexProfile(args) => {
+            print_changes(pmx::commands::openai_codex::reset_codex_profile(
+                &storage,
+                &with_global_flags(args.apply_options()),
+            )?);
+        }
+        cli::Command::RemoveCodexProfile(args) => {
+            print_changes(pmx::commands::openai_codex::remove_codex_profile(
+                &storage,
+                &args.name,
+                &with_global_flags(args.apply_options()),
+            )?);
+        }
+        cli::Command::RestoreCodexProfile(args) => {
+            print_changes(pmx::commands::openai_codex::restore_codex_profile(
+                &storage,
+                args.project,
+            )?);
         }
         cli::Command::AppendCodexProfile(profile) => {
-            pmx::commands::openai_codex::append_codex_profile(&storage, &profile.path)?;
+            print_changes(pmx::commands::openai_codex::append_codex_profile(
+                &storage,
+                &profile.profiles,
+                &with_global_flags(profile.apply_options()),
+            )?);
+        }
+
+        // gemini
+        cli::Command::SetGeminiProfile(profile) => {
+            print_changes(pmx::commands::gemini::set_gemini_profile(
+                &storage,
+                &profile.profiles,
+                &with_global_flags(profile.apply_options()),
+            )?);
+        }
+        cli::Command::ResetGeminiProfile(args) => {
+            print_changes(pmx::commands::gemini::reset_gemini_profile(
+                &storage,
+                &with_global_flags(args.apply_options()),
+            )?);
+        }
+        cli::Command::RemoveGeminiProfile(args) => {
+            print_changes(pmx::commands::gemini::remove_gemini_profile(
+                &storage,
+                &args.name,
+                &with_global_flags(args.apply_options()),
+            )?);
+        }
+        cli::Command::RestoreGeminiProfile(args) => {
+            print_changes(pmx::commands::gemini::restore_gemini_profile(
+                &storage,
+                args.project,
+            )?);
+        }
+        cli::Command::AppendGeminiProfile(profile) => {
+            print_changes(pmx::commands::gemini::append_gemini_profile(
+                &storage,
+                &profile.profiles,
+                &with_global_flags(profile.apply_options()),
+            )?);
+        }
+
+        // cursor
+        cli::Command::SetCursorProfile(profile) => {
+            print_changes(pmx::commands::cursor::set_cursor_profile(
+                &storage,
+                &profile.profiles,
+                &with_global_flags(profile.apply_options()),
+            )?);
+        }
+        cli::Command::ResetCursorProfile(args) => {
+            print_changes(pmx::commands::cursor::reset_cursor_profile(
+                &storage,
+                &with_global_flags(args.apply_options()),
+            )?);
+        }
+        cli::Command::RemoveCursorProfile(args) => {
+            print_changes(pmx::commands::cursor::remove_cursor_profile(
+                &storage,
+                &args.name,
+                &with_global_flags(args.apply_options()),
+            )?);
+        }
+        cli::Command::RestoreCursorProfile(args) => {
+            print_changes(pmx::commands::cursor::restore_cursor_profile(
+                &storage,
+                args.project,
+            )?);
+        }
+        cli::Command::AppendCursorProfile(profile) => {
+            print_changes(pmx::commands::cursor::append_cursor_profile(
+                &storage,
+                &profile.profiles,
+                &with_global_flags(profile.apply_options()),
+            )?);
+        }
+
+        // continue
+        cli::Command::SetContinueProfile(profile) => {
+            print_changes(pmx::commands::continue_dev::set_continue_profile(
+                &storage,
+                &profile.profiles,
+                &with_global_flags(profile.apply_options()),
+            )?);
+        }
+        cli::Command::ResetContinueProfile(args) => {
+            print_changes(pmx::commands::continue_dev::reset_continue_profile(
+                &storage,
+                &with_global_flags(args.apply_options()),
+            )?);
+        }
+        cli::Command::RestoreContinueProfile(args) => {
+            print_changes(pmx::commands::continue_dev::restore_continue_profile(
+                &storage,
+                args.project,
+            )?);
+        }
+        cli::Command::RemoveContinueProfile(args) => {
+            print_changes(pmx::commands::continue_dev::remove_continue_profile(
+                &storage,
+                &args.name,
+                &with_global_flags(args.apply_options()),
+            )?);
+        }
+        cli::Command::AppendContinueProfile(profile) => {
+            print_changes(pmx::commands::continue_dev::append_continue_profile(
+                &storage,
+                &profile.profiles,
+                &with_global_flags(profile.apply_options()),
+            )?);
+        }
+
+        // any agent
+        cli::Command::SetProfile(args) => {
+            print_changes(pmx::commands::apply::set_profile(
+                &storage,
+                pmx::agent::Agent::named(&storage, &args.agent)?,
+                &args.profile.profiles,
+                &with_global_flags(args.profile.apply_options()),
+            )?);
+        }
+        cli::Command::ResetProfile(args) => {
+            print_changes(pmx::commands::apply::reset_profile(
+                &storage,
+                pmx::agent::Agent::named(&storage, &args.agent)?,
+                &with_global_flags(args.reset.apply_options()),
+            )?);
+        }
+        cli::Command::RemoveProfile(args) => {
+            print_changes(pmx::commands::apply::remove_profile(
+                &storage,
+                pmx::agent::Agent::named(&storage, &args.agent)?,
+                &args.profile.name,
+                &with_global_flags(args.profile.apply_options()),
+            )?);
+        }
+        cli::Command::RestoreProfile(args) => {
+            print_changes(pmx::commands::apply::restore_profile(
+                &storage,
+                pmx::agent::Agent::named(&storage, &args.agent)?,
+                args.reset.project,
+            )?);
+        }
+        cli::Command::AppendProfile(args) => {
+            print_changes(pmx::commands::apply::append_profile(
+                &storage,
+                pmx::agent::Agent::named(&storage, &args.agent)?,
+                &args.profile.profiles,
+                &with_global_flags(args.profile.apply_options()),
+            )?);
         }
 
         // internal completion
@@ -66,9 +630,75 @@ fn main() -> anyhow::Result<()> {
             pmx::commands::utils::internal_completion(&storage, &completion_cmd)?;
         }
 
+        cli::Command::InternalUpdateCheck => {
+            pmx::update::check_now(&storage)?;
+        }
+
         // MCP server
-        cli::Command::Mcp(_args) => {
-            pmx::commands::mcp::run_mcp_server(storage)?;
+        cli::Command::Mcp(cli::McpArgs {
+            command: Some(cli::McpCommand::Call(args)),
+            ..
+        }) => {
+            pmx::commands::mcp_client::call(&storage, &args)?;
+        }
+        cli::Command::Mcp(cli::McpArgs {
+            command: Some(cli::McpCommand::Install(args)),
+            ..
+        }) => {
+            pmx::commands::mcp_install::install(&storage, &args)?;
+        }
+        cli::Command::Mcp(args) => {
+            return pmx::commands::mcp::run_mcp_server(
+                storage,
+                args.transport,
+                args.bind.as_deref(),
+                args.port,
+            );
+        }
+
+        cli::Command::Apply(args) => {
+            let applied =
+                pmx::commands::project::apply(&storage, &with_global_flags(args.apply_options()))?;
+            print!("{applied}");
+        }
+        cli::Command::Status(args) if args.porcelain => {
+            print!("{}", pmx::commands::project::porcelain(&storage)?);
+        }
+        cli::Command::Status(_) => {
+            print!("{}", pmx::commands::project::status(&storage)?);
+        }
+
+        cli::Command::Bootstrap(_)
+        | cli::Command::Init(_)
+        | cli::Command::Paths
+        | cli::Command::Doctor => {
+            unreachable!("handled before loading storage")
+        }
+
+        cli::Command::Which(args) => {
+            print!(
+                "{}",
+                pmx::commands::which::which(&storage, args.agent, args.project)?
+            );
+        }
+        cli::Command::Diff(args) => {
+            pmx::commands::diff::diff(&storage, &args)?;
+        }
+        cli::Command::Watch(args) => {
+            pmx::commands::watch::watch(
+                &storage,
+                args.lint,
+                args.debounce.map(std::time::Duration::from_millis),
+            )?;
+        }
+        cli::Command::Alias(cli::AliasCommand::Generate(args)) => {
+            pmx::commands::alias::generate(&storage, &args.profiles, args.limit)?;
+        }
+        cli::Command::Refactor(cli::RefactorCommand::RenameVar(args)) => {
+            pmx::commands::refactor::rename_var(&storage, &args, yes)?;
+        }
+        cli::Command::Vars(cli::VarsCommand::List) => {
+            pmx::commands::vars::list(&storage)?;
         }
 
         // Extension subcommands
@@ -77,5 +707,9 @@ fn main() -> anyhow::Result<()> {
         }
     }
 
+    if notify_updates {
+        pmx::update::notify(&storage);
+    }
+
     Ok(())
 }
@@ -0,0 +1,169 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::pack::Deviation;
+
+/// Checksums of every file in `repo/`, stored next to `config.toml` in `sha256sum` format
+pub const MANIFEST_FILE: &str = "SHA256SUMS";
+
+/// Content hashes of the repository as pmx last wrote it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Manifest {
+    /// Hash per file, keyed by `/`-separated path relative to `repo/`
+    pub files: BTreeMap<String, String>,
+}
+
+impl Manifest {
+    /// Load the manifest, if one has been recorded
+    pub fn load(storage_path: &Path) -> crate::Result<Option<Self>> {
+        let path = storage_path.join(MANIFEST_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| anyhow::anyhow!("Failed to read manifest: {}", e))?;
+        let files = crate::pack::parse_checksums(&content)
+            .map_err(|e| anyhow::anyhow!("Failed to parse manifest: {}", e))?;
+        Ok(Some(Self { files }))
+    }
+
+    pub fn persist(&self, storage_path: &Path) -> crate::Result<()> {
+        let content: String = self
+            .files
+            .iter()
+            .map(|(path, hash)| format!("{hash}  {path}\n"))
+            .collect();
+        std::fs::write(storage_path.join(MANIFEST_FILE), content)
+            .map_err(|e| anyhow::anyhow!("Failed to write manifest: {}", e))
+    }
+
+    /// Hash every file currently in the repository
+    pub fn scan(storage_path: &Path) -> crate::Result<Self> {
+        let repo = storage_path.join("repo");
+        let files = crate::pack::hash_files(&repo, &crate::utils::visible_files(&repo)?)?;
+        Ok(Self { files })
+    }
+}
+
+/// Re-record the entries for `paths` (files or directories relative to `repo/`)
+/// after pmx changed them. Without a manifest yet, the whole repository is recorded.
+pub fn refresh(storage_path: &Path, paths: &[PathBuf]) -> crate::Result<()> {
+    let Some(mut manifest) = Manifest::load(storage_path)? else {
+        return Manifest::scan(storage_path)?.persist(storage_path);
+    };
+
+    let repo = storage_path.join("repo");
+    for relative in paths {
+        let prefix = relative.to_string_lossy().replace('\\', "/");
+        manifest
+            .files
+            .retain(|path, _| *path != prefix && !path.starts_with(&format!("{prefix}/")));
+
+        let path = repo.join(relative);
+        let files = if path.is_dir() {
+            crate::utils::visible_files(&path)?
+                .into_iter()
+                .map(|file| relative.join(file))
+                .collect()
+        } else if path.is_file() {
+            vec![relative.clone()]
+        } else {
+            Vec::new()
+        };
+        manifest
+            .files
+            .extend(crate::pack::hash_files(&repo, &files)?);
+    }
+    manifest.persist(storage_path)
+}
+
+/// Compare the repository against the manifest; `None` if no manifest was recorded
+pub fn verify(storage_path: &Path) -> crate::Result<Option<Vec<Deviation>>> {
+    let Some(manifest) = Manifest::load(storage_path)? else {
+        return Ok(None);
+    };
+
+    let repo = storage_path.join("repo");
+    let mut current = BTreeMap::new();
+    let mut deviations = Vec::new();
+    for relative in crate::utils::visible_files(&repo)? {
+        let key = relative.to_string_lossy().replace('\\', "/");
+        match std::fs::read(repo.join(&relative)) {
+            Ok(content) => {
+                current.insert(key, crate::utils::content_hash(&content));
+            }
+            Err(_) => deviations.push(Deviation::Unreadable(key)),
+        }
+    }
+
+    for (path, hash) in &manifest.files {
+        match current.get(path) {
+            None if deviations.contains(&Deviation::Unreadable(path.clone())) => {}
+            None => deviations.push(Deviation::Missing(path.clone())),
+            Some(actual) if actual != hash => deviations.push(Deviation::Modified(path.clone())),
+            Some(_) => {}
+        }
+    }
+    deviations.extend(
+        current
+            .keys()
+            .filter(|path| !manifest.files.contains_key(*path))
+            .map(|path| Deviation::Added(path.clone())),
+    );
+    Ok(Some(deviations))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Storage;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_mutations_keep_manifest_current() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::initialize(temp_dir.path().join("storage")).unwrap();
+        assert_eq!(verify(&storage.path).unwrap(), None);
+
+        storage.create_profile("base", "Base\n").unwrap();
+        storage.create_profile("lang/rust", "Rust\n").unwrap();
+        storage.create_profile("gone", "Gone\n").unwrap();
+        storage.delete_profile("gone").unwrap();
+
+        let manifest = Manifest::load(&storage.path).unwrap().unwrap();
+        assert_eq!(
+            manifest.files.keys().collect::<Vec<_>>(),
+            vec!["base.md", "lang/rust.md"]
+        );
+        assert_eq!(verify(&storage.path).unwrap(), Some(Vec::new()));
+    }
+
+    #[test]
+    fn test_verify_detects_out_of_band_changes() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::initialize(temp_dir.path().join("storage")).unwrap();
+        storage.create_profile("base", "Base\n").unwrap();
+        storage.create_profile("lang/rust", "Rust\n").unwrap();
+
+        let repo = storage.path.join("repo");
+        std::fs::write(repo.join("base.md"), "Edited elsewhere\n").unwrap();
+        std::fs::remove_file(repo.join("lang/rust.md")).unwrap();
+        std::fs::write(repo.join("synced.md"), "Dropped in\n").unwrap();
+
+        assert_eq!(
+            verify(&storage.path).unwrap().unwrap(),
+            vec![
+                Deviation::Modified("base.md".to_string()),
+                Deviation::Missing("lang/rust.md".to_string()),
+                Deviation::Added("synced.md".to_string()),
+            ]
+        );
+
+        Manifest::scan(&storage.path)
+            .unwrap()
+            .persist(&storage.path)
+            .unwrap();
+        assert_eq!(verify(&storage.path).unwrap(), Some(Vec::new()));
+    }
+}
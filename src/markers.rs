@@ -0,0 +1,135 @@
+use std::ops::Range;
+
+/// Closing marker of a pmx-managed block inside an agent file
+pub const END_MARKER: &str = "<!-- pmx:end -->";
+
+/// Opening marker of the block holding `profile`
+pub fn begin_marker(profile: &str) -> String {
+    format!("<!-- pmx:begin:{profile} -->")
+}
+
+/// Wrap profile content in begin/end markers, ending with a newline
+pub fn wrap(profile: &str, content: &str) -> String {
+    format!(
+        "{}\n{}\n{END_MARKER}\n",
+        begin_marker(profile),
+        content.trim_end_matches('\n')
+    )
+}
+
+/// Byte range of the first block for `profile`, including the end marker's newline
+pub fn find_block(content: &str, profile: &str) -> Option<Range<usize>> {
+    let begin = begin_marker(profile);
+    let mut offset = 0;
+    let mut start = None;
+
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim_end();
+        match start {
+            None if trimmed == begin => start = Some(offset),
+            Some(start) if trimmed == END_MARKER => return Some(start..offset + line.len()),
+            _ => {}
+        }
+        offset += line.len();
+    }
+
+    None
+}
+
+/// Names of all profiles with a marker block in `content`, in order of appearance
+pub fn profiles(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .filter_map(|line| {
+            line.trim_end()
+                .strip_prefix("<!-- pmx:begin:")?
+                .strip_suffix(" -->")
+                .map(str::to_string)
+        })
+        .collect()
+}
+
+/// Placeholder in the banner text that is replaced by the applied profile names
+pub const BANNER_PROFILE: &str = "{profile}";
+
+/// The comment line identifying a file written by pmx, rendered from the banner `text`
+pub fn banner(text: &str, profiles: &[String]) -> String {
+    format!(
+        "<!-- {} -->",
+        text.replace(BANNER_PROFILE, &profiles.join(" + "))
+    )
+}
+
+/// Whether `content` starts with a banner rendered from `text`, for any profiles
+pub fn has_banner(text: &str, content: &str) -> bool {
+    let Some(inner) = content
+        .lines()
+        .next()
+        .and_then(|line| line.trim_end().strip_prefix("<!-- ")?.strip_suffix(" -->"))
+    else {
+        return false;
+    };
+
+    match text.split_once(BANNER_PROFILE) {
+        Some((prefix, suffix)) => {
+            inner.len() >= prefix.len() + suffix.len()
+                && inner.starts_with(prefix)
+                && inner.ends_with(suffix)
+        }
+        None => inner == text,
+    }
+}
+
+/// `content` without its leading banner line, if it has one
+pub fn strip_banner<'a>(text: &str, content: &'a str) -> &'a str {
+    if !has_banner(text, content) {
+        return content;
+    }
+    content.split_once('\n').map_or("", |(_, rest)| rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap_and_find_block() {
+        let block = wrap("rust", "Use clippy.\n");
+        assert_eq!(
+            block,
+            "<!-- pmx:begin:rust -->\nUse clippy.\n<!-- pmx:end -->\n"
+        );
+
+        let content = format!("# Base\n\n{block}\ntrailing\n");
+        let range = find_block(&content, "rust").unwrap();
+        assert_eq!(&content[range], block);
+        assert!(find_block(&content, "other").is_none());
+    }
+
+    #[test]
+    fn test_find_block_requires_end_marker() {
+        let content = "<!-- pmx:begin:rust -->\nUse clippy.\n";
+        assert!(find_block(content, "rust").is_none());
+    }
+
+    #[test]
+    fn test_profiles() {
+        let content = format!("{}\n{}", wrap("a", "x"), wrap("nested/b", "y"));
+        assert_eq!(profiles(&content), vec!["a", "nested/b"]);
+    }
+
+    #[test]
+    fn test_banner() {
+        let text = "managed by pmx: profile {profile}";
+        let line = banner(text, &["base".to_string(), "rust".to_string()]);
+        assert_eq!(line, "<!-- managed by pmx: profile base + rust -->");
+
+        assert!(has_banner(text, &format!("{line}\n# Base\n")));
+        assert!(!has_banner(text, "# Hand-written\n"));
+        assert!(!has_banner(text, "<!-- managed by someone else -->\n"));
+        assert!(has_banner("generated", "<!-- generated -->\n"));
+
+        assert_eq!(strip_banner(text, &format!("{line}\n# Base\n")), "# Base\n");
+        assert_eq!(strip_banner(text, "# Base\n"), "# Base\n");
+    }
+}
@@ -0,0 +1,184 @@
+use similar::{Algorithm, DiffOp, capture_diff_slices};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeResult {
+    /// Merged content, with conflict markers around unresolved hunks
+    pub content: String,
+    /// Number of conflicting hunks
+    pub conflicts: usize,
+}
+
+/// Labels written next to conflict markers
+#[derive(Debug, Clone, Copy)]
+pub struct MergeLabels<'a> {
+    pub ours: &'a str,
+    pub theirs: &'a str,
+}
+
+/// Three-way merge of `ours` and `theirs` against their common ancestor `base`.
+///
+/// Hunks changed on only one side are taken from that side; hunks changed
+/// identically on both sides are taken once; anything else becomes a conflict
+/// wrapped in `<<<<<<<`/`=======`/`>>>>>>>` markers.
+///
+/// Without a base, the lines common to both sides act as the ancestor, so
+/// lines present on only one side are kept and diverging lines conflict.
+pub fn merge3(
+    base: Option<&str>,
+    ours: &str,
+    theirs: &str,
+    labels: MergeLabels<'_>,
+) -> MergeResult {
+    let ours_lines: Vec<&str> = ours.split_inclusive('\n').collect();
+    let theirs_lines: Vec<&str> = theirs.split_inclusive('\n').collect();
+    let base_lines: Vec<&str> = match base {
+        Some(base) => base.split_inclusive('\n').collect(),
+        None => common_lines(&ours_lines, &theirs_lines),
+    };
+
+    let ours_map = match_lines(&base_lines, &ours_lines);
+    let theirs_map = match_lines(&base_lines, &theirs_lines);
+
+    let mut output = String::new();
+    let mut conflicts = 0;
+    let (mut o, mut a, mut b) = (0, 0, 0);
+
+    while o < base_lines.len() || a < ours_lines.len() || b < theirs_lines.len() {
+        if o < base_lines.len() && ours_map[o] == Some(a) && theirs_map[o] == Some(b) {
+            output.push_str(base_lines[o]);
+            o += 1;
+            a += 1;
+            b += 1;
+            continue;
+        }
+
+        // Find the next base line that is unchanged on both sides
+        let stable = (o..base_lines.len()).find_map(|i| Some((i, ours_map[i]?, theirs_map[i]?)));
+        let (next_o, next_a, next_b) =
+            stable.unwrap_or((base_lines.len(), ours_lines.len(), theirs_lines.len()));
+
+        let base_chunk = &base_lines[o..next_o];
+        let ours_chunk = &ours_lines[a..next_a];
+        let theirs_chunk = &theirs_lines[b..next_b];
+
+        if ours_chunk == base_chunk || ours_chunk == theirs_chunk {
+            theirs_chunk.iter().for_each(|line| output.push_str(line));
+        } else if theirs_chunk == base_chunk {
+            ours_chunk.iter().for_each(|line| output.push_str(line));
+        } else {
+            conflicts += 1;
+            push_marker(&mut output, &format!("<<<<<<< {}", labels.ours));
+            push_chunk(&mut output, ours_chunk);
+            push_marker(&mut output, "=======");
+            push_chunk(&mut output, theirs_chunk);
+            push_marker(&mut output, &format!(">>>>>>> {}", labels.theirs));
+        }
+
+        (o, a, b) = (next_o, next_a, next_b);
+    }
+
+    MergeResult {
+        content: output,
+        conflicts,
+    }
+}
+
+/// For every line of `base`, the index of the matching line in `other`, if any
+fn match_lines(base: &[&str], other: &[&str]) -> Vec<Option<usize>> {
+    let mut map = vec![None; base.len()];
+    for op in capture_diff_slices(Algorithm::Myers, base, other) {
+        if let DiffOp::Equal {
+            old_index,
+            new_index,
+            len,
+        } = op
+        {
+            for offset in 0..len {
+                map[old_index + offset] = Some(new_index + offset);
+            }
+        }
+    }
+    map
+}
+
+fn common_lines<'a>(ours: &[&'a str], theirs: &[&'a str]) -> Vec<&'a str> {
+    capture_diff_slices(Algorithm::Myers, ours, theirs)
+        .into_iter()
+        .filter_map(|op| match op {
+            DiffOp::Equal { old_index, len, .. } => Some(&ours[old_index..old_index + len]),
+            _ => None,
+        })
+        .flatten()
+        .copied()
+        .collect()
+}
+
+fn push_marker(output: &mut String, marker: &str) {
+    if !output.is_empty() && !output.ends_with('\n') {
+        output.push('\n');
+    }
+    output.push_str(marker);
+    output.push('\n');
+}
+
+fn push_chunk(output: &mut String, chunk: &[&str]) {
+    chunk.iter().for_each(|line| output.push_str(line));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LABELS: MergeLabels<'static> = MergeLabels {
+        ours: "ours",
+        theirs: "theirs",
+    };
+
+    #[test]
+    fn test_merge_non_overlapping_changes() {
+        let base = "a\nb\nc\nd\n";
+        let ours = "a\nB\nc\nd\n";
+        let theirs = "a\nb\nc\nD\n";
+        let result = merge3(Some(base), ours, theirs, LABELS);
+        assert_eq!(result.content, "a\nB\nc\nD\n");
+        assert_eq!(result.conflicts, 0);
+    }
+
+    #[test]
+    fn test_merge_identical_changes() {
+        let base = "a\nb\n";
+        let result = merge3(Some(base), "a\nX\n", "a\nX\n", LABELS);
+        assert_eq!(result.content, "a\nX\n");
+        assert_eq!(result.conflicts, 0);
+    }
+
+    #[test]
+    fn test_merge_conflict() {
+        let base = "a\nb\nc\n";
+        let ours = "a\nmine\nc\n";
+        let theirs = "a\nyours\nc\n";
+        let result = merge3(Some(base), ours, theirs, LABELS);
+        assert_eq!(
+            result.content,
+            "a\n<<<<<<< ours\nmine\n=======\nyours\n>>>>>>> theirs\nc\n"
+        );
+        assert_eq!(result.conflicts, 1);
+    }
+
+    #[test]
+    fn test_merge_deletion_on_one_side() {
+        let base = "a\nb\nc\n";
+        let result = merge3(Some(base), "a\nc\n", "a\nb\nc\nd\n", LABELS);
+        assert_eq!(result.content, "a\nc\nd\n");
+        assert_eq!(result.conflicts, 0);
+    }
+
+    #[test]
+    fn test_merge_without_base() {
+        let ours = "a\nonly ours\nc\n";
+        let theirs = "a\nc\nonly theirs\n";
+        let result = merge3(None, ours, theirs, LABELS);
+        assert_eq!(result.content, "a\nonly ours\nc\nonly theirs\n");
+        assert_eq!(result.conflicts, 0);
+    }
+}
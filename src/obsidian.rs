@@ -0,0 +1,267 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use regex::{Captures, Regex};
+
+use crate::frontmatter::ProfileMetadata;
+
+/// Prefix of the `source:` front-matter of imported profiles, followed by the
+/// note's path in the vault
+pub const SOURCE_PREFIX: &str = "obsidian:";
+
+/// Mapping rules read from `--map`: which notes become which profiles
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MapRules {
+    /// What to do with `[[wiki-links]]` unless a rule says otherwise
+    #[serde(default)]
+    pub wikilinks: WikiLinks,
+    /// Rules tried in order; a note is imported by the first one it matches.
+    /// Without rules every note is imported.
+    #[serde(default, rename = "rule")]
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    /// Only notes below this vault folder; profile names are relative to it
+    pub folder: Option<String>,
+    /// Only notes with this tag, in their front-matter or inline as `#tag`
+    pub tag: Option<String>,
+    /// Directory in the repository the profiles are created under
+    pub prefix: Option<String>,
+    pub wikilinks: Option<WikiLinks>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WikiLinks {
+    /// Replace links with their text
+    #[default]
+    Strip,
+    /// Turn links to other imported notes into `{{include: ...}}` directives
+    Include,
+}
+
+impl MapRules {
+    pub fn load(path: &Path) -> crate::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+        toml::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", path.display(), e))
+    }
+}
+
+/// A note selected for import and the profile it becomes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Import {
+    /// `/`-separated path of the note in the vault
+    pub note: String,
+    pub name: String,
+    pub content: String,
+}
+
+/// Select the notes of `vault` matching `rules` and render them as profiles
+pub fn plan(vault: &Path, rules: &MapRules) -> crate::Result<Vec<Import>> {
+    let mut selected = Vec::new();
+    for relative in crate::utils::visible_files(vault)? {
+        if relative.extension().is_none_or(|ext| ext != "md") {
+            continue;
+        }
+        let note = relative.to_string_lossy().replace('\\', "/");
+        let content = crate::encoding::read_text(&vault.join(&relative))
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", note, e))?;
+        let tags = tags(&content);
+        let Some((name, wikilinks)) = select(rules, &note, &tags) else {
+            continue;
+        };
+        selected.push((note, name, wikilinks, content, tags));
+    }
+
+    // Links name a note by file name or by path, without the extension
+    let mut targets = BTreeMap::new();
+    for (note, name, ..) in &selected {
+        let path = note.trim_end_matches(".md");
+        let stem = path.rsplit('/').next().unwrap_or(path);
+        targets.insert(stem.to_lowercase(), name.clone());
+        targets.insert(path.to_lowercase(), name.clone());
+    }
+
+    selected
+        .into_iter()
+        .map(|(note, name, wikilinks, content, tags)| {
+            let (_, body) = crate::frontmatter::split(&content);
+            let title = note.trim_end_matches(".md").rsplit('/').next();
+            let metadata = ProfileMetadata {
+                title: title.map(str::to_string),
+                source: Some(format!("{SOURCE_PREFIX}{note}")),
+                tags,
+                ..Default::default()
+            };
+            let frontmatter = serde_yaml::to_string(&metadata)
+                .map_err(|e| anyhow::anyhow!("Failed to serialize front-matter: {}", e))?;
+            let body = convert_links(body.trim_start_matches('\n'), wikilinks, &targets);
+            Ok(Import {
+                note,
+                name,
+                content: format!("---\n{frontmatter}---\n{body}"),
+            })
+        })
+        .collect()
+}
+
+/// Profile name and link handling for a note, from the first rule it matches
+fn select(rules: &MapRules, note: &str, tags: &[String]) -> Option<(String, WikiLinks)> {
+    let path = note.trim_end_matches(".md");
+    if rules.rules.is_empty() {
+        return Some((crate::commands::profile::slugify(path), rules.wikilinks));
+    }
+
+    rules.rules.iter().find_map(|rule| {
+        let relative = match &rule.folder {
+            Some(folder) => path
+                .strip_prefix(folder.trim_matches('/'))?
+                .strip_prefix('/')?,
+            None => path,
+        };
+        if let Some(tag) = &rule.tag {
+            let tag = tag.trim_start_matches('#');
+            if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                return None;
+            }
+        }
+        let name = match &rule.prefix {
+            Some(prefix) => format!("{}/{relative}", prefix.trim_matches('/')),
+            None => relative.to_string(),
+        };
+        Some((
+            crate::commands::profile::slugify(&name),
+            rule.wikilinks.unwrap_or(rules.wikilinks),
+        ))
+    })
+}
+
+/// Tags from the note's front-matter (a list or a single string) and inline `#tags`
+fn tags(content: &str) -> Vec<String> {
+    let (frontmatter, body) = crate::frontmatter::split(content);
+    let declared = frontmatter
+        .and_then(|frontmatter| serde_yaml::from_str::<serde_yaml::Value>(frontmatter).ok())
+        .and_then(|value| value.get("tags").cloned());
+    let mut tags: Vec<String> = match declared {
+        Some(serde_yaml::Value::Sequence(items)) => items
+            .iter()
+            .filter_map(|item| item.as_str().map(str::to_string))
+            .collect(),
+        Some(serde_yaml::Value::String(list)) => list
+            .split([',', ' '])
+            .filter(|tag| !tag.is_empty())
+            .map(str::to_string)
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    let inline = Regex::new(r"(?:^|\s)#([\p{L}\p{N}_/-]*[\p{L}_/-][\p{L}\p{N}_/-]*)").unwrap();
+    tags.extend(
+        inline
+            .captures_iter(body)
+            .map(|captures| captures[1].to_string()),
+    );
+    let mut seen = std::collections::BTreeSet::new();
+    tags.retain(|tag| seen.insert(tag.trim_start_matches('#').to_lowercase()));
+    tags.iter_mut()
+        .for_each(|tag| *tag = tag.trim_start_matches('#').to_string());
+    tags
+}
+
+/// Rewrite `[[note]]`, `[[note|text]]`, `[[note#heading]]` and `![[embeds]]`
+fn convert_links(body: &str, mode: WikiLinks, targets: &BTreeMap<String, String>) -> String {
+    let link = Regex::new(r"!?\[\[([^\]|#]*)(#[^\]|]*)?(?:\|([^\]]*))?\]\]").unwrap();
+    link.replace_all(body, |captures: &Captures| {
+        let target = captures[1].trim();
+        if mode == WikiLinks::Include
+            && let Some(name) = targets.get(&target.trim_end_matches(".md").to_lowercase())
+        {
+            return format!("{{{{include: {name}}}}}");
+        }
+        match captures.get(3) {
+            Some(text) => text.as_str().trim().to_string(),
+            None => match captures.get(2) {
+                Some(heading) if target.is_empty() => heading.as_str()[1..].to_string(),
+                _ => target.to_string(),
+            },
+        }
+    })
+    .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_tags() {
+        let content =
+            "---\ntags: [prompt, Draft]\n---\n# Heading\nText #rust and #prompt, not a#b or #42\n";
+        assert_eq!(tags(content), vec!["prompt", "Draft", "rust"]);
+        assert_eq!(tags("---\ntags: a, b\n---\n"), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_convert_links() {
+        let targets = BTreeMap::from([("style".to_string(), "shared/style".to_string())]);
+        let body = "See [[Style]], [[Other|the other note]] and [[Other#Usage]]. ![[style]]\n";
+        assert_eq!(
+            convert_links(body, WikiLinks::Strip, &targets),
+            "See Style, the other note and Other. style\n"
+        );
+        assert_eq!(
+            convert_links(body, WikiLinks::Include, &targets),
+            "See {{include: shared/style}}, the other note and Other. {{include: shared/style}}\n"
+        );
+    }
+
+    #[test]
+    fn test_plan_with_rules() {
+        let temp_dir = TempDir::new().unwrap();
+        let vault = temp_dir.path();
+        std::fs::create_dir_all(vault.join("Prompts/Coding")).unwrap();
+        std::fs::create_dir_all(vault.join("Journal")).unwrap();
+        std::fs::create_dir_all(vault.join(".obsidian")).unwrap();
+        std::fs::write(
+            vault.join("Prompts/Coding/Rust Review.md"),
+            "---\naliases: [rr]\n---\nFollow [[Style Guide]].\n",
+        )
+        .unwrap();
+        std::fs::write(vault.join("Journal/Style Guide.md"), "Be terse. #prompt\n").unwrap();
+        std::fs::write(vault.join("Journal/Diary.md"), "Private.\n").unwrap();
+        std::fs::write(vault.join(".obsidian/app.md"), "ignored").unwrap();
+
+        let rules: MapRules = toml::from_str(
+            r#"
+wikilinks = "include"
+
+[[rule]]
+folder = "Prompts"
+
+[[rule]]
+tag = "prompt"
+prefix = "shared"
+"#,
+        )
+        .unwrap();
+        let imports = plan(vault, &rules).unwrap();
+        let names: Vec<&str> = imports.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["shared/journal/style-guide", "coding/rust-review"]
+        );
+        assert_eq!(
+            imports[1].content,
+            "---\ntitle: Rust Review\nsource: obsidian:Prompts/Coding/Rust Review.md\n---\n\
+             Follow {{include: shared/journal/style-guide}}.\n"
+        );
+        assert!(imports[0].content.contains("tags:\n- prompt\n"));
+    }
+}
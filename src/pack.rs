@@ -0,0 +1,214 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+const PACKS_FILE: &str = "packs.toml";
+
+/// Checksum list a publisher may ship (and sign) with a pack
+pub const CHECKSUMS_FILE: &str = "SHA256SUMS";
+
+/// Manifest at the root of a pack
+pub const MANIFEST_FILE: &str = "pack.toml";
+
+/// Contents of `pack.toml`
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PackManifest {
+    pub pack: PackInfo,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PackInfo {
+    pub name: String,
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub authors: Vec<String>,
+}
+
+impl PackManifest {
+    /// Load `pack.toml` from a pack directory, if it has one
+    pub fn load(dir: &Path) -> crate::Result<Option<Self>> {
+        let path = dir.join(MANIFEST_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", MANIFEST_FILE, e))?;
+        toml::from_str(&content)
+            .map(Some)
+            .map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", MANIFEST_FILE, e))
+    }
+}
+
+/// Files of a pack with a manifest that describe the pack rather than being profiles
+pub fn is_pack_metadata(relative: &Path) -> bool {
+    relative == Path::new(MANIFEST_FILE)
+        || relative == Path::new("README.md")
+        || relative.starts_with("tests")
+}
+
+/// Packs installed into `repo/`, stored next to `config.toml`
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct InstalledPacks {
+    #[serde(default)]
+    pub packs: BTreeMap<String, InstalledPack>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct InstalledPack {
+    /// Where the pack was installed from
+    pub source: String,
+    /// How the publisher's signature was checked, if it was
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    /// Content hash of every installed file, keyed by path relative to the pack directory
+    pub files: BTreeMap<String, String>,
+}
+
+/// A difference between an installed pack and what was recorded at install time
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Deviation {
+    Modified(String),
+    Missing(String),
+    Added(String),
+    Unreadable(String),
+}
+
+impl std::fmt::Display for Deviation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Deviation::Modified(path) => write!(f, "modified: {path}"),
+            Deviation::Missing(path) => write!(f, "missing:  {path}"),
+            Deviation::Added(path) => write!(f, "added:    {path}"),
+            Deviation::Unreadable(path) => write!(f, "unreadable: {path}"),
+        }
+    }
+}
+
+impl InstalledPacks {
+    pub fn load(storage_path: &Path) -> crate::Result<Self> {
+        let path = storage_path.join(PACKS_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| anyhow::anyhow!("Failed to read packs file: {}", e))?;
+        toml::from_str(&content).map_err(|e| anyhow::anyhow!("Failed to parse packs file: {}", e))
+    }
+
+    pub fn persist(&self, storage_path: &Path) -> crate::Result<()> {
+        let content = toml::to_string(self)
+            .map_err(|e| anyhow::anyhow!("Failed to serialize packs: {}", e))?;
+        std::fs::write(storage_path.join(PACKS_FILE), content)
+            .map_err(|e| anyhow::anyhow!("Failed to write packs file: {}", e))
+    }
+}
+
+/// Directory an installed pack lives in
+pub fn pack_dir(storage: &crate::storage::Storage, name: &str) -> PathBuf {
+    storage.path.join("repo").join(name)
+}
+
+/// Content hashes of `files` below `root`, keyed by `/`-separated relative path
+pub fn hash_files(root: &Path, files: &[PathBuf]) -> crate::Result<BTreeMap<String, String>> {
+    files
+        .iter()
+        .map(|relative| {
+            let content = std::fs::read(root.join(relative))
+                .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", relative.display(), e))?;
+            Ok((key(relative), crate::utils::content_hash(&content)))
+        })
+        .collect()
+}
+
+/// Compare the pack directory against the hashes recorded at install time
+pub fn verify(dir: &Path, pack: &InstalledPack) -> crate::Result<Vec<Deviation>> {
+    let current = if dir.is_dir() {
+        hash_files(dir, &crate::utils::visible_files(dir)?)?
+    } else {
+        BTreeMap::new()
+    };
+
+    let mut deviations = Vec::new();
+    for (path, hash) in &pack.files {
+        match current.get(path) {
+            None => deviations.push(Deviation::Missing(path.clone())),
+            Some(actual) if actual != hash => deviations.push(Deviation::Modified(path.clone())),
+            Some(_) => {}
+        }
+    }
+    deviations.extend(
+        current
+            .keys()
+            .filter(|path| !pack.files.contains_key(*path))
+            .map(|path| Deviation::Added(path.clone())),
+    );
+    Ok(deviations)
+}
+
+/// Parse `sha256sum` output (`<hash>  <path>` per line)
+pub fn parse_checksums(content: &str) -> crate::Result<BTreeMap<String, String>> {
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let (hash, path) = line
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| anyhow::anyhow!("Malformed checksum line: {}", line))?;
+            let path = path.trim_start().trim_start_matches('*');
+            Ok((
+                path.trim_start_matches("./").to_string(),
+                hash.to_lowercase(),
+            ))
+        })
+        .collect()
+}
+
+fn key(relative: &Path) -> String {
+    relative.to_string_lossy().replace('\\', "/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_verify_reports_deviations() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        std::fs::write(dir.join("a.md"), "a").unwrap();
+        std::fs::write(dir.join("b.md"), "b").unwrap();
+        let files = crate::utils::visible_files(dir).unwrap();
+        let pack = InstalledPack {
+            source: "test".to_string(),
+            signature: None,
+            files: hash_files(dir, &files).unwrap(),
+        };
+        assert!(verify(dir, &pack).unwrap().is_empty());
+
+        std::fs::write(dir.join("a.md"), "changed").unwrap();
+        std::fs::remove_file(dir.join("b.md")).unwrap();
+        std::fs::write(dir.join("c.md"), "c").unwrap();
+        assert_eq!(
+            verify(dir, &pack).unwrap(),
+            vec![
+                Deviation::Modified("a.md".to_string()),
+                Deviation::Missing("b.md".to_string()),
+                Deviation::Added("c.md".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_checksums() {
+        let sums = parse_checksums("ABC  ./review.md\ndef *lang/rust.md\n\n").unwrap();
+        assert_eq!(sums["review.md"], "abc");
+        assert_eq!(sums["lang/rust.md"], "def");
+        assert!(parse_checksums("nohash").is_err());
+    }
+}
@@ -0,0 +1,146 @@
+use std::path::{Path, PathBuf};
+
+/// Environment variable naming the storage directory
+pub const HOME_ENV: &str = "PMX_HOME";
+
+/// Older name of [`HOME_ENV`], still honoured after it
+pub const LEGACY_ENV: &str = "PMX_CONFIG_FILE";
+
+/// Where the storage directory location came from, in order of precedence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    /// `--storage` (or its old name `--config`)
+    Flag,
+    /// `$PMX_HOME`
+    Home,
+    /// `$PMX_CONFIG_FILE`
+    LegacyEnv,
+    /// `$XDG_CONFIG_HOME/pmx`
+    Xdg,
+    /// `~/.config/pmx`, or `%APPDATA%\pmx` on Windows
+    Default,
+}
+
+impl Source {
+    /// Whether the user named the directory, so pmx must not create it on its own
+    pub fn is_explicit(self) -> bool {
+        matches!(self, Source::Flag | Source::Home | Source::LegacyEnv)
+    }
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Source::Flag => "--storage",
+            Source::Home => "$PMX_HOME",
+            Source::LegacyEnv => "$PMX_CONFIG_FILE",
+            Source::Xdg => "$XDG_CONFIG_HOME",
+            Source::Default => "default",
+        })
+    }
+}
+
+/// A resolved storage directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoragePath {
+    pub path: PathBuf,
+    pub source: Source,
+}
+
+/// The storage directory named by `--storage`, `$PMX_HOME` or `$PMX_CONFIG_FILE`
+pub fn explicit(flag: Option<&Path>) -> Option<StoragePath> {
+    if let Some(path) = flag {
+        return Some(StoragePath {
+            path: path.to_path_buf(),
+            source: Source::Flag,
+        });
+    }
+
+    [(HOME_ENV, Source::Home), (LEGACY_ENV, Source::LegacyEnv)]
+        .into_iter()
+        .find_map(|(var, source)| {
+            let value = std::env::var_os(var).filter(|value| !value.is_empty())?;
+            Some(StoragePath {
+                path: PathBuf::from(value),
+                source,
+            })
+        })
+}
+
+/// Resolve the storage directory: `--storage` > `$PMX_HOME` > `$PMX_CONFIG_FILE` >
+/// `$XDG_CONFIG_HOME/pmx` > `~/.config/pmx` (`%APPDATA%\pmx` on Windows)
+pub fn resolve(flag: Option<&Path>) -> crate::Result<StoragePath> {
+    if let Some(explicit) = explicit(flag) {
+        return Ok(explicit);
+    }
+
+    if let Some(xdg) = std::env::var_os("XDG_CONFIG_HOME").filter(|value| !value.is_empty()) {
+        return Ok(StoragePath {
+            path: xdg_storage(Path::new(&xdg)),
+            source: Source::Xdg,
+        });
+    }
+
+    Ok(StoragePath {
+        path: default_storage()?,
+        source: Source::Default,
+    })
+}
+
+fn default_storage() -> crate::Result<PathBuf> {
+    if cfg!(windows)
+        && let Some(appdata) = std::env::var_os("APPDATA").filter(|value| !value.is_empty())
+    {
+        return Ok(PathBuf::from(appdata).join("pmx"));
+    }
+    Ok(crate::utils::home_dir()?.join(".config").join("pmx"))
+}
+
+/// `$XDG_CONFIG_HOME/pmx`, unless storage was set up directly in `$XDG_CONFIG_HOME`
+/// by an older pmx release
+fn xdg_storage(xdg: &Path) -> PathBuf {
+    let storage = xdg.join("pmx");
+    if !storage.exists() && is_storage(xdg) {
+        eprintln!(
+            "Warning: using storage directly in {}; move it to {} (or set {})",
+            xdg.display(),
+            storage.display(),
+            HOME_ENV
+        );
+        return xdg.to_path_buf();
+    }
+    storage
+}
+
+/// Whether `dir` looks like a pmx storage directory
+fn is_storage(dir: &Path) -> bool {
+    dir.join("config.toml").is_file() && dir.join("repo").is_dir()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_flag_wins() {
+        let resolved = resolve(Some(Path::new("/tmp/pmx-storage"))).unwrap();
+        assert_eq!(resolved.path, Path::new("/tmp/pmx-storage"));
+        assert_eq!(resolved.source, Source::Flag);
+        assert!(resolved.source.is_explicit());
+    }
+
+    #[test]
+    fn test_xdg_storage_prefers_pmx_subdirectory() {
+        let temp_dir = TempDir::new().unwrap();
+        assert_eq!(xdg_storage(temp_dir.path()), temp_dir.path().join("pmx"));
+
+        // Storage created by older releases directly in $XDG_CONFIG_HOME keeps working
+        std::fs::write(temp_dir.path().join("config.toml"), "").unwrap();
+        std::fs::create_dir(temp_dir.path().join("repo")).unwrap();
+        assert_eq!(xdg_storage(temp_dir.path()), temp_dir.path());
+
+        std::fs::create_dir(temp_dir.path().join("pmx")).unwrap();
+        assert_eq!(xdg_storage(temp_dir.path()), temp_dir.path().join("pmx"));
+    }
+}
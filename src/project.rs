@@ -0,0 +1,128 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::agent::Agent;
+
+/// Name of the per-project file pinning profiles per agent
+pub const PROJECT_FILE: &str = ".pmx.toml";
+
+/// Contents of a `.pmx.toml` file
+///
+/// ```toml
+/// [profiles]
+/// claude = ["base", "rust"]
+/// codex = ["base"]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ProjectConfig {
+    /// Profiles per agent name; the first one is set, the rest appended
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Vec<String>>,
+}
+
+impl ProjectConfig {
+    pub fn path(dir: &Path) -> PathBuf {
+        dir.join(PROJECT_FILE)
+    }
+
+    /// Load `.pmx.toml` from `dir`, if there is one
+    pub fn load(dir: &Path) -> crate::Result<Option<Self>> {
+        let path = Self::path(dir);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+        Self::parse(&content)
+            .map(Some)
+            .map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", path.display(), e))
+    }
+
+    /// Find the nearest `.pmx.toml` in `start` or one of its parents
+    pub fn find(start: &Path) -> crate::Result<Option<(PathBuf, Self)>> {
+        for dir in start.ancestors() {
+            if let Some(config) = Self::load(dir)? {
+                return Ok(Some((dir.to_path_buf(), config)));
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn parse(content: &str) -> crate::Result<Self> {
+        let config: Self = toml::from_str(content)?;
+        config.pins()?;
+        Ok(config)
+    }
+
+    /// Pinned profiles per agent, in agent order
+    pub fn pins(&self) -> crate::Result<Vec<(Agent, &[String])>> {
+        use clap::ValueEnum;
+
+        let mut pins = self
+            .profiles
+            .iter()
+            .map(|(name, profiles)| {
+                Agent::from_str(name, true)
+                    .map(|agent| (agent, profiles.as_slice()))
+                    .map_err(|_| anyhow::anyhow!("Unknown agent '{}'", name))
+            })
+            .collect::<crate::Result<Vec<_>>>()?;
+        pins.sort_by_key(|(agent, _)| Agent::ALL.iter().position(|a| a == agent));
+        Ok(pins)
+    }
+}
+
+/// Whether pmx last wrote exactly `profiles` to `target` and the file is still there
+pub fn is_applied(state: &crate::state::State, target: &Path, profiles: &[String]) -> bool {
+    target.exists()
+        && state
+            .get(target)
+            .is_some_and(|applied| applied.profiles == profiles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_pins_in_agent_order() {
+        let config =
+            ProjectConfig::parse("[profiles]\ncodex = [\"base\"]\nclaude = [\"base\", \"rust\"]\n")
+                .unwrap();
+        let pins = config.pins().unwrap();
+        assert_eq!(pins.len(), 2);
+        assert_eq!(pins[0].0, Agent::Claude);
+        assert_eq!(pins[0].1, ["base", "rust"]);
+        assert_eq!(pins[1].0, Agent::Codex);
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_agent() {
+        let err = ProjectConfig::parse("[profiles]\nvim = [\"base\"]\n").unwrap_err();
+        assert!(err.to_string().contains("Unknown agent 'vim'"));
+    }
+
+    #[test]
+    fn test_load_missing_file() {
+        let temp_dir = TempDir::new().unwrap();
+        assert!(ProjectConfig::load(temp_dir.path()).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_find_in_parent() {
+        let temp_dir = TempDir::new().unwrap();
+        let nested = temp_dir.path().join("src").join("module");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(
+            temp_dir.path().join(PROJECT_FILE),
+            "[profiles]\nclaude = [\"base\"]\n",
+        )
+        .unwrap();
+
+        let (dir, config) = ProjectConfig::find(&nested).unwrap().unwrap();
+        assert_eq!(dir, temp_dir.path());
+        assert_eq!(config.profiles["claude"], ["base"]);
+    }
+}
@@ -0,0 +1,94 @@
+use regex::Regex;
+use serde_json::Value;
+
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Hides secret values before they reach the logs
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    patterns: Vec<Regex>,
+}
+
+impl Redactor {
+    pub fn new(patterns: &[String]) -> crate::Result<Self> {
+        let patterns = patterns
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern).map_err(|e| {
+                    anyhow::anyhow!("Failed to parse redaction pattern '{}': {}", pattern, e)
+                })
+            })
+            .collect::<crate::Result<_>>()?;
+        Ok(Self { patterns })
+    }
+
+    fn is_secret(&self, value: &str) -> bool {
+        self.patterns.iter().any(|pattern| pattern.is_match(value))
+    }
+
+    /// Argument values matching a secret pattern
+    pub fn secrets<'a>(&self, arguments: impl IntoIterator<Item = &'a Value>) -> Vec<String> {
+        arguments
+            .into_iter()
+            .map(|value| match value {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            })
+            .filter(|value| !value.is_empty() && self.is_secret(value))
+            .collect()
+    }
+
+    /// Copy of `value` where strings matching a pattern are replaced entirely and
+    /// every occurrence of a known secret (e.g. substituted into a prompt) is masked
+    pub fn redact(&self, value: &Value, secrets: &[String]) -> Value {
+        match value {
+            Value::String(s) if self.is_secret(s) => Value::String(REDACTED.to_string()),
+            Value::String(s) => Value::String(
+                secrets
+                    .iter()
+                    .fold(s.clone(), |s, secret| s.replace(secret.as_str(), REDACTED)),
+            ),
+            Value::Array(items) => Value::Array(
+                items
+                    .iter()
+                    .map(|item| self.redact(item, secrets))
+                    .collect(),
+            ),
+            Value::Object(map) => Value::Object(
+                map.iter()
+                    .map(|(key, item)| (key.clone(), self.redact(item, secrets)))
+                    .collect(),
+            ),
+            other => other.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_redact_arguments_and_substituted_values() {
+        let redactor = Redactor::new(&["^sk-".to_string(), "(?i)password".to_string()]).unwrap();
+        let arguments = json!({"API_KEY": "sk-123", "NAME": "Ada", "NOTE": "my Password is x"});
+        let secrets = redactor.secrets(arguments.as_object().unwrap().values());
+        assert_eq!(secrets, vec!["sk-123", "my Password is x"]);
+
+        assert_eq!(
+            redactor.redact(&arguments, &secrets),
+            json!({"API_KEY": REDACTED, "NAME": "Ada", "NOTE": REDACTED})
+        );
+        assert_eq!(
+            redactor.redact(&json!({"text": "Use sk-123 as Ada"}), &secrets),
+            json!({"text": "Use [REDACTED] as Ada"})
+        );
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        let error = Redactor::new(&["(".to_string()]).unwrap_err();
+        assert!(error.to_string().contains("redaction pattern '('"));
+    }
+}
@@ -0,0 +1,239 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, ensure};
+
+use crate::storage::Storage;
+use crate::utils::git;
+
+/// Where `repo/` is synchronised: `[sync] url`, or else its `origin` remote
+fn remote(storage: &Storage) -> crate::Result<String> {
+    if let Some(url) = &storage.config.sync.url {
+        return Ok(url.clone());
+    }
+    let repo = storage.path.join("repo");
+    if crate::history::is_enabled(&repo) && git(&repo, &["remote", "get-url", "origin"]).is_ok() {
+        return Ok("origin".to_string());
+    }
+    bail!("No sync remote configured; set `url` under [sync] in config.toml")
+}
+
+/// Whether the remote has the sync branch yet
+fn has_branch(repo: &Path, url: &str, branch: &str) -> crate::Result<bool> {
+    let output = git(
+        repo,
+        &["ls-remote", "--heads", url, &format!("refs/heads/{branch}")],
+    )?;
+    Ok(!output.trim().is_empty())
+}
+
+fn head(repo: &Path) -> Option<String> {
+    git(repo, &["rev-parse", "--verify", "--quiet", "HEAD"])
+        .ok()
+        .map(|output| output.trim().to_string())
+}
+
+fn is_ancestor(repo: &Path, ancestor: &str, descendant: &str) -> bool {
+    git(repo, &["merge-base", "--is-ancestor", ancestor, descendant]).is_ok()
+}
+
+/// Commit whatever changed in `repo/` outside of pmx's history, so that it takes
+/// part in the merge rather than being overwritten by it
+fn commit_local_changes(repo: &Path) -> crate::Result<()> {
+    crate::history::commit(repo, Path::new("."), "Local changes before sync")
+}
+
+/// Fill an empty `repo/` with a clone of the remote's sync branch
+pub fn clone(storage: &Storage, url: Option<&str>) -> crate::Result<()> {
+    let url = match url {
+        Some(url) => url.to_string(),
+        None => remote(storage)?,
+    };
+    let repo = storage.path.join("repo");
+    ensure!(
+        !repo.exists() || crate::utils::visible_files(&repo)?.is_empty(),
+        "{} already has profiles; use `pmx sync pull` to combine them with the remote",
+        repo.display()
+    );
+
+    let staging = storage.path.join("repo.clone");
+    if staging.exists() {
+        std::fs::remove_dir_all(&staging)
+            .map_err(|e| anyhow::anyhow!("Failed to remove {}: {}", staging.display(), e))?;
+    }
+    git(
+        &storage.path,
+        &[
+            "clone",
+            "--quiet",
+            "--branch",
+            &storage.config.sync.branch,
+            &url,
+            "repo.clone",
+        ],
+    )?;
+    if repo.exists() {
+        std::fs::remove_dir_all(&repo)
+            .map_err(|e| anyhow::anyhow!("Failed to remove {}: {}", repo.display(), e))?;
+    }
+    std::fs::rename(&staging, &repo)
+        .map_err(|e| anyhow::anyhow!("Failed to move the clone into place: {}", e))?;
+
+    crate::manifest::Manifest::scan(&storage.path)?.persist(&storage.path)
+}
+
+/// Merge the remote's sync branch into `repo/`, committing local changes first.
+/// Returns the files that changed, relative to `repo/`. When both sides changed
+/// the same lines the merge is abandoned and the conflicting files are reported.
+pub fn pull(storage: &Storage) -> crate::Result<Vec<String>> {
+    let url = remote(storage)?;
+    let branch = &storage.config.sync.branch;
+    let repo = storage.path.join("repo");
+    commit_local_changes(&repo)?;
+    ensure!(
+        has_branch(&repo, &url, branch)?,
+        "The remote has no branch '{}' yet; run `pmx sync push` to create it",
+        branch
+    );
+    git(&repo, &["fetch", "--quiet", &url, branch])?;
+
+    let before = head(&repo);
+    match &before {
+        Some(before) if is_ancestor(&repo, "FETCH_HEAD", before) => return Ok(Vec::new()),
+        Some(_) => {
+            let mut args = crate::history::identity(&repo);
+            args.extend([
+                "merge",
+                "--quiet",
+                "--no-edit",
+                "--allow-unrelated-histories",
+                "FETCH_HEAD",
+            ]);
+            if git(&repo, &args).is_err() {
+                let conflicts = git(&repo, &["diff", "--name-only", "--diff-filter=U"])?;
+                let _ = git(&repo, &["merge", "--abort"]);
+                let conflicts: Vec<&str> = conflicts.lines().collect();
+                ensure!(
+                    conflicts.is_empty(),
+                    "Local and remote changes conflict in {}; reconcile them and pull again",
+                    conflicts.join(", ")
+                );
+                bail!(
+                    "Failed to merge the remote's changes into {}",
+                    repo.display()
+                );
+            }
+        }
+        None => {
+            git(&repo, &["reset", "--quiet", "--hard", "FETCH_HEAD"])?;
+        }
+    }
+
+    let changed = match &before {
+        Some(before) => git(&repo, &["diff", "--name-only", before, "HEAD"])?,
+        None => git(&repo, &["ls-files"])?,
+    };
+    let changed: Vec<String> = changed.lines().map(str::to_string).collect();
+    let paths: Vec<PathBuf> = changed.iter().map(PathBuf::from).collect();
+    crate::manifest::refresh(&storage.path, &paths)?;
+    Ok(changed)
+}
+
+/// Send `repo/`, with its local changes committed, to the remote's sync branch.
+/// Refused while the remote has commits that have not been pulled. Returns
+/// whether anything was sent.
+pub fn push(storage: &Storage) -> crate::Result<bool> {
+    let url = remote(storage)?;
+    let branch = &storage.config.sync.branch;
+    let repo = storage.path.join("repo");
+    commit_local_changes(&repo)?;
+    let Some(local) = head(&repo) else {
+        bail!("Nothing to push; {} has no profiles", repo.display());
+    };
+
+    if has_branch(&repo, &url, branch)? {
+        git(&repo, &["fetch", "--quiet", &url, branch])?;
+        ensure!(
+            is_ancestor(&repo, "FETCH_HEAD", &local),
+            "The remote has changes that are not in {} yet; run `pmx sync pull` first",
+            repo.display()
+        );
+        if git(&repo, &["rev-parse", "FETCH_HEAD"])?.trim() == local {
+            return Ok(false);
+        }
+    }
+    git(
+        &repo,
+        &[
+            "push",
+            "--quiet",
+            &url,
+            &format!("HEAD:refs/heads/{branch}"),
+        ],
+    )?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn storage(dir: &Path, name: &str, url: &Path) -> Storage {
+        let path = dir.join(name);
+        std::fs::create_dir_all(path.join("repo")).unwrap();
+        std::fs::write(
+            path.join("config.toml"),
+            format!(
+                "[agents]\n\n[sync]\nurl = {:?}\n",
+                url.display().to_string()
+            ),
+        )
+        .unwrap();
+        Storage::new(path).unwrap()
+    }
+
+    #[test]
+    fn test_push_clone_pull_and_conflicts() {
+        let temp_dir = TempDir::new().unwrap();
+        let remote = temp_dir.path().join("remote.git");
+        git(
+            temp_dir.path(),
+            &["init", "--quiet", "--bare", "remote.git"],
+        )
+        .unwrap();
+
+        let laptop = storage(temp_dir.path(), "laptop", &remote);
+        assert!(pull(&laptop).is_err());
+        std::fs::write(laptop.path.join("repo/rust.md"), "Use cargo.\n").unwrap();
+        assert!(push(&laptop).unwrap());
+        assert!(!push(&laptop).unwrap());
+
+        let desktop = storage(temp_dir.path(), "desktop", &remote);
+        clone(&desktop, None).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(desktop.path.join("repo/rust.md")).unwrap(),
+            "Use cargo.\n"
+        );
+        assert!(clone(&desktop, None).is_err());
+
+        // Changes to different files merge
+        std::fs::write(desktop.path.join("repo/go.md"), "Use go vet.\n").unwrap();
+        push(&desktop).unwrap();
+        std::fs::write(laptop.path.join("repo/notes.md"), "Notes\n").unwrap();
+        assert!(push(&laptop).is_err());
+        assert_eq!(pull(&laptop).unwrap(), vec!["go.md"]);
+        push(&laptop).unwrap();
+
+        // Changes to the same lines are reported and leave both sides untouched
+        assert_eq!(pull(&desktop).unwrap(), vec!["notes.md"]);
+        std::fs::write(desktop.path.join("repo/rust.md"), "Use clippy.\n").unwrap();
+        push(&desktop).unwrap();
+        std::fs::write(laptop.path.join("repo/rust.md"), "Use rustfmt.\n").unwrap();
+        let error = pull(&laptop).unwrap_err();
+        assert!(error.to_string().contains("conflict in rust.md"));
+        assert_eq!(
+            std::fs::read_to_string(laptop.path.join("repo/rust.md")).unwrap(),
+            "Use rustfmt.\n"
+        );
+    }
+}
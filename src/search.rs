@@ -0,0 +1,164 @@
+use std::ops::Range;
+
+use regex::{Regex, RegexBuilder};
+
+use crate::storage::Storage;
+
+/// What to look for in profiles
+#[derive(Debug, Clone, Default)]
+pub struct SearchOptions {
+    /// Treat the query as a regular expression rather than literal text
+    pub regex: bool,
+    /// Match regardless of letter case
+    pub ignore_case: bool,
+    /// Lines of context shown around each match
+    pub context: usize,
+}
+
+/// A profile with matches in its name or content
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileMatch {
+    pub profile: String,
+    /// Byte ranges of the matches within the profile name
+    pub name_matches: Vec<Range<usize>>,
+    pub lines: Vec<Line>,
+}
+
+/// A line shown in the results: a match, or context around one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    /// 1-based line number
+    pub number: usize,
+    pub text: String,
+    /// Byte ranges of the matches within `text`; empty for context lines
+    pub matches: Vec<Range<usize>>,
+}
+
+pub fn matcher(query: &str, options: &SearchOptions) -> crate::Result<Regex> {
+    let pattern = if options.regex {
+        query.to_string()
+    } else {
+        regex::escape(query)
+    };
+    RegexBuilder::new(&pattern)
+        .case_insensitive(options.ignore_case)
+        .build()
+        .map_err(|e| anyhow::anyhow!("Failed to parse search pattern '{}': {}", query, e))
+}
+
+/// Search every profile as stored, in name order. Unreadable profiles are skipped.
+pub fn search(
+    storage: &Storage,
+    query: &str,
+    options: &SearchOptions,
+) -> crate::Result<Vec<ProfileMatch>> {
+    let matcher = matcher(query, options)?;
+    let mut profiles = storage.list_repos()?;
+    profiles.sort();
+
+    Ok(profiles
+        .into_iter()
+        .filter_map(|profile| {
+            let content = storage.get_profile_content(&profile).ok()?;
+            let name_matches = ranges(&matcher, &profile);
+            let lines = matching_lines(&matcher, &content, options.context);
+            (!name_matches.is_empty() || !lines.is_empty()).then_some(ProfileMatch {
+                profile,
+                name_matches,
+                lines,
+            })
+        })
+        .collect())
+}
+
+fn ranges(matcher: &Regex, text: &str) -> Vec<Range<usize>> {
+    matcher
+        .find_iter(text)
+        .filter(|m| !m.is_empty())
+        .map(|m| m.range())
+        .collect()
+}
+
+/// Matching lines of `content` with up to `context` lines around each
+pub fn matching_lines(matcher: &Regex, content: &str, context: usize) -> Vec<Line> {
+    let all: Vec<&str> = content.lines().collect();
+    let hits: Vec<(usize, Vec<Range<usize>>)> = all
+        .iter()
+        .enumerate()
+        .map(|(index, line)| (index, ranges(matcher, line)))
+        .filter(|(_, matches)| !matches.is_empty())
+        .collect();
+
+    let mut lines: Vec<Line> = Vec::new();
+    for (index, matches) in hits {
+        let first = index.saturating_sub(context);
+        let last = (index + context).min(all.len() - 1);
+        for (shown, text) in all.iter().enumerate().take(last + 1).skip(first) {
+            let number = shown + 1;
+            let matches = if shown == index {
+                matches.clone()
+            } else {
+                Vec::new()
+            };
+            // Lines arrive in order, so an overlap is always near the end
+            match lines.iter_mut().rev().find(|line| line.number == number) {
+                Some(line) if !matches.is_empty() => line.matches = matches,
+                Some(_) => {}
+                None => lines.push(Line {
+                    number,
+                    text: text.to_string(),
+                    matches,
+                }),
+            }
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_matching_lines_with_context() {
+        let matcher = matcher("cargo", &SearchOptions::default()).unwrap();
+        let content = "a\nrun cargo test\nb\nc\nd\ncargo fmt\n";
+        let lines = matching_lines(&matcher, content, 1);
+        let numbers: Vec<_> = lines.iter().map(|line| line.number).collect();
+        assert_eq!(numbers, vec![1, 2, 3, 5, 6]);
+        assert_eq!(lines[1].matches, vec![4..9]);
+        assert!(lines[2].matches.is_empty());
+    }
+
+    #[test]
+    fn test_search_modes() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::initialize(temp_dir.path().join("storage")).unwrap();
+        storage
+            .create_profile("coding/rust", "# Rust\nRun Clippy.\n")
+            .unwrap();
+        storage.create_profile("writing", "Be clear.\n").unwrap();
+
+        let found = |query: &str, options: SearchOptions| {
+            search(&storage, query, &options)
+                .unwrap()
+                .into_iter()
+                .map(|m| m.profile)
+                .collect::<Vec<_>>()
+        };
+        assert!(found("clippy", SearchOptions::default()).is_empty());
+        let ignore_case = SearchOptions {
+            ignore_case: true,
+            ..Default::default()
+        };
+        assert_eq!(found("clippy", ignore_case), vec!["coding/rust"]);
+        let regex = SearchOptions {
+            regex: true,
+            ..Default::default()
+        };
+        assert_eq!(found(r"^Be \w+\.$", regex.clone()), vec!["writing"]);
+        assert_eq!(found("rust", SearchOptions::default()), vec!["coding/rust"]);
+        assert!(search(&storage, "(", &regex).is_err());
+    }
+}
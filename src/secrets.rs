@@ -0,0 +1,111 @@
+use std::process::{Command, Stdio};
+
+use anyhow::ensure;
+use regex::Regex;
+
+/// Keychain service secrets are looked up under
+pub const KEYCHAIN_SERVICE: &str = "pmx";
+
+/// Shown instead of a secret that is not revealed
+pub const MASK: &str = "********";
+
+/// A `<{{secret:NAME}}>` placeholder; the name has letters, digits and `_`
+pub fn placeholder() -> Regex {
+    Regex::new(r"<\{\{secret:([A-Za-z_][A-Za-z0-9_]*)\}\}>").unwrap()
+}
+
+/// Value of secret `name`: the environment variable of that name, else the
+/// entry for it in the OS keychain
+pub fn lookup(name: &str) -> Option<String> {
+    if let Ok(value) = std::env::var(name)
+        && !value.is_empty()
+    {
+        return Some(value);
+    }
+    keychain(name)
+}
+
+/// Password stored for `name` under the `pmx` service, through `security` on
+/// macOS and `secret-tool` (libsecret) elsewhere
+fn keychain(name: &str) -> Option<String> {
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = Command::new("security");
+        command.args([
+            "find-generic-password",
+            "-s",
+            KEYCHAIN_SERVICE,
+            "-a",
+            name,
+            "-w",
+        ]);
+        command
+    } else if cfg!(unix) {
+        let mut command = Command::new("secret-tool");
+        command.args(["lookup", "service", KEYCHAIN_SERVICE, "account", name]);
+        command
+    } else {
+        return None;
+    };
+    let output = command
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let value = String::from_utf8(output.stdout).ok()?;
+    let value = value.trim_end_matches(['\r', '\n']);
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+/// Replace each secret placeholder with its value; a secret without one is an error
+pub fn resolve(content: &str) -> crate::Result<String> {
+    resolve_with(content, lookup)
+}
+
+fn resolve_with(content: &str, lookup: impl Fn(&str) -> Option<String>) -> crate::Result<String> {
+    let mut missing: Vec<String> = Vec::new();
+    let resolved = placeholder()
+        .replace_all(content, |captures: &regex::Captures| {
+            lookup(&captures[1]).unwrap_or_else(|| {
+                if !missing.iter().any(|name| *name == captures[1]) {
+                    missing.push(captures[1].to_string());
+                }
+                captures[0].to_string()
+            })
+        })
+        .into_owned();
+    ensure!(
+        missing.is_empty(),
+        "Secret {} not found in the environment or the keychain (service '{}')",
+        missing.join(", "),
+        KEYCHAIN_SERVICE
+    );
+    Ok(resolved)
+}
+
+/// `content` with each secret placeholder masked
+pub fn mask(content: &str) -> String {
+    placeholder().replace_all(content, MASK).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_and_mask_secrets() {
+        let content = "Token: <{{secret:API_TOKEN}}>, user <{{USER}}>.\n";
+        let lookup = |name: &str| (name == "API_TOKEN").then(|| "s3cr3t".to_string());
+
+        assert_eq!(
+            resolve_with(content, lookup).unwrap(),
+            "Token: s3cr3t, user <{{USER}}>.\n"
+        );
+        assert_eq!(mask(content), "Token: ********, user <{{USER}}>.\n");
+
+        let error = resolve_with("<{{secret:GONE}}> <{{secret:GONE}}>", lookup).unwrap_err();
+        assert!(error.to_string().starts_with("Secret GONE not found"));
+    }
+}
@@ -0,0 +1,296 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+pub(crate) const STATE_FILE: &str = "state.toml";
+
+/// Bookkeeping about what pmx has written to agent files, stored next to `config.toml`
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct State {
+    /// Applied profiles keyed by the target file path
+    #[serde(default)]
+    pub applied: BTreeMap<String, AppliedProfile>,
+    /// Shell hook decisions per project directory
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub trusted_projects: BTreeMap<String, ProjectTrust>,
+    /// Cached result of the last release check
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub update_check: Option<crate::update::UpdateCheck>,
+    /// How often each profile has been applied
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub usage: BTreeMap<String, u64>,
+    /// Earlier versions of each target file, oldest first
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub backups: BTreeMap<String, Vec<Backup>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct AppliedProfile {
+    pub agent: String,
+    /// Profiles in the order they were written; the first one was set, the rest appended
+    pub profiles: Vec<String>,
+    /// Hash of the content pmx last wrote, to notice edits made to the file since
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+}
+
+/// A target file as it was before pmx changed it
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Backup {
+    /// Copy under `backups/`; `None` when the target did not exist
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// When the backup was taken, as an RFC 3339 timestamp
+    pub created: String,
+    /// What pmx had recorded for the target at the time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub applied: Option<AppliedProfile>,
+    /// For a rules directory, the files in it that changed together, each with
+    /// its copy under `backups/` or `None` when it did not exist
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub files: BTreeMap<String, Option<String>>,
+}
+
+/// Whether the shell hook may apply a directory's `.pmx.toml`
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ProjectTrust {
+    /// Hash of the `.pmx.toml` the decision was made for
+    pub hash: String,
+    pub allowed: bool,
+}
+
+impl State {
+    pub fn load(storage_path: &Path) -> crate::Result<Self> {
+        let state_path = storage_path.join(STATE_FILE);
+        if !state_path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(&state_path)
+            .map_err(|e| anyhow::anyhow!("Failed to read state file: {}", e))?;
+        toml::from_str(&content).map_err(|e| anyhow::anyhow!("Failed to parse state file: {}", e))
+    }
+
+    pub fn persist(&self, storage_path: &Path) -> crate::Result<()> {
+        let content = toml::to_string(self)
+            .map_err(|e| anyhow::anyhow!("Failed to serialize state: {}", e))?;
+        std::fs::write(storage_path.join(STATE_FILE), content)
+            .map_err(|e| anyhow::anyhow!("Failed to write state file: {}", e))
+    }
+
+    pub fn get(&self, target: &Path) -> Option<&AppliedProfile> {
+        self.applied.get(&target.to_string_lossy().to_string())
+    }
+
+    /// Record that `profile` replaced the contents of `target`
+    pub fn record_set(&mut self, agent: crate::agent::Agent, target: &Path, profile: &str) {
+        self.record_use(profile);
+        self.applied.insert(
+            target.to_string_lossy().to_string(),
+            AppliedProfile {
+                agent: agent.name().to_string(),
+                profiles: vec![profile.to_string()],
+                hash: None,
+            },
+        );
+    }
+
+    /// Record that `profile` was appended to `target`
+    pub fn record_append(&mut self, agent: crate::agent::Agent, target: &Path, profile: &str) {
+        self.record_use(profile);
+        self.applied
+            .entry(target.to_string_lossy().to_string())
+            .or_insert_with(|| AppliedProfile {
+                agent: agent.name().to_string(),
+                profiles: Vec::new(),
+                hash: None,
+            })
+            .profiles
+            .push(profile.to_string());
+    }
+
+    /// Record that the block `profile` appended was removed from `target`
+    pub fn record_remove(&mut self, target: &Path, profile: &str) {
+        if let Some(applied) = self.applied.get_mut(&target.to_string_lossy().to_string())
+            && let Some(index) = applied.profiles.iter().position(|p| p == profile)
+        {
+            applied.profiles.remove(index);
+        }
+    }
+
+    /// Remember what was written to `target`, after `record_set`/`record_append`
+    pub fn record_written(&mut self, target: &Path, content: &[u8]) {
+        if let Some(applied) = self.applied.get_mut(&target.to_string_lossy().to_string()) {
+            applied.hash = Some(crate::utils::content_hash(content));
+        }
+    }
+
+    /// Whether `target` now holds something other than what pmx last wrote to it
+    pub fn has_drifted(&self, target: &Path, current: &[u8]) -> bool {
+        self.get(target)
+            .and_then(|applied| applied.hash.as_deref())
+            .is_some_and(|hash| hash != crate::utils::content_hash(current))
+    }
+
+    fn record_use(&mut self, profile: &str) {
+        *self.usage.entry(profile.to_string()).or_default() += 1;
+    }
+
+    /// Up to `limit` profiles, most applied first
+    pub fn most_used(&self, limit: usize) -> Vec<&str> {
+        let mut profiles: Vec<(&String, &u64)> = self.usage.iter().collect();
+        profiles.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        profiles
+            .into_iter()
+            .take(limit)
+            .map(|(profile, _)| profile.as_str())
+            .collect()
+    }
+
+    pub fn clear(&mut self, target: &Path) {
+        self.applied.remove(&target.to_string_lossy().to_string());
+    }
+
+    /// Replace what is recorded for `target`, e.g. with what a backup remembered
+    pub fn set_applied(&mut self, target: &Path, applied: Option<AppliedProfile>) {
+        match applied {
+            Some(applied) => {
+                self.applied
+                    .insert(target.to_string_lossy().to_string(), applied);
+            }
+            None => self.clear(target),
+        }
+    }
+
+    /// Add a backup of `target`, returning the oldest ones beyond `keep`
+    pub fn push_backup(&mut self, target: &Path, backup: Backup, keep: usize) -> Vec<Backup> {
+        let backups = self
+            .backups
+            .entry(target.to_string_lossy().to_string())
+            .or_default();
+        backups.push(backup);
+        let excess = backups.len().saturating_sub(keep);
+        backups.drain(..excess).collect()
+    }
+
+    pub fn last_backup(&self, target: &Path) -> Option<&Backup> {
+        self.backups
+            .get(&target.to_string_lossy().to_string())
+            .and_then(|backups| backups.last())
+    }
+
+    pub fn pop_backup(&mut self, target: &Path) -> Option<Backup> {
+        let key = target.to_string_lossy().to_string();
+        let backup = self.backups.get_mut(&key)?.pop();
+        if self.backups.get(&key).is_some_and(Vec::is_empty) {
+            self.backups.remove(&key);
+        }
+        backup
+    }
+
+    /// The remembered decision for `dir`, if it was made for a file with `hash`
+    pub fn project_trust(&self, dir: &Path, hash: &str) -> Option<bool> {
+        self.trusted_projects
+            .get(&dir.to_string_lossy().to_string())
+            .filter(|trust| trust.hash == hash)
+            .map(|trust| trust.allowed)
+    }
+
+    pub fn record_project_trust(&mut self, dir: &Path, hash: &str, allowed: bool) {
+        self.trusted_projects.insert(
+            dir.to_string_lossy().to_string(),
+            ProjectTrust {
+                hash: hash.to_string(),
+                allowed,
+            },
+        );
+    }
+}
+
+/// Load, modify and persist the state in one step
+pub fn update(storage: &crate::storage::Storage, f: impl FnOnce(&mut State)) -> crate::Result<()> {
+    let mut state = State::load(&storage.path)?;
+    f(&mut state);
+    state.persist(&storage.path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::Agent;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_state_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let target = temp_dir.path().join("CLAUDE.md");
+
+        let mut state = State::load(temp_dir.path()).unwrap();
+        assert!(state.get(&target).is_none());
+
+        state.record_set(Agent::Claude, &target, "base");
+        state.record_append(Agent::Claude, &target, "extra");
+        state.persist(temp_dir.path()).unwrap();
+
+        let state = State::load(temp_dir.path()).unwrap();
+        let applied = state.get(&target).unwrap();
+        assert_eq!(applied.agent, "claude");
+        assert_eq!(applied.profiles, vec!["base", "extra"]);
+    }
+
+    #[test]
+    fn test_state_set_replaces_and_clear_removes() {
+        let temp_dir = TempDir::new().unwrap();
+        let target = temp_dir.path().join("AGENTS.md");
+
+        let mut state = State::default();
+        state.record_set(Agent::Codex, &target, "first");
+        state.record_append(Agent::Codex, &target, "second");
+        state.record_set(Agent::Codex, &target, "third");
+        assert_eq!(state.get(&target).unwrap().profiles, vec!["third"]);
+
+        state.clear(&target);
+        assert!(state.get(&target).is_none());
+    }
+
+    #[test]
+    fn test_has_drifted() {
+        let temp_dir = TempDir::new().unwrap();
+        let target = temp_dir.path().join("CLAUDE.md");
+
+        let mut state = State::default();
+        state.record_set(Agent::Claude, &target, "base");
+        // Entries written before hashes were recorded never count as drifted
+        assert!(!state.has_drifted(&target, b"anything"));
+
+        state.record_written(&target, b"Be helpful.\n");
+        assert!(!state.has_drifted(&target, b"Be helpful.\n"));
+        assert!(state.has_drifted(&target, b"Be helpful, and brief.\n"));
+    }
+
+    #[test]
+    fn test_most_used_orders_by_count() {
+        let temp_dir = TempDir::new().unwrap();
+        let target = temp_dir.path().join("CLAUDE.md");
+
+        let mut state = State::default();
+        state.record_set(Agent::Claude, &target, "b");
+        state.record_set(Agent::Claude, &target, "a");
+        state.record_append(Agent::Claude, &target, "c");
+        state.record_set(Agent::Claude, &target, "c");
+
+        assert_eq!(state.most_used(10), vec!["c", "a", "b"]);
+        assert_eq!(state.most_used(1), vec!["c"]);
+    }
+
+    #[test]
+    fn test_project_trust_is_tied_to_hash() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut state = State::default();
+        state.record_project_trust(temp_dir.path(), "abc", true);
+        state.persist(temp_dir.path()).unwrap();
+
+        let state = State::load(temp_dir.path()).unwrap();
+        assert_eq!(state.project_trust(temp_dir.path(), "abc"), Some(true));
+        assert_eq!(state.project_trust(temp_dir.path(), "def"), None);
+    }
+}
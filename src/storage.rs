@@ -1,31 +1,274 @@
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
 use anyhow::ensure;
 
+use crate::agent::Agent;
+
+/// Entry point of a folder profile (`repo/<name>/index.md`)
+pub const INDEX_FILE: &str = "index.md";
+
+/// Empty file that makes a directory with an `index.md` a folder profile. Without
+/// it, `<name>/index.md` is the plain profile `<name>/index` next to its siblings.
+pub const FOLDER_MARKER: &str = ".pmx-folder";
+
+/// Whether `dir` is a folder profile: it holds both `index.md` and the marker
+pub(crate) fn is_folder_profile(dir: &Path) -> bool {
+    dir.join(INDEX_FILE).is_file() && dir.join(FOLDER_MARKER).is_file()
+}
+
 #[derive(Debug, Clone)]
 pub struct Storage {
     pub(crate) path: PathBuf,
     pub(crate) config: Config,
 }
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-pub(crate) struct Config {
+/// Contents of `config.toml` in the storage directory. Each table has a getter
+/// such as [`Config::mcp`]; [`Config::value`] reads a setting by the dotted key
+/// `pmx config get` takes.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct Config {
     pub(crate) agents: Agents,
     #[serde(default)]
     pub(crate) mcp: McpConfig,
     #[serde(default)]
     pub(crate) extensions: ExtensionsConfig,
+    #[serde(default)]
+    pub(crate) format: FormatConfig,
+    #[serde(default)]
+    pub(crate) limits: LimitsConfig,
+    #[serde(default)]
+    pub(crate) updates: UpdatesConfig,
+    #[serde(default)]
+    pub(crate) clipboard: ClipboardConfig,
+    #[serde(default)]
+    pub(crate) logging: LoggingConfig,
+    #[serde(default)]
+    pub(crate) naming: NamingConfig,
+    #[serde(default)]
+    pub(crate) editor: EditorConfig,
+    #[serde(default)]
+    pub(crate) banner: BannerConfig,
+    #[serde(default)]
+    pub(crate) wrap: WrapConfig,
+    #[serde(default)]
+    pub(crate) watch: WatchConfig,
+    #[serde(default)]
+    pub(crate) lint: LintConfig,
+    #[serde(default)]
+    pub(crate) tokens: TokensConfig,
+    #[serde(default)]
+    pub(crate) review: ReviewConfig,
+    #[serde(default)]
+    pub(crate) shared: SharedConfig,
+    #[serde(default)]
+    pub(crate) history: HistoryConfig,
+    #[serde(default)]
+    pub(crate) backups: BackupsConfig,
+    #[serde(default)]
+    pub(crate) trash: TrashConfig,
+    #[serde(default)]
+    pub(crate) sync: SyncConfig,
+    #[serde(default)]
+    pub(crate) includes: IncludesConfig,
+    #[serde(default)]
+    pub(crate) encryption: EncryptionConfig,
+    /// Named, ordered lists of profiles applied together by `pmx bundle apply`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) bundles: BTreeMap<String, Vec<String>>,
 }
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+/// Settings per agent under `[agents.<name>]`, and further agents declared under
+/// `[agents.custom.<name>]`.
+///
+/// The older `disable_claude = true` style flags are still accepted when reading.
+#[derive(Debug, Clone)]
 pub(crate) struct Agents {
-    pub(crate) disable_claude: bool,
-    pub(crate) disable_codex: bool,
+    pub(crate) agents: BTreeMap<String, AgentConfig>,
+    pub(crate) custom: BTreeMap<String, CustomAgentConfig>,
+}
+
+/// A tool pmx has no built-in support for, which reads a markdown system prompt
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub(crate) struct CustomAgentConfig {
+    /// User-wide prompt file the tool reads; `~/` is expanded
+    pub(crate) target: PathBuf,
+    /// File name written inside a project with `--project`; defaults to the target's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) project_file: Option<String>,
+    /// Text written between the previous content and an appended profile, instead
+    /// of a blank line
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) separator: Option<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub(crate) struct AgentConfig {
+    /// Offer the agent's commands and allow writing its files
+    #[serde(default = "default_agent_enabled")]
+    pub(crate) enabled: bool,
+    /// Prompt file to write instead of the agent's default; `~/` is expanded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) target: Option<PathBuf>,
+    /// How profiles are written to the target
+    #[serde(default)]
+    pub(crate) mode: ApplyMode,
+    /// Replaces the `[wrap]` preamble for this agent; empty to leave it out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) preamble: Option<String>,
+    /// Replaces the `[wrap]` postamble for this agent; empty to leave it out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) postamble: Option<String>,
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+    schemars::JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ApplyMode {
+    /// Write the rendered profile content into the target file
+    #[default]
+    Copy,
+    /// Make the target a symbolic link to the profile file, so edits to the
+    /// profile reach the agent without applying it again
+    Link,
+}
+
+fn default_agent_enabled() -> bool {
+    true
+}
+
+impl Default for AgentConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_agent_enabled(),
+            target: None,
+            mode: ApplyMode::default(),
+            preamble: None,
+            postamble: None,
+        }
+    }
+}
+
+impl Default for Agents {
+    fn default() -> Self {
+        Self {
+            agents: Agent::ALL
+                .iter()
+                .map(|agent| (agent.name().to_string(), AgentConfig::default()))
+                .collect(),
+            custom: BTreeMap::new(),
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Agents {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        let mut agents = BTreeMap::new();
+        let mut custom: BTreeMap<String, CustomAgentConfig> = BTreeMap::new();
+        let mut disabled = Vec::new();
+        for (key, entry) in BTreeMap::<String, toml::Value>::deserialize(deserializer)? {
+            match entry {
+                toml::Value::Boolean(flag) => match key.strip_prefix("disable_") {
+                    Some(name) => disabled.push((name.to_string(), flag)),
+                    None => {
+                        return Err(D::Error::custom(format!(
+                            "unknown agents setting '{key}'; expected an [agents.<name>] table"
+                        )));
+                    }
+                },
+                entry if key == "custom" => {
+                    custom = entry.try_into().map_err(D::Error::custom)?;
+                }
+                entry => {
+                    agents.insert(key, entry.try_into().map_err(D::Error::custom)?);
+                }
+            }
+        }
+        for (name, flag) in disabled {
+            agents
+                .entry(name)
+                .or_insert_with(AgentConfig::default)
+                .enabled = !flag;
+        }
+
+        for name in custom.keys() {
+            if <Agent as clap::ValueEnum>::from_str(name, true).is_ok() {
+                return Err(D::Error::custom(format!(
+                    "custom agent '{name}' has the name of a built-in agent"
+                )));
+            }
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                return Err(D::Error::custom(format!(
+                    "invalid custom agent name '{name}': use letters, digits, '-' and '_'"
+                )));
+            }
+        }
+        Ok(Self { agents, custom })
+    }
+}
+
+impl serde::Serialize for Agents {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(None)?;
+        for (name, config) in &self.agents {
+            map.serialize_entry(name, config)?;
+        }
+        if !self.custom.is_empty() {
+            map.serialize_entry("custom", &self.custom)?;
+        }
+        map.end()
+    }
+}
+
+impl schemars::JsonSchema for Agents {
+    fn schema_name() -> String {
+        "Agents".to_string()
+    }
+
+    fn json_schema(generator: &mut schemars::r#gen::SchemaGenerator) -> schemars::schema::Schema {
+        let mut schema = generator
+            .subschema_for::<BTreeMap<String, AgentConfig>>()
+            .into_object();
+        schema.object().properties.insert(
+            "custom".to_string(),
+            generator.subschema_for::<BTreeMap<String, CustomAgentConfig>>(),
+        );
+        schema.into()
+    }
 }
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+impl Agents {
+    pub(crate) fn get(&self, agent: Agent) -> Option<&AgentConfig> {
+        self.agents.get(agent.name())
+    }
+
+    /// Agents without a table are enabled
+    pub(crate) fn is_enabled(&self, agent: Agent) -> bool {
+        self.get(agent).is_none_or(|config| config.enabled)
+    }
+}
+
+/// Either `true`/`false` for everything, or a list of names to disable
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 #[serde(untagged)]
-pub(crate) enum DisableOption {
+pub enum DisableOption {
     Bool(bool),
     List(Vec<String>),
 }
@@ -36,21 +279,521 @@ impl Default for DisableOption {
     }
 }
 
-#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
-pub(crate) struct McpConfig {
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct McpConfig {
+    /// Profiles the MCP server exposes as prompts, by name or glob pattern such as
+    /// `work/*`; every profile when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enable_prompts: Option<Vec<String>>,
+    /// Profiles the MCP server should not expose as prompts, by name or glob pattern
+    #[serde(default)]
+    pub disable_prompts: DisableOption,
+    /// Tools the MCP server should not expose
+    #[serde(default)]
+    pub disable_tools: DisableOption,
+    /// Tools that are off unless listed here: `copy_to_clipboard`, which
+    /// replaces the user's clipboard, and `create_profile`, which writes to the
+    /// storage
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub enable_tools: Vec<String>,
+    /// Log every request and response to the log file
+    #[serde(default)]
+    pub log_requests: bool,
+    /// Regular expressions for secret argument values, which are masked in logs
+    #[serde(default)]
+    pub redact: Vec<String>,
+    /// Address the `ws` and `http` transports listen on when `--bind` is not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bind: Option<String>,
+    /// Bearer token `ws` and `http` clients must send; any local client is served
+    /// when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    /// Browser origins, besides `localhost` ones, that may connect to the `ws`
+    /// and `http` transports, e.g. `https://inspector.example.com`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_origins: Vec<String>,
+    /// Prompts per `prompts/list` page; 0 lists them all at once
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_size: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct ExtensionsConfig {
+    /// `pmx-<name>` executables that may run as `pmx <name>`
+    #[serde(default)]
+    pub allowed_subcommands: Vec<String>,
+    /// Extensions whose output may be spliced into profiles as `<{{fn:<name> ...}}>`;
+    /// each must also be in `allowed_subcommands`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub functions: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct FormatConfig {
+    /// Maximum line width used by `profile fmt`; lines are not wrapped when unset
+    #[serde(default)]
+    pub wrap_width: Option<usize>,
+}
+
+/// Size thresholds for the content written to agent files
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct LimitsConfig {
+    /// Warn when the written file exceeds this many bytes
+    #[serde(default)]
+    pub warn_bytes: Option<usize>,
+    /// Refuse to write files larger than this many bytes
+    #[serde(default)]
+    pub max_bytes: Option<usize>,
+    /// Warn when the written file exceeds this many estimated tokens
+    #[serde(default)]
+    pub warn_tokens: Option<usize>,
+    /// Refuse to write files larger than this many estimated tokens
+    #[serde(default)]
+    pub max_tokens: Option<usize>,
+}
+
+/// Background checks for newer pmx releases
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct UpdatesConfig {
+    /// Print a notice when a newer release is available
+    #[serde(default = "default_update_check")]
+    pub check: bool,
+    /// Hours between release checks
+    #[serde(default = "default_update_interval_hours")]
+    pub interval_hours: u64,
+}
+
+fn default_update_check() -> bool {
+    true
+}
+
+fn default_update_interval_hours() -> u64 {
+    24
+}
+
+impl Default for UpdatesConfig {
+    fn default() -> Self {
+        Self {
+            check: default_update_check(),
+            interval_hours: default_update_interval_hours(),
+        }
+    }
+}
+
+/// Settings for `profile copy`
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct ClipboardConfig {
+    /// Text placed between profiles when copying several at once
+    #[serde(default = "default_clipboard_separator")]
+    pub separator: String,
+}
+
+fn default_clipboard_separator() -> String {
+    "\n\n".to_string()
+}
+
+impl Default for ClipboardConfig {
+    fn default() -> Self {
+        Self {
+            separator: default_clipboard_separator(),
+        }
+    }
+}
+
+/// Where and how much to log; nothing is logged unless a file is set
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct LoggingConfig {
+    /// File receiving log lines (overridden by `--log-file`)
     #[serde(default)]
-    pub(crate) disable_prompts: DisableOption,
+    pub file: Option<PathBuf>,
+    /// Format of log lines: JSON in the file and text on stderr unless set
+    /// (overridden by `--log-format`)
     #[serde(default)]
-    pub(crate) disable_tools: DisableOption,
+    pub format: Option<crate::logging::LogFormat>,
+    /// Tracing filter, e.g. `info` or `pmx=debug` (overridden by `PMX_LOG`)
+    #[serde(default = "default_log_level")]
+    pub level: String,
 }
 
-#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
-pub(crate) struct ExtensionsConfig {
+fn default_log_level() -> String {
+    "info".to_string()
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            file: None,
+            format: None,
+            level: default_log_level(),
+        }
+    }
+}
+
+/// How `profile create` treats the names it is given
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct NamingConfig {
+    /// Turn names like `My Cool Prompt!` into `my-cool-prompt`, keeping the original as the title
+    #[serde(default)]
+    pub slugify: bool,
+}
+
+/// Editor for `profile create` and `profile edit`, preferred over `$EDITOR`
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct EditorConfig {
+    /// Editor command, split like a shell command line, e.g. `code --wait`
+    #[serde(default)]
+    pub command: Option<String>,
+    /// Arguments placed after the command and before the file, e.g. `["--wait"]`
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+/// The comment pmx puts at the top of agent files it writes
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct BannerConfig {
+    /// Start `set`/`apply` output with the banner comment
+    #[serde(default = "default_banner_enabled")]
+    pub enabled: bool,
+    /// Banner text; `{profile}` is replaced by the applied profile names
+    #[serde(default = "default_banner_text")]
+    pub text: String,
+}
+
+fn default_banner_enabled() -> bool {
+    true
+}
+
+fn default_banner_text() -> String {
+    "managed by pmx: profile {profile}".to_string()
+}
+
+impl Default for BannerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_banner_enabled(),
+            text: default_banner_text(),
+        }
+    }
+}
+
+/// Text wrapped around the profiles written to agent files, kept out of the profiles
+/// themselves; `{agent}` is filled in when applying
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct WrapConfig {
+    /// Placed after the banner, before the first profile
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preamble: Option<String>,
+    /// Placed after the last profile
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub postamble: Option<String>,
+}
+
+/// Read-only profile repositories layered under the personal `repo/`
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct SharedConfig {
+    /// Searched after `repo/`, in order: a directory of profiles, or a pmx storage
+    /// directory whose `repo/` is used
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<PathBuf>,
+}
+
+/// Git history of the profile repository
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct HistoryConfig {
+    /// Commit every profile change to a git repository in `repo/`
+    #[serde(default)]
+    pub enabled: bool,
+}
+
+/// Where the passphrase of profiles marked `sensitive: true` comes from when
+/// `PMX_PASSPHRASE` is not set
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct EncryptionConfig {
+    /// Command printing the passphrase on its first line, e.g. `pass show pmx`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub passphrase_command: Option<String>,
+}
+
+/// How `{{include: ...}}` directives are expanded
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct IncludesConfig {
+    /// How deeply included profiles may include others in turn
+    #[serde(default = "default_max_depth")]
+    pub max_depth: usize,
+}
+
+fn default_max_depth() -> usize {
+    8
+}
+
+impl Default for IncludesConfig {
+    fn default() -> Self {
+        Self {
+            max_depth: default_max_depth(),
+        }
+    }
+}
+
+/// Git remote that `pmx sync clone|pull|push` exchanges `repo/` with
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct SyncConfig {
+    /// Remote URL or path; without it the `origin` remote of `repo/` is used
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Branch on the remote holding the profiles
+    #[serde(default = "default_sync_branch")]
+    pub branch: String,
+}
+
+fn default_sync_branch() -> String {
+    "main".to_string()
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        Self {
+            url: None,
+            branch: default_sync_branch(),
+        }
+    }
+}
+
+/// Reminders for profiles past their `review_by:` or `expires:` date
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct ReviewConfig {
+    /// Print a warning when such a profile is applied to an agent
+    #[serde(default = "default_warn_on_apply")]
+    pub warn_on_apply: bool,
+}
+
+fn default_warn_on_apply() -> bool {
+    true
+}
+
+impl Default for ReviewConfig {
+    fn default() -> Self {
+        Self {
+            warn_on_apply: default_warn_on_apply(),
+        }
+    }
+}
+
+/// Budgets `profile lint` checks profiles against, measured on their content as
+/// agents receive it
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct LintConfig {
+    /// Warn about profiles longer than this many characters
     #[serde(default)]
-    pub(crate) allowed_subcommands: Vec<String>,
+    pub max_chars: Option<usize>,
+    /// Warn about profiles longer than this many estimated tokens
+    #[serde(default)]
+    pub max_tokens: Option<usize>,
+}
+
+/// How token counts are estimated for `[limits]`, `[lint]` and `profile stats`
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct TokensConfig {
+    /// `chars` (about four characters per token) or `cl100k`
+    #[serde(default)]
+    pub tokenizer: crate::tokens::Tokenizer,
+}
+
+/// How `pmx watch` batches bursts of file changes
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct WatchConfig {
+    /// Milliseconds without changes before a batch is reported
+    #[serde(default = "default_debounce_ms")]
+    pub debounce_ms: u64,
+    /// Report a burst that keeps going after this many milliseconds anyway
+    #[serde(default = "default_max_delay_ms")]
+    pub max_delay_ms: u64,
+}
+
+fn default_debounce_ms() -> u64 {
+    500
+}
+
+fn default_max_delay_ms() -> u64 {
+    5000
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        Self {
+            debounce_ms: default_debounce_ms(),
+            max_delay_ms: default_max_delay_ms(),
+        }
+    }
+}
+
+/// Copies of agent files taken before pmx overwrites or removes them
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct BackupsConfig {
+    /// How many earlier versions to keep per file; 0 turns backups off
+    #[serde(default = "default_backups_keep")]
+    pub keep: usize,
+}
+
+fn default_backups_keep() -> usize {
+    10
+}
+
+impl Default for BackupsConfig {
+    fn default() -> Self {
+        Self {
+            keep: default_backups_keep(),
+        }
+    }
+}
+
+/// Deleted profiles kept under `.trash/` for `pmx profile trash restore`
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct TrashConfig {
+    /// Days after which deleted profiles are removed for good; 0 keeps them
+    #[serde(default = "default_purge_after_days")]
+    pub purge_after_days: u64,
+}
+
+fn default_purge_after_days() -> u64 {
+    30
+}
+
+impl Default for TrashConfig {
+    fn default() -> Self {
+        Self {
+            purge_after_days: default_purge_after_days(),
+        }
+    }
 }
 
 impl Config {
+    /// The setting at a dotted `key` such as `mcp.page_size`, defaults included;
+    /// `None` when it is not set
+    pub fn value(&self, key: &str) -> crate::Result<Option<toml::Value>> {
+        let config = toml::Value::try_from(self)
+            .map_err(|e| anyhow::anyhow!("Failed to serialize config: {}", e))?;
+        Ok(key
+            .split('.')
+            .try_fold(&config, |value, part| value.get(part))
+            .cloned())
+    }
+
+    /// The `[mcp]` table
+    pub fn mcp(&self) -> &McpConfig {
+        &self.mcp
+    }
+
+    /// The `[extensions]` table
+    pub fn extensions(&self) -> &ExtensionsConfig {
+        &self.extensions
+    }
+
+    /// The `[format]` table
+    pub fn format(&self) -> &FormatConfig {
+        &self.format
+    }
+
+    /// The `[limits]` table
+    pub fn limits(&self) -> &LimitsConfig {
+        &self.limits
+    }
+
+    /// The `[updates]` table
+    pub fn updates(&self) -> &UpdatesConfig {
+        &self.updates
+    }
+
+    /// The `[clipboard]` table
+    pub fn clipboard(&self) -> &ClipboardConfig {
+        &self.clipboard
+    }
+
+    /// The `[logging]` table
+    pub fn logging(&self) -> &LoggingConfig {
+        &self.logging
+    }
+
+    /// The `[naming]` table
+    pub fn naming(&self) -> &NamingConfig {
+        &self.naming
+    }
+
+    /// The `[editor]` table
+    pub fn editor(&self) -> &EditorConfig {
+        &self.editor
+    }
+
+    /// The `[banner]` table
+    pub fn banner(&self) -> &BannerConfig {
+        &self.banner
+    }
+
+    /// The `[wrap]` table
+    pub fn wrap(&self) -> &WrapConfig {
+        &self.wrap
+    }
+
+    /// The `[watch]` table
+    pub fn watch(&self) -> &WatchConfig {
+        &self.watch
+    }
+
+    /// The `[lint]` table
+    pub fn lint(&self) -> &LintConfig {
+        &self.lint
+    }
+
+    /// The `[tokens]` table
+    pub fn tokens(&self) -> &TokensConfig {
+        &self.tokens
+    }
+
+    /// The `[review]` table
+    pub fn review(&self) -> &ReviewConfig {
+        &self.review
+    }
+
+    /// The `[shared]` table
+    pub fn shared(&self) -> &SharedConfig {
+        &self.shared
+    }
+
+    /// The `[history]` table
+    pub fn history(&self) -> &HistoryConfig {
+        &self.history
+    }
+
+    /// The `[backups]` table
+    pub fn backups(&self) -> &BackupsConfig {
+        &self.backups
+    }
+
+    /// The `[trash]` table
+    pub fn trash(&self) -> &TrashConfig {
+        &self.trash
+    }
+
+    /// The `[sync]` table
+    pub fn sync(&self) -> &SyncConfig {
+        &self.sync
+    }
+
+    /// The `[includes]` table
+    pub fn includes(&self) -> &IncludesConfig {
+        &self.includes
+    }
+
+    /// The `[encryption]` table
+    pub fn encryption(&self) -> &EncryptionConfig {
+        &self.encryption
+    }
+
+    /// Bundles declared under `[bundles]`, by name
+    pub fn bundles(&self) -> &BTreeMap<String, Vec<String>> {
+        &self.bundles
+    }
+
     pub fn persist(&self, path: &Path) -> crate::Result<()> {
         let config_path = path.join("config.toml");
         let config_content = toml::to_string(self)
@@ -78,6 +821,16 @@ impl Config {
 }
 
 impl Storage {
+    /// Storage directory, holding `config.toml` and the `repo/` of profiles
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Settings loaded from `config.toml`
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
     pub fn new(path: PathBuf) -> crate::Result<Self> {
         Self::validate(&path)?;
         let config = Config::load(&path)?;
@@ -141,14 +894,7 @@ impl Storage {
         std::fs::create_dir_all(&repo)
             .map_err(|e| anyhow::anyhow!("Failed to create repo directory: {}", e))?;
 
-        let config = Config {
-            agents: Agents {
-                disable_claude: false,
-                disable_codex: false,
-            },
-            mcp: McpConfig::default(),
-            extensions: ExtensionsConfig::default(),
-        };
+        let config = Config::default();
 
         config.persist(&path)?;
         Self::validate(&path)?;
@@ -157,37 +903,127 @@ impl Storage {
         Ok(storage)
     }
 
+    /// Log file and level from the configuration
+    pub fn logging(&self) -> (Option<&Path>, &str, Option<crate::logging::LogFormat>) {
+        (
+            self.config.logging.file.as_deref(),
+            &self.config.logging.level,
+            self.config.logging.format,
+        )
+    }
+
+    /// Profiles of every layer; a profile in `repo/` hides a shared one with the same name
     pub fn list_repos(&self) -> crate::Result<Vec<String>> {
-        let repo_path = self.path.join("repo");
-        let list = recursive_list(&repo_path)
-            .map_err(|e| anyhow::anyhow!("Failed to list repositories: {}", e))?;
-        let list = list
+        let mut list = Vec::new();
+        for layer in self.layers() {
+            if !layer.is_dir() {
+                continue;
+            }
+            for name in list_layer(&layer)? {
+                if !list.contains(&name) {
+                    list.push(name);
+                }
+            }
+        }
+        Ok(list)
+    }
+
+    /// Directories profiles are looked up in: the writable `repo/`, then each
+    /// `[shared]` path
+    pub fn layers(&self) -> Vec<PathBuf> {
+        let mut layers = vec![self.path.join("repo")];
+        layers.extend(self.config.shared.paths.iter().map(|path| {
+            let path = crate::agent::expand_home(path).unwrap_or_else(|_| path.clone());
+            if path.join("repo").is_dir() {
+                path.join("repo")
+            } else {
+                path
+            }
+        }));
+        layers
+    }
+
+    /// The shared layer a profile is read from, when `repo/` has no copy of its own
+    pub fn shared_layer(&self, name: &str) -> Option<PathBuf> {
+        self.locate(name)
+            .map(|(layer, _)| layer)
+            .filter(|layer| *layer != self.path.join("repo"))
+    }
+
+    /// First layer holding `name`, with its markdown file there
+    fn locate(&self, name: &str) -> Option<(PathBuf, PathBuf)> {
+        self.layers().into_iter().find_map(|layer| {
+            let file = layer.join(format!("{name}.md"));
+            let folder = layer.join(name);
+            if file.exists() {
+                Some((layer, file))
+            } else if is_folder_profile(&folder) {
+                Some((layer, folder.join(INDEX_FILE)))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Markdown file holding a profile's content: `<layer>/<name>.md`, or
+    /// `<layer>/<name>/index.md` for a folder profile; `repo/<name>.md` for a new one
+    fn profile_file(&self, name: &str) -> PathBuf {
+        match self.locate(name) {
+            Some((_, file)) => file,
+            None => self.path.join("repo").join(format!("{name}.md")),
+        }
+    }
+
+    /// Directory of a folder profile, if `name` is one
+    pub fn profile_folder(&self, name: &str) -> Option<PathBuf> {
+        let (layer, file) = self.locate(name)?;
+        let dir = layer.join(name);
+        (file == dir.join(INDEX_FILE)).then_some(dir)
+    }
+
+    /// Files of a folder profile other than `index.md` and the marker, relative to
+    /// its directory
+    pub fn auxiliary_files(&self, name: &str) -> crate::Result<Vec<PathBuf>> {
+        let Some(dir) = self.profile_folder(name) else {
+            return Ok(Vec::new());
+        };
+
+        let mut files: Vec<PathBuf> = recursive_list(&dir)?
             .into_iter()
-            .filter(|path| path.is_file())
-            .filter(|path| path.extension().map(|e| e == "md").unwrap_or(false))
-            .map(|path| {
-                path.strip_prefix(&repo_path)
-                    .map(|p| p.to_string_lossy().to_string())
-                    .unwrap_or_else(|_| path.to_string_lossy().to_string())
-            })
-            .map(|s| s.trim_end_matches(".md").to_string())
+            .filter_map(|path| path.strip_prefix(&dir).ok().map(Path::to_path_buf))
+            .filter(|path| path != Path::new(INDEX_FILE) && path != Path::new(FOLDER_MARKER))
             .collect();
-        Ok(list)
+        files.sort();
+        Ok(files)
     }
 
     pub fn get_repo_path(&self, path: &str) -> crate::Result<PathBuf> {
-        let repo_path = self.path.join("repo").join(format!("{path}.md"));
+        let repo_path = self.profile_file(path);
         ensure!(repo_path.exists(), "Profile not found: {}", path);
         Ok(repo_path)
     }
 
     pub fn profile_exists(&self, name: &str) -> bool {
-        let repo_path = self.path.join("repo").join(format!("{name}.md"));
-        repo_path.exists()
+        self.profile_file(name).exists()
     }
 
     pub fn create_profile(&self, name: &str, content: &str) -> crate::Result<()> {
-        let repo_path = self.path.join("repo").join(format!("{name}.md"));
+        let repo = self.path.join("repo");
+        let repo_path = match self.shared_layer(name) {
+            // Writing to a shared profile makes a personal copy; a folder profile
+            // is copied whole so its auxiliary files come along
+            Some(_) => match self.profile_folder(name) {
+                Some(dir) => {
+                    let mut files = self.auxiliary_files(name)?;
+                    files.push(PathBuf::from(FOLDER_MARKER));
+                    crate::utils::copy_files(&dir, &repo.join(name), &files)?;
+                    self.record(&repo.join(name))?;
+                    repo.join(name).join(INDEX_FILE)
+                }
+                None => repo.join(format!("{name}.md")),
+            },
+            None => self.profile_file(name),
+        };
 
         // Ensure parent directory exists
         if let Some(parent) = repo_path.parent() {
@@ -195,30 +1031,200 @@ impl Storage {
                 .map_err(|e| anyhow::anyhow!("Failed to create profile directory: {}", e))?;
         }
 
+        let verb = if repo_path.exists() {
+            "Update"
+        } else {
+            "Create"
+        };
+        // Profiles marked sensitive are only ever stored encrypted
+        let sensitive = crate::frontmatter::parse_metadata(content).is_ok_and(|m| m.sensitive);
+        let content = if sensitive {
+            crate::crypto::encrypt(self, content)?
+        } else {
+            content.to_string()
+        };
         std::fs::write(&repo_path, content)
             .map_err(|e| anyhow::anyhow!("Failed to create profile '{}': {}", name, e))?;
 
+        self.record(&repo_path)?;
+        let changed = self.profile_folder(name).unwrap_or(repo_path);
+        self.commit(&changed, &format!("{verb} {name}"));
+        Ok(())
+    }
+
+    /// Copy profile `from` to the new name `to` in the personal repository, with its
+    /// front-matter and, for a folder profile, its auxiliary files
+    pub fn duplicate_profile(&self, from: &str, to: &str) -> crate::Result<()> {
+        let source = self.get_repo_path(from)?;
+        ensure!(!self.profile_exists(to), "Profile '{}' already exists", to);
+
+        let repo = self.path.join("repo");
+        let copied = match self.profile_folder(from) {
+            Some(dir) => {
+                let mut files = self.auxiliary_files(from)?;
+                files.extend([PathBuf::from(INDEX_FILE), PathBuf::from(FOLDER_MARKER)]);
+                crate::utils::copy_files(&dir, &repo.join(to), &files)?;
+                repo.join(to)
+            }
+            None => {
+                let dest = repo.join(format!("{to}.md"));
+                if let Some(parent) = dest.parent() {
+                    std::fs::create_dir_all(parent).map_err(|e| {
+                        anyhow::anyhow!("Failed to create profile directory: {}", e)
+                    })?;
+                }
+                std::fs::copy(&source, &dest).map_err(|e| {
+                    anyhow::anyhow!("Failed to copy profile '{}' to '{}': {}", from, to, e)
+                })?;
+                dest
+            }
+        };
+
+        self.record(&copied)?;
+        self.commit(&copied, &format!("Copy {from} to {to}"));
         Ok(())
     }
 
     pub fn delete_profile(&self, name: &str) -> crate::Result<()> {
         let repo_path = self.get_repo_path(name)?; // This ensures the profile exists
+        if let Some(layer) = self.shared_layer(name) {
+            anyhow::bail!(
+                "Profile '{}' is shared from {} and cannot be deleted",
+                name,
+                layer.display()
+            );
+        }
 
-        std::fs::remove_file(&repo_path)
+        let removed = self.profile_folder(name).unwrap_or(repo_path);
+        crate::trash::put(self, &removed)
             .map_err(|e| anyhow::anyhow!("Failed to delete profile '{}': {}", name, e))?;
 
+        self.record(&removed)?;
+        self.commit(&removed, &format!("Delete {name}"));
+        Ok(())
+    }
+
+    /// Put the most recently deleted profile called `name` back from the trash
+    pub fn restore_profile(&self, name: &str) -> crate::Result<crate::trash::Trashed> {
+        let (trashed, restored) = crate::trash::take(self, name)?;
+        self.record(&restored)?;
+        self.commit(&restored, &format!("Restore {name}"));
+        Ok(trashed)
+    }
+
+    /// Commit a change to the repository history when `[history]` is enabled. The
+    /// change itself already happened, so a failing commit is only reported.
+    fn commit(&self, path: &Path, message: &str) {
+        if !self.config.history.enabled {
+            return;
+        }
+        let repo_path = self.path.join("repo");
+        let relative = path.strip_prefix(&repo_path).unwrap_or(path);
+        if let Err(e) = crate::history::commit(&repo_path, relative, message) {
+            tracing::warn!("Failed to record history: {e}");
+        }
+    }
+
+    /// Path of a profile relative to `repo/` as tracked by the history: its
+    /// directory for a folder profile, else its markdown file. A deleted profile is
+    /// looked up in the history itself.
+    fn history_path(&self, name: &str) -> PathBuf {
+        let repo_path = self.path.join("repo");
+        if let Some(dir) = self.profile_folder(name)
+            && dir.starts_with(&repo_path)
+        {
+            return PathBuf::from(name);
+        }
+        let file = PathBuf::from(format!("{name}.md"));
+        if repo_path.join(&file).exists() {
+            return file;
+        }
+        let marker = Path::new(name).join(FOLDER_MARKER);
+        if crate::history::exists_at(&repo_path, "HEAD", &marker) {
+            PathBuf::from(name)
+        } else {
+            file
+        }
+    }
+
+    /// Commits that changed a profile, newest first
+    pub fn history(&self, name: &str) -> crate::Result<Vec<crate::history::Entry>> {
+        let repo_path = self.path.join("repo");
+        anyhow::ensure!(
+            crate::history::is_enabled(&repo_path),
+            "The profile repository has no history; set enabled = true under [history] in config.toml"
+        );
+        crate::history::log(&repo_path, &self.history_path(name))
+    }
+
+    /// Restore a profile as it was at `revision` and record that as a new change
+    pub fn revert(&self, name: &str, revision: &str) -> crate::Result<()> {
+        let repo_path = self.path.join("repo");
+        anyhow::ensure!(
+            crate::history::is_enabled(&repo_path),
+            "The profile repository has no history; set enabled = true under [history] in config.toml"
+        );
+        let path = self.history_path(name);
+        crate::history::restore(&repo_path, revision, &path)?;
+        self.record(&repo_path.join(&path))?;
+        self.commit(
+            &repo_path.join(&path),
+            &format!("Revert {name} to {revision}"),
+        );
         Ok(())
     }
 
+    /// Update the checksum manifest after pmx wrote or removed `path` in the repository
+    fn record(&self, path: &Path) -> crate::Result<()> {
+        let repo_path = self.path.join("repo");
+        let relative = path.strip_prefix(&repo_path).unwrap_or(path);
+        crate::manifest::refresh(&self.path, &[relative.to_path_buf()])
+    }
+
     pub fn get_profile_content(&self, name: &str) -> crate::Result<String> {
         let repo_path = self.get_repo_path(name)?; // This ensures the profile exists
 
-        std::fs::read_to_string(&repo_path)
-            .map_err(|e| anyhow::anyhow!("Failed to read profile '{}': {}", name, e))
+        let content = crate::encoding::read_text(&repo_path)
+            .map_err(|e| anyhow::anyhow!("Failed to read profile '{}': {}", name, e))?;
+        if crate::crypto::is_encrypted(&content) {
+            return crate::crypto::decrypt(self, &content)
+                .map_err(|e| anyhow::anyhow!("Failed to decrypt profile '{}': {}", name, e));
+        }
+        Ok(content)
+    }
+
+    /// Front-matter a profile declares; empty when it has none
+    pub fn metadata(&self, name: &str) -> crate::Result<crate::frontmatter::ProfileMetadata> {
+        let content = self.get_profile_content(name)?;
+        crate::frontmatter::parse_metadata(&content)
+            .map_err(|e| anyhow::anyhow!("Failed to parse front-matter of '{}': {}", name, e))
     }
 
+    /// Whether a profile's `expires:` or `review_by:` date has passed
+    pub fn staleness(&self, name: &str) -> Option<crate::frontmatter::Staleness> {
+        self.metadata(name).ok()?.staleness(&crate::utils::today())
+    }
+
+    /// Content as agents receive it, with any `extends:` chain and includes resolved
     pub fn get_content(&self, name: &str) -> crate::Result<String> {
-        self.get_profile_content(name)
+        let content = crate::extends::resolve(self, name)?;
+        crate::include::expand(self, name, &content)
+    }
+
+    /// Content to hand to an agent: the body of `get_content` with template
+    /// functions run and secrets filled in
+    pub fn render(&self, name: &str) -> crate::Result<String> {
+        let content = self.get_content(name)?;
+        let body = crate::frontmatter::body(&content);
+        crate::secrets::resolve(&crate::functions::expand(self, body)?)
+    }
+
+    /// Profiles of the bundle `name` declared under `[bundles]`, in order
+    pub fn bundle(&self, name: &str) -> crate::Result<&[String]> {
+        match self.config.bundles.get(name) {
+            Some(profiles) => Ok(profiles),
+            None => anyhow::bail!("Bundle '{}' not found under [bundles] in config.toml", name),
+        }
     }
 
     pub fn is_mcp_enabled(&self) -> bool {
@@ -232,6 +1238,11 @@ impl Storage {
         )
     }
 
+    pub fn is_function_allowed(&self, name: &str) -> bool {
+        self.is_extension_allowed(name)
+            && self.config.extensions.functions.iter().any(|f| f == name)
+    }
+
     pub fn is_extension_allowed(&self, subcommand: &str) -> bool {
         self.config
             .extensions
@@ -239,24 +1250,58 @@ impl Storage {
             .contains(&subcommand.to_string())
     }
 
+    /// Load storage from the default location, creating it on first use
     pub fn auto() -> crate::Result<Self> {
-        let xdg_data_home = std::env::var("XDG_CONFIG_HOME").ok();
-        let other_path = crate::utils::home_dir()
-            .map(|p| p.join(".config/pmx"))
-            .expect("Failed to get home directory");
-
-        let path = xdg_data_home
-            .map(PathBuf::from)
-            .unwrap_or_else(|| other_path.clone());
-
-        Self::new(path).or_else(|e| {
-            eprintln!("Failed to load storage from {other_path:?}: {e}");
-            Self::initialize(other_path)
-        })
+        Self::open(&crate::paths::resolve(None)?)
+    }
+
+    /// Load a resolved storage directory, creating it when it is the implicit default
+    pub fn open(resolved: &crate::paths::StoragePath) -> crate::Result<Self> {
+        if resolved.source.is_explicit() || resolved.path.exists() {
+            Self::new(resolved.path.clone())
+        } else {
+            Self::initialize(resolved.path.clone())
+        }
     }
 }
 
-fn recursive_list(path: &Path) -> crate::Result<Vec<PathBuf>> {
+/// Profile names in one layer directory
+fn list_layer(repo_path: &Path) -> crate::Result<Vec<String>> {
+    let list = recursive_list(repo_path)
+        .map_err(|e| anyhow::anyhow!("Failed to list repositories: {}", e))?;
+    Ok(list
+        .into_iter()
+        .filter(|path| path.is_file())
+        .filter(|path| path.extension().map(|e| e == "md").unwrap_or(false))
+        .filter_map(|path| {
+            // A folder profile is listed once, by its directory; its other
+            // files are auxiliary rather than profiles of their own
+            match enclosing_folder_profile(repo_path, &path) {
+                Some(dir) => (path == dir.join(INDEX_FILE)).then_some(dir),
+                None => Some(path.with_extension("")),
+            }
+        })
+        .map(|path| {
+            // Names use `/` on every platform, as they are typed
+            path.strip_prefix(repo_path)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .replace('\\', "/")
+        })
+        .collect())
+}
+
+/// Outermost folder profile directory that contains `path`, if any
+fn enclosing_folder_profile(repo_path: &Path, path: &Path) -> Option<PathBuf> {
+    path.parent()?
+        .ancestors()
+        .take_while(|dir| *dir != repo_path)
+        .filter(|dir| is_folder_profile(dir))
+        .last()
+        .map(Path::to_path_buf)
+}
+
+pub(crate) fn recursive_list(path: &Path) -> crate::Result<Vec<PathBuf>> {
     match path {
         path if path.is_dir() => {
             let list = std::fs::read_dir(path)
@@ -293,15 +1338,14 @@ mod tests {
         Storage::initialize(path.clone()).unwrap();
 
         let config = Config {
-            agents: Agents {
-                disable_claude: false,
-                disable_codex: false,
-            },
+            agents: Agents::default(),
             mcp: McpConfig {
                 disable_prompts: DisableOption::Bool(true),
                 disable_tools: DisableOption::Bool(true),
+                ..Default::default()
             },
             extensions: ExtensionsConfig::default(),
+            ..Default::default()
         };
         config.persist(&path).unwrap();
         let storage = Storage::new(path).unwrap();
@@ -316,15 +1360,14 @@ mod tests {
         Storage::initialize(path.clone()).unwrap();
 
         let config = Config {
-            agents: Agents {
-                disable_claude: false,
-                disable_codex: false,
-            },
+            agents: Agents::default(),
             mcp: McpConfig {
                 disable_prompts: DisableOption::Bool(false),
                 disable_tools: DisableOption::Bool(true),
+                ..Default::default()
             },
             extensions: ExtensionsConfig::default(),
+            ..Default::default()
         };
         config.persist(&path).unwrap();
         let storage = Storage::new(path).unwrap();
@@ -339,15 +1382,14 @@ mod tests {
         Storage::initialize(path.clone()).unwrap();
 
         let config = Config {
-            agents: Agents {
-                disable_claude: false,
-                disable_codex: false,
-            },
+            agents: Agents::default(),
             mcp: McpConfig {
                 disable_prompts: DisableOption::Bool(true),
                 disable_tools: DisableOption::Bool(false),
+                ..Default::default()
             },
             extensions: ExtensionsConfig::default(),
+            ..Default::default()
         };
         config.persist(&path).unwrap();
         let storage = Storage::new(path).unwrap();
@@ -362,15 +1404,14 @@ mod tests {
         Storage::initialize(path.clone()).unwrap();
 
         let config = Config {
-            agents: Agents {
-                disable_claude: false,
-                disable_codex: false,
-            },
+            agents: Agents::default(),
             mcp: McpConfig {
                 disable_prompts: DisableOption::List(vec!["prompt1".to_string()]),
                 disable_tools: DisableOption::Bool(true),
+                ..Default::default()
             },
             extensions: ExtensionsConfig::default(),
+            ..Default::default()
         };
         config.persist(&path).unwrap();
         let storage = Storage::new(path).unwrap();
@@ -378,6 +1419,184 @@ mod tests {
         assert!(storage.is_mcp_enabled());
     }
 
+    #[test]
+    fn test_folder_profiles() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::initialize(temp_dir.path().join("test_storage")).unwrap();
+        let folder = storage.path.join("repo").join("team").join("review");
+        std::fs::create_dir_all(folder.join("refs")).unwrap();
+        std::fs::write(folder.join(INDEX_FILE), "Review carefully.\n").unwrap();
+        std::fs::write(folder.join(FOLDER_MARKER), "").unwrap();
+        std::fs::write(folder.join("checklist.md"), "- tests\n").unwrap();
+        std::fs::write(folder.join("refs").join("style.txt"), "style").unwrap();
+        storage.create_profile("plain", "Plain.\n").unwrap();
+
+        let mut profiles = storage.list_repos().unwrap();
+        profiles.sort();
+        assert_eq!(profiles, vec!["plain", "team/review"]);
+
+        assert!(storage.profile_exists("team/review"));
+        assert_eq!(
+            storage.get_content("team/review").unwrap(),
+            "Review carefully.\n"
+        );
+        assert_eq!(
+            storage.auxiliary_files("team/review").unwrap(),
+            vec![
+                PathBuf::from("checklist.md"),
+                PathBuf::from("refs").join("style.txt")
+            ]
+        );
+        assert!(storage.auxiliary_files("plain").unwrap().is_empty());
+
+        storage.create_profile("team/review", "Updated.\n").unwrap();
+        assert_eq!(
+            std::fs::read_to_string(folder.join(INDEX_FILE)).unwrap(),
+            "Updated.\n"
+        );
+
+        storage.delete_profile("team/review").unwrap();
+        assert!(!folder.exists());
+    }
+
+    #[test]
+    fn test_index_without_marker_is_a_plain_profile() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::initialize(temp_dir.path().join("test_storage")).unwrap();
+        storage.create_profile("docs/index", "Index.\n").unwrap();
+        storage.create_profile("docs/style", "Style.\n").unwrap();
+
+        let mut profiles = storage.list_repos().unwrap();
+        profiles.sort();
+        assert_eq!(profiles, vec!["docs/index", "docs/style"]);
+        assert!(!storage.profile_exists("docs"));
+
+        storage.delete_profile("docs/index").unwrap();
+        assert_eq!(storage.list_repos().unwrap(), vec!["docs/style"]);
+    }
+
+    #[test]
+    fn test_duplicate_profile() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::initialize(temp_dir.path().join("test_storage")).unwrap();
+        let folder = storage.path.join("repo").join("team").join("review");
+        std::fs::create_dir_all(folder.join("refs")).unwrap();
+        std::fs::write(folder.join(INDEX_FILE), "Review carefully.\n").unwrap();
+        std::fs::write(folder.join(FOLDER_MARKER), "").unwrap();
+        std::fs::write(folder.join("refs").join("style.txt"), "style").unwrap();
+        storage
+            .create_profile("plain", "---\ndescription: Plain\n---\nPlain.\n")
+            .unwrap();
+
+        storage
+            .duplicate_profile("plain", "copies/deep/plain")
+            .unwrap();
+        assert_eq!(
+            storage.get_profile_content("copies/deep/plain").unwrap(),
+            "---\ndescription: Plain\n---\nPlain.\n"
+        );
+        storage
+            .duplicate_profile("team/review", "mine/review")
+            .unwrap();
+        assert_eq!(
+            storage.get_content("mine/review").unwrap(),
+            "Review carefully.\n"
+        );
+        assert_eq!(
+            storage.auxiliary_files("mine/review").unwrap(),
+            vec![PathBuf::from("refs").join("style.txt")]
+        );
+
+        assert!(storage.duplicate_profile("plain", "mine/review").is_err());
+        assert!(storage.duplicate_profile("missing", "new").is_err());
+    }
+
+    #[test]
+    fn test_shared_layer_copy_on_write() {
+        let temp_dir = TempDir::new().unwrap();
+        let team = temp_dir.path().join("team");
+        let team_storage = Storage::initialize(team.clone()).unwrap();
+        team_storage
+            .create_profile("style", "Team style.\n")
+            .unwrap();
+        let folder = team.join("repo").join("review");
+        std::fs::create_dir_all(&folder).unwrap();
+        std::fs::write(folder.join(INDEX_FILE), "Review.\n").unwrap();
+        std::fs::write(folder.join(FOLDER_MARKER), "").unwrap();
+        std::fs::write(folder.join("checklist.md"), "- tests\n").unwrap();
+
+        let path = temp_dir.path().join("test_storage");
+        Storage::initialize(path.clone()).unwrap();
+        let config = Config {
+            shared: SharedConfig {
+                paths: vec![team.clone()],
+            },
+            ..Default::default()
+        };
+        config.persist(&path).unwrap();
+        let storage = Storage::new(path).unwrap();
+        storage.create_profile("mine", "Mine.\n").unwrap();
+
+        let mut profiles = storage.list_repos().unwrap();
+        profiles.sort();
+        assert_eq!(profiles, vec!["mine", "review", "style"]);
+        assert_eq!(storage.get_content("style").unwrap(), "Team style.\n");
+        assert_eq!(storage.shared_layer("style"), Some(team.join("repo")));
+        assert!(storage.delete_profile("style").is_err());
+
+        storage.create_profile("style", "My style.\n").unwrap();
+        storage.create_profile("review", "My review.\n").unwrap();
+        assert_eq!(storage.shared_layer("style"), None);
+        assert_eq!(storage.get_content("style").unwrap(), "My style.\n");
+        assert_eq!(
+            storage.auxiliary_files("review").unwrap(),
+            vec![PathBuf::from("checklist.md")]
+        );
+        let mut profiles = storage.list_repos().unwrap();
+        profiles.sort();
+        assert_eq!(profiles, vec!["mine", "review", "style"]);
+        assert_eq!(
+            std::fs::read_to_string(team.join("repo").join("style.md")).unwrap(),
+            "Team style.\n"
+        );
+
+        // Dropping the personal copy falls back to the shared profile
+        storage.delete_profile("style").unwrap();
+        assert_eq!(storage.get_content("style").unwrap(), "Team style.\n");
+    }
+
+    #[test]
+    fn test_history_records_and_reverts_changes() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("test_storage");
+        Storage::initialize(path.clone()).unwrap();
+        let config = Config {
+            history: HistoryConfig { enabled: true },
+            ..Default::default()
+        };
+        config.persist(&path).unwrap();
+        let storage = Storage::new(path).unwrap();
+
+        storage.create_profile("rust", "First\n").unwrap();
+        storage.create_profile("rust", "Overwritten\n").unwrap();
+        storage.delete_profile("rust").unwrap();
+
+        let history = storage.history("rust").unwrap();
+        let subjects: Vec<_> = history.iter().map(|e| e.subject.as_str()).collect();
+        assert_eq!(subjects, vec!["Delete rust", "Update rust", "Create rust"]);
+
+        storage.revert("rust", &history[2].revision).unwrap();
+        assert_eq!(storage.get_content("rust").unwrap(), "First\n");
+        assert_eq!(
+            storage.history("rust").unwrap()[0].subject,
+            format!("Revert rust to {}", history[2].revision)
+        );
+        assert_eq!(
+            crate::manifest::verify(&storage.path).unwrap(),
+            Some(Vec::new())
+        );
+    }
+
     #[test]
     fn test_is_extension_allowed() {
         let temp_dir = TempDir::new().unwrap();
@@ -385,14 +1604,13 @@ mod tests {
         Storage::initialize(path.clone()).unwrap();
 
         let config = Config {
-            agents: Agents {
-                disable_claude: false,
-                disable_codex: false,
-            },
+            agents: Agents::default(),
             mcp: McpConfig::default(),
             extensions: ExtensionsConfig {
                 allowed_subcommands: vec!["test-cmd".to_string(), "another-cmd".to_string()],
+                ..Default::default()
             },
+            ..Default::default()
         };
         config.persist(&path).unwrap();
         let storage = Storage::new(path).unwrap();
@@ -402,4 +1620,92 @@ mod tests {
         assert!(!storage.is_extension_allowed("not-allowed"));
         assert!(!storage.is_extension_allowed("malicious/path"));
     }
+
+    #[test]
+    fn test_agents_config() {
+        let config: Config = toml::from_str(
+            "[agents.claude]\nenabled = false\n\n[agents.codex]\ntarget = \"/work/AGENTS.md\"\n",
+        )
+        .unwrap();
+        assert!(!config.agents.is_enabled(Agent::Claude));
+        assert!(config.agents.is_enabled(Agent::Codex));
+        assert_eq!(
+            config.agents.get(Agent::Codex).unwrap().target.as_deref(),
+            Some(Path::new("/work/AGENTS.md"))
+        );
+
+        // Configs written before `[agents.<name>]` tables still load
+        let legacy: Config = toml::from_str(
+            "[agents]\ndisable_claude = false\ndisable_codex = true\ndisable_cursor = true\n",
+        )
+        .unwrap();
+        assert!(legacy.agents.is_enabled(Agent::Claude));
+        assert!(!legacy.agents.is_enabled(Agent::Codex));
+        assert!(!legacy.agents.is_enabled(Agent::Cursor));
+
+        assert!(toml::from_str::<Config>("[agents]\nclaude = true\n").is_err());
+    }
+
+    #[test]
+    fn test_config_getters() {
+        let config: Config = toml::from_str(
+            "[agents]\n\n[mcp]\nlog_requests = true\npage_size = 20\n\n[limits]\nmax_bytes = 4096\n",
+        )
+        .unwrap();
+        assert!(config.mcp().log_requests);
+        assert_eq!(config.mcp().page_size, Some(20));
+        assert_eq!(config.limits().max_bytes, Some(4096));
+        assert_eq!(config.limits().warn_bytes, None);
+        // Tables left out of the file have their defaults
+        assert_eq!(config.backups().keep, BackupsConfig::default().keep);
+        assert_eq!(
+            config.value("mcp.log_requests").unwrap(),
+            Some(toml::Value::Boolean(config.mcp().log_requests))
+        );
+    }
+
+    #[test]
+    fn test_custom_agents() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut storage = Storage::initialize(temp_dir.path().join("storage")).unwrap();
+        storage.config = toml::from_str(
+            "[agents.claude]\nenabled = false\n\n\
+             [agents.custom.aider]\ntarget = \"/home/me/.aider/CONVENTIONS.md\"\nseparator = \"---\"\n\n\
+             [agents.custom.zed]\ntarget = \"/home/me/.config/zed/rules.md\"\nproject_file = \".rules\"\n",
+        )
+        .unwrap();
+
+        let aider = Agent::named(&storage, "aider").unwrap();
+        assert_eq!(aider, Agent::named(&storage, "aider").unwrap());
+        assert_eq!(aider.name(), "aider");
+        assert_eq!(aider.separator(), Some("---"));
+        assert!(aider.is_enabled(&storage));
+        assert_eq!(
+            aider.global_target(&storage).unwrap(),
+            Path::new("/home/me/.aider/CONVENTIONS.md")
+        );
+        assert_eq!(
+            aider.project_target(Path::new("/work")),
+            Path::new("/work/CONVENTIONS.md")
+        );
+        let zed = Agent::named(&storage, "zed").unwrap();
+        assert_eq!(
+            zed.project_target(Path::new("/work")),
+            Path::new("/work/.rules")
+        );
+        assert_eq!(Agent::named(&storage, "Claude").unwrap(), Agent::Claude);
+        assert!(Agent::named(&storage, "vim").is_err());
+        assert_eq!(Agent::all(&storage).unwrap().len(), Agent::ALL.len() + 2);
+
+        let written = toml::to_string(&storage.config).unwrap();
+        assert!(written.contains("[agents.custom.aider]"));
+        assert!(written.contains("[agents.claude]"));
+
+        assert!(
+            toml::from_str::<Config>("[agents.custom.codex]\ntarget = \"/tmp/a.md\"\n").is_err()
+        );
+        assert!(
+            toml::from_str::<Config>("[agents.custom.\"a b\"]\ntarget = \"/tmp/a.md\"\n").is_err()
+        );
+    }
 }
@@ -0,0 +1,83 @@
+use std::path::Path;
+
+/// Paths of the git submodules declared in `repo/.gitmodules`, relative to `repo/`
+pub fn paths(repo: &Path) -> crate::Result<Vec<String>> {
+    let file = repo.join(".gitmodules");
+    if !file.is_file() {
+        return Ok(Vec::new());
+    }
+
+    let content = std::fs::read_to_string(&file)
+        .map_err(|e| anyhow::anyhow!("Failed to read .gitmodules: {}", e))?;
+    Ok(parse(&content))
+}
+
+fn parse(content: &str) -> Vec<String> {
+    let mut paths: Vec<String> = content
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once('=')?;
+            (key.trim() == "path").then(|| value.trim().trim_matches('"').to_string())
+        })
+        .filter(|path| !path.is_empty())
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// The submodule a profile (or any `/`-separated path in `repo/`) belongs to
+pub fn containing<'a>(modules: &'a [String], name: &str) -> Option<&'a str> {
+    modules
+        .iter()
+        .find(|module| {
+            name == module.as_str()
+                || name
+                    .strip_prefix(module.as_str())
+                    .is_some_and(|rest| rest.starts_with('/'))
+        })
+        .map(String::as_str)
+}
+
+/// Whether a submodule has been checked out, rather than being an empty directory
+pub fn is_checked_out(repo: &Path, module: &str) -> bool {
+    repo.join(module).join(".git").exists()
+}
+
+/// Check out every submodule at the tip of its tracked branch
+pub fn update(repo: &Path) -> crate::Result<()> {
+    crate::utils::git(
+        repo,
+        &["submodule", "update", "--init", "--remote", "--merge"],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_gitmodules() {
+        let content = r#"[submodule "team"]
+	path = team
+	url = git@example.com:team/prompts.git
+[submodule "vendor/shared"]
+	path = "vendor/shared"
+	url = https://example.com/shared.git
+	branch = main
+"#;
+        assert_eq!(parse(content), vec!["team", "vendor/shared"]);
+    }
+
+    #[test]
+    fn test_containing() {
+        let modules = vec!["team".to_string(), "vendor/shared".to_string()];
+        assert_eq!(containing(&modules, "team/review"), Some("team"));
+        assert_eq!(
+            containing(&modules, "vendor/shared/rust"),
+            Some("vendor/shared")
+        );
+        assert_eq!(containing(&modules, "teamwork/notes"), None);
+        assert_eq!(containing(&modules, "personal"), None);
+    }
+}
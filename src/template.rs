@@ -0,0 +1,322 @@
+use std::collections::BTreeMap;
+
+use anyhow::{bail, ensure};
+use regex::Regex;
+
+use crate::frontmatter::ProfileMetadata;
+use crate::storage::Storage;
+
+/// A `<{{NAME}}>` placeholder; the name has letters, digits and `_`
+pub fn placeholder() -> Regex {
+    Regex::new(r"<\{\{([A-Za-z_][A-Za-z0-9_]*)\}\}>").unwrap()
+}
+
+/// Names of the placeholders in `content`, each once, in order of appearance
+pub fn variables(content: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for captures in placeholder().captures_iter(content) {
+        if !names.iter().any(|name| *name == captures[1]) {
+            names.push(captures[1].to_string());
+        }
+    }
+    names
+}
+
+/// Replace each placeholder with the value `lookup` gives for its name; those
+/// without a value are kept as they are
+pub fn substitute(content: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    placeholder()
+        .replace_all(content, |captures: &regex::Captures| {
+            lookup(&captures[1]).unwrap_or_else(|| captures[0].to_string())
+        })
+        .into_owned()
+}
+
+/// Where `render` looks for placeholder values
+#[derive(Debug, Clone, Default)]
+pub struct Values {
+    /// Values given explicitly, which win over everything else
+    pub vars: Vec<(String, String)>,
+    /// Fall back to environment variables of the same name
+    pub env: bool,
+}
+
+/// Text a front-matter value stands for in a profile: strings as they are,
+/// anything else as YAML
+pub fn value_text(value: &serde_yaml::Value) -> Option<String> {
+    Some(match value {
+        serde_yaml::Value::String(text) => text.clone(),
+        other => serde_yaml::to_string(other).ok()?.trim_end().to_string(),
+    })
+}
+
+/// Values for the placeholders in `content`: from `lookup`, else the `default`
+/// declared under `arguments`. A value outside the declared `enum` is refused,
+/// as is a missing argument declared `required`; other missing ones are left out.
+pub fn resolve(
+    content: &str,
+    metadata: &ProfileMetadata,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> crate::Result<BTreeMap<String, String>> {
+    let mut values = BTreeMap::new();
+    for var in variables(content) {
+        let spec = metadata.arguments.get(&var);
+        let value = match lookup(&var) {
+            Some(value) => {
+                if let Some(spec) = spec.filter(|spec| !spec.allowed.is_empty()) {
+                    let allowed: Vec<String> = spec.allowed.iter().filter_map(value_text).collect();
+                    ensure!(
+                        allowed.contains(&value),
+                        "Invalid value '{}' for {}; expected one of: {}",
+                        value,
+                        var,
+                        allowed.join(", ")
+                    );
+                }
+                value
+            }
+            None => match spec.and_then(|spec| spec.default.as_ref()) {
+                Some(default) => value_text(default).unwrap_or_default(),
+                None if spec.and_then(|spec| spec.required) == Some(true) => {
+                    bail!("Missing required argument {}", var)
+                }
+                None => continue,
+            },
+        };
+        values.insert(var, value);
+    }
+    Ok(values)
+}
+
+/// Content of a profile as served over MCP, with its placeholders filled from
+/// `values` and then from the defaults its front-matter declares. Returns the
+/// content and the names left without a value.
+pub fn render(
+    storage: &Storage,
+    name: &str,
+    values: &Values,
+) -> crate::Result<(String, Vec<String>)> {
+    let content = storage.get_content(name)?;
+    // Front-matter that does not parse declares no defaults, so every placeholder
+    // then needs a value from `values`
+    let metadata = crate::frontmatter::parse_metadata(&content).unwrap_or_default();
+    let body = crate::frontmatter::body(&content);
+
+    let resolved = resolve(body, &metadata, |var: &str| {
+        values
+            .vars
+            .iter()
+            .rev()
+            .find(|(key, _)| key == var)
+            .map(|(_, value)| value.clone())
+            .or_else(|| values.env.then(|| std::env::var(var).ok()).flatten())
+    })?;
+    // Function calls and secrets are expanded before the values go in, so that
+    // a value cannot make one up
+    let expanded = crate::functions::expand(storage, body)?;
+    let expanded = crate::secrets::resolve(&expanded)?;
+    let rendered = substitute(&expanded, |var| resolved.get(var).cloned());
+    let missing = variables(&rendered);
+    Ok((rendered, missing))
+}
+
+/// Directory next to `config.toml` holding templates for `pmx profile create --template`
+pub const TEMPLATES_DIR: &str = "templates";
+
+/// Templates `pmx init` writes to `templates/`; also used when the storage has
+/// no template of the name
+pub const BUILTIN_TEMPLATES: [(&str, &str); 3] = [
+    ("architect", include_str!("../init/templates/architect.md")),
+    (
+        "code-review",
+        include_str!("../init/templates/code-review.md"),
+    ),
+    ("debugging", include_str!("../init/templates/debugging.md")),
+];
+
+/// Names of the templates in `templates/` and the built-in ones, sorted
+pub fn template_names(storage: &Storage) -> Vec<String> {
+    let mut names: Vec<String> = BUILTIN_TEMPLATES
+        .iter()
+        .map(|(name, _)| name.to_string())
+        .collect();
+    if let Ok(entries) = std::fs::read_dir(storage.path.join(TEMPLATES_DIR)) {
+        names.extend(entries.filter_map(Result::ok).filter_map(|entry| {
+            let path = entry.path();
+            (path.extension()? == "md").then(|| path.file_stem()?.to_str().map(String::from))?
+        }));
+    }
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Content of template `name`: `templates/<name>.md` in the storage, else the
+/// built-in template of that name
+pub fn load_template(storage: &Storage, name: &str) -> crate::Result<String> {
+    ensure!(
+        !name.is_empty() && !name.contains(['/', '\\']) && name != "..",
+        "Invalid template name '{}'",
+        name
+    );
+    let path = storage.path.join(TEMPLATES_DIR).join(format!("{name}.md"));
+    if path.is_file() {
+        return crate::encoding::read_text(&path)
+            .map_err(|e| anyhow::anyhow!("Failed to read template '{}': {}", name, e));
+    }
+    match BUILTIN_TEMPLATES
+        .iter()
+        .find(|(builtin, _)| *builtin == name)
+    {
+        Some((_, content)) => Ok(content.to_string()),
+        None => bail!(
+            "Template not found: {}. Available templates: {}",
+            name,
+            template_names(storage).join(", ")
+        ),
+    }
+}
+
+/// Fill the placeholders of template `content` with `vars`, else with what
+/// `ask` answers for them; a placeholder left empty stays in the profile
+pub fn fill_template(
+    content: &str,
+    vars: &[(String, String)],
+    ask: impl Fn(&str) -> crate::Result<Option<String>>,
+) -> crate::Result<String> {
+    let mut values = BTreeMap::new();
+    for var in variables(content) {
+        let value = match vars.iter().rev().find(|(key, _)| *key == var) {
+            Some((_, value)) => Some(value.clone()),
+            None => ask(&var)?,
+        };
+        if let Some(value) = value.filter(|value| !value.is_empty()) {
+            values.insert(var, value);
+        }
+    }
+    Ok(substitute(content, |var| values.get(var).cloned()))
+}
+
+/// Parse a `KEY=VALUE` pair given on the command line
+pub fn parse_var(pair: &str) -> Result<(String, String), String> {
+    let (key, value) = pair
+        .split_once('=')
+        .ok_or_else(|| format!("expected KEY=VALUE, got '{pair}'"))?;
+    if !Regex::new(r"^[A-Za-z_][A-Za-z0-9_]*$")
+        .unwrap()
+        .is_match(key)
+    {
+        return Err(format!(
+            "invalid placeholder name '{key}': use letters, digits and '_'"
+        ));
+    }
+    Ok((key.to_string(), value.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_render_fills_vars_then_defaults() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::initialize(temp_dir.path().join("storage")).unwrap();
+        storage
+            .create_profile(
+                "review",
+                "---\narguments:\n  LANG:\n    default: Rust\n  DEPTH:\n    default: 2\n---\nReview <{{TARGET}}> in <{{LANG}}>, depth <{{DEPTH}}>, for <{{TEAM}}>.\n",
+            )
+            .unwrap();
+
+        let values = Values {
+            vars: vec![
+                ("TARGET".to_string(), "main.rs".to_string()),
+                ("DEPTH".to_string(), "3".to_string()),
+            ],
+            env: false,
+        };
+        let (content, missing) = render(&storage, "review", &values).unwrap();
+        assert_eq!(
+            content,
+            "Review main.rs in Rust, depth 3, for <{{TEAM}}>.\n"
+        );
+        assert_eq!(missing, vec!["TEAM"]);
+
+        assert_eq!(
+            parse_var("A=b=c").unwrap(),
+            ("A".to_string(), "b=c".to_string())
+        );
+        assert!(parse_var("A").is_err());
+        assert!(parse_var("not valid=1").is_err());
+    }
+
+    #[test]
+    fn test_resolve_checks_enum_and_required() {
+        let content = "---\narguments:\n  TONE:\n    enum: [formal, casual]\n    default: formal\n  TARGET:\n    required: true\n---\nReview <{{TARGET}}> in a <{{TONE}}> tone.\n";
+        let metadata = crate::frontmatter::parse_metadata(content).unwrap();
+        let given = |pairs: &'static [(&'static str, &'static str)]| {
+            move |var: &str| {
+                pairs
+                    .iter()
+                    .find(|(key, _)| *key == var)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+
+        let values = resolve(content, &metadata, given(&[("TARGET", "main.rs")])).unwrap();
+        assert_eq!(values["TONE"], "formal");
+        assert_eq!(values["TARGET"], "main.rs");
+
+        let error = resolve(
+            content,
+            &metadata,
+            given(&[("TARGET", "main.rs"), ("TONE", "rude")]),
+        )
+        .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("expected one of: formal, casual")
+        );
+        let error = resolve(content, &metadata, given(&[])).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("Missing required argument TARGET")
+        );
+    }
+
+    #[test]
+    fn test_fill_template() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let storage = Storage::initialize(temp_dir.path().join("storage")).unwrap();
+        assert!(
+            load_template(&storage, "debugging")
+                .unwrap()
+                .contains("# Debugging")
+        );
+        assert!(load_template(&storage, "../config").is_err());
+
+        let dir = storage.path.join(TEMPLATES_DIR);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("debugging.md"),
+            "Debug <{{PROJECT}}> in <{{LANGUAGE}}> for <{{USER}}>.\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("notes.txt"), "Not a template").unwrap();
+        assert_eq!(
+            template_names(&storage),
+            ["architect", "code-review", "debugging"]
+        );
+
+        let content = load_template(&storage, "debugging").unwrap();
+        let vars = [("PROJECT".to_string(), "pmx".to_string())];
+        let ask = |var: &str| Ok((var == "LANGUAGE").then(|| "Rust".to_string()));
+        assert_eq!(
+            fill_template(&content, &vars, ask).unwrap(),
+            "Debug pmx in Rust for <{{USER}}>.\n"
+        );
+    }
+}
@@ -0,0 +1,95 @@
+use regex::Regex;
+
+/// Rough token count for `text`, assuming about four characters per token
+/// as is typical for English prose with BPE tokenizers
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// How token counts are estimated
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+    schemars::JsonSchema,
+    clap::ValueEnum,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Tokenizer {
+    /// About four characters per token
+    #[default]
+    Chars,
+    /// Splits text the way cl100k-style BPE tokenizers do before merging, and
+    /// counts the pieces
+    Cl100k,
+}
+
+impl Tokenizer {
+    pub fn count(self, text: &str) -> usize {
+        match self {
+            Tokenizer::Chars => estimate_tokens(text),
+            Tokenizer::Cl100k => estimate_cl100k(text),
+        }
+    }
+}
+
+/// cl100k's pre-tokenization: contractions, words with a leading space or
+/// symbol, numbers of up to three digits, runs of punctuation, and whitespace
+fn pieces() -> Regex {
+    Regex::new(
+        r"(?i:'s|'t|'re|'ve|'m|'ll|'d)|[^\r\n\p{L}\p{N}]?\p{L}+|\p{N}{1,3}| ?[^\s\p{L}\p{N}]+[\r\n]*|\s*[\r\n]+|\s+",
+    )
+    .unwrap()
+}
+
+/// Each piece is one token when short; without the vocabulary, longer words
+/// are taken to split every six letters, punctuation every two characters and
+/// non-ASCII text every character
+fn estimate_cl100k(text: &str) -> usize {
+    pieces()
+        .find_iter(text)
+        .map(|piece| {
+            let piece = piece.as_str();
+            let chars = piece.chars().count();
+            if piece.trim().is_empty() {
+                1
+            } else if !piece.is_ascii() {
+                chars
+            } else if piece.trim_start().starts_with(|c: char| c.is_alphabetic()) {
+                chars.div_ceil(6).max(1)
+            } else if piece.trim_start().starts_with(|c: char| c.is_numeric()) {
+                1
+            } else {
+                piece.trim().chars().count().div_ceil(2).max(1)
+            }
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abc"), 1);
+        assert_eq!(estimate_tokens("abcdefgh"), 2);
+        assert_eq!(estimate_tokens("abcdefghi"), 3);
+    }
+
+    #[test]
+    fn test_estimate_cl100k() {
+        let tokenizer = Tokenizer::Cl100k;
+        assert_eq!(tokenizer.count(""), 0);
+        assert_eq!(tokenizer.count("Hello, world!"), 4);
+        assert_eq!(tokenizer.count("I don't use 12345."), 8);
+        assert_eq!(tokenizer.count("internationalization"), 4);
+        assert_eq!(Tokenizer::Chars.count("Hello, world!"), 4);
+    }
+}
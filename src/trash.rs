@@ -0,0 +1,212 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, ensure};
+
+use crate::storage::Storage;
+
+/// Directory next to `config.toml` holding deleted profiles, one directory per deletion
+pub(crate) const TRASH_DIR: &str = ".trash";
+
+/// A deleted profile kept in the trash
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trashed {
+    pub name: String,
+    /// When it was deleted, as an RFC 3339 UTC timestamp
+    pub deleted: String,
+    /// Directory under `.trash/` holding it
+    dir: PathBuf,
+    /// The profile's file or folder relative to `repo/`
+    relative: PathBuf,
+}
+
+/// Move `removed`, the file or folder of a profile under `repo/`, to a new
+/// directory in the trash, then purge what is older than `[trash] purge_after_days`
+pub fn put(storage: &Storage, removed: &Path) -> crate::Result<()> {
+    let repo = storage.path.join("repo");
+    let relative = removed.strip_prefix(&repo).map_err(|_| {
+        anyhow!(
+            "Failed to move {} to the trash: not in {}",
+            removed.display(),
+            repo.display()
+        )
+    })?;
+
+    let trash = storage.path.join(TRASH_DIR);
+    // Colons are not allowed in file names on Windows
+    let stem = crate::utils::format_timestamp(std::time::SystemTime::now()).replace(':', "");
+    let mut dir = trash.join(&stem);
+    let mut n = 1;
+    while dir.exists() {
+        dir = trash.join(format!("{stem}-{n}"));
+        n += 1;
+    }
+
+    let dest = dir.join(relative);
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| anyhow!("Failed to create directory {}: {}", parent.display(), e))?;
+    }
+    std::fs::rename(removed, &dest)
+        .map_err(|e| anyhow!("Failed to move {} to the trash: {}", removed.display(), e))?;
+
+    let days = storage.config.trash.purge_after_days;
+    if days > 0 {
+        purge(storage, Some(days))?;
+    }
+    Ok(())
+}
+
+/// Profiles in the trash, most recently deleted first
+pub fn list(storage: &Storage) -> crate::Result<Vec<Trashed>> {
+    let trash = storage.path.join(TRASH_DIR);
+    let Ok(entries) = std::fs::read_dir(&trash) else {
+        return Ok(Vec::new());
+    };
+
+    let mut trashed = Vec::new();
+    for entry in entries {
+        let dir = entry
+            .map_err(|e| anyhow!("Failed to read {}: {}", trash.display(), e))?
+            .path();
+        let stem = dir
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        // `2026-10-16T173102Z`, maybe followed by `-<n>`
+        if stem.len() < 18 || !stem.is_char_boundary(18) {
+            continue;
+        }
+        let deleted = format!("{}:{}:{}", &stem[..13], &stem[13..15], &stem[15..18]);
+        let n: usize = stem[18..].trim_start_matches('-').parse().unwrap_or(0);
+        if let Some(relative) = find_profile(&dir, Path::new("")) {
+            let name = if dir.join(&relative).is_dir() {
+                relative.clone()
+            } else {
+                relative.with_extension("")
+            };
+            trashed.push((
+                n,
+                Trashed {
+                    name: name.to_string_lossy().replace('\\', "/"),
+                    deleted,
+                    dir,
+                    relative,
+                },
+            ));
+        }
+    }
+    // Deletions within the same second are numbered in order
+    trashed.sort_by(|(a_n, a), (b_n, b)| (&b.deleted, b_n).cmp(&(&a.deleted, a_n)));
+    Ok(trashed.into_iter().map(|(_, trashed)| trashed).collect())
+}
+
+/// The markdown file or folder profile under `dir`, relative to it
+fn find_profile(dir: &Path, relative: &Path) -> Option<PathBuf> {
+    let path = dir.join(relative);
+    if path.is_file() {
+        return (path.extension()? == "md").then(|| relative.to_path_buf());
+    }
+    if !relative.as_os_str().is_empty() && crate::storage::is_folder_profile(&path) {
+        return Some(relative.to_path_buf());
+    }
+    std::fs::read_dir(&path)
+        .ok()?
+        .filter_map(Result::ok)
+        .find_map(|entry| find_profile(dir, &relative.join(entry.file_name())))
+}
+
+/// Move the most recently deleted profile called `name` back under `repo/`,
+/// returning where it now is
+pub fn take(storage: &Storage, name: &str) -> crate::Result<(Trashed, PathBuf)> {
+    let Some(trashed) = list(storage)?.into_iter().find(|t| t.name == name) else {
+        bail!("Profile '{}' is not in the trash", name);
+    };
+    ensure!(
+        !storage.profile_exists(name),
+        "Profile '{}' already exists; delete or rename it before restoring",
+        name
+    );
+
+    let restored = storage.path.join("repo").join(&trashed.relative);
+    if let Some(parent) = restored.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| anyhow!("Failed to create directory {}: {}", parent.display(), e))?;
+    }
+    std::fs::rename(trashed.dir.join(&trashed.relative), &restored)
+        .map_err(|e| anyhow!("Failed to restore profile '{}': {}", name, e))?;
+    let _ = std::fs::remove_dir_all(&trashed.dir);
+    Ok((trashed, restored))
+}
+
+/// Remove profiles from the trash for good: those deleted more than
+/// `older_than_days` ago, or all of them. Returns how many were removed.
+pub fn purge(storage: &Storage, older_than_days: Option<u64>) -> crate::Result<usize> {
+    let cutoff = older_than_days.map(|days| {
+        let age = std::time::Duration::from_secs(days * 86_400);
+        let now = std::time::SystemTime::now();
+        crate::utils::format_timestamp(now.checked_sub(age).unwrap_or(std::time::UNIX_EPOCH))
+    });
+
+    let mut purged = 0;
+    for trashed in list(storage)? {
+        if cutoff
+            .as_ref()
+            .is_some_and(|cutoff| trashed.deleted >= *cutoff)
+        {
+            continue;
+        }
+        std::fs::remove_dir_all(&trashed.dir)
+            .map_err(|e| anyhow!("Failed to purge {}: {}", trashed.dir.display(), e))?;
+        purged += 1;
+    }
+    Ok(purged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_delete_restore_and_purge() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::initialize(temp_dir.path().join("storage")).unwrap();
+        storage.create_profile("coding/rust", "Rust\n").unwrap();
+        storage.create_profile("review/index", "Review\n").unwrap();
+        std::fs::write(storage.path.join("repo/review/checklist.md"), "- Tests\n").unwrap();
+        std::fs::write(
+            storage
+                .path
+                .join("repo/review")
+                .join(crate::storage::FOLDER_MARKER),
+            "",
+        )
+        .unwrap();
+
+        storage.delete_profile("coding/rust").unwrap();
+        storage.delete_profile("review").unwrap();
+        assert!(!storage.profile_exists("coding/rust"));
+        let names: Vec<_> = list(&storage)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.name)
+            .collect();
+        assert_eq!(names, ["review", "coding/rust"]);
+
+        storage.create_profile("coding/rust", "New\n").unwrap();
+        assert!(storage.restore_profile("coding/rust").is_err());
+        storage.delete_profile("coding/rust").unwrap();
+        storage.restore_profile("coding/rust").unwrap();
+        assert_eq!(storage.get_content("coding/rust").unwrap(), "New\n");
+        storage.restore_profile("review").unwrap();
+        assert_eq!(
+            storage.auxiliary_files("review").unwrap(),
+            [PathBuf::from("checklist.md")]
+        );
+
+        assert_eq!(purge(&storage, Some(1)).unwrap(), 0);
+        assert_eq!(purge(&storage, None).unwrap(), 1);
+        assert!(list(&storage).unwrap().is_empty());
+        assert!(storage.restore_profile("coding/rust").is_err());
+    }
+}
@@ -0,0 +1,183 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const CRATES_IO_URL: &str = "https://crates.io/api/v1/crates/pmx";
+
+/// Result of the last release check, cached in `state.toml`
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct UpdateCheck {
+    /// Unix timestamp (seconds) of the last check
+    pub checked_at: u64,
+    /// Newest published version seen at that time
+    pub latest_version: Option<String>,
+}
+
+/// Print a one-line notice when a newer release is known, and refresh the
+/// cached release information in a detached background process when it is stale.
+///
+/// Never fails: update checks must not get in the way of the actual command.
+pub fn notify(storage: &crate::storage::Storage) {
+    use is_terminal::IsTerminal;
+
+    let config = &storage.config.updates;
+    if !config.check
+        || std::env::var_os("PMX_NO_UPDATE_CHECK").is_some()
+        || std::env::var_os("CI").is_some()
+        || !std::io::stderr().is_terminal()
+    {
+        return;
+    }
+
+    let Ok(state) = crate::state::State::load(&storage.path) else {
+        return;
+    };
+
+    if let Some(latest) = state
+        .update_check
+        .as_ref()
+        .and_then(|check| check.latest_version.as_deref())
+        && is_newer(latest, env!("CARGO_PKG_VERSION"))
+        && !crate::logging::is_quiet()
+    {
+        eprintln!(
+            "A new version of pmx is available: {} -> {latest} (run `cargo install pmx` to update)",
+            env!("CARGO_PKG_VERSION")
+        );
+    }
+
+    let interval = Duration::from_secs(config.interval_hours.saturating_mul(3600));
+    if is_stale(state.update_check.as_ref(), now(), interval) {
+        spawn_background_check(storage);
+    }
+}
+
+/// Query crates.io for the newest release and cache the answer in the state file
+pub fn check_now(storage: &crate::storage::Storage) -> crate::Result<()> {
+    let latest_version = fetch_latest_version().ok();
+    crate::state::update(storage, |state| {
+        state.update_check = Some(UpdateCheck {
+            checked_at: now(),
+            latest_version,
+        })
+    })
+}
+
+fn spawn_background_check(storage: &crate::storage::Storage) {
+    use std::process::{Command, Stdio};
+
+    let Ok(exe) = std::env::current_exe() else {
+        return;
+    };
+    // Record the attempt first so concurrent invocations don't all spawn a check
+    let attempted = crate::state::update(storage, |state| {
+        let latest_version = state
+            .update_check
+            .take()
+            .and_then(|check| check.latest_version);
+        state.update_check = Some(UpdateCheck {
+            checked_at: now(),
+            latest_version,
+        });
+    });
+    if attempted.is_err() {
+        return;
+    }
+
+    let _ = Command::new(exe)
+        .arg("--storage")
+        .arg(&storage.path)
+        .arg("internal-update-check")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+}
+
+fn fetch_latest_version() -> crate::Result<String> {
+    let response: serde_json::Value = ureq::get(CRATES_IO_URL)
+        .set(
+            "User-Agent",
+            concat!("pmx/", env!("CARGO_PKG_VERSION"), " (update check)"),
+        )
+        .timeout(Duration::from_secs(5))
+        .call()
+        .map_err(|e| anyhow::anyhow!("Failed to query crates.io: {}", e))?
+        .into_json()
+        .map_err(|e| anyhow::anyhow!("Failed to parse crates.io response: {}", e))?;
+
+    response["crate"]["max_stable_version"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| anyhow::anyhow!("crates.io response has no stable version"))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn is_stale(check: Option<&UpdateCheck>, now: u64, interval: Duration) -> bool {
+    check.is_none_or(|check| now.saturating_sub(check.checked_at) >= interval.as_secs())
+}
+
+/// Whether `candidate` is a newer `major.minor.patch` release than `current`
+fn is_newer(candidate: &str, current: &str) -> bool {
+    fn parse(version: &str) -> Option<(u64, u64, u64)> {
+        let version = version.split(['-', '+']).next()?;
+        let mut parts = version.split('.').map(|part| part.parse::<u64>().ok());
+        Some((parts.next()??, parts.next()??, parts.next()??))
+    }
+
+    match (parse(candidate), parse(current)) {
+        (Some(candidate), Some(current)) => candidate > current,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_newer() {
+        assert!(is_newer("0.2.0", "0.1.9"));
+        assert!(is_newer("1.0.0", "0.99.99"));
+        assert!(!is_newer("0.1.0", "0.1.0"));
+        assert!(!is_newer("0.1.0", "0.2.0"));
+        assert!(!is_newer("garbage", "0.1.0"));
+        assert!(is_newer("0.2.0-beta.1", "0.1.0"));
+    }
+
+    #[test]
+    fn test_is_stale() {
+        let day = Duration::from_secs(24 * 3600);
+        assert!(is_stale(None, 100, day));
+
+        let check = UpdateCheck {
+            checked_at: 1_000,
+            latest_version: None,
+        };
+        assert!(!is_stale(Some(&check), 1_000 + 3600, day));
+        assert!(is_stale(Some(&check), 1_000 + 24 * 3600, day));
+    }
+
+    #[test]
+    fn test_check_is_cached_in_state() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let storage = crate::storage::Storage::initialize(temp_dir.path().join("storage")).unwrap();
+
+        crate::state::update(&storage, |state| {
+            state.update_check = Some(UpdateCheck {
+                checked_at: 42,
+                latest_version: Some("9.9.9".to_string()),
+            })
+        })
+        .unwrap();
+
+        let state = crate::state::State::load(&storage.path).unwrap();
+        let check = state.update_check.unwrap();
+        assert_eq!(check.checked_at, 42);
+        assert_eq!(check.latest_version.as_deref(), Some("9.9.9"));
+    }
+}
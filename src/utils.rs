@@ -1,11 +1,333 @@
 pub fn home_dir() -> anyhow::Result<std::path::PathBuf> {
+    // The standard library asks Windows for the profile folder when this is unset
     #[cfg(windows)]
-    {
-        anyhow::bail!(
-            "Home directory retrieval is not supported on Windows. Please set the environment variable manually."
-        );
+    if let Some(profile) = std::env::var_os("USERPROFILE").filter(|value| !value.is_empty()) {
+        return Ok(std::path::PathBuf::from(profile));
     }
 
     #[allow(deprecated)]
     std::env::home_dir().ok_or_else(|| anyhow::anyhow!("Failed to get home directory"))
 }
+
+/// Whether output written to stdout should use ANSI colors
+pub fn use_color() -> bool {
+    use is_terminal::IsTerminal;
+
+    std::env::var_os("NO_COLOR").is_none() && std::io::stdout().is_terminal()
+}
+
+/// Whether there is a terminal to ask the user on
+pub fn is_interactive() -> bool {
+    use is_terminal::IsTerminal;
+
+    std::io::stdin().is_terminal() && std::io::stderr().is_terminal()
+}
+
+/// Ask `prompt` on the terminal, defaulting to no. `yes` (`--yes`) answers it
+/// without asking; without a terminal that is the only way to proceed.
+pub fn confirm(prompt: &str, yes: bool) -> anyhow::Result<bool> {
+    if yes {
+        return Ok(true);
+    }
+    anyhow::ensure!(
+        is_interactive(),
+        "Cannot ask \"{}\" without a terminal; pass --yes to proceed",
+        prompt
+    );
+    dialoguer::Confirm::new()
+        .with_prompt(prompt)
+        .default(false)
+        .interact()
+        .map_err(|e| anyhow::anyhow!("Failed to get confirmation: {}", e))
+}
+
+/// Where `path` points when it is a symbolic link
+pub fn symlink_target(path: &std::path::Path) -> Option<std::path::PathBuf> {
+    let metadata = std::fs::symlink_metadata(path).ok()?;
+    metadata
+        .file_type()
+        .is_symlink()
+        .then(|| std::fs::read_link(path).ok())?
+}
+
+/// Create a symbolic link at `link` pointing to the file `original`
+pub fn symlink(original: &std::path::Path, link: &std::path::Path) -> std::io::Result<()> {
+    #[cfg(unix)]
+    return std::os::unix::fs::symlink(original, link);
+    #[cfg(windows)]
+    return std::os::windows::fs::symlink_file(original, link);
+    #[cfg(not(any(unix, windows)))]
+    return Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "symbolic links are not supported on this platform",
+    ));
+}
+
+/// Write `content` to `path`. A symbolic link there that points into `repo`,
+/// as `pmx apply --link` makes, is replaced with a regular file so the stored
+/// profile is left alone; any other link is written through. The file is
+/// replaced atomically, so an interrupted write never leaves it half written.
+pub fn write_unlinked(
+    path: &std::path::Path,
+    content: impl AsRef<[u8]>,
+    repo: &std::path::Path,
+) -> std::io::Result<()> {
+    let path = match symlink_target(path) {
+        Some(_) if links_into(path, repo) => path.to_path_buf(),
+        Some(target) => {
+            let target = path
+                .parent()
+                .map_or(target.clone(), |parent| parent.join(&target));
+            target.canonicalize().unwrap_or(target)
+        }
+        None => path.to_path_buf(),
+    };
+    write_atomic(&path, content)
+}
+
+/// Write `content` to a temporary file next to `path` and rename it over `path`,
+/// keeping the permissions of the file it replaces
+pub fn write_atomic(path: &std::path::Path, content: impl AsRef<[u8]>) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => std::path::Path::new("."),
+    };
+    let existing = std::fs::metadata(path)
+        .ok()
+        .map(|metadata| metadata.permissions());
+    let mut builder = tempfile::Builder::new();
+    builder.prefix(".pmx-");
+    // A new file gets the permissions `std::fs::write` would give it
+    #[cfg(unix)]
+    if existing.is_none() {
+        use std::os::unix::fs::PermissionsExt;
+        builder.permissions(std::fs::Permissions::from_mode(0o666));
+    }
+    let mut file = builder.tempfile_in(dir)?;
+    file.write_all(content.as_ref())?;
+    file.as_file().sync_all()?;
+    if let Some(permissions) = existing {
+        file.as_file().set_permissions(permissions)?;
+    }
+    file.persist(path).map_err(|e| e.error)?;
+    Ok(())
+}
+
+/// Whether `path` is a symbolic link to a file under `dir`. Relative targets
+/// are resolved against the link's directory.
+fn links_into(path: &std::path::Path, dir: &std::path::Path) -> bool {
+    let Some(target) = symlink_target(path) else {
+        return false;
+    };
+    let target = match path.parent() {
+        Some(parent) => parent.join(target),
+        None => target,
+    };
+    let target = target.canonicalize().unwrap_or(target);
+    target.starts_with(dir) || dir.canonicalize().is_ok_and(|dir| target.starts_with(dir))
+}
+
+/// Hex-encoded SHA-256 digest of `content`
+pub fn content_hash(content: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+
+    format!("{:x}", Sha256::digest(content))
+}
+
+/// Whether `source` names a git remote rather than a local path
+pub fn is_git_url(source: &str) -> bool {
+    source.contains("://") || source.starts_with("git@") || source.ends_with(".git")
+}
+
+/// Shallow-clone `url` into `dest` without prompting for credentials
+pub fn git_clone(url: &str, dest: &std::path::Path) -> anyhow::Result<()> {
+    let status = std::process::Command::new("git")
+        .args(["clone", "--depth", "1", "--quiet", url])
+        .arg(dest)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(std::process::Stdio::null())
+        .status()
+        .map_err(|e| anyhow::anyhow!("Failed to run git: {}", e))?;
+    anyhow::ensure!(status.success(), "Failed to clone {}", url);
+    Ok(())
+}
+
+/// Run git in `dir` without prompting for credentials, returning its standard output
+pub fn git(dir: &std::path::Path, args: &[&str]) -> anyhow::Result<String> {
+    let output = std::process::Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(std::process::Stdio::null())
+        .output()
+        .map_err(|e| anyhow::anyhow!("Failed to run git: {}", e))?;
+    anyhow::ensure!(
+        output.status.success(),
+        "git {} failed: {}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Abbreviated hash of the last commit touching `file`, or `None` outside a git work tree
+pub fn git_revision(file: &std::path::Path) -> Option<String> {
+    let dir = file.parent()?;
+    let output = std::process::Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["log", "-1", "--format=%h", "--"])
+        .arg(file.file_name()?)
+        .stdin(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .output()
+        .ok()?;
+    let revision = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (output.status.success() && !revision.is_empty()).then_some(revision)
+}
+
+/// Today's UTC date as `YYYY-MM-DD`
+pub fn today() -> String {
+    format_timestamp(std::time::SystemTime::now())[..10].to_string()
+}
+
+/// Format a time as an RFC 3339 UTC timestamp with second precision
+pub fn format_timestamp(time: std::time::SystemTime) -> String {
+    let secs = time
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        rem / 3_600,
+        rem % 3_600 / 60,
+        rem % 60
+    )
+}
+
+/// Files below `root` as paths relative to it, skipping hidden files and directories
+pub fn visible_files(root: &std::path::Path) -> anyhow::Result<Vec<std::path::PathBuf>> {
+    let mut files: Vec<_> = crate::storage::recursive_list(root)?
+        .into_iter()
+        .filter_map(|path| path.strip_prefix(root).ok().map(|p| p.to_path_buf()))
+        .filter(|relative| {
+            !relative
+                .components()
+                .any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Copy `files` (relative paths) from `from` to `to`, creating directories as needed
+pub fn copy_files(
+    from: &std::path::Path,
+    to: &std::path::Path,
+    files: &[std::path::PathBuf],
+) -> anyhow::Result<()> {
+    for relative in files {
+        let dest = to.join(relative);
+        if let Some(parent) = dest.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| anyhow::anyhow!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        std::fs::copy(from.join(relative), &dest)
+            .map_err(|e| anyhow::anyhow!("Failed to copy {}: {}", relative.display(), e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        assert_eq!(
+            format_timestamp(UNIX_EPOCH + Duration::from_secs(1_709_210_096)),
+            "2024-02-29T12:34:56Z"
+        );
+    }
+
+    #[test]
+    fn test_is_git_url() {
+        assert!(is_git_url("https://github.com/team/prompts"));
+        assert!(is_git_url("git@github.com:team/prompts.git"));
+        assert!(!is_git_url("./prompts"));
+    }
+
+    #[test]
+    fn test_confirm_without_terminal() {
+        assert!(confirm("Delete profile 'x'?", true).unwrap());
+        if !is_interactive() {
+            let error = confirm("Delete profile 'x'?", false).unwrap_err();
+            assert!(error.to_string().contains("pass --yes"));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_atomic_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("AGENTS.md");
+        std::fs::write(&path, "old").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o640)).unwrap();
+
+        write_atomic(&path, "new").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+        assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_unlinked_only_replaces_links_into_repo() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let repo = temp_dir.path().join("repo");
+        std::fs::create_dir(&repo).unwrap();
+        std::fs::write(repo.join("rust.md"), "stored").unwrap();
+        let dotfiles = temp_dir.path().join("dotfiles");
+        std::fs::create_dir(&dotfiles).unwrap();
+        std::fs::write(dotfiles.join("CLAUDE.md"), "mine").unwrap();
+
+        let linked = temp_dir.path().join("linked.md");
+        symlink(&repo.join("rust.md"), &linked).unwrap();
+        write_unlinked(&linked, "applied", &repo).unwrap();
+        assert!(symlink_target(&linked).is_none());
+        assert_eq!(
+            std::fs::read_to_string(repo.join("rust.md")).unwrap(),
+            "stored"
+        );
+
+        let managed = temp_dir.path().join("CLAUDE.md");
+        symlink(std::path::Path::new("dotfiles/CLAUDE.md"), &managed).unwrap();
+        write_unlinked(&managed, "applied", &repo).unwrap();
+        assert!(symlink_target(&managed).is_some());
+        assert_eq!(
+            std::fs::read_to_string(dotfiles.join("CLAUDE.md")).unwrap(),
+            "applied"
+        );
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::storage::Storage;
+
+/// A profile that changed between two snapshots
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Added(String),
+    Modified(String),
+    Removed(String),
+}
+
+/// Modification time and size of every profile, used to detect changes by polling
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Snapshot {
+    profiles: BTreeMap<String, (Option<SystemTime>, u64)>,
+}
+
+impl Snapshot {
+    pub fn take(storage: &Storage) -> crate::Result<Self> {
+        let mut profiles = BTreeMap::new();
+        for name in storage.list_repos()? {
+            // A profile deleted while listing simply drops out of the snapshot
+            let Ok(metadata) = storage
+                .get_repo_path(&name)
+                .and_then(|path| Ok(std::fs::metadata(path)?))
+            else {
+                continue;
+            };
+            profiles.insert(name, (metadata.modified().ok(), metadata.len()));
+        }
+        Ok(Self { profiles })
+    }
+
+    /// Profiles that were added, modified or removed in `newer`, by name
+    pub fn changes(&self, newer: &Snapshot) -> Vec<Change> {
+        let mut changes = Vec::new();
+        for (name, stamp) in &newer.profiles {
+            match self.profiles.get(name) {
+                None => changes.push(Change::Added(name.clone())),
+                Some(old) if old != stamp => changes.push(Change::Modified(name.clone())),
+                Some(_) => {}
+            }
+        }
+        for name in self.profiles.keys() {
+            if !newer.profiles.contains_key(name) {
+                changes.push(Change::Removed(name.clone()));
+            }
+        }
+        changes.sort_by(|a, b| a.name().cmp(b.name()));
+        changes
+    }
+}
+
+/// Collapses bursts of changes, such as editor swap-file churn or a `git checkout`,
+/// into one batch: changes are reported once the repository has been quiet for
+/// `quiet`, or `max_delay` after the first change of a burst that keeps going
+#[derive(Debug, Clone)]
+pub struct Debouncer {
+    quiet: Duration,
+    max_delay: Duration,
+    /// Snapshot as of the last reported batch
+    settled: Snapshot,
+    latest: Snapshot,
+    /// First and most recent change of the pending burst
+    burst: Option<(Instant, Instant)>,
+}
+
+impl Debouncer {
+    pub fn new(snapshot: Snapshot, quiet: Duration, max_delay: Duration) -> Self {
+        Self {
+            quiet,
+            max_delay,
+            settled: snapshot.clone(),
+            latest: snapshot,
+            burst: None,
+        }
+    }
+
+    /// Record a polled snapshot; returns the net changes when a batch is due. Files
+    /// that changed and changed back within a batch are not reported.
+    pub fn poll(&mut self, snapshot: Snapshot, now: Instant) -> Vec<Change> {
+        if snapshot != self.latest {
+            let first = self.burst.map_or(now, |(first, _)| first);
+            self.burst = Some((first, now));
+            self.latest = snapshot;
+        }
+
+        let Some((first, last)) = self.burst else {
+            return Vec::new();
+        };
+        if now.duration_since(last) < self.quiet && now.duration_since(first) < self.max_delay {
+            return Vec::new();
+        }
+
+        self.burst = None;
+        let changes = self.settled.changes(&self.latest);
+        self.settled = self.latest.clone();
+        changes
+    }
+}
+
+impl Change {
+    pub fn name(&self) -> &str {
+        match self {
+            Change::Added(name) | Change::Modified(name) | Change::Removed(name) => name,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(entries: &[(&str, u64)]) -> Snapshot {
+        Snapshot {
+            profiles: entries
+                .iter()
+                .map(|(name, len)| (name.to_string(), (None, *len)))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_changes() {
+        let old = snapshot(&[("a", 1), ("b", 2), ("c", 3)]);
+        let new = snapshot(&[("a", 1), ("b", 5), ("d", 4)]);
+        assert_eq!(
+            old.changes(&new),
+            vec![
+                Change::Modified("b".to_string()),
+                Change::Removed("c".to_string()),
+                Change::Added("d".to_string()),
+            ]
+        );
+        assert!(new.changes(&new).is_empty());
+    }
+
+    #[test]
+    fn test_debouncer_batches_bursts() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut debouncer = Debouncer::new(
+            snapshot(&[("a", 1)]),
+            Duration::from_millis(500),
+            Duration::from_millis(2000),
+        );
+
+        // A swap file appears and disappears while `a` is saved
+        assert!(
+            debouncer
+                .poll(snapshot(&[("a", 1), ("a.swp", 9)]), at(100))
+                .is_empty()
+        );
+        assert!(debouncer.poll(snapshot(&[("a", 2)]), at(300)).is_empty());
+        assert!(debouncer.poll(snapshot(&[("a", 2)]), at(700)).is_empty());
+        assert_eq!(
+            debouncer.poll(snapshot(&[("a", 2)]), at(800)),
+            vec![Change::Modified("a".to_string())]
+        );
+        assert!(debouncer.poll(snapshot(&[("a", 2)]), at(2000)).is_empty());
+
+        // A burst that never goes quiet is still reported after `max_delay`
+        for ms in (3000..5000).step_by(250) {
+            assert!(debouncer.poll(snapshot(&[("a", ms)]), at(ms)).is_empty());
+        }
+        assert_eq!(
+            debouncer.poll(snapshot(&[("a", 5000)]), at(5000)),
+            vec![Change::Modified("a".to_string())]
+        );
+    }
+}